@@ -27,6 +27,13 @@ pub enum Command {
     Clone {
         #[clap(required = true)]
         url: Option<String>,
+    },
+    /// Reformat .cad files, or every .cad file in a directory.
+    Fmt {
+        files: Vec<String>,
+        /// Only report files which are not formatted, for CI.
+        #[clap(long)]
+        check: bool,
     }
 }
 
@@ -1,7 +1,7 @@
 
 use common::{cadlang, hbam2};
 
-use super::error::Result;
+use super::error::{Error, Result};
 
 use std::{io::{BufWriter, Write}, path::{Path, PathBuf}};
 
@@ -33,24 +33,87 @@ pub fn init_cadmus_repo(path: &PathBuf) -> Result<()> {
         let handle = std::fs::OpenOptions::new()
             .create(true)
             .write(true)
-            .open(path).unwrap();
+            .open(&path).unwrap();
 
         let mut writer = BufWriter::new(handle);
-        writer.write_all(file.to_cad_with_externs(&files.iter().map(|f| f.1.clone()).collect::<Vec<_>>()).as_bytes())?;
+        let code = file.to_cad_with_externs(&files.iter().map(|f| f.1.clone()).collect::<Vec<_>>());
+        /* Generated code that the parser does not understand yet is written
+         * out as is. */
+        let code = cadlang::formatter::format(&code).unwrap_or(code);
+        writer.write_all(code.as_bytes())?;
+
+        /* New objects must not take the id of one from this .fmp12, even
+         * after it is deleted from the generated code. */
+        let mut lock = cadlang::lockfile::Lockfile::default();
+        lock.sync_file(file);
+        lock.save(&cadlang::lockfile::lock_path(&path))?;
     }
     Ok(())
 }
 
+fn find_cad_files(path: &Path, result: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            find_cad_files(&entry, result)?;
+        }
+    } else if path.extension().is_some_and(|e| e == "cad") {
+        result.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/* Formats each file in place, or with `check` only lists the files which
+ * would change. Returns the files that were not already formatted. */
+pub fn format_cad_files(paths: &[PathBuf], check: bool) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        find_cad_files(path, &mut files)?;
+    }
+
+    let mut changed = vec![];
+    for path in files {
+        let code = std::fs::read_to_string(&path)?;
+        let formatted = cadlang::formatter::format(&code)
+            .map_err(|errors| Error::FormatErr { path: path.clone(), errors })?;
+        if formatted != code {
+            if !check {
+                std::fs::write(&path, formatted)?;
+            }
+            changed.push(path);
+        }
+    }
+    Ok(changed)
+}
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use super::init_cadmus_repo;
+    use super::{format_cad_files, init_cadmus_repo};
 
     #[test]
     fn basic_init_test() {
         let res = init_cadmus_repo(&PathBuf::from("./fmp_project/"));
         println!("{:?}", res);
     }
+
+    #[test]
+    fn format_check_test() {
+        let dir = std::env::temp_dir().join(format!("cadmus_fmt_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("person.cad");
+        std::fs::write(&path, "table %1 Person = {\n    field %1 id = { datatype = Number }\n}\n").unwrap();
+
+        assert_eq!(format_cad_files(&[dir.clone()], true).unwrap(), vec![path.clone()]);
+        assert_eq!(format_cad_files(&[dir.clone()], false).unwrap(), vec![path.clone()]);
+        assert!(format_cad_files(&[dir.clone()], true).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(),
+            "table %1 Person = {\n  field %1 id = {\n    datatype = Number,\n  },\n}\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
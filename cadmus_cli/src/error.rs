@@ -1,14 +1,27 @@
+use std::path::PathBuf;
+
+use common::cadlang::error::CompileErr;
+
 
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
-    FsErr(std::io::Error)
+    FsErr(std::io::Error),
+    FormatErr { path: PathBuf, errors: Vec<CompileErr> },
 }
 
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        match self {
+            Self::FormatErr { path, errors } => {
+                for e in errors {
+                    writeln!(f, "{}: {}", path.display(), e)?;
+                }
+                Ok(())
+            }
+            _ => write!(f, "{self:?}"),
+        }
     }
 }
 
@@ -1,11 +1,14 @@
 mod cli;
 mod error;
 mod commands;
+
+use std::path::PathBuf;
+
 use clap::Parser;
 
 use cli::CommandLine;
 use common::hbam2::get_schema_contents;
-use commands::init_cadmus_repo;
+use commands::{format_cad_files, init_cadmus_repo};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = CommandLine::parse();
@@ -13,6 +16,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         cli::Command::Init => {
             init_cadmus_repo(&std::env::current_dir()?)?
         }
+        cli::Command::Fmt { files, check } => {
+            let paths = if files.is_empty() {
+                vec![std::env::current_dir()?]
+            } else {
+                files.iter().map(PathBuf::from).collect()
+            };
+            let changed = format_cad_files(&paths, check)?;
+            for path in &changed {
+                println!("{}", path.display());
+            }
+            if check && !changed.is_empty() {
+                std::process::exit(1);
+            }
+        }
         _ => {}
     }
 
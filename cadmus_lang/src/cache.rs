@@ -1,8 +1,10 @@
-use std::{collections::HashMap, path::Path};
+use std::collections::HashMap;
 
 use cadmus_objects::schema::Schema;
 
 use crate::proto_schema::ProtoSchema;
 
+/* Both caches are keyed by file name relative to the project directory,
+ * which is how extern declarations refer to other files. */
 pub type ProtoSchemaCache = HashMap<String, ProtoSchema>;
 pub type SchemaCache = HashMap<String, Schema>;
@@ -1,38 +1,124 @@
-use super::cache;
-use super::error::Result;
-use crate::cache::ProtoSchemaCache;
-use crate::error::Error;
+use crate::cache::{ProtoSchemaCache, SchemaCache};
+use crate::error::{Error, Result};
 use crate::parser_worker::ParserWorker;
-use crate::proto_schema::ProtoSchema;
-use cadmus_objects::schema::Schema;
 use std::path::Path;
 
 use rayon::prelude::*;
 
-pub fn compile_project(path: &Path) -> Result<Schema> {
-    let proto_cache = ProtoSchemaCache::new();
-    let paths = std::fs::read_dir(path)
+/* Compiles every .cad file in a project directory. Files are parsed in
+ * parallel, then each file's schema is resolved against the others so that
+ * occurrences of extern tables point at the right data source. */
+pub fn compile_project(path: &Path) -> Result<SchemaCache> {
+    let mut paths = std::fs::read_dir(path)
         .map_err(Error::Fs)?
         .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map(|ext| ext == "cad").unwrap_or(false))
         .collect::<Vec<_>>();
+    paths.sort();
 
     let worker_results = paths
         .par_iter()
         .map(|path| ParserWorker::new().with_path(path.clone()).build())
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut diagnostics = vec![];
+    let mut proto_cache = ProtoSchemaCache::new();
+    for parsed in worker_results {
+        diagnostics.extend(parsed.diagnostics.into_iter().map(|d| (parsed.path.clone(), d)));
+        if let Some(schema) = parsed.schema {
+            let name = parsed.path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            proto_cache.insert(name, schema);
+        }
+    }
+    /* Files with syntax errors are still resolved, so that one compile
+     * reports every problem in the project. */
+    let resolved = proto_cache
+        .par_iter()
+        .map(|(name, proto)| (name, proto.resolve(&proto_cache)))
         .collect::<Vec<_>>();
 
-    Ok(Schema::new())
+    let mut result = SchemaCache::new();
+    for (name, schema) in resolved {
+        match schema {
+            Ok(schema) => {
+                result.insert(name.clone(), schema);
+            }
+            Err(errors) => diagnostics.extend(errors.into_iter().map(|d| (path.join(name), d))),
+        }
+    }
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by(|a, b| a.0.cmp(&b.0));
+        return Err(Error::Diagnostics(diagnostics));
+    }
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
-    use super::super::error::Result;
+    use cadmus_objects::schema::relationgraph::relation::{Relation, RelationComparison, RelationCriteria, RelationOptions};
+
+    use crate::error::Error;
     use super::compile_project;
 
     #[test]
     fn basic() {
-        let _ = compile_project(Path::new("./test_data/multi_file_solution"));
+        let schemas = compile_project(Path::new("./test_data/multi_file_solution")).unwrap();
+        assert_eq!(schemas.len(), 3);
+
+        let customers = &schemas["customers.cad"];
+        assert_eq!(customers.tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["Customers", "Customers_BACKUP"]);
+        assert_eq!(customers.tables[0].fields.len(), 2);
+
+        let quotes = &schemas["quotes.cad"];
+        let nodes = &quotes.relation_graph.nodes;
+        assert_eq!(nodes.iter().map(|n| (n.id, n.base.data_source, n.base.table_id)).collect::<Vec<_>>(),
+            vec![(1, 0, 1), (2, 1, 1), (3, 1, 2), (4, 2, 1), (5, 0, 2)]);
+        assert_eq!(nodes[0].relations, vec![
+            Relation { id: 1, other_occurrence: 2, criteria: vec![
+                RelationCriteria { field_self: 2, field_other: 1, comparison: RelationComparison::Equal },
+            ], options: RelationOptions::default() },
+            Relation { id: 2, other_occurrence: 5, criteria: vec![
+                RelationCriteria { field_self: 1, field_other: 1, comparison: RelationComparison::Equal },
+            ], options: RelationOptions::default() },
+        ]);
+        assert_eq!(nodes[1].relations[0].other_occurrence, 1);
+    }
+
+    #[test]
+    fn unresolved_extern_table() {
+        let err = compile_project(Path::new("./test_data/unresolved_project")).unwrap_err();
+        match err {
+            Error::Diagnostics(diagnostics) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].1.snippet, "Missing");
+            }
+            _ => panic!("expected diagnostics"),
+        }
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let err = compile_project(Path::new("./test_data/misspelled_project")).unwrap_err();
+        let json = err.to_json();
+        let found = json.as_array().unwrap().iter()
+            .map(|d| (d["code"].as_str().unwrap(), d["line"].as_u64().unwrap(), d["suggestion"].as_str().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![
+            ("E0008", 5, "Number"),
+            ("E0010", 13, "Customers"),
+            ("E0010", 14, "Quotes"),
+            ("E0010", 16, "customer_id"),
+        ]);
+
+        let rendered = err.render();
+        assert!(rendered.starts_with("error[E0008]: invalid value `Numbr`"));
+        assert!(rendered.contains("5 |     datatype = Numbr,\n  |                ^^^^^\n"));
+        assert!(rendered.contains("  = help: did you mean `Number`?"));
+        assert!(rendered.contains("13 | table_occurrence %2 Customers : Customers::Custmers\n   |                                            ^^^^^^^^\n"));
     }
 }
@@ -1,11 +1,219 @@
+use std::path::Path;
+
 use crate::token::SourceLoc;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DiagnosticCategory {
     BadIDFormat,
+    BadNumberFormat,
+    UnterminatedString,
+    UnterminatedCalculation,
+    UnknownCharacter,
+    UnexpectedToken,
+    UnexpectedEOF,
+    InvalidValue,
+    MissingAttribute,
+    UnresolvedReference,
+}
+
+impl DiagnosticCategory {
+    /* Codes are stable, new categories are appended. */
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadIDFormat => "E0001",
+            Self::BadNumberFormat => "E0002",
+            Self::UnterminatedString => "E0003",
+            Self::UnterminatedCalculation => "E0004",
+            Self::UnknownCharacter => "E0005",
+            Self::UnexpectedToken => "E0006",
+            Self::UnexpectedEOF => "E0007",
+            Self::InvalidValue => "E0008",
+            Self::MissingAttribute => "E0009",
+            Self::UnresolvedReference => "E0010",
+        }
+    }
 }
 
+/* A single problem in a source file. `snippet` is the offending source text,
+ * and the span underlined when rendering starts at `location` and covers
+ * `length` characters. */
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Diagnostic {
-    category: DiagnosticCategory,
-    location: SourceLoc,
-    snippet: String,
+    pub category: DiagnosticCategory,
+    pub location: SourceLoc,
+    pub length: u32,
+    pub snippet: String,
+    pub notes: Vec<String>,
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(category: DiagnosticCategory, location: SourceLoc, snippet: String) -> Self {
+        Self {
+            category,
+            location,
+            length: snippet.chars().count().max(1) as u32,
+            snippet,
+            notes: vec![],
+            suggestion: None,
+        }
+    }
+
+    pub fn with_length(mut self, length: u32) -> Self {
+        self.length = length.max(1);
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.category.code()
+    }
+
+    pub fn message(&self) -> String {
+        match self.category {
+            DiagnosticCategory::BadIDFormat => format!("invalid object id `{}`", self.snippet),
+            DiagnosticCategory::BadNumberFormat => format!("invalid number `{}`", self.snippet),
+            DiagnosticCategory::UnterminatedString => String::from("unterminated string literal"),
+            DiagnosticCategory::UnterminatedCalculation => String::from("unterminated calculation"),
+            DiagnosticCategory::UnknownCharacter => format!("unknown character `{}`", self.snippet),
+            DiagnosticCategory::UnexpectedToken => format!("unexpected `{}`", self.snippet),
+            DiagnosticCategory::UnexpectedEOF => String::from("unexpected end of file"),
+            DiagnosticCategory::InvalidValue => format!("invalid value `{}`", self.snippet),
+            DiagnosticCategory::MissingAttribute => format!("missing attribute `{}`", self.snippet),
+            DiagnosticCategory::UnresolvedReference => format!("cannot find `{}`", self.snippet),
+        }
+    }
+
+    /* Renders in the style of rustc:
+     * error[E0010]: cannot find `Custmers`
+     *  --> quotes.cad:3:15
+     *   |
+     * 3 | table_occurrence %2 Customers : Custmers
+     *   |                                 ^^^^^^^^
+     *   = help: did you mean `Customers`?
+     * The source line is left out when the source is not available. */
+    pub fn render(&self, path: &Path, source: Option<&str>) -> String {
+        let line_no = self.location.line().to_string();
+        let gutter = " ".repeat(line_no.len());
+        let mut buffer = format!("error[{}]: {}\n", self.code(), self.message());
+        buffer.push_str(&format!("{}--> {}:{}\n", gutter, path.display(), self.location));
+
+        let line = source.and_then(|s| s.lines().nth((self.location.line() as usize).saturating_sub(1)));
+        if let Some(line) = line {
+            /* Tabs are kept so the caret lines up with the source line. */
+            let indent = line.chars()
+                .take((self.location.column() as usize).saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            buffer.push_str(&format!("{} |\n", gutter));
+            buffer.push_str(&format!("{} | {}\n", line_no, line));
+            buffer.push_str(&format!("{} | {}{}\n", gutter, indent, "^".repeat(self.length as usize)));
+        }
+
+        for note in &self.notes {
+            buffer.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            buffer.push_str(&format!("{} = help: did you mean `{}`?\n", gutter, suggestion));
+        }
+        buffer
+    }
+
+    pub fn to_json(&self, path: &Path) -> serde_json::Value {
+        serde_json::json!({
+            "severity": "error",
+            "code": self.code(),
+            "message": self.message(),
+            "file": path.display().to_string(),
+            "line": self.location.line(),
+            "column": self.location.column(),
+            "length": self.length,
+            "snippet": self.snippet,
+            "notes": self.notes,
+            "suggestion": self.suggestion,
+        })
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/* Picks the closest candidate to a misspelt name, ignoring case. Candidates
+ * more than a third of the name's length away are not considered. */
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let lowered = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    candidates.into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (edit_distance(&lowered, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::token::SourceLoc;
+    use super::{suggest, Diagnostic, DiagnosticCategory};
+
+    #[test]
+    fn did_you_mean() {
+        let names = ["Customers", "Customers_BACKUP", "Materials"];
+        assert_eq!(suggest("Custmers", names), Some(String::from("Customers")));
+        assert_eq!(suggest("customers", names), Some(String::from("Customers")));
+        assert_eq!(suggest("Quotes", names), None);
+    }
+
+    #[test]
+    fn human_rendering() {
+        let source = "table %1 A = {}\ntable_occurrence %2 Customers : Custmers\n";
+        let diagnostic = Diagnostic::new(DiagnosticCategory::UnresolvedReference, SourceLoc::new(2, 33), String::from("Custmers"))
+            .with_note(String::from("no table named `Custmers` in this file"))
+            .with_suggestion(Some(String::from("Customers")));
+
+        assert_eq!(diagnostic.render(Path::new("quotes.cad"), Some(source)), "\
+error[E0010]: cannot find `Custmers`
+ --> quotes.cad:2:33
+  |
+2 | table_occurrence %2 Customers : Custmers
+  |                                 ^^^^^^^^
+  = note: no table named `Custmers` in this file
+  = help: did you mean `Customers`?
+");
+    }
+
+    #[test]
+    fn json_rendering() {
+        let diagnostic = Diagnostic::new(DiagnosticCategory::BadIDFormat, SourceLoc::new(1, 7), String::from("%x1"));
+        let json = diagnostic.to_json(Path::new("a.cad"));
+        assert_eq!(json["code"], "E0001");
+        assert_eq!(json["message"], "invalid object id `%x1`");
+        assert_eq!(json["line"], 1);
+        assert_eq!(json["column"], 7);
+        assert_eq!(json["length"], 3);
+        assert!(json["suggestion"].is_null());
+    }
 }
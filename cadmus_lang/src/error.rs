@@ -1,13 +1,56 @@
+use std::path::PathBuf;
+
+use crate::diagnostic::Diagnostic;
+
 pub type Result<T> = ::core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
     Fs(std::io::Error),
+    Diagnostics(Vec<(PathBuf, Diagnostic)>),
+}
+
+impl Error {
+    /* Renders every diagnostic with the offending source line, reading each
+     * file once. */
+    pub fn render(&self) -> String {
+        match self {
+            Self::Fs(e) => format!("error: {e}\n"),
+            Self::Diagnostics(diagnostics) => {
+                let mut sources = std::collections::HashMap::new();
+                diagnostics.iter()
+                    .map(|(path, diagnostic)| {
+                        let source = sources.entry(path.clone())
+                            .or_insert_with(|| std::fs::read_to_string(path).ok());
+                        diagnostic.render(path, source.as_deref())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Fs(e) => serde_json::json!([{ "severity": "error", "message": e.to_string() }]),
+            Self::Diagnostics(diagnostics) => diagnostics.iter()
+                .map(|(path, diagnostic)| diagnostic.to_json(path))
+                .collect(),
+        }
+    }
 }
 
 impl ::core::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        match self {
+            Self::Fs(e) => write!(f, "{e}"),
+            Self::Diagnostics(diagnostics) => {
+                for (path, diagnostic) in diagnostics {
+                    write!(f, "{}", diagnostic.render(path, None))?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -2,7 +2,7 @@
 use crate::token::SourceLoc;
 
 pub struct KeyValueBlock {
-    entries: Vec<KeyValueEntry>
+    pub entries: Vec<KeyValueEntry>
 }
 
 impl KeyValueBlock {
@@ -31,17 +31,19 @@ pub enum Key {
 }
 
 pub struct KeyValueEntry {
-    key: Key,
-    value: BlockValue,
-    location: SourceLoc,
+    pub key: Key,
+    pub value: BlockValue,
+    pub location: SourceLoc,
+    pub value_location: SourceLoc,
 }
 
 impl KeyValueEntry {
-    pub fn new(key: Key, location: SourceLoc, value: BlockValue) -> Self {
+    pub fn new(key: Key, location: SourceLoc, value: BlockValue, value_location: SourceLoc) -> Self {
             Self {
                 key,
                 location,
                 value,
+                value_location,
             }
         }
     }
@@ -65,6 +67,7 @@ mod tests {
                 KeyValueEntry {
                     key: Key::TopLevelObject(CadmusObject::Table { id: 1, name: String::from("Quotes") }),
                     location: SourceLoc::new(1, 1),
+                    value_location: SourceLoc::new(1, 26),
                     value: BlockValue::Block(KeyValueBlock { entries: vec![
 
                     ] })
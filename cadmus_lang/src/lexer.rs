@@ -1,9 +1,9 @@
 use std::iter::Peekable;
 
 use crate::{
-    diagnostic::Diagnostic,
+    diagnostic::{Diagnostic, DiagnosticCategory},
     error::Result,
-    token::{match_keyword, SourceLoc, Token, TokenValue, KEYWORD_MAP},
+    token::{match_keyword, SourceLoc, Token, TokenValue},
 };
 
 struct LexIter<'a> {
@@ -28,7 +28,7 @@ impl<'a> LexIter<'a> {
             let mut column = self.column;
             if *c == '\n' {
                 line += 1;
-                column = 1;
+                column = 0;
             } else {
                 column += 1;
             }
@@ -46,7 +46,7 @@ impl<'a> Iterator for LexIter<'a> {
         if let Some(c) = self.chars.next() {
             if c == '\n' {
                 self.line += 1;
-                self.column = 1;
+                self.column = 0;
             } else {
                 self.column += 1;
             }
@@ -57,23 +57,54 @@ impl<'a> Iterator for LexIter<'a> {
     }
 }
 
+fn lex_delimited(iter: &mut LexIter, delimiter: char) -> Option<String> {
+    let mut buffer = String::new();
+    for (c, _, _) in iter.by_ref() {
+        if c == delimiter {
+            return Some(buffer);
+        }
+        buffer.push(c);
+    }
+    None
+}
+
+fn lex_number(iter: &mut LexIter, first: Option<char>) -> String {
+    let mut buffer = String::new();
+    buffer.extend(first);
+    while let Some(&next_c) = iter.chars.peek() {
+        if next_c.is_alphanumeric() {
+            iter.next();
+            buffer.push(next_c);
+        } else {
+            break;
+        }
+    }
+    buffer
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 pub fn lex(text: &str, diagnostics: &mut Vec<Diagnostic>) -> Result<Vec<Token>> {
     let mut tokens = Vec::<Token>::new();
     let mut iter = LexIter::new(text);
     while let Some((c, line, col)) = iter.next() {
+        let loc = SourceLoc::new(line, col);
         match c {
-            c if c.is_alphabetic() => {
+            c if c.is_alphabetic() || c == '_' => {
                 let mut buffer = String::new();
                 buffer.push(c);
 
+                /* Identifiers may contain spaces, but never span lines. */
                 while let Some(&next_c) = iter.chars.peek() {
-                    if next_c.is_alphanumeric() {
+                    if is_identifier_char(next_c) {
                         if let Some((c, _, _)) = iter.next() {
                             buffer.push(c);
                         }
-                    } else if next_c.is_whitespace() {
+                    } else if next_c == ' ' || next_c == '\t' {
                         if let Some(keyword) = match_keyword(&buffer) {
-                            tokens.push(Token::new(keyword, SourceLoc::new(line, col)));
+                            tokens.push(Token::new(keyword, loc));
                             iter.next(); // consume the whitespace
                             buffer.clear();
                             break;
@@ -87,45 +118,112 @@ pub fn lex(text: &str, diagnostics: &mut Vec<Diagnostic>) -> Result<Vec<Token>>
                 }
 
                 if !buffer.is_empty() {
-                    if let Some(keyword) = match_keyword(&buffer) {
-                        tokens.push(Token::new(keyword, SourceLoc::new(line, col)));
+                    if let Some(keyword) = match_keyword(buffer.trim()) {
+                        tokens.push(Token::new(keyword, loc));
                     } else {
                         tokens.push(Token::new(
                             TokenValue::Identifier(buffer.trim().to_string()),
-                            SourceLoc::new(line, col),
+                            loc,
                         ));
                     }
                 }
             }
             '%' => {
-                let mut n_buffer = String::new();
+                let n_buffer = lex_number(&mut iter, None);
+                match n_buffer.parse::<u32>() {
+                    Ok(id) => tokens.push(Token::new(TokenValue::ObjectNumber(id), loc)),
+                    Err(_) => diagnostics.push(Diagnostic::new(
+                        DiagnosticCategory::BadIDFormat,
+                        loc,
+                        format!("%{}", n_buffer),
+                    )),
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let n_buffer = lex_number(&mut iter, Some(c));
+                match n_buffer.parse::<u32>() {
+                    Ok(n) => tokens.push(Token::new(TokenValue::IntegerLiteral(n), loc)),
+                    Err(_) => diagnostics.push(Diagnostic::new(
+                        DiagnosticCategory::BadNumberFormat,
+                        loc,
+                        n_buffer,
+                    )),
+                }
+            }
+            '$' => {
+                let mut buffer = String::from("$");
                 while let Some(&next_c) = iter.chars.peek() {
-                    if next_c.is_alphanumeric() {
-                        if let Some((c, _, _)) = iter.next() {
-                            n_buffer.push(c);
-                        }
+                    if is_identifier_char(next_c) || next_c == '$' {
+                        iter.next();
+                        buffer.push(next_c);
                     } else {
                         break;
                     }
                 }
-
-                let id = n_buffer.parse::<u32>().unwrap();
-                tokens.push(Token::new(
-                    TokenValue::ObjectNumber(id),
-                    SourceLoc::new(line, col),
-                ));
+                tokens.push(Token::new(TokenValue::Variable(buffer), loc));
             }
-            '=' => tokens.push(Token::new(
-                TokenValue::Assignment,
-                SourceLoc::new(line, col),
-            )),
-            '{' => tokens.push(Token::new(TokenValue::OpenBrace, SourceLoc::new(line, col))),
-            '}' => tokens.push(Token::new(
-                TokenValue::CloseBrace,
-                SourceLoc::new(line, col),
+            '"' => match lex_delimited(&mut iter, '"') {
+                Some(text) => tokens.push(Token::new(TokenValue::String(text), loc)),
+                None => diagnostics.push(Diagnostic::new(
+                    DiagnosticCategory::UnterminatedString,
+                    loc,
+                    String::from("\""),
+                )),
+            },
+            '|' => match lex_delimited(&mut iter, '|') {
+                Some(code) => tokens.push(Token::new(TokenValue::Calculation(code.trim().to_string()), loc)),
+                None => diagnostics.push(Diagnostic::new(
+                    DiagnosticCategory::UnterminatedCalculation,
+                    loc,
+                    String::from("|"),
+                )),
+            },
+            '/' if iter.chars.peek() == Some(&'/') => {
+                while let Some(&next_c) = iter.chars.peek() {
+                    if next_c == '\n' {
+                        break;
+                    }
+                    iter.next();
+                }
+            }
+            ':' => {
+                if iter.chars.peek() == Some(&':') {
+                    iter.next();
+                    tokens.push(Token::new(TokenValue::ScopeResolution, loc));
+                } else {
+                    tokens.push(Token::new(TokenValue::Colon, loc));
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                let with_eq = iter.chars.peek() == Some(&'=');
+                if with_eq {
+                    iter.next();
+                }
+                let val = match (c, with_eq) {
+                    ('=', false) => TokenValue::Assignment,
+                    ('=', true) => TokenValue::Eq,
+                    ('!', false) => TokenValue::Exclamation,
+                    ('!', true) => TokenValue::Neq,
+                    ('<', false) => TokenValue::Lt,
+                    ('<', true) => TokenValue::Lte,
+                    ('>', false) => TokenValue::Gt,
+                    (_, _) => TokenValue::Gte,
+                };
+                tokens.push(Token::new(val, loc));
+            }
+            '{' => tokens.push(Token::new(TokenValue::OpenBrace, loc)),
+            '}' => tokens.push(Token::new(TokenValue::CloseBrace, loc)),
+            '(' => tokens.push(Token::new(TokenValue::OpenParen, loc)),
+            ')' => tokens.push(Token::new(TokenValue::CloseParen, loc)),
+            '[' => tokens.push(Token::new(TokenValue::OpenSquare, loc)),
+            ']' => tokens.push(Token::new(TokenValue::CloseSquare, loc)),
+            ',' => tokens.push(Token::new(TokenValue::Comma, loc)),
+            c if c.is_whitespace() => {}
+            c => diagnostics.push(Diagnostic::new(
+                DiagnosticCategory::UnknownCharacter,
+                loc,
+                c.to_string(),
             )),
-            ',' => tokens.push(Token::new(TokenValue::Comma, SourceLoc::new(line, col))),
-            _ => {}
         }
     }
     Ok(tokens)
@@ -167,6 +265,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn identifiers_end_at_newline() {
+        let text = "table_occurrence %1 Customers : Customers\ntable_occurrence %2 backup : Customers::Customers_BACKUP";
+        let mut diags = vec![];
+        let tokens = lex(text, &mut diags).unwrap();
+        let vals = tokens.iter().map(|t| t.token_val.clone()).collect::<Vec<_>>();
+        assert_eq!(vals, vec![
+            TokenValue::TableOccurrence,
+            TokenValue::ObjectNumber(1),
+            TokenValue::Identifier(String::from("Customers")),
+            TokenValue::Colon,
+            TokenValue::Identifier(String::from("Customers")),
+            TokenValue::TableOccurrence,
+            TokenValue::ObjectNumber(2),
+            TokenValue::Identifier(String::from("backup")),
+            TokenValue::Colon,
+            TokenValue::Identifier(String::from("Customers")),
+            TokenValue::ScopeResolution,
+            TokenValue::Identifier(String::from("Customers_BACKUP")),
+        ]);
+        assert!(diags.is_empty());
+    }
+
+    #[test]
+    fn literals_and_comments() {
+        let text = "// comment\nrelation %1 = A::x >= B::y\nextern %1 Customers : \"customers.cad\"\nnext = 10, |$i + 1|";
+        let mut diags = vec![];
+        let tokens = lex(text, &mut diags).unwrap();
+        let vals = tokens.iter().map(|t| t.token_val.clone()).collect::<Vec<_>>();
+        assert_eq!(vals[0], TokenValue::Relation);
+        assert_eq!(vals[6], TokenValue::Gte);
+        assert_eq!(vals[14], TokenValue::String(String::from("customers.cad")));
+        assert_eq!(vals[17], TokenValue::IntegerLiteral(10));
+        assert_eq!(vals[19], TokenValue::Calculation(String::from("$i + 1")));
+        assert_eq!(tokens[0].source_loc, crate::token::SourceLoc::new(2, 1));
+
+        let _ = lex("table %x1 = {}", &mut diags).unwrap();
+        assert_eq!(diags.len(), 1);
+    }
+
     #[test]
     fn keyword_repeated() {
         let text = "table table table %1 Quotes = {}";
@@ -1,11 +1,11 @@
-mod cache;
-mod compiler;
-mod diagnostic;
-mod error;
+pub mod cache;
+pub mod compiler;
+pub mod diagnostic;
+pub mod error;
 mod keyvalue;
 mod lexer;
 mod parsed_file;
 mod parser;
 mod parser_worker;
-mod proto_schema;
-mod token;
+pub mod proto_schema;
+pub mod token;
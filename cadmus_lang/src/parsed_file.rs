@@ -1,14 +1,18 @@
+use std::path::PathBuf;
+
 use crate::diagnostic::Diagnostic;
 use crate::proto_schema::ProtoSchema;
 
 pub struct ParsedFile {
-    schema: Option<ProtoSchema>,
-    diagnostics: Vec<Diagnostic>,
+    pub path: PathBuf,
+    pub schema: Option<ProtoSchema>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl ParsedFile {
-    pub fn new() -> Self {
+    pub fn new(path: PathBuf) -> Self {
         Self {
+            path,
             schema: None,
             diagnostics: vec![],
         }
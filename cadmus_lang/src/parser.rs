@@ -1,21 +1,58 @@
-use crate::diagnostic::Diagnostic;
+use cadmus_objects::schema::relationgraph::relation::RelationComparison;
+
+use crate::diagnostic::{Diagnostic, DiagnosticCategory};
 use crate::error::Result;
-use crate::token::Token;
+use crate::token::{SourceLoc, Token, TokenValue};
 use crate::keyvalue::{BlockValue, CadmusObject, Key, KeyValueBlock, KeyValueEntry};
 
+/* A field named through a table occurrence, e.g Quotes::customer_id. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    pub occurrence: String,
+    pub field: String,
+    pub location: SourceLoc,
+    pub field_location: SourceLoc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationCriterion {
+    pub left: FieldPath,
+    pub comparison: RelationComparison,
+    pub right: FieldPath,
+}
+
 pub enum TopLevelObject {
+    Extern {
+        id: u32,
+        name: String,
+        path: String,
+        path_location: SourceLoc,
+    },
     Table {
         id: u32,
         name: String,
         entries: KeyValueBlock,
     },
-    Field {
+    TableOccurrence {
         id: u32,
         name: String,
-        entries: KeyValueBlock,
-    }
+        data_source: Option<(String, SourceLoc)>,
+        base: String,
+        base_location: SourceLoc,
+    },
+    Relation {
+        id: u32,
+        criteria: Vec<RelationCriterion>,
+    },
 }
 
+pub struct Declaration {
+    pub object: TopLevelObject,
+    pub location: SourceLoc,
+}
+
+type ParseResult<T> = ::core::result::Result<T, Diagnostic>;
+
 struct Parser<'a> {
     stream: std::iter::Peekable<std::slice::Iter<'a, Token>>,
     current: Option<&'a Token>,
@@ -29,60 +66,314 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn next(&mut self) -> Option<&&Token> {
+    pub fn next(&mut self) -> Option<&'a Token> {
         self.current = self.stream.next();
-        self.current.as_ref()
+        self.current
     }
 
-    pub fn peek(&mut self) -> Option<&&Token> {
-        self.stream.peek()
+    pub fn peek(&mut self) -> Option<&'a Token> {
+        self.stream.peek().copied()
     }
-}
 
-fn parse(tokens: &[Token], diagnostics: &mut Vec<Diagnostic>) -> Result<KeyValueBlock> {
-    use crate::token::TokenValue;
-    let mut parser = Parser::new(tokens);
-    let mut entries = KeyValueBlock::new();
+    fn eof(&self) -> Diagnostic {
+        Diagnostic::new(
+            DiagnosticCategory::UnexpectedEOF,
+            self.current.map(|t| t.source_loc).unwrap_or(SourceLoc::new(1, 1)),
+            String::new(),
+        )
+    }
 
-    while let Some(token) = parser.next() {
-        let tmp = match token.token_val {
-            TokenValue::Table => {
-                // parse top level decl,
-                // parse compound block of kvpairs
-                KeyValueEntry::new(
-                    Key::TopLevelObject(
-                        CadmusObject::Table 
-                            { 
-                                id: 1,
-                                name: String::from("Quotes_OCC") 
-                            }
-                        )
-                    , token.source_loc
-                    , BlockValue::Empty
-                )
+    fn next_or_eof(&mut self) -> ParseResult<&'a Token> {
+        match self.next() {
+            Some(token) => Ok(token),
+            None => Err(self.eof()),
+        }
+    }
+
+    /* Consumes the next token, which must be the same kind of token as
+     * `expected`. Payloads are not compared. */
+    fn expect(&mut self, expected: TokenValue) -> ParseResult<&'a Token> {
+        let token = self.next_or_eof()?;
+        if std::mem::discriminant(&token.token_val) == std::mem::discriminant(&expected) {
+            Ok(token)
+        } else {
+            let expected = match expected {
+                TokenValue::String(..) => String::from("a quoted path"),
+                other => format!("`{}`", other),
+            };
+            Err(unexpected(token).with_note(format!("expected {}", expected)))
+        }
+    }
+
+    fn expect_id(&mut self) -> ParseResult<u32> {
+        let token = self.next_or_eof()?;
+        match token.token_val {
+            TokenValue::ObjectNumber(id) => Ok(id),
+            _ => Err(unexpected(token).with_note(String::from("expected an object id, e.g `%1`"))),
+        }
+    }
+
+    fn expect_identifier(&mut self) -> ParseResult<(String, SourceLoc)> {
+        let token = self.next_or_eof()?;
+        match &token.token_val {
+            TokenValue::Identifier(name) => Ok((name.clone(), token.source_loc)),
+            _ => Err(unexpected(token).with_note(String::from("expected a name"))),
+        }
+    }
+
+    fn skip_if(&mut self, val: TokenValue) -> bool {
+        if self.peek().map(|t| t.token_val == val).unwrap_or(false) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /* Skips to the start of the next top level object that is not nested in
+     * a block. */
+    fn synchronize(&mut self) {
+        let mut depth = match self.current.map(|t| &t.token_val) {
+            Some(TokenValue::OpenBrace) => 1,
+            _ => 0,
+        };
+        while let Some(token) = self.peek() {
+            match token.token_val {
+                TokenValue::OpenBrace => depth += 1,
+                TokenValue::CloseBrace => depth = (depth - 1).max(0),
+                ref val if val.is_top_level() && depth == 0 => return,
+                _ => {}
             }
-            TokenValue::TableOccurrence => {
-                KeyValueEntry::new(
-                    Key::TopLevelObject(
-                        CadmusObject::TableOccurrence 
-                            { 
-                                id: 1,
-                                name: String::from("Quotes_OCC") 
-                            }
-                        )
-                    , token.source_loc
-                    , BlockValue::Empty
-                )
+            self.next();
+        }
+    }
+}
+
+fn unexpected(token: &Token) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticCategory::UnexpectedToken,
+        token.source_loc,
+        token.token_val.to_string(),
+    )
+}
+
+fn parse_value(parser: &mut Parser) -> ParseResult<BlockValue> {
+    let token = parser.next_or_eof()?;
+    match &token.token_val {
+        TokenValue::OpenBrace => Ok(BlockValue::Block(parse_block(parser)?)),
+        TokenValue::Identifier(text) | TokenValue::String(text) => Ok(BlockValue::Literal(text.clone())),
+        TokenValue::IntegerLiteral(n) => Ok(BlockValue::Literal(n.to_string())),
+        TokenValue::Calculation(code) => Ok(BlockValue::Expression(code.clone())),
+        _ => Err(unexpected(token)),
+    }
+}
+
+/* Parses the entries of a block up to and including the closing brace. The
+ * opening brace has already been consumed. Commas between entries are
+ * optional. */
+fn parse_block(parser: &mut Parser) -> ParseResult<KeyValueBlock> {
+    let mut block = KeyValueBlock::new();
+    loop {
+        let token = parser.next_or_eof()?;
+        let key = match &token.token_val {
+            TokenValue::CloseBrace => return Ok(block),
+            TokenValue::Comma => continue,
+            TokenValue::Field => {
+                let id = parser.expect_id()?;
+                let (name, _) = parser.expect_identifier()?;
+                Key::TopLevelObject(CadmusObject::Field { id, name })
             }
-            _ => unreachable!()
+            TokenValue::Identifier(name) => Key::Name(name.clone()),
+            _ => return Err(unexpected(token).with_note(String::from("expected an attribute name or `}`"))),
         };
 
-        entries.add(tmp);
+        let (value, value_location) = if parser.skip_if(TokenValue::Assignment) {
+            let location = parser.peek().map(|t| t.source_loc).unwrap_or(token.source_loc);
+            (parse_value(parser)?, location)
+        } else {
+            (BlockValue::Empty, token.source_loc)
+        };
+        block.add(KeyValueEntry::new(key, token.source_loc, value, value_location));
+    }
+}
 
+fn parse_field_path(parser: &mut Parser) -> ParseResult<FieldPath> {
+    let (occurrence, location) = parser.expect_identifier()?;
+    parser.expect(TokenValue::ScopeResolution)?;
+    let (field, field_location) = parser.expect_identifier()?;
+    Ok(FieldPath { occurrence, field, location, field_location })
+}
+
+fn parse_criterion(parser: &mut Parser) -> ParseResult<RelationCriterion> {
+    let left = parse_field_path(parser)?;
+    let token = parser.next_or_eof()?;
+    let comparison = match token.token_val {
+        TokenValue::Eq => RelationComparison::Equal,
+        TokenValue::Neq => RelationComparison::NotEqual,
+        TokenValue::Gt => RelationComparison::Greater,
+        TokenValue::Gte => RelationComparison::GreaterEqual,
+        TokenValue::Lt => RelationComparison::Less,
+        TokenValue::Lte => RelationComparison::LessEqual,
+        TokenValue::Cartesian => RelationComparison::Cartesian,
+        _ => return Err(unexpected(token).with_note(String::from("expected a comparison, e.g `==`"))),
+    };
+    let right = parse_field_path(parser)?;
+    Ok(RelationCriterion { left, comparison, right })
+}
+
+fn parse_relation_criteria(parser: &mut Parser) -> ParseResult<Vec<RelationCriterion>> {
+    if !parser.skip_if(TokenValue::OpenBrace) {
+        return Ok(vec![parse_criterion(parser)?]);
+    }
+
+    let mut criteria = vec![];
+    loop {
+        match parser.peek().map(|t| &t.token_val) {
+            Some(TokenValue::CloseBrace) => {
+                parser.next();
+                return Ok(criteria);
+            }
+            Some(TokenValue::Comma) => {
+                parser.next();
+            }
+            Some(..) => criteria.push(parse_criterion(parser)?),
+            None => return Err(parser.eof()),
+        }
+    }
+}
+
+/* Parses one top level object. Objects that do not contribute to the schema
+ * (layouts, scripts, tests and value lists) are skipped. */
+fn parse_declaration(parser: &mut Parser, token: &Token) -> ParseResult<Option<TopLevelObject>> {
+    let object = match token.token_val {
+        TokenValue::Extern => {
+            let id = parser.expect_id()?;
+            let (name, _) = parser.expect_identifier()?;
+            parser.expect(TokenValue::Colon)?;
+            let token = parser.expect(TokenValue::String(String::new()))?;
+            let path = match &token.token_val {
+                TokenValue::String(path) => path.clone(),
+                _ => unreachable!(),
+            };
+            TopLevelObject::Extern { id, name, path, path_location: token.source_loc }
+        }
+        TokenValue::Table => {
+            let id = parser.expect_id()?;
+            let (name, _) = parser.expect_identifier()?;
+            parser.expect(TokenValue::Assignment)?;
+            parser.expect(TokenValue::OpenBrace)?;
+            TopLevelObject::Table { id, name, entries: parse_block(parser)? }
+        }
+        TokenValue::TableOccurrence => {
+            let id = parser.expect_id()?;
+            let (name, _) = parser.expect_identifier()?;
+            parser.expect(TokenValue::Colon)?;
+            let (mut base, mut base_location) = parser.expect_identifier()?;
+            let mut data_source = None;
+            if parser.skip_if(TokenValue::ScopeResolution) {
+                data_source = Some((base, base_location));
+                (base, base_location) = parser.expect_identifier()?;
+            }
+            TopLevelObject::TableOccurrence { id, name, data_source, base, base_location }
+        }
+        TokenValue::Relation => {
+            let id = parser.expect_id()?;
+            parser.expect(TokenValue::Assignment)?;
+            TopLevelObject::Relation { id, criteria: parse_relation_criteria(parser)? }
+        }
+        TokenValue::Layout | TokenValue::Script | TokenValue::Test | TokenValue::ValueList => {
+            parser.synchronize();
+            return Ok(None);
+        }
+        _ => return Err(unexpected(token).with_note(String::from("expected a top level object, e.g `table`"))),
+    };
+    Ok(Some(object))
+}
 
+pub fn parse(tokens: &[Token], diagnostics: &mut Vec<Diagnostic>) -> Result<Vec<Declaration>> {
+    let mut parser = Parser::new(tokens);
+    let mut declarations = vec![];
 
+    while let Some(token) = parser.next() {
+        match parse_declaration(&mut parser, token) {
+            Ok(Some(object)) => declarations.push(Declaration {
+                object,
+                location: token.source_loc,
+            }),
+            Ok(None) => {}
+            Err(diagnostic) => {
+                diagnostics.push(diagnostic);
+                parser.synchronize();
+            }
+        }
     }
 
-    Ok(KeyValueBlock::new())
+    Ok(declarations)
+}
+
+#[cfg(test)]
+mod tests {
+    use cadmus_objects::schema::relationgraph::relation::RelationComparison;
+
+    use crate::diagnostic::DiagnosticCategory;
+    use crate::keyvalue::{BlockValue, CadmusObject, Key};
+    use crate::lexer::lex;
+    use super::{parse, TopLevelObject};
 
+    #[test]
+    fn top_level_objects() {
+        let text = "extern %1 Customers : \"customers.cad\"
+            table %1 Quotes = {
+              field %1 id = {
+                datatype = Number,
+                serial = { generate = on_creation, next = 1, increment = 1, }
+              },
+            }
+            table_occurrence %2 Customers : Customers::Customers
+            script %1 show = {
+              loop { exit_loop_if(|$i = 10|) }
+            }
+            relation %1 = {
+              Quotes::customer_id == Customers::id,
+              Quotes::id <= Customers::id,
+            }";
+        let mut diags = vec![];
+        let declarations = parse(&lex(text, &mut diags).unwrap(), &mut diags).unwrap();
+        assert!(diags.is_empty());
+        assert_eq!(declarations.len(), 4);
+
+        assert!(matches!(&declarations[0].object,
+            TopLevelObject::Extern { id: 1, name, path, .. } if name == "Customers" && path == "customers.cad"));
+        match &declarations[1].object {
+            TopLevelObject::Table { entries, .. } => {
+                assert!(matches!(&entries.entries[0].key,
+                    Key::TopLevelObject(CadmusObject::Field { id: 1, name }) if name == "id"));
+                assert!(matches!(&entries.entries[0].value, BlockValue::Block(field) if field.entries.len() == 2));
+            }
+            _ => panic!("expected table"),
+        }
+        assert!(matches!(&declarations[2].object,
+            TopLevelObject::TableOccurrence { id: 2, data_source: Some((ds, _)), base, .. } if ds == "Customers" && base == "Customers"));
+        match &declarations[3].object {
+            TopLevelObject::Relation { id: 1, criteria } => {
+                assert_eq!(criteria.len(), 2);
+                assert_eq!(criteria[1].comparison, RelationComparison::LessEqual);
+                assert_eq!(criteria[1].right.field, "id");
+            }
+            _ => panic!("expected relation"),
+        }
+    }
+
+    #[test]
+    fn recovers_at_next_object() {
+        let text = "table_occurrence %1 : Quotes
+            table %1 Quotes = { field %1 id = { datatype = Number } }";
+        let mut diags = vec![];
+        let declarations = parse(&lex(text, &mut diags).unwrap(), &mut diags).unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].category, DiagnosticCategory::UnexpectedToken);
+        assert_eq!(diags[0].snippet, ":");
+        assert_eq!(diags[0].notes, vec![String::from("expected a name")]);
+        assert_eq!(declarations.len(), 1);
+    }
 }
@@ -1,12 +1,12 @@
 use std::{
     fs::read_to_string,
-    path::{Path, PathBuf},
+    path::PathBuf,
 };
 
 use crate::{
-    diagnostic::Diagnostic,
     error::{Error, Result},
     lexer::lex,
+    parser::parse,
     parsed_file::ParsedFile,
     proto_schema::ProtoSchema,
 };
@@ -30,12 +30,12 @@ impl ParserWorker {
     }
 
     pub fn build(&mut self) -> Result<ParsedFile> {
-        let result = ParsedFile::new();
+        let mut result = ParsedFile::new(self.path.clone());
         self.contents = read_to_string(&self.path).map_err(Error::Fs)?;
 
-        let mut diagnostics = vec![];
-
-        let tokens = lex(&self.contents, &mut diagnostics)?;
+        let tokens = lex(&self.contents, &mut result.diagnostics)?;
+        let declarations = parse(&tokens, &mut result.diagnostics)?;
+        result.schema = Some(ProtoSchema::from_declarations(declarations, &mut result.diagnostics));
 
         Ok(result)
     }
@@ -1,9 +1,330 @@
-use cadmus_objects::schema::Schema;
+use cadmus_objects::{
+    reference::TableReference,
+    schema::{
+        field::{AutoEntryType, DataType, Field, SerialTrigger, ValidationType},
+        relationgraph::{graph::RelationGraph, relation::{Relation, RelationCriteria, RelationOptions}, table_occurrence::TableOccurrence},
+        table::Table,
+        Schema,
+    },
+};
 
-pub struct ProtoSchema {}
+use crate::{
+    cache::ProtoSchemaCache,
+    diagnostic::{suggest, Diagnostic, DiagnosticCategory},
+    keyvalue::{BlockValue, CadmusObject, Key, KeyValueBlock, KeyValueEntry},
+    parser::{Declaration, FieldPath, RelationCriterion, TopLevelObject},
+    token::SourceLoc,
+};
 
-impl From<ProtoSchema> for Schema {
-    fn from(value: ProtoSchema) -> Self {
-        todo!()
+pub struct ProtoExtern {
+    pub id: u32,
+    pub name: String,
+    pub path: String,
+    pub location: SourceLoc,
+    pub path_location: SourceLoc,
+}
+
+pub struct ProtoOccurrence {
+    pub id: u32,
+    pub name: String,
+    pub data_source: Option<(String, SourceLoc)>,
+    pub base: String,
+    pub location: SourceLoc,
+    pub base_location: SourceLoc,
+}
+
+pub struct ProtoRelation {
+    pub id: u32,
+    pub criteria: Vec<RelationCriterion>,
+    pub location: SourceLoc,
+}
+
+/* The schema of a single file before references are resolved. Tables only
+ * depend on their own file so they are built straight away, everything that
+ * names other objects waits until every file in the project has been parsed. */
+pub struct ProtoSchema {
+    pub tables: Vec<Table>,
+    pub externs: Vec<ProtoExtern>,
+    pub occurrences: Vec<ProtoOccurrence>,
+    pub relations: Vec<ProtoRelation>,
+}
+
+fn invalid_value(entry: &KeyValueEntry, value: &str) -> Diagnostic {
+    Diagnostic::new(DiagnosticCategory::InvalidValue, entry.value_location, value.to_string())
+}
+
+/* Reports a name that does not resolve, suggesting the closest of the names
+ * that were in scope. */
+fn unresolved<'a>(location: SourceLoc, construct: &str, name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Diagnostic {
+    Diagnostic::new(DiagnosticCategory::UnresolvedReference, location, name.to_string())
+        .with_note(format!("no {} named `{}`", construct, name))
+        .with_suggestion(suggest(name, candidates))
+}
+
+fn literal(entry: &KeyValueEntry) -> Result<&str, Diagnostic> {
+    match &entry.value {
+        BlockValue::Literal(text) => Ok(text),
+        _ => Err(invalid_value(entry, "").with_note(String::from("expected a single value"))),
+    }
+}
+
+fn bool_value(entry: &KeyValueEntry) -> Result<bool, Diagnostic> {
+    match literal(entry)? {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(invalid_value(entry, other)
+            .with_note(String::from("expected `true` or `false`"))
+            .with_suggestion(suggest(other, ["true", "false"]))),
+    }
+}
+
+fn number_value<T: std::str::FromStr>(entry: &KeyValueEntry) -> Result<T, Diagnostic> {
+    let text = literal(entry)?;
+    text.parse().map_err(|_| invalid_value(entry, text).with_note(String::from("expected a number")))
+}
+
+fn key_name(entry: &KeyValueEntry) -> Option<String> {
+    match &entry.key {
+        Key::Name(name) => Some(name.to_lowercase()),
+        Key::TopLevelObject(..) => None,
+    }
+}
+
+fn build_serial(entry: &KeyValueEntry) -> Result<AutoEntryType, Diagnostic> {
+    let block = match &entry.value {
+        BlockValue::Block(block) => block,
+        _ => return Err(invalid_value(entry, "")),
+    };
+
+    let (mut trigger, mut next, mut increment) = (None, None, None);
+    for attribute in &block.entries {
+        match key_name(attribute).as_deref() {
+            Some("generate") => trigger = Some(match literal(attribute)? {
+                "on_creation" => SerialTrigger::OnCreation,
+                "on_commit" => SerialTrigger::OnCommit,
+                other => return Err(invalid_value(attribute, other)
+                    .with_note(String::from("serial numbers are generated `on_creation` or `on_commit`"))
+                    .with_suggestion(suggest(other, ["on_creation", "on_commit"]))),
+            }),
+            Some("next") => next = Some(number_value(attribute)?),
+            Some("increment") => increment = Some(number_value(attribute)?),
+            _ => {}
+        }
+    }
+
+    let missing = |attribute: &str| Diagnostic::new(DiagnosticCategory::MissingAttribute, entry.location, attribute.to_string())
+        .with_length(6)
+        .with_note(String::from("`serial` requires `generate`, `next` and `increment`"));
+    match (trigger, next, increment) {
+        (Some(trigger), Some(next), Some(increment)) => Ok(AutoEntryType::Serial { next, increment, trigger }),
+        (None, _, _) => Err(missing("generate")),
+        (_, None, _) => Err(missing("next")),
+        (_, _, None) => Err(missing("increment")),
+    }
+}
+
+const DATATYPES: [&str; 6] = ["Text", "Number", "Date", "Time", "Timestamp", "Container"];
+
+/* Unknown attributes are ignored, the same as the older cadlang pipeline. */
+fn apply_attribute(field: &mut Field, entry: &KeyValueEntry) -> Result<(), Diagnostic> {
+    match key_name(entry).as_deref() {
+        Some("datatype") => {
+            let value = literal(entry)?;
+            field.dtype = match value.to_lowercase().as_str() {
+                "text" => DataType::Text,
+                "number" => DataType::Number,
+                "date" => DataType::Date,
+                "time" => DataType::Time,
+                "timestamp" => DataType::Timestamp,
+                "container" => DataType::Container,
+                _ => return Err(invalid_value(entry, value)
+                    .with_note(String::from("expected a datatype"))
+                    .with_suggestion(suggest(value, DATATYPES))),
+            };
+        }
+        Some("serial") => field.autoentry.definition = build_serial(entry)?,
+        Some("not_empty") if bool_value(entry)? => field.validation.checks.push(ValidationType::NotEmpty),
+        Some("unique") if bool_value(entry)? => field.validation.checks.push(ValidationType::Unique),
+        Some("required") if bool_value(entry)? => field.validation.checks.push(ValidationType::Required),
+        Some("validation_message") => field.validation.message = literal(entry)?.to_string(),
+        Some("comment") => field.comment = literal(entry)?.to_string(),
+        Some("global") => field.global = bool_value(entry)?,
+        Some("repetitions") => field.repetitions = number_value(entry)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/* A field with a bad attribute is still added to its table, so references to
+ * it do not produce a second error. */
+fn build_field(id: u32, name: &str, block: &KeyValueBlock, diagnostics: &mut Vec<Diagnostic>) -> Field {
+    let mut field = Field::new(id, name.to_string());
+    for entry in &block.entries {
+        if let Err(diagnostic) = apply_attribute(&mut field, entry) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    field
+}
+
+fn build_table(id: u32, name: &str, entries: &KeyValueBlock, diagnostics: &mut Vec<Diagnostic>) -> Table {
+    let mut table = Table::new(id).name(name.to_string());
+    for entry in &entries.entries {
+        if let (Key::TopLevelObject(CadmusObject::Field { id, name }), BlockValue::Block(block)) = (&entry.key, &entry.value) {
+            table.fields.insert(*id, build_field(*id, name, block, diagnostics));
+        }
+    }
+    table
+}
+
+impl ProtoSchema {
+    pub fn from_declarations(declarations: Vec<Declaration>, diagnostics: &mut Vec<Diagnostic>) -> Self {
+        let mut result = Self {
+            tables: vec![],
+            externs: vec![],
+            occurrences: vec![],
+            relations: vec![],
+        };
+        for Declaration { object, location } in declarations {
+            match object {
+                TopLevelObject::Extern { id, name, path, path_location } => {
+                    result.externs.push(ProtoExtern { id, name, path, location, path_location });
+                }
+                TopLevelObject::Table { id, name, entries } => {
+                    result.tables.push(build_table(id, &name, &entries, diagnostics));
+                }
+                TopLevelObject::TableOccurrence { id, name, data_source, base, base_location } => {
+                    result.occurrences.push(ProtoOccurrence { id, name, data_source, base, location, base_location });
+                }
+                TopLevelObject::Relation { id, criteria } => {
+                    result.relations.push(ProtoRelation { id, criteria, location });
+                }
+            }
+        }
+        result
+    }
+
+    /* Finds the base table of an occurrence, following externs into the other
+     * files of the project. The data source of a local table is 0, matching
+     * the hbam reader. */
+    fn resolve_base<'a>(&'a self, occurrence: &ProtoOccurrence, cache: &'a ProtoSchemaCache) -> Result<(TableReference, &'a Table), Diagnostic> {
+        let (data_source, schema) = match &occurrence.data_source {
+            None => (0, self),
+            Some((name, location)) => {
+                let source = self.externs.iter()
+                    .find(|e| e.name == *name)
+                    .ok_or_else(|| unresolved(*location, "data source", name, self.externs.iter().map(|e| e.name.as_str())))?;
+                let schema = cache.get(source.path.trim_start_matches("./"))
+                    .ok_or_else(|| unresolved(source.path_location, "file in this project", &source.path, cache.keys().map(|k| k.as_str()))
+                        .with_length(source.path.chars().count() as u32 + 2))?;
+                (source.id, schema)
+            }
+        };
+
+        let table = schema.tables.iter()
+            .find(|t| t.name == occurrence.base)
+            .ok_or_else(|| unresolved(occurrence.base_location, "table", &occurrence.base, schema.tables.iter().map(|t| t.name.as_str())))?;
+        Ok((TableReference { data_source, table_id: table.id }, table))
+    }
+
+    fn resolve_field(path: &FieldPath, bases: &[(&ProtoOccurrence, &Table)]) -> Result<(u32, u32), Diagnostic> {
+        let (occurrence, table) = bases.iter()
+            .find(|(o, _)| o.name == path.occurrence)
+            .map(|(o, table)| (o.id, *table))
+            .ok_or_else(|| unresolved(path.location, "table occurrence", &path.occurrence, bases.iter().map(|(o, _)| o.name.as_str())))?;
+        let field = table.fields.values()
+            .find(|f| f.name == path.field)
+            .ok_or_else(|| unresolved(path.field_location, "field", &path.field, table.fields.values().map(|f| f.name.as_str()))
+                .with_note(format!("`{}` is an occurrence of table `{}`", path.occurrence, table.name)))?;
+        Ok((occurrence, field.id))
+    }
+
+    /* Resolves every reference in the file, reporting all of the names that
+     * could not be resolved rather than stopping at the first. */
+    pub fn resolve(&self, cache: &ProtoSchemaCache) -> Result<Schema, Vec<Diagnostic>> {
+        let mut diagnostics = vec![];
+        let mut nodes = vec![];
+        let mut bases = vec![];
+        let mut failed = vec![];
+        for occurrence in &self.occurrences {
+            match self.resolve_base(occurrence, cache) {
+                Ok((base, table)) => {
+                    nodes.push(TableOccurrence {
+                        id: occurrence.id,
+                        name: occurrence.name.clone(),
+                        base,
+                        relations: vec![],
+                    });
+                    bases.push((occurrence, table));
+                }
+                Err(diagnostic) => {
+                    diagnostics.push(diagnostic);
+                    failed.push(occurrence.name.as_str());
+                }
+            }
+        }
+
+        for relation in &self.relations {
+            let mut pair = None;
+            let mut criteria = vec![];
+            for criterion in &relation.criteria {
+                /* Occurrences that failed to resolve have already been reported. */
+                if failed.contains(&criterion.left.occurrence.as_str()) || failed.contains(&criterion.right.occurrence.as_str()) {
+                    continue;
+                }
+                let left = Self::resolve_field(&criterion.left, &bases);
+                let right = Self::resolve_field(&criterion.right, &bases);
+                let ((left_occ, left_field), (right_occ, right_field)) = match (left, right) {
+                    (Ok(left), Ok(right)) => (left, right),
+                    (left, right) => {
+                        diagnostics.extend(left.err());
+                        diagnostics.extend(right.err());
+                        continue;
+                    }
+                };
+
+                /* Every criterion of a relation joins the same two occurrences. */
+                if *pair.get_or_insert((left_occ, right_occ)) != (left_occ, right_occ) {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticCategory::InvalidValue,
+                        criterion.left.location,
+                        format!("{}::{}", criterion.left.occurrence, criterion.left.field),
+                    ).with_note(String::from("every criterion of a relation must join the same two occurrences")));
+                    continue;
+                }
+                criteria.push(RelationCriteria {
+                    field_self: left_field,
+                    field_other: right_field,
+                    comparison: criterion.comparison,
+                });
+            }
+
+            let Some((left, right)) = pair else { continue };
+            let mirrored = criteria.iter()
+                .map(|c| RelationCriteria {
+                    field_self: c.field_other,
+                    field_other: c.field_self,
+                    comparison: c.comparison.mirrored(),
+                })
+                .collect();
+            for (occurrence, other, criteria) in [(left, right, criteria), (right, left, mirrored)] {
+                if let Some(node) = nodes.iter_mut().find(|n| n.id == occurrence) {
+                    node.relations.push(Relation {
+                        id: relation.id,
+                        other_occurrence: other,
+                        criteria,
+                        options: RelationOptions::default(),
+                    });
+                }
+            }
+        }
+
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(Schema {
+            tables: self.tables.clone(),
+            relation_graph: RelationGraph { nodes },
+        })
     }
 }
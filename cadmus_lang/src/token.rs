@@ -1,5 +1,3 @@
-use std::path::PathBuf;
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SourceLoc {
     line: u32,
@@ -10,6 +8,20 @@ impl SourceLoc {
     pub fn new(line: u32, column: u32) -> Self {
         Self { line, column }
     }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl ::core::fmt::Display for SourceLoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -46,11 +58,12 @@ pub enum TokenValue {
     Datatype,
 
     Identifier(String),
+    Variable(String),
     ObjectNumber(u32),
     Assignment,
-    Calculation,
-    IntegerLiteral,
-    String,
+    Calculation(String),
+    IntegerLiteral(u32),
+    String(String),
 
     // Auto Entry Tab
     Creation,
@@ -132,11 +145,15 @@ pub struct KeywordEntry {
     pub val: TokenValue,
 }
 
-pub const KEYWORD_MAP: [KeywordEntry; 8] = [
+pub const KEYWORD_MAP: [KeywordEntry; 9] = [
     KeywordEntry {
         text: "extern",
         val: TokenValue::Extern,
     },
+    KeywordEntry {
+        text: "field",
+        val: TokenValue::Field,
+    },
     KeywordEntry {
         text: "layout",
         val: TokenValue::Layout,
@@ -167,6 +184,53 @@ pub const KEYWORD_MAP: [KeywordEntry; 8] = [
     },
 ];
 
+/* Renders the token the way it is written in source, used to quote tokens in
+ * diagnostics and to measure how much of a line to underline. */
+impl ::core::fmt::Display for TokenValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(entry) = KEYWORD_MAP.iter().find(|e| e.val == *self) {
+            return write!(f, "{}", entry.text);
+        }
+        match self {
+            TokenValue::Identifier(text) | TokenValue::Variable(text) => write!(f, "{}", text),
+            TokenValue::ObjectNumber(n) => write!(f, "%{}", n),
+            TokenValue::Calculation(code) => write!(f, "|{}|", code),
+            TokenValue::IntegerLiteral(n) => write!(f, "{}", n),
+            TokenValue::String(text) => write!(f, "\"{}\"", text),
+            TokenValue::Assignment => write!(f, "="),
+            TokenValue::Eq => write!(f, "=="),
+            TokenValue::Neq => write!(f, "!="),
+            TokenValue::Gt => write!(f, ">"),
+            TokenValue::Gte => write!(f, ">="),
+            TokenValue::Lt => write!(f, "<"),
+            TokenValue::Lte => write!(f, "<="),
+            TokenValue::OpenBrace => write!(f, "{{"),
+            TokenValue::CloseBrace => write!(f, "}}"),
+            TokenValue::OpenParen => write!(f, "("),
+            TokenValue::CloseParen => write!(f, ")"),
+            TokenValue::OpenSquare => write!(f, "["),
+            TokenValue::CloseSquare => write!(f, "]"),
+            TokenValue::Comma => write!(f, ","),
+            TokenValue::Colon => write!(f, ":"),
+            TokenValue::ScopeResolution => write!(f, "::"),
+            TokenValue::Exclamation => write!(f, "!"),
+            TokenValue::EOF => write!(f, "end of file"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl TokenValue {
+    /* Objects that may only appear at the top level of a file. These are the
+     * points the parser resynchronises on after an error. */
+    pub fn is_top_level(&self) -> bool {
+        matches!(self,
+            TokenValue::Table | TokenValue::TableOccurrence | TokenValue::Relation
+            | TokenValue::ValueList | TokenValue::Layout | TokenValue::Script
+            | TokenValue::Test | TokenValue::Extern)
+    }
+}
+
 pub fn match_keyword(word: &str) -> Option<TokenValue> {
     KEYWORD_MAP
         .iter()
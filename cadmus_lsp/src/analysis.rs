@@ -0,0 +1,385 @@
+use std::path::{Path, PathBuf};
+
+use common::cadlang::{
+    compiler::{collect_modules, merge_modules, Module},
+    error::CompileErr,
+    lexer::lex,
+    lockfile::{allocate_ids, lock_path, Lockfile},
+    parser::parse_partial,
+    validate::validate,
+    staging::{Stage, StagedAutoEntryType, StagedField, StagedTable},
+    token::{Token, TokenType},
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DocumentSymbol,
+    Position, Range, SymbolKind,
+};
+
+/* Everything the server knows about one open .cad file. The text is
+ * re-analysed from scratch on every change, the files are small enough that
+ * incremental parsing is not worth the trouble. */
+pub struct Document {
+    pub text: String,
+    tokens: Vec<Token>,
+    stage: Stage,
+    errors: Vec<CompileErr>,
+}
+
+/* What an identifier in the source refers to. */
+enum Symbol<'a> {
+    Table(&'a StagedTable),
+    Occurrence { name: &'a Token, base_table: &'a Token },
+    Field(&'a StagedField),
+}
+
+/* cadlang locations are 1-based, LSP positions are 0-based. */
+fn token_range(token: &Token) -> Range {
+    let line = token.location.line.saturating_sub(1);
+    let start = token.location.column.saturating_sub(1);
+    let length = token.value.chars().count().max(1) as u32;
+    Range::new(Position::new(line, start), Position::new(line, start + length))
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/* The token an error points at, if it has one. */
+fn error_token(error: &CompileErr) -> Option<&Token> {
+    match error {
+        CompileErr::UnexpectedToken { token, .. }
+        | CompileErr::RelationCriteria { token }
+        | CompileErr::UnknownTable { token }
+        | CompileErr::UnknownTableOccurrence { token }
+        | CompileErr::UnknownField { token }
+        | CompileErr::UnrelatedOccurrence { token, .. }
+        | CompileErr::WrongBaseTable { token, .. }
+        | CompileErr::InvalidAssert { token }
+        | CompileErr::UnknownAttribute { token, .. }
+        | CompileErr::UnimplementedLanguageFeauture { token, .. }
+        | CompileErr::UndefinedReference { token, .. }
+        | CompileErr::DuplicateId { token, .. }
+        | CompileErr::DuplicateName { token, .. }
+        | CompileErr::ModuleIdClash { token, .. }
+        | CompileErr::UnknownModule { path: token }
+        | CompileErr::UnclosedBlock { token }
+        | CompileErr::UnmatchedBlock { token }
+        | CompileErr::UnknownScriptStep { token }
+        | CompileErr::UnassignedVariable { token }
+        | CompileErr::InvalidRepetitions { token }
+        | CompileErr::IntegerOutOfRange { token }
+        | CompileErr::RelationOptionSide { token }
+        | CompileErr::RelationCycle { token, .. }
+        | CompileErr::CalculationCycle { token, .. }
+        | CompileErr::MixedArguments { token }
+        | CompileErr::MissingArgument { token, .. }
+        | CompileErr::ExtraArgument { token, .. }
+        | CompileErr::InvalidArgument { token, .. }
+        | CompileErr::UnsupportedArgument { token, .. }
+        | CompileErr::UnknownFileType { filename: token } => Some(token),
+        CompileErr::MissingAttribute { .. }
+        | CompileErr::FormatChangedMeaning { .. }
+        | CompileErr::UnexpectedEOF => None,
+    }
+}
+
+impl Document {
+    /* `path` is where the file lives on disk, if anywhere, which is needed
+     * to follow its imports. */
+    pub fn new(text: String, path: Option<&Path>) -> Self {
+        match lex(&text) {
+            Ok(tokens) => {
+                let mut import_errors = vec![];
+                let mut modules = match path {
+                    Some(path) => collect_modules(path, tokens, &mut import_errors),
+                    None => vec![Module { path: PathBuf::new(), tokens }],
+                };
+                /* The lockfile is only read, it is written when compiling. */
+                let mut lock = path.map(|p| Lockfile::load(&lock_path(p))).unwrap_or_default();
+                allocate_ids(modules.iter_mut().map(|m| &mut m.tokens), &mut lock);
+
+                let tokens = modules[0].tokens.clone();
+                let (stage, mut errors) = parse_partial(&tokens);
+                /* References into objects which failed to parse would only
+                 * add noise, so semantic checks wait for a clean parse. */
+                if errors.is_empty() {
+                    let merged = merge_modules(&modules, &mut import_errors);
+                    errors.extend(import_errors.into_iter()
+                        .filter(|(module, _)| path == Some(module.as_path()))
+                        .map(|(_, e)| e));
+                    /* Problems inside imported modules belong to those. */
+                    errors.extend(validate(&merged).into_iter()
+                        .filter(|e| error_token(e).map_or(true, |t| tokens.contains(t))));
+                }
+                Self { text, tokens, stage, errors }
+            }
+            Err(..) => Self {
+                text,
+                tokens: vec![],
+                stage: Stage::new(),
+                errors: vec![CompileErr::UnexpectedEOF],
+            },
+        }
+    }
+
+    fn end_of_file(&self) -> Position {
+        let line = self.text.lines().count().saturating_sub(1);
+        let column = self.text.lines().last().map(|l| l.chars().count()).unwrap_or(0);
+        Position::new(line as u32, column as u32)
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors.iter()
+            .map(|e| {
+                let range = match (e, error_token(e)) {
+                    (_, Some(token)) => token_range(token),
+                    (CompileErr::UnexpectedEOF, None) => Range::new(self.end_of_file(), self.end_of_file()),
+                    (_, None) => Range::default(),
+                };
+                Diagnostic {
+                    range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some(String::from("cadlang")),
+                    message: e.to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn token_at(&self, position: Position) -> Option<usize> {
+        self.tokens.iter().position(|token| {
+            let range = token_range(token);
+            token.ttype == TokenType::Identifier
+                && range.start.line == position.line
+                && range.start.character <= position.character
+                && position.character < range.end.character
+        })
+    }
+
+    fn table(&self, name: &str) -> Option<&StagedTable> {
+        self.stage.tables.values().find(|t| t.name.value == name)
+    }
+
+    /* Occurrences of tables in other files have nothing to point at here. */
+    fn occurrence_table(&self, name: &str) -> Option<&StagedTable> {
+        match self.stage.table_occurrences.values().find(|o| o.name.value == name) {
+            Some(occurrence) if occurrence.data_source.is_none() => self.table(&occurrence.base_table.value),
+            Some(..) => None,
+            None => self.table(name),
+        }
+    }
+
+    fn symbol_at(&self, position: Position) -> Option<Symbol<'_>> {
+        let index = self.token_at(position)?;
+        let name = &self.tokens[index].value;
+
+        let scoped = index >= 2 && self.tokens[index - 1].ttype == TokenType::ScopeResolution;
+        if scoped {
+            let table = self.occurrence_table(&self.tokens[index - 2].value)?;
+            return table.fields.values()
+                .find(|f| f.name.value == *name)
+                .map(Symbol::Field);
+        }
+
+        /* In the middle of a table, identifiers are field names. */
+        if let Some(field) = self.stage.tables.values()
+            .flat_map(|t| t.fields.values())
+            .find(|f| f.name == self.tokens[index]) {
+            return Some(Symbol::Field(field));
+        }
+
+        if let Some(occurrence) = self.stage.table_occurrences.values().find(|o| o.name.value == *name) {
+            return Some(Symbol::Occurrence { name: &occurrence.name, base_table: &occurrence.base_table });
+        }
+        self.table(name).map(Symbol::Table)
+    }
+
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        match self.symbol_at(position)? {
+            Symbol::Table(table) => Some(token_range(&table.name)),
+            Symbol::Occurrence { name, .. } => Some(token_range(name)),
+            Symbol::Field(field) => Some(token_range(&field.name)),
+        }
+    }
+
+    pub fn hover(&self, position: Position) -> Option<String> {
+        match self.symbol_at(position)? {
+            Symbol::Table(table) => Some(format!("table %{} {}\n\n{} fields", table.id, table.name.value, table.fields.len())),
+            Symbol::Occurrence { name, base_table } => {
+                Some(format!("table_occurrence {} : {}", name.value, base_table.value))
+            }
+            Symbol::Field(field) => {
+                let mut result = format!("field %{} {}\n\ndatatype: {:?}", field.id, field.name.value, field.dtype);
+                let autoentry = match &field.autoentry.definition {
+                    StagedAutoEntryType::NA => None,
+                    StagedAutoEntryType::Serial { next, increment, trigger } => {
+                        Some(format!("serial, next {next}, increment {increment}, {trigger:?}"))
+                    }
+                    StagedAutoEntryType::Lookup { from, occurrence, field } => {
+                        Some(format!("lookup {}::{} from {}", occurrence.value, field.value, from.value))
+                    }
+                    StagedAutoEntryType::Creation(preset) => Some(format!("creation {preset:?}")),
+                    StagedAutoEntryType::Modification(preset) => Some(format!("modification {preset:?}")),
+                    StagedAutoEntryType::LastVisited => Some(String::from("last visited")),
+                    StagedAutoEntryType::Data(data) => Some(format!("data \"{data}\"")),
+                    StagedAutoEntryType::Calculation { code, .. } => Some(format!("calculation |{code}|")),
+                };
+                if let Some(autoentry) = autoentry {
+                    result.push_str(&format!("\n\nauto-enter: {autoentry}"));
+                }
+                Some(result)
+            }
+        }
+    }
+
+    /* Completion works on the raw text rather than tokens, as the line being
+     * typed usually does not parse yet. */
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let Some(line) = self.text.lines().nth(position.line as usize) else {
+            return vec![];
+        };
+        let before = line.chars().take(position.character as usize).collect::<String>();
+        let Some(scope) = before.trim_end_matches(is_identifier_char).strip_suffix("::") else {
+            return vec![];
+        };
+        let occurrence = scope.chars().rev()
+            .take_while(|c| is_identifier_char(*c))
+            .collect::<String>()
+            .chars().rev().collect::<String>();
+
+        self.occurrence_table(&occurrence)
+            .map(|table| table.fields.values()
+                .map(|field| CompletionItem {
+                    label: field.name.value.clone(),
+                    kind: Some(CompletionItemKind::FIELD),
+                    detail: Some(format!("{:?}", field.dtype)),
+                    ..Default::default()
+                })
+                .collect())
+            .unwrap_or_default()
+    }
+
+    #[allow(deprecated)]
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        let symbol = |name: &Token, detail: String, kind: SymbolKind, children: Option<Vec<DocumentSymbol>>| DocumentSymbol {
+            name: name.value.clone(),
+            detail: Some(detail),
+            kind,
+            tags: None,
+            deprecated: None,
+            range: token_range(name),
+            selection_range: token_range(name),
+            children,
+        };
+
+        let mut result = vec![];
+        for table in self.stage.tables.values() {
+            let fields = table.fields.values()
+                .map(|f| symbol(&f.name, format!("%{}", f.id), SymbolKind::FIELD, None))
+                .collect();
+            result.push(symbol(&table.name, format!("table %{}", table.id), SymbolKind::CLASS, Some(fields)));
+        }
+        for occurrence in self.stage.table_occurrences.values() {
+            result.push(symbol(&occurrence.name, format!("table_occurrence %{}", occurrence.id), SymbolKind::OBJECT, None));
+        }
+        for value_list in self.stage.value_lists.values() {
+            result.push(symbol(&value_list.name, format!("value_list %{}", value_list.id), SymbolKind::ENUM, None));
+        }
+        for layout in self.stage.layouts.values() {
+            result.push(symbol(&layout.name, format!("layout %{}", layout.id), SymbolKind::STRUCT, None));
+        }
+        for (construct, scripts) in [("script", &self.stage.scripts), ("test", &self.stage.tests)] {
+            for (id, script) in scripts {
+                let name = Token::with_value(TokenType::Identifier, script.location, script.name.clone());
+                result.push(symbol(&name, format!("{} %{}", construct, id), SymbolKind::FUNCTION, None));
+            }
+        }
+        result.sort_by_key(|s| (s.range.start.line, s.range.start.character));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use lsp_types::{Position, SymbolKind};
+
+    use super::Document;
+
+    const CODE: &str = "table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+        serial = {
+            generate = on_creation,
+            next = 1,
+            increment = 1,
+        }
+    },
+    field %2 name = {
+        datatype = Text,
+    }
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Person
+relation %1 = Person_occ::id == Person_occ2::name
+";
+
+    #[test]
+    fn definition_and_hover() {
+        let document = Document::new(CODE.to_string(), None);
+        assert!(document.diagnostics().is_empty());
+
+        /* Person_occ2::na|me */
+        let definition = document.definition(Position::new(15, 46)).unwrap();
+        assert_eq!((definition.start.line, definition.start.character), (9, 13));
+        /* Person|_occ */
+        let definition = document.definition(Position::new(15, 18)).unwrap();
+        assert_eq!((definition.start.line, definition.start.character), (13, 20));
+        /* : Pe|rson */
+        let definition = document.definition(Position::new(13, 35)).unwrap();
+        assert_eq!((definition.start.line, definition.start.character), (0, 9));
+
+        let hover = document.hover(Position::new(15, 27)).unwrap();
+        assert!(hover.contains("datatype: Number"));
+        assert!(hover.contains("auto-enter: serial, next 1, increment 1, OnCreation"));
+        assert!(document.hover(Position::new(15, 0)).is_none());
+    }
+
+    #[test]
+    fn completion_after_scope() {
+        let code = format!("{}relation %2 = Person_occ::", CODE);
+        let document = Document::new(code, None);
+        let labels = document.completion(Position::new(16, 26)).into_iter()
+            .map(|item| item.label)
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["id", "name"]);
+        assert!(!document.diagnostics().is_empty());
+
+        assert!(document.completion(Position::new(16, 10)).is_empty());
+    }
+
+    #[test]
+    fn document_symbols() {
+        let code = format!("{}script %1 hello = {{\n    set_variable($x, |0|)\n}}\n", CODE);
+        let document = Document::new(code, None);
+        let symbols = document.symbols();
+        let summary = symbols.iter().map(|s| (s.name.as_str(), s.kind)).collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("Person", SymbolKind::CLASS),
+            ("Person_occ", SymbolKind::OBJECT),
+            ("Person_occ2", SymbolKind::OBJECT),
+            ("hello", SymbolKind::FUNCTION),
+        ]);
+        assert_eq!(symbols[0].children.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn follows_imports() {
+        let path = Path::new("../common/test_data/cad_files/module_solution/main.cad");
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(Document::new(text.clone(), Some(path)).diagnostics().is_empty());
+        assert!(!Document::new(text, None).diagnostics().is_empty());
+    }
+}
@@ -0,0 +1,16 @@
+mod analysis;
+mod server;
+
+use lsp_server::Connection;
+
+use server::{capabilities, Server};
+
+/* Language server for .cad files, speaking LSP over stdio. */
+fn main() -> server::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    Server::new(&connection).run()?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
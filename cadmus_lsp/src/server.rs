@@ -0,0 +1,237 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _},
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse, Hover,
+    HoverContents, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf,
+    PublishDiagnosticsParams, SaveOptions, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, Uri,
+};
+
+use crate::analysis::Document;
+
+pub type Result<T> = ::core::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+            open_close: Some(true),
+            change: Some(TextDocumentSyncKind::FULL),
+            save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions { include_text: Some(false) })),
+            ..Default::default()
+        })),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![String::from(":")]),
+            ..Default::default()
+        }),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+fn cast<R: lsp_types::request::Request>(request: Request) -> ::core::result::Result<(RequestId, R::Params), ExtractError<Request>> {
+    request.extract(R::METHOD)
+}
+
+fn uri_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme().map(|s| s.as_str()) != Some("file") {
+        return None;
+    }
+    Some(PathBuf::from(uri.path().as_estr().decode().into_string_lossy().into_owned()))
+}
+
+pub struct Server<'a> {
+    connection: &'a Connection,
+    documents: HashMap<Uri, Document>,
+}
+
+impl<'a> Server<'a> {
+    pub fn new(connection: &'a Connection) -> Self {
+        Self {
+            connection,
+            documents: HashMap::new(),
+        }
+    }
+
+    /* Runs until the client asks us to shut down. Expects `initialize` to
+     * have been handled already. */
+    pub fn run(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(..) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => cast::<GotoDefinition>(request).map(|(_, params)| {
+                let uri = params.text_document_position_params.text_document.uri;
+                let position = params.text_document_position_params.position;
+                self.documents.get(&uri)
+                    .and_then(|d| d.definition(position))
+                    .map(|range| serde_json::to_value(GotoDefinitionResponse::Scalar(Location { uri, range })).unwrap())
+            }),
+            HoverRequest::METHOD => cast::<HoverRequest>(request).map(|(_, params)| {
+                let uri = params.text_document_position_params.text_document.uri;
+                let position = params.text_document_position_params.position;
+                self.documents.get(&uri)
+                    .and_then(|d| d.hover(position))
+                    .map(|text| serde_json::to_value(Hover {
+                        contents: HoverContents::Markup(MarkupContent {
+                            kind: MarkupKind::Markdown,
+                            value: format!("```\n{}\n```", text),
+                        }),
+                        range: None,
+                    }).unwrap())
+            }),
+            Completion::METHOD => cast::<Completion>(request).map(|(_, params)| {
+                let uri = params.text_document_position.text_document.uri;
+                let position = params.text_document_position.position;
+                self.documents.get(&uri)
+                    .map(|d| serde_json::to_value(CompletionResponse::Array(d.completion(position))).unwrap())
+            }),
+            DocumentSymbolRequest::METHOD => cast::<DocumentSymbolRequest>(request).map(|(_, params)| {
+                self.documents.get(&params.text_document.uri)
+                    .map(|d| serde_json::to_value(DocumentSymbolResponse::Nested(d.symbols())).unwrap())
+            }),
+            _ => {
+                return Response::new_err(id, lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request {}", request.method));
+            }
+        };
+
+        match result {
+            Ok(value) => Response::new_ok(id, value.unwrap_or(serde_json::Value::Null)),
+            Err(e) => Response::new_err(id, lsp_server::ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)?;
+                let uri = params.text_document.uri;
+                self.documents.insert(uri.clone(), Document::new(params.text_document.text, uri_path(&uri).as_deref()));
+                self.publish_diagnostics(uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD)?;
+                /* Full sync, so the last change holds the whole document. */
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let path = uri_path(&params.text_document.uri);
+                    self.documents.insert(params.text_document.uri, Document::new(change.text, path.as_deref()));
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidSaveTextDocumentParams>(DidSaveTextDocument::METHOD)?;
+                self.publish_diagnostics(params.text_document.uri)?;
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)?;
+                self.documents.remove(&params.text_document.uri);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Result<()> {
+        let diagnostics = self.documents.get(&uri)
+            .map(|d| d.diagnostics())
+            .unwrap_or_default();
+        let params = PublishDiagnosticsParams { uri, diagnostics, version: None };
+        self.connection.sender.send(Message::Notification(Notification::new(PublishDiagnostics::METHOD.to_string(), params)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lsp_server::{Connection, Message, Notification, Request, RequestId};
+    use lsp_types::Uri;
+    use serde_json::json;
+
+    use super::{capabilities, Server};
+
+    fn request(client: &Connection, id: i32, method: &str, params: serde_json::Value) -> serde_json::Value {
+        client.sender.send(Message::Request(Request::new(RequestId::from(id), method.to_string(), params))).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => {
+                assert_eq!(response.id, RequestId::from(id));
+                response.result.unwrap()
+            }
+            message => panic!("expected a response, got {:?}", message),
+        }
+    }
+
+    fn notify(client: &Connection, method: &str, params: serde_json::Value) {
+        client.sender.send(Message::Notification(Notification::new(method.to_string(), params))).unwrap();
+    }
+
+    #[test]
+    fn session() {
+        let (server, client) = Connection::memory();
+        let handle = std::thread::spawn(move || {
+            server.initialize(serde_json::to_value(capabilities()).unwrap()).unwrap();
+            Server::new(&server).run().unwrap();
+        });
+
+        let initialized = request(&client, 1, "initialize", json!({ "capabilities": {} }));
+        assert_eq!(initialized["capabilities"]["definitionProvider"], true);
+        notify(&client, "initialized", json!({}));
+
+        let uri = Uri::from_str("file:///project/person.cad").unwrap();
+        let text = "table %1 Person = {\n    field %1 id = {\n        datatype = Number,\n    }\n}\ntable_occurrence %1 Person_occ : Persn\n";
+        notify(&client, "textDocument/didOpen", json!({
+            "textDocument": { "uri": uri, "languageId": "cadlang", "version": 1, "text": text },
+        }));
+        let Message::Notification(published) = client.receiver.recv().unwrap() else { panic!() };
+        assert_eq!(published.method, "textDocument/publishDiagnostics");
+        let diagnostics = published.params["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["message"], "Invalid reference to table: Persn @ 6, 34");
+
+        let text = "table %1 Person = {\n    field %1 id = {\n        datatype = Numbr,\n    }\n}\n";
+        notify(&client, "textDocument/didChange", json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": text }],
+        }));
+        notify(&client, "textDocument/didSave", json!({ "textDocument": { "uri": uri } }));
+        let Message::Notification(published) = client.receiver.recv().unwrap() else { panic!() };
+        let diagnostics = published.params["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 2, "character": 19 }));
+
+        let symbols = request(&client, 2, "textDocument/documentSymbol", json!({ "textDocument": { "uri": uri } }));
+        assert_eq!(symbols[0]["name"], "Person");
+
+        let hover = request(&client, 3, "textDocument/hover", json!({
+            "textDocument": { "uri": uri }, "position": { "line": 0, "character": 10 },
+        }));
+        assert!(hover["contents"]["value"].as_str().unwrap().contains("table %1 Person"));
+
+        request(&client, 4, "shutdown", json!(null));
+        notify(&client, "exit", json!(null));
+        handle.join().unwrap();
+    }
+}
@@ -3,10 +3,11 @@ pub mod context;
 mod parser;
 pub mod token;
 
-use super::calculation::token::{Token, Function, GetArgument}; 
+use super::calculation::token::{Token, TokenKind, Function, GetArgument}; 
 use super::reference;
 use super::calculation::context::CalculationContext; 
 use super::calculation::parser::*;
+use super::file::File;
 use cadmus_util::encoding_util::fm_string_decrypt;
 
 use serde::{Serialize, Deserialize};
@@ -52,6 +53,41 @@ impl Calculation {
         Calculation::from_tokens(&lex_text(code))
     }
 
+    /* Renders the calculation back to FileMaker syntax. Whitespace is not
+     * kept in the bytecode, so operators are spaced out uniformly. Resolved
+     * field references carry no names, so they are written as ids. */
+    pub fn to_text(&self) -> String {
+        self.render(|_| None)
+    }
+
+    /* Same as to_text, but resolved field references are written by name as
+     * "Occurrence::Field", looked up in the file the calculation belongs to.
+     * This is the form FileMaker accepts when a calculation is pasted back.
+     * References the file does not know about are still written as ids. */
+    pub fn to_text_in(&self, file: &File) -> String {
+        self.render(|reference| file.field_reference_names(reference)
+            .map(|(occurrence, field)| format!("{}::{}", occurrence, field)))
+    }
+
+    fn render(&self, name_of: impl Fn(&reference::FieldReference) -> Option<String>) -> String {
+        self.lex().iter()
+            .map(|token| match token {
+                Token::ResolvedFieldReference(reference) => name_of(reference)
+                    .unwrap_or_else(|| token.to_string()),
+                _ => match token.kind() {
+                    TokenKind::Equal | TokenKind::NotEqual
+                        | TokenKind::Less | TokenKind::LessEqual
+                        | TokenKind::Greater | TokenKind::GreaterEqual
+                        | TokenKind::Add | TokenKind::Subtract
+                        | TokenKind::Multiply | TokenKind::Divide
+                        | TokenKind::Concatenate => format!(" {} ", token),
+                    TokenKind::SemiColon => format!("{} ", token),
+                    _ => token.to_string(),
+                },
+            })
+            .collect()
+    }
+
     fn lex(&self) -> Vec<Token> {
         let mut result = vec![];
         let mut ptr = 0;
@@ -79,8 +115,8 @@ impl Calculation {
                 0x16 => {
                     result.push(Token::ResolvedFieldReference(reference::FieldReference {
                         data_source: 0,
-                        table_occurrence_id: 2,
-                        field_id: 2,
+                        table_occurrence_id: self.0[ptr + 6] as u32,
+                        field_id: self.0[ptr + 9] as u32,
                     }));
                     ptr += 12;
                 }
@@ -108,9 +144,11 @@ impl Calculation {
                             _ => {}
                         }
                     }
+                    ptr += 1;
                 }
                 0x9d => {
                     result.push(Token::Function(token::Function::Acos));
+                    ptr += 1;
                 }
                 0xfb => {
                     ptr += 1;
@@ -118,6 +156,7 @@ impl Calculation {
                         0x3 => { result.push(Token::Function(Function::Char)) }
                         _ => eprintln!("unrecognized intrinsic.")
                     }
+                    ptr += 1;
                 }
                 0x10 => {
                     /* decode number */
@@ -134,7 +173,9 @@ impl Calculation {
                     /* Processing String */
                     ptr += 1;
                     let len = self.0[ptr] as usize;
+                    ptr += 1;
                     result.push(Token::String(String::from(&fm_string_decrypt(&self.0[ptr..ptr+len]))));
+                    ptr += len;
                 }
                 0x1a => {
                     /* decode variable */
@@ -186,8 +227,10 @@ impl Calculation {
                 }
                 0x50 => {
                     result.push(Token::Concatenate);
+                    ptr += 1;
                 }
                 _ => {
+                    ptr += 1;
                 }
             }
         }
@@ -329,7 +372,34 @@ pub fn lex_text(code: &str) -> Vec<Token> {
             }
             '=' => {
                 result.push(Token::Equal);
-                iter.next();
+                if iter.peek() == Some(&'=') {
+                    iter.next();
+                }
+            }
+            '<' => {
+                if iter.peek() == Some(&'=') {
+                    result.push(Token::LessEqual);
+                    iter.next();
+                } else {
+                    result.push(Token::Less);
+                }
+            }
+            '>' => {
+                if iter.peek() == Some(&'=') {
+                    result.push(Token::GreaterEqual);
+                    iter.next();
+                } else {
+                    result.push(Token::Greater);
+                }
+            }
+            '≠' => {
+                result.push(Token::NotEqual);
+            }
+            '≤' => {
+                result.push(Token::LessEqual);
+            }
+            '≥' => {
+                result.push(Token::GreaterEqual);
             }
             '(' => {
                 result.push(Token::OpenParen);
@@ -499,6 +569,23 @@ mod tests {
         println!("COde: {:?}", code);
         assert_eq!(code.eval(&DummyContext::new()).unwrap(), "15".to_string());
     }
+
+    #[test]
+    fn to_text_test() {
+        for code in ["1 + 2", "$x", "$name & \"abc\"", "($x + 1) * 2 ≥ 10", "$x ≠ 3"] {
+            assert_eq!(Calculation::from_text(code).to_text(), code);
+        }
+        assert_eq!(Calculation::from_text("$x==1").to_text(), "$x = 1");
+        assert_eq!(Calculation::from_text("$x>=1").to_text(), "$x ≥ 1");
+    }
+
+    #[test]
+    fn long_string_test() {
+        let text = "é".repeat(200);
+        let code = Calculation::from_tokens(&vec![Token::String(text.clone())]);
+        assert_eq!(code.eval(&DummyContext::new()).unwrap(), text);
+        assert_eq!(code.to_text(), format!("(\"{}\" & \"{}\")", "é".repeat(127), "é".repeat(73)));
+    }
 }
 
 
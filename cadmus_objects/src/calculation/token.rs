@@ -158,9 +158,48 @@ impl Token {
             Self::Number(n) => {
                 vec![16, 2, 0, 1, 0, 16, 0, 0, 0, (*n) as u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32]
             }
+            Self::String(text) => {
+                /* The length is a single byte. Longer text is written as a
+                 * bracketed concatenation of pieces that each fit, split on
+                 * char boundaries so every piece still decodes on its own. */
+                let mut pieces = vec![];
+                let mut start = 0;
+                for (offset, c) in text.char_indices() {
+                    if offset + c.len_utf8() - start > u8::MAX as usize {
+                        pieces.push(&text[start..offset]);
+                        start = offset;
+                    }
+                }
+                pieces.push(&text[start..]);
+
+                let encode_piece = |piece: &str| {
+                    let mut result = vec![0x13, piece.len() as u8];
+                    result.extend(fm_string_encrypt(piece));
+                    result
+                };
+                if pieces.len() == 1 {
+                    return encode_piece(pieces[0]);
+                }
+                let mut result = vec![0x4];
+                for (i, piece) in pieces.iter().enumerate() {
+                    if i > 0 {
+                        result.push(0x50);
+                    }
+                    result.extend(encode_piece(piece));
+                }
+                result.push(0x5);
+                result
+            }
+            Self::OpenParen => vec![0x4],
+            Self::CloseParen => vec![0x5],
+            Self::Less => vec![0x41],
+            Self::LessEqual => vec![0x43],
             Self::Equal => {
                 vec![0x44]
             }
+            Self::NotEqual => vec![0x46],
+            Self::GreaterEqual => vec![0x47],
+            Self::Greater => vec![0x49],
             Self::Space => {
                 vec![12, 19, 1, 122, 0]
             }
@@ -206,6 +245,66 @@ impl Token {
     }
 }
 
+impl std::fmt::Display for GetArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CurrentTime => write!(f, "CurrentTime"),
+            Self::AccountName => write!(f, "AccountName"),
+            Self::DocumentsPath => write!(f, "DocumentsPath"),
+            Self::DocumentsPathListing => write!(f, "DocumentsPathListing"),
+        }
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Char => write!(f, "Char"),
+            Self::Abs => write!(f, "Abs"),
+            Self::Cos => write!(f, "Cos"),
+            Self::Sin => write!(f, "Sin"),
+            Self::Tan => write!(f, "Tan"),
+            Self::Acos => write!(f, "Acos"),
+            Self::Asin => write!(f, "Asin"),
+            Self::Atan => write!(f, "Atan"),
+            Self::Get(arg) => write!(f, "Get ( {} )", arg),
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable(name) | Self::Global(name) | Self::Identifier(name) => write!(f, "{}", name),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::String(text) => write!(f, "\"{}\"", text),
+            Self::FieldReference(table, field) => write!(f, "{}::{}", table, field),
+            Self::ResolvedFieldReference(reference) => write!(f, "{}::{}", reference.table_occurrence_id, reference.field_id),
+            Self::Function(function) => write!(f, "{}", function),
+
+            Self::Equal => write!(f, "="),
+            Self::NotEqual => write!(f, "≠"),
+            Self::Less => write!(f, "<"),
+            Self::LessEqual => write!(f, "≤"),
+            Self::Greater => write!(f, ">"),
+            Self::GreaterEqual => write!(f, "≥"),
+
+            Self::Add => write!(f, "+"),
+            Self::Multiply => write!(f, "*"),
+            Self::Subtract | Self::Negate => write!(f, "-"),
+            Self::Divide => write!(f, "/"),
+            Self::Concatenate => write!(f, "&"),
+
+            Self::OpenParen => write!(f, "("),
+            Self::CloseParen => write!(f, ")"),
+            Self::OpenSquare => write!(f, "["),
+            Self::CloseSquare => write!(f, "]"),
+            Self::SemiColon => write!(f, ";"),
+            Self::Space => write!(f, " "),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,13 +1,15 @@
+use serde::{Serialize, Deserialize};
 
 use std::collections::HashMap;
 use std::path::Path;
 
 use super::schema::Schema;
-use super::scripting::script::Script;
+use super::scripting::{script::Script, trigger::ScriptTrigger};
 use super::layout::Layout;
 use super::data_source::*;
+use super::reference::FieldReference;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct File {
     pub name: String,
     pub schema: Schema,
@@ -15,9 +17,24 @@ pub struct File {
     pub data_sources: Vec<DataSource>,
     pub scripts: Vec<Script>,
     pub tests: Vec<Script>,
+    pub triggers: Vec<ScriptTrigger>,
+    #[serde(skip)]
     pub working_dir: String,
 }
 
+impl File {
+    /* Looks up the occurrence and field names a resolved field reference
+     * points at. References into other files are not followed. */
+    pub fn field_reference_names(&self, reference: &FieldReference) -> Option<(&str, &str)> {
+        let occurrence = self.schema.relation_graph.nodes.iter()
+            .find(|o| o.id == reference.table_occurrence_id)?;
+        let field = self.schema.tables.iter()
+            .find(|t| t.id == occurrence.base.table_id)?
+            .fields.get(&reference.field_id)?;
+        Some((occurrence.name.as_str(), field.name.as_str()))
+    }
+}
+
 //impl File {
 //    pub fn to_cad(&self) -> String {
 //        let mut buffer = String::new();
@@ -1,25 +1,35 @@
+use serde::{Serialize, Deserialize};
 
-use super::{file::File, reference::TableOccurrenceReference};
+use super::{file::File, reference::TableOccurrenceReference, scripting::trigger::ScriptTrigger};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Layout {
     pub id: u32,
     pub name: String,
     pub occurrence: TableOccurrenceReference,
+    pub triggers: Vec<ScriptTrigger>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LayoutAttribute {
 }
 
 impl Layout {
     pub fn to_cad(&self, file: &File) -> String {
-        format!("layout %{} {} : {} = {{}}",
+        let mut buffer = format!("layout %{} {} : {} = {{",
             self.id,
             self.name,
             file.schema.relation_graph.nodes.iter()
             .inspect(|oc| println!("{}::{} == {}::{}?", self.occurrence.table_occurrence_id, self.name, oc.id, oc.name))
             .find(|oc| oc.id == self.occurrence.table_occurrence_id)
-            .unwrap().name)
+            .unwrap().name);
+        if !self.triggers.is_empty() {
+            buffer.push('\n');
+        }
+        for trigger in &self.triggers {
+            buffer.push_str(&format!("    {},\n", trigger.to_cad(file)));
+        }
+        buffer.push('}');
+        buffer
     }
 }
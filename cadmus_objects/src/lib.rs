@@ -6,4 +6,5 @@ pub mod metadata;
 pub mod reference;
 pub mod schema;
 pub mod scripting;
+pub mod xml;
 
@@ -4,6 +4,12 @@ use serde::{Serialize, Deserialize};
 
 use crate::{calculation::Calculation, file::File, reference::*};
 
+/* A cadlang string literal for `text`. Backslashes and double quotes are
+ * escaped with a backslash, as in common's cadlang::token::quote. */
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum SerialTrigger {
     OnCreation,
@@ -138,7 +144,7 @@ impl Validation {
         let mut buffer = String::new();
         buffer.push_str(&format!("        trigger = {},\n", self.trigger));
         buffer.push_str(&format!("        user_override = {},\n", self.user_override));
-        buffer.push_str(&format!("        message = \"{}\",\n", self.message));
+        buffer.push_str(&format!("        message = {},\n", quote(&self.message)));
         for check in &self.checks {
             buffer.push_str(&check.to_cad());
         }
@@ -158,6 +164,7 @@ pub struct Field {
     pub repetitions: u8,
     pub created_by: String,
     pub modified_by: String,
+    pub comment: String,
 }
 
 impl Field {
@@ -167,6 +174,7 @@ impl Field {
             name: name_,
             created_by: String::from("admin"),
             modified_by: String::from("admin"),
+            comment: String::new(),
             dtype: DataType::Text,
             global: false,
             repetitions: 1,
@@ -190,6 +198,9 @@ impl Field {
         buffer.push_str(&format!("        datatype = {},\n", self.dtype));
         buffer.push_str(&self.autoentry.to_cad());
         buffer.push_str(&self.validation.to_cad());
+        if !self.comment.is_empty() {
+            buffer.push_str(&format!("        comment = {},\n", quote(&self.comment)));
+        }
 
         buffer.push_str("    }");
         buffer
@@ -215,6 +226,11 @@ impl Field {
         self
     }
 
+    pub fn comment(mut self, comment_: String) -> Self {
+        self.comment = comment_;
+        self
+    }
+
     pub fn autoentry(mut self, definition_: AutoEntryType, nomodify_: bool) -> Self {
         self.autoentry = AutoEntry {
             definition: definition_,
@@ -229,3 +245,28 @@ impl Field {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{file::File, schema::{relationgraph::graph::RelationGraph, Schema}};
+    use super::Field;
+
+    #[test]
+    fn comment_is_escaped() {
+        let field = Field::new(1, String::from("name"))
+            .comment(String::from("the \"full\" name, see C:\\names"));
+        let file = File {
+            name: String::new(),
+            schema: Schema { tables: vec![], relation_graph: RelationGraph { nodes: vec![] } },
+            layouts: vec![],
+            data_sources: vec![],
+            scripts: vec![],
+            tests: vec![],
+            triggers: vec![],
+            working_dir: String::new(),
+        };
+        assert!(field.to_cad(&file, &HashMap::new())
+            .contains("comment = \"the \\\"full\\\" name, see C:\\\\names\",\n"));
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Serialize, Deserialize};
 
 pub mod field;
 pub mod table;
@@ -9,7 +10,7 @@ use super::file::File;
 use super::schema::relationgraph::graph::RelationGraph;
 use super::schema::table::Table;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub tables: Vec<Table>,
     pub relation_graph: RelationGraph,
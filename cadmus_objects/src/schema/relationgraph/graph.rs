@@ -1,3 +1,4 @@
+use serde::{Serialize, Deserialize};
 
 use std::collections::{HashMap, HashSet};
 use crate::file::File;
@@ -5,7 +6,7 @@ use crate::file::File;
 use super::{table_occurrence::TableOccurrence, relation::Relation};
 use std::collections::VecDeque;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct RelationGraph {
     pub nodes: Vec<TableOccurrence>
 }
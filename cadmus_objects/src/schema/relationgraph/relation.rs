@@ -49,9 +49,26 @@ pub struct RelationCriteria {
     pub comparison: RelationComparison,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct RelationSort {
+    pub field: u32,
+    pub descending: bool,
+}
+
+/* How records of the other occurrence are treated when reached through the
+ * relation. Each side of a relation has its own. */
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct RelationOptions {
+    pub allow_creation: bool,
+    pub delete_related: bool,
+    pub sort: Vec<RelationSort>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Relation {
     pub id: u32,
     pub other_occurrence: u32,
     pub criteria: Vec<RelationCriteria>,
+    #[serde(default)]
+    pub options: RelationOptions,
 }
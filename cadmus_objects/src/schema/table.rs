@@ -2,7 +2,7 @@
 use serde::{Serialize, Deserialize};
 use std::collections::{BTreeMap, HashMap};
 
-use crate::file::File;
+use crate::{file::File, metadata::Metadata};
 
 use super::field::Field;
 
@@ -14,8 +14,7 @@ pub struct Table {
     pub name: String,
     pub comment: String,
     pub fields: BTreeMap<u32, Field>,
-    pub created_by: String,
-    pub modified_by: String,
+    pub metadata: Metadata,
 }
 
 impl Table {
@@ -25,8 +24,7 @@ impl Table {
             name: String::new(),
             comment: String::new(),
             fields: BTreeMap::new(),
-            created_by: String::new(),
-            modified_by: String::new(),
+            metadata: Metadata::default(),
         }
     }
 
@@ -37,6 +35,9 @@ impl Table {
 
     pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
         let mut buffer = String::new();
+        for line in self.comment.lines() {
+            buffer.push_str(&format!("// {}\n", line));
+        }
         buffer.push_str(&format!("table %{} {} = {{\n", self.id, self.name));
         for (_, field) in &self.fields {
             buffer.push_str(&format!("    {}\n", &field.to_cad(file, externs)));
@@ -2,3 +2,4 @@
 pub mod instructions;
 pub mod script;
 pub mod arguments;
+pub mod trigger;
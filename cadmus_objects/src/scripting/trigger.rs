@@ -0,0 +1,83 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{file::File, reference::ScriptReference};
+
+/* What a trigger can be declared on. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriggerScope {
+    File,
+    Layout,
+    Object,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum TriggerEvent {
+    OnFirstWindowOpen,
+    OnLastWindowClose,
+    OnWindowOpen,
+    OnWindowClose,
+    OnRecordLoad,
+    OnRecordCommit,
+    OnLayoutEnter,
+    OnLayoutExit,
+    OnObjectEnter,
+    OnObjectExit,
+    OnObjectModify,
+    OnObjectSave,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ScriptTrigger {
+    pub event: TriggerEvent,
+    pub script: ScriptReference,
+}
+
+impl ScriptTrigger {
+    pub fn to_cad(&self, file: &File) -> String {
+        let script = match (self.script.data_source, file.scripts.iter().find(|s| s.id == self.script.script_id)) {
+            (0, Some(found)) => found.name.clone(),
+            _ => format!("script_{}", self.script.script_id),
+        };
+        format!("{} = {}", self.event.to_cad(), script)
+    }
+}
+
+impl TriggerEvent {
+    pub const ALL: [TriggerEvent; 12] = [
+        Self::OnFirstWindowOpen, Self::OnLastWindowClose, Self::OnWindowOpen, Self::OnWindowClose,
+        Self::OnRecordLoad, Self::OnRecordCommit, Self::OnLayoutEnter, Self::OnLayoutExit,
+        Self::OnObjectEnter, Self::OnObjectExit, Self::OnObjectModify, Self::OnObjectSave,
+    ];
+
+    pub fn scope(&self) -> TriggerScope {
+        match self {
+            Self::OnFirstWindowOpen | Self::OnLastWindowClose
+                | Self::OnWindowOpen | Self::OnWindowClose => TriggerScope::File,
+            Self::OnRecordLoad | Self::OnRecordCommit
+                | Self::OnLayoutEnter | Self::OnLayoutExit => TriggerScope::Layout,
+            Self::OnObjectEnter | Self::OnObjectExit
+                | Self::OnObjectModify | Self::OnObjectSave => TriggerScope::Object,
+        }
+    }
+
+    pub fn to_cad(&self) -> &'static str {
+        match self {
+            Self::OnFirstWindowOpen => "on_first_window_open",
+            Self::OnLastWindowClose => "on_last_window_close",
+            Self::OnWindowOpen => "on_window_open",
+            Self::OnWindowClose => "on_window_close",
+            Self::OnRecordLoad => "on_record_load",
+            Self::OnRecordCommit => "on_record_commit",
+            Self::OnLayoutEnter => "on_layout_enter",
+            Self::OnLayoutExit => "on_layout_exit",
+            Self::OnObjectEnter => "on_object_enter",
+            Self::OnObjectExit => "on_object_exit",
+            Self::OnObjectModify => "on_object_modify",
+            Self::OnObjectSave => "on_object_save",
+        }
+    }
+
+    pub fn from_cad(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.to_cad() == name)
+    }
+}
@@ -0,0 +1,206 @@
+/* FMPReport, as written by the Database Design Report. Catalogs live under
+ * <File>, and the relationship graph refers to occurrences by name. */
+
+use roxmltree::Node;
+
+use crate::{
+    data_source::{DataSource, DataSourceType},
+    file::File,
+    layout::Layout,
+    metadata::Metadata,
+    reference::{TableOccurrenceReference, TableReference},
+    schema::{relationgraph::{graph::RelationGraph, relation::RelationCriteria, table_occurrence::TableOccurrence}, table::Table, Schema},
+    scripting::script::Script,
+};
+
+use super::{
+    add_relation, attribute, child, children, descendants, expect_child, field::parse_field,
+    grouped, id_attribute, local_occurrence_id, local_table_id, parse_comparison, parse_relation_options,
+    step::{parse_steps, StepContext}, Error, Result,
+};
+
+pub(crate) fn parse(root: Node) -> Result<File> {
+    let file = expect_child(root, "File")?;
+    let name = attribute(file, "name")?.to_string();
+
+    let tables = match child(file, "BaseTableCatalog") {
+        Some(catalog) => parse_tables(catalog)?,
+        None => vec![],
+    };
+
+    let mut occurrences = vec![];
+    if let Some(graph) = child(file, "RelationshipGraph") {
+        if let Some(list) = child(graph, "TableList") {
+            occurrences = parse_occurrences(list)?;
+        }
+        if let Some(list) = child(graph, "RelationshipList") {
+            parse_relationships(list, &mut occurrences)?;
+        }
+    }
+
+    let layouts = match child(file, "LayoutCatalog") {
+        Some(catalog) => parse_layouts(catalog, &occurrences)?,
+        None => vec![],
+    };
+
+    let data_sources = match child(file, "ExternalDataSourcesCatalog") {
+        Some(catalog) => parse_data_sources(catalog)?,
+        None => vec![],
+    };
+
+    let ctx = StepContext {
+        file_name: &name,
+        occurrences: &occurrences,
+    };
+    let scripts = match child(file, "ScriptCatalog") {
+        Some(catalog) => parse_scripts(catalog, &ctx)?,
+        None => vec![],
+    };
+
+    Ok(File {
+        name,
+        schema: Schema {
+            tables,
+            relation_graph: RelationGraph {
+                nodes: occurrences,
+            },
+        },
+        layouts,
+        data_sources,
+        scripts,
+        tests: vec![],
+        triggers: vec![],
+        working_dir: String::new(),
+    })
+}
+
+fn parse_tables(catalog: Node) -> Result<Vec<Table>> {
+    let mut result = vec![];
+    for table in children(catalog, "BaseTable") {
+        let mut tmp = Table::new(local_table_id(id_attribute(table, "id")?));
+        tmp.name = attribute(table, "name")?.to_string();
+        tmp.comment = child(table, "Comment")
+            .and_then(|c| c.text())
+            .unwrap_or_default()
+            .to_string();
+
+        if let Some(fields) = child(table, "FieldCatalog") {
+            for field in children(fields, "Field") {
+                let field = parse_field(field)?;
+                tmp.fields.insert(field.id, field);
+            }
+        }
+        result.push(tmp);
+    }
+    Ok(result)
+}
+
+fn parse_occurrences(list: Node) -> Result<Vec<TableOccurrence>> {
+    let mut result = vec![];
+    for occurrence in children(list, "Table") {
+        result.push(TableOccurrence {
+            id: local_occurrence_id(id_attribute(occurrence, "id")?),
+            name: attribute(occurrence, "name")?.to_string(),
+            base: TableReference {
+                data_source: 0,
+                table_id: local_table_id(id_attribute(occurrence, "baseTableId")?),
+            },
+            relations: vec![],
+        });
+    }
+    Ok(result)
+}
+
+fn occurrence_by_name(occurrences: &[TableOccurrence], name: &str) -> Result<u32> {
+    occurrences.iter()
+        .find(|o| o.name == name)
+        .map(|o| o.id)
+        .ok_or_else(|| Error::UnresolvedReference {
+            construct: String::from("table occurrence"),
+            name: name.to_string(),
+        })
+}
+
+fn parse_relationships(list: Node, occurrences: &mut [TableOccurrence]) -> Result<()> {
+    for relationship in children(list, "Relationship") {
+        let left_side = expect_child(relationship, "LeftTable")?;
+        let right_side = expect_child(relationship, "RightTable")?;
+        let left = (occurrence_by_name(occurrences, attribute(left_side, "name")?)?, parse_relation_options(left_side, "Field")?);
+        let right = (occurrence_by_name(occurrences, attribute(right_side, "name")?)?, parse_relation_options(right_side, "Field")?);
+
+        let mut criteria = vec![];
+        for predicate in descendants(expect_child(relationship, "JoinPredicateList")?, "JoinPredicate") {
+            let left_field = expect_child(expect_child(predicate, "LeftField")?, "Field")?;
+            let right_field = expect_child(expect_child(predicate, "RightField")?, "Field")?;
+            criteria.push(RelationCriteria {
+                field_self: id_attribute(left_field, "id")?,
+                field_other: id_attribute(right_field, "id")?,
+                comparison: parse_comparison(predicate)?,
+            });
+        }
+
+        add_relation(occurrences, id_attribute(relationship, "id")?, left, right, criteria)?;
+    }
+    Ok(())
+}
+
+fn parse_layouts(catalog: Node, occurrences: &[TableOccurrence]) -> Result<Vec<Layout>> {
+    let mut result = vec![];
+    for layout in grouped(catalog, "Layout") {
+        let occurrence = expect_child(layout, "Table")?;
+        result.push(Layout {
+            id: id_attribute(layout, "id")?,
+            name: attribute(layout, "name")?.to_string(),
+            occurrence: TableOccurrenceReference {
+                data_source: 0,
+                table_occurrence_id: occurrence_by_name(occurrences, attribute(occurrence, "name")?)?,
+            },
+            triggers: vec![],
+        });
+    }
+    Ok(result)
+}
+
+fn parse_data_sources(catalog: Node) -> Result<Vec<DataSource>> {
+    let mut result = vec![];
+    for source in children(catalog, "FileReference") {
+        let paths = source.attribute("pathList")
+            .unwrap_or_default()
+            .lines()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.strip_prefix("file:").unwrap_or(p).to_string())
+            .collect();
+
+        result.push(DataSource {
+            id: id_attribute(source, "id")?,
+            name: attribute(source, "name")?.to_string(),
+            dstype: DataSourceType::FileMaker,
+            paths,
+        });
+    }
+    Ok(result)
+}
+
+fn parse_scripts(catalog: Node, ctx: &StepContext) -> Result<Vec<Script>> {
+    let mut result = vec![];
+    for script in grouped(catalog, "Script") {
+        let instructions = match child(script, "StepList") {
+            Some(steps) => parse_steps(steps, ctx)?,
+            None => vec![],
+        };
+
+        result.push(Script {
+            id: id_attribute(script, "id")?,
+            name: attribute(script, "name")?.to_string(),
+            args: vec![],
+            instructions,
+            metadata: Metadata {
+                created_by: String::new(),
+                modified_by: String::new(),
+                modified_at: None,
+            },
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,41 @@
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Fs(std::io::Error),
+    Xml(roxmltree::Error),
+    UnknownFormat(String),
+    MissingElement { parent: String, element: String },
+    MissingAttribute { element: String, attribute: String },
+    InvalidValue { element: String, attribute: String, value: String },
+    UnresolvedReference { construct: String, name: String },
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fs(e) => write!(f, "{}", e),
+            Self::Xml(e) => write!(f, "Malformed XML: {}", e),
+            Self::UnknownFormat(root) => write!(f, "Unrecognised XML document with root element <{}>.", root),
+            Self::MissingElement { parent, element } => write!(f, "<{}> is missing child element <{}>.", parent, element),
+            Self::MissingAttribute { element, attribute } => write!(f, "<{}> is missing attribute \"{}\".", element, attribute),
+            Self::InvalidValue { element, attribute, value } => write!(f, "Invalid value \"{}\" for {}.{}.", value, element, attribute),
+            Self::UnresolvedReference { construct, name } => write!(f, "Unable to resolve {} \"{}\".", construct, name),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Fs(value)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(value: roxmltree::Error) -> Self {
+        Self::Xml(value)
+    }
+}
+
+impl std::error::Error for Error {}
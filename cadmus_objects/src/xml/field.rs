@@ -0,0 +1,251 @@
+/* Field definitions are written the same way in the DDR and on the clipboard:
+ * <Field id="1" dataType="Text" fieldType="Normal" name="PrimaryKey">
+ *   <AutoEnter .../> <Validation .../> <Storage .../>
+ * </Field>
+ * Lookups are not written yet, they are read back as no auto-entry. */
+
+use roxmltree::Node;
+
+use crate::{
+    calculation::Calculation,
+    file::File,
+    schema::field::{AutoEntry, AutoEntryDataPresets, AutoEntryType, DataType, Field, SerialTrigger, Validation, ValidationTrigger, ValidationType},
+};
+
+use super::{attribute, bool_attribute, cdata, child, escape, id_attribute, invalid_value, parse_datatype, xml_bool, Result};
+
+pub(crate) fn parse_field(field: Node) -> Result<Field> {
+    let mut result = Field::new(id_attribute(field, "id")?, attribute(field, "name")?.to_string())
+        .datatype(parse_datatype(field, "dataType")?)
+        .comment(child(field, "Comment")
+            .and_then(|c| c.text())
+            .unwrap_or_default()
+            .to_string());
+
+    if let Some(storage) = child(field, "Storage") {
+        result.global = bool_attribute(storage, "global")?;
+        result = result.repetitions(storage.attribute("maxRepetition")
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(1));
+    }
+
+    if let Some(autoentry) = child(field, "AutoEnter") {
+        result.autoentry = parse_autoentry(autoentry)?;
+    }
+
+    if let Some(validation) = child(field, "Validation") {
+        result = result.validation(parse_validation(validation)?);
+    }
+    Ok(result)
+}
+
+fn calculation(node: Node) -> Calculation {
+    match child(node, "Calculation") {
+        Some(calc) => Calculation::from_text(calc.text().unwrap_or_default().trim()),
+        None => Calculation(vec![]),
+    }
+}
+
+fn parse_preset(node: Node, value: &str) -> Result<AutoEntryDataPresets> {
+    match value {
+        "Date" => Ok(AutoEntryDataPresets::Date),
+        "Time" => Ok(AutoEntryDataPresets::Time),
+        "Timestamp" => Ok(AutoEntryDataPresets::Timestamp),
+        "Name" => Ok(AutoEntryDataPresets::Name),
+        "AccountName" => Ok(AutoEntryDataPresets::AccountName),
+        _ => Err(invalid_value(node, "value", value)),
+    }
+}
+
+fn preset_name(preset: &AutoEntryDataPresets) -> &'static str {
+    match preset {
+        AutoEntryDataPresets::Date => "Date",
+        AutoEntryDataPresets::Time => "Time",
+        AutoEntryDataPresets::Timestamp => "Timestamp",
+        AutoEntryDataPresets::Name => "Name",
+        AutoEntryDataPresets::AccountName => "AccountName",
+    }
+}
+
+fn parse_autoentry(autoentry: Node) -> Result<AutoEntry> {
+    let definition = if let Some(serial) = child(autoentry, "Serial") {
+        let number = |name: &str| -> Result<usize> {
+            let value = attribute(serial, name)?;
+            value.parse().map_err(|_| invalid_value(serial, name, value))
+        };
+        AutoEntryType::Serial {
+            next: number("nextValue")?,
+            increment: number("increment")?,
+            trigger: match attribute(serial, "generate")? {
+                "OnCommit" => SerialTrigger::OnCommit,
+                _ => SerialTrigger::OnCreation,
+            },
+        }
+    } else if bool_attribute(autoentry, "calculation")? {
+        AutoEntryType::Calculation {
+            code: calculation(autoentry),
+            noreplace: !bool_attribute(autoentry, "overwriteExistingValue")?,
+        }
+    } else if bool_attribute(autoentry, "constant")? {
+        AutoEntryType::Data(child(autoentry, "ConstantData")
+            .and_then(|c| c.text())
+            .unwrap_or_default()
+            .to_string())
+    } else {
+        match autoentry.attribute("value") {
+            None => AutoEntryType::NA,
+            Some("PreviousRecord") => AutoEntryType::LastVisited,
+            Some(value) => match (value.strip_prefix("Creation"), value.strip_prefix("Modification")) {
+                (Some(preset), _) => AutoEntryType::Creation(parse_preset(autoentry, preset)?),
+                (_, Some(preset)) => AutoEntryType::Modification(parse_preset(autoentry, preset)?),
+                _ => return Err(invalid_value(autoentry, "value", value)),
+            },
+        }
+    };
+
+    Ok(AutoEntry {
+        nomodify: !bool_attribute(autoentry, "allowEditing")?,
+        definition,
+    })
+}
+
+fn check_enabled(validation: Node, name: &str) -> Result<bool> {
+    match child(validation, name) {
+        Some(check) => bool_attribute(check, "value"),
+        None => Ok(false),
+    }
+}
+
+pub(crate) fn parse_validation(validation: Node) -> Result<Validation> {
+    let mut checks = vec![];
+    if check_enabled(validation, "NotEmpty")? {
+        checks.push(ValidationType::NotEmpty);
+    }
+    if check_enabled(validation, "Unique")? {
+        checks.push(ValidationType::Unique);
+    }
+    if check_enabled(validation, "Existing")? {
+        checks.push(ValidationType::Required);
+    }
+    if bool_attribute(validation, "valuelist")? {
+        if let Some(list) = child(validation, "ValueList") {
+            checks.push(ValidationType::MemberOf(attribute(list, "name")?.to_string()));
+        }
+    }
+    if let Some(range) = child(validation, "Range") {
+        let bound = |name: &str| -> Result<usize> {
+            let value = attribute(range, name)?;
+            value.parse().map_err(|_| invalid_value(range, name, value))
+        };
+        checks.push(ValidationType::Range { start: bound("from")?, end: bound("to")? });
+    }
+    if bool_attribute(validation, "calculation")? {
+        checks.push(ValidationType::Calculation(calculation(validation)));
+    }
+    if bool_attribute(validation, "maxLength")? {
+        if let Some(length) = child(validation, "MaxDataLength") {
+            let value = attribute(length, "value")?;
+            checks.push(ValidationType::MaxChars(value.parse().map_err(|_| invalid_value(length, "value", value))?));
+        }
+    }
+
+    Ok(Validation {
+        trigger: match validation.attribute("type") {
+            Some("OnlyDuringDataEntry") => ValidationTrigger::OnEntry,
+            _ => ValidationTrigger::OnCommit,
+        },
+        user_override: !check_enabled(validation, "StrictValidation")?,
+        checks,
+        message: child(validation, "ErrorMessage")
+            .and_then(|m| m.text())
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+fn datatype_name(dtype: &DataType) -> &'static str {
+    match dtype {
+        DataType::Text => "Text",
+        DataType::Number => "Number",
+        DataType::Date => "Date",
+        DataType::Time => "Time",
+        DataType::Timestamp => "Timestamp",
+        DataType::Container => "Binary",
+    }
+}
+
+fn write_autoentry(autoentry: &AutoEntry, file: &File, buffer: &mut String) {
+    let (constant, calculation) = match autoentry.definition {
+        AutoEntryType::Data(..) => (true, false),
+        AutoEntryType::Calculation { .. } => (false, true),
+        _ => (false, false),
+    };
+    buffer.push_str(&format!("<AutoEnter allowEditing=\"{}\" constant=\"{}\" furigana=\"False\" lookup=\"False\" calculation=\"{}\"",
+        xml_bool(!autoentry.nomodify), xml_bool(constant), xml_bool(calculation)));
+
+    match &autoentry.definition {
+        AutoEntryType::Creation(preset) => buffer.push_str(&format!(" value=\"Creation{}\">", preset_name(preset))),
+        AutoEntryType::Modification(preset) => buffer.push_str(&format!(" value=\"Modification{}\">", preset_name(preset))),
+        AutoEntryType::LastVisited => buffer.push_str(" value=\"PreviousRecord\">"),
+        AutoEntryType::Serial { next, increment, trigger } => {
+            let generate = match trigger {
+                SerialTrigger::OnCreation => "OnCreation",
+                SerialTrigger::OnCommit => "OnCommit",
+            };
+            buffer.push_str(&format!(">\n<Serial generate=\"{}\" nextValue=\"{}\" increment=\"{}\"></Serial>\n", generate, next, increment));
+        }
+        AutoEntryType::Calculation { code, noreplace } => {
+            buffer.push_str(&format!(" overwriteExistingValue=\"{}\">\n<Calculation>{}</Calculation>\n",
+                xml_bool(!noreplace), cdata(&code.to_text_in(file))));
+        }
+        AutoEntryType::Data(data) => {
+            buffer.push_str(&format!(">\n<ConstantData>{}</ConstantData>\n", escape(data)));
+        }
+        AutoEntryType::NA | AutoEntryType::Lookup { .. } => buffer.push('>'),
+    }
+    buffer.push_str("</AutoEnter>\n");
+}
+
+fn write_validation(validation: &Validation, file: &File, buffer: &mut String) {
+    let has = |f: fn(&ValidationType) -> bool| validation.checks.iter().any(f);
+    let trigger = match validation.trigger {
+        ValidationTrigger::OnEntry => "OnlyDuringDataEntry",
+        ValidationTrigger::OnCommit => "Always",
+    };
+    buffer.push_str(&format!("<Validation message=\"{}\" maxLength=\"{}\" valuelist=\"{}\" calculation=\"{}\" alwaysValidateCalculation=\"False\" type=\"{}\">\n",
+        xml_bool(!validation.message.is_empty()),
+        xml_bool(has(|c| matches!(c, ValidationType::MaxChars(..)))),
+        xml_bool(has(|c| matches!(c, ValidationType::MemberOf(..)))),
+        xml_bool(has(|c| matches!(c, ValidationType::Calculation(..)))),
+        trigger));
+
+    buffer.push_str(&format!("<NotEmpty value=\"{}\"></NotEmpty>\n", xml_bool(has(|c| *c == ValidationType::NotEmpty))));
+    buffer.push_str(&format!("<Unique value=\"{}\"></Unique>\n", xml_bool(has(|c| *c == ValidationType::Unique))));
+    buffer.push_str(&format!("<Existing value=\"{}\"></Existing>\n", xml_bool(has(|c| *c == ValidationType::Required))));
+    buffer.push_str(&format!("<StrictValidation value=\"{}\"></StrictValidation>\n", xml_bool(!validation.user_override)));
+
+    for check in &validation.checks {
+        match check {
+            ValidationType::MemberOf(list) => buffer.push_str(&format!("<ValueList name=\"{}\"></ValueList>\n", escape(list))),
+            ValidationType::Range { start, end } => buffer.push_str(&format!("<Range from=\"{}\" to=\"{}\"></Range>\n", start, end)),
+            ValidationType::Calculation(code) => buffer.push_str(&format!("<Calculation>{}</Calculation>\n", cdata(&code.to_text_in(file)))),
+            ValidationType::MaxChars(length) => buffer.push_str(&format!("<MaxDataLength value=\"{}\"></MaxDataLength>\n", length)),
+            ValidationType::NotEmpty | ValidationType::Unique | ValidationType::Required => {}
+        }
+    }
+    if !validation.message.is_empty() {
+        buffer.push_str(&format!("<ErrorMessage>{}</ErrorMessage>\n", escape(&validation.message)));
+    }
+    buffer.push_str("</Validation>\n");
+}
+
+pub(crate) fn write_field(field: &Field, file: &File, buffer: &mut String) {
+    buffer.push_str(&format!("<Field id=\"{}\" dataType=\"{}\" fieldType=\"Normal\" name=\"{}\">\n",
+        field.id, datatype_name(&field.dtype), escape(&field.name)));
+    buffer.push_str(&format!("<Comment>{}</Comment>\n", escape(&field.comment)));
+    write_autoentry(&field.autoentry, file, buffer);
+    write_validation(&field.validation, file, buffer);
+    buffer.push_str(&format!("<Storage autoIndex=\"True\" index=\"None\" indexLanguage=\"English\" global=\"{}\" maxRepetition=\"{}\"></Storage>\n",
+        xml_bool(field.global), field.repetitions));
+    buffer.push_str("</Field>\n");
+}
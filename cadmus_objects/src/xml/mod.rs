@@ -0,0 +1,344 @@
+/* Importers for the XML FileMaker produces about a solution. Two formats are
+ * understood, both producing the same File model as the hbam reader:
+ * - FMSaveAsXML: output of "Save a Copy as XML".
+ * - FMPReport: output of the Database Design Report (DDR).
+ *
+ * Ids are normalised to match the ids decoded from the binary format.
+ * Tables are numbered from 129 in XML, and table occurrences additionally
+ * carry a 0x104000 prefix, e.g table occurrence 1065089 is occurrence 1. */
+
+mod ddr;
+mod error;
+mod field;
+mod save_as_xml;
+pub mod snippet;
+pub(crate) mod step;
+
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+
+use crate::{file::File, schema::{field::DataType, relationgraph::{relation::{Relation, RelationComparison, RelationCriteria, RelationOptions, RelationSort}, table_occurrence::TableOccurrence}}};
+
+pub use error::{Error, Result};
+
+const OCCURRENCE_ID_PREFIX: u32 = 0x104000;
+
+pub fn file_from_xml(text: &str) -> Result<File> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    match root.tag_name().name() {
+        "FMSaveAsXML" => save_as_xml::parse(root),
+        "FMPReport" => ddr::parse(root),
+        other => Err(Error::UnknownFormat(other.to_string())),
+    }
+}
+
+pub fn file_from_xml_path(path: &Path) -> Result<File> {
+    let text = std::fs::read_to_string(path)?;
+    let mut file = file_from_xml(&text)?;
+    file.working_dir = path.parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(file)
+}
+
+pub(crate) fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+pub(crate) fn children<'a, 'i>(node: Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+pub(crate) fn descendants<'a, 'i>(node: Node<'a, 'i>, name: &'a str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.descendants().filter(move |n| n.has_tag_name(name))
+}
+
+/* Layouts and scripts may be nested inside any number of folders (<Group>). */
+pub(crate) fn grouped<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Vec<Node<'a, 'i>> {
+    let mut result = vec![];
+    for n in node.children() {
+        if n.has_tag_name(name) {
+            result.push(n);
+        } else if n.has_tag_name("Group") {
+            result.extend(grouped(n, name));
+        }
+    }
+    result
+}
+
+pub(crate) fn expect_child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Result<Node<'a, 'i>> {
+    child(node, name).ok_or_else(|| Error::MissingElement {
+        parent: node.tag_name().name().to_string(),
+        element: name.to_string(),
+    })
+}
+
+pub(crate) fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| Error::MissingAttribute {
+        element: node.tag_name().name().to_string(),
+        attribute: name.to_string(),
+    })
+}
+
+pub(crate) fn invalid_value(node: Node, name: &str, value: &str) -> Error {
+    Error::InvalidValue {
+        element: node.tag_name().name().to_string(),
+        attribute: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+pub(crate) fn id_attribute(node: Node, name: &str) -> Result<u32> {
+    let value = attribute(node, name)?;
+    value.parse::<u32>().map_err(|_| invalid_value(node, name, value))
+}
+
+pub(crate) fn bool_attribute(node: Node, name: &str) -> Result<bool> {
+    match node.attribute(name) {
+        None | Some("False") => Ok(false),
+        Some("True") => Ok(true),
+        Some(other) => Err(invalid_value(node, name, other)),
+    }
+}
+
+pub(crate) fn xml_bool(value: bool) -> &'static str {
+    if value { "True" } else { "False" }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/* Calculations are written verbatim, a literal "]]>" has to be split across
+ * two sections. */
+pub(crate) fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+pub(crate) fn local_table_id(id: u32) -> u32 {
+    if id >= 128 { id - 128 } else { id }
+}
+
+pub(crate) fn local_occurrence_id(id: u32) -> u32 {
+    if id >= OCCURRENCE_ID_PREFIX {
+        local_table_id(id - OCCURRENCE_ID_PREFIX)
+    } else {
+        id
+    }
+}
+
+pub(crate) fn parse_datatype(node: Node, name: &str) -> Result<DataType> {
+    match attribute(node, name)? {
+        "Text" => Ok(DataType::Text),
+        "Number" => Ok(DataType::Number),
+        "Date" => Ok(DataType::Date),
+        "Time" => Ok(DataType::Time),
+        "Timestamp" => Ok(DataType::Timestamp),
+        "Binary" | "Container" => Ok(DataType::Container),
+        other => Err(invalid_value(node, name, other)),
+    }
+}
+
+pub(crate) fn parse_comparison(node: Node) -> Result<RelationComparison> {
+    match attribute(node, "type")? {
+        "Equal" => Ok(RelationComparison::Equal),
+        "NotEqual" => Ok(RelationComparison::NotEqual),
+        "GreaterThan" => Ok(RelationComparison::Greater),
+        "GreaterThanOrEqual" => Ok(RelationComparison::GreaterEqual),
+        "LessThan" => Ok(RelationComparison::Less),
+        "LessThanOrEqual" => Ok(RelationComparison::LessEqual),
+        "CartesianProduct" => Ok(RelationComparison::Cartesian),
+        other => Err(invalid_value(node, "type", other)),
+    }
+}
+
+/* The options on each side of a relationship in XML describe that side's
+ * records: whether they can be created and deleted through the relationship,
+ * and their sort order as a <SortList> of <Sort> entries. Fields are
+ * <FieldReference> in Save a Copy as XML and <Field> in the DDR. No sample
+ * export sorts a relationship, so the <SortList> shape is unconfirmed. */
+pub(crate) fn parse_relation_options(side: Node, field_tag: &str) -> Result<RelationOptions> {
+    let mut sort = vec![];
+    if let Some(list) = child(side, "SortList") {
+        for entry in descendants(list, "Sort") {
+            let field = entry.descendants()
+                .find(|n| n.has_tag_name(field_tag))
+                .ok_or_else(|| Error::MissingElement {
+                    parent: String::from("Sort"),
+                    element: field_tag.to_string(),
+                })?;
+            sort.push(RelationSort {
+                field: id_attribute(field, "id")?,
+                descending: entry.attribute("type") == Some("Descending"),
+            });
+        }
+    }
+
+    Ok(RelationOptions {
+        allow_creation: bool_attribute(side, "cascadeCreate")?,
+        delete_related: bool_attribute(side, "cascadeDelete")?,
+        sort,
+    })
+}
+
+/* Relationships are stored once in XML, but on both occurrences in the model,
+ * mirroring how the hbam reader builds the relation graph. The relation kept
+ * on one occurrence holds the options of the other. */
+pub(crate) fn add_relation(occurrences: &mut [TableOccurrence], id: u32, left: (u32, RelationOptions), right: (u32, RelationOptions), criteria: Vec<RelationCriteria>) -> Result<()> {
+    let mirrored = criteria.iter()
+        .map(|c| RelationCriteria {
+            field_self: c.field_other,
+            field_other: c.field_self,
+            comparison: c.comparison.mirrored(),
+        })
+        .collect();
+
+    let ((left, left_options), (right, right_options)) = (left, right);
+    for (occurrence, other, criteria, options) in [(left, right, criteria, right_options), (right, left, mirrored, left_options)] {
+        occurrences.iter_mut()
+            .find(|o| o.id == occurrence)
+            .ok_or_else(|| Error::UnresolvedReference {
+                construct: String::from("table occurrence"),
+                name: occurrence.to_string(),
+            })?
+            .relations.push(Relation {
+                id,
+                other_occurrence: other,
+                criteria,
+                options,
+            });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{reference::*, schema::{field::{DataType, ValidationType}, relationgraph::relation::*}, scripting::{arguments::*, instructions::Instruction}, calculation::Calculation};
+    use super::{file_from_xml, file_from_xml_path, Error};
+
+    fn check_relation_file(path: &str) {
+        let file = file_from_xml_path(Path::new(path)).unwrap();
+        assert_eq!(file.name, "relation.fmp12");
+        assert_eq!(file.working_dir, "test_data/xml_files");
+
+        let tables = &file.schema.tables;
+        assert_eq!(tables.len(), 2);
+        assert_eq!((tables[0].id, tables[0].name.as_str()), (1, "blank"));
+        assert_eq!((tables[1].id, tables[1].name.as_str()), (2, "other_table"));
+        assert_eq!(tables[1].comment, "Looked up from blank");
+        assert_eq!(tables[0].fields.len(), 6);
+        assert_eq!(tables[1].fields.len(), 5);
+
+        let pk = &tables[0].fields[&1];
+        assert_eq!(pk.name, "PrimaryKey");
+        assert_eq!(pk.validation.checks, vec![ValidationType::NotEmpty, ValidationType::Unique]);
+        assert_eq!(tables[0].fields[&2].dtype, DataType::Timestamp);
+        assert_eq!(tables[0].fields[&6].repetitions, 3);
+
+        let nodes = &file.schema.relation_graph.nodes;
+        assert_eq!(nodes.iter().map(|n| (n.id, n.name.as_str(), n.base.table_id)).collect::<Vec<_>>(),
+            vec![(1, "blank", 1), (2, "blank 2", 1), (3, "other_table", 2), (4, "second_table", 2)]);
+        assert_eq!(nodes[0].relations, vec![
+            Relation { id: 1, other_occurrence: 2, criteria: vec![
+                RelationCriteria { field_self: 1, field_other: 1, comparison: RelationComparison::Equal }
+            ], options: RelationOptions::default() },
+            Relation { id: 3, other_occurrence: 3, criteria: vec![
+                RelationCriteria { field_self: 6, field_other: 1, comparison: RelationComparison::Equal }
+            ], options: RelationOptions {
+                allow_creation: true,
+                delete_related: true,
+                sort: vec![RelationSort { field: 2, descending: true }],
+            }},
+        ]);
+        /* The other side keeps its own, unset, options. */
+        assert_eq!(nodes[2].relations[0].options, RelationOptions::default());
+        assert_eq!(nodes[3].relations, vec![
+            Relation { id: 4, other_occurrence: 3, criteria: vec![
+                RelationCriteria { field_self: 2, field_other: 1, comparison: RelationComparison::GreaterEqual }
+            ], options: RelationOptions::default() },
+        ]);
+
+        assert_eq!(file.layouts.iter().map(|l| (l.id, l.name.as_str(), l.occurrence.table_occurrence_id)).collect::<Vec<_>>(),
+            vec![(1, "blank", 1), (2, "other_table", 3)]);
+
+        assert_eq!(file.data_sources.len(), 1);
+        assert_eq!(file.data_sources[0].paths, vec![String::from("mixed")]);
+
+        assert_eq!(file.scripts.len(), 1);
+        let steps = file.scripts[0].instructions.iter().map(|s| s.instruction.clone()).collect::<Vec<_>>();
+        assert_eq!(steps, vec![
+            Instruction::SetVariable {
+                name: String::from("$x"),
+                value: Calculation::from_text("1 + 2"),
+                repetition: Calculation::from_text("1"),
+            },
+            Instruction::GoToLayout {
+                layout: LayoutSelection::FromList(LayoutReference { file_name: String::from("relation.fmp12"), layout_id: 2 }),
+                animation: LayoutAnimation::None,
+            },
+            Instruction::SetField {
+                field: FieldReference { data_source: 0, table_occurrence_id: 3, field_id: 3 },
+                value: Calculation::from_text("$x"),
+                repetition: Calculation(vec![]),
+            },
+            Instruction::PerformScript {
+                script: ScriptSelection::FromList(ScriptReference { data_source: 0, script_id: 1 }),
+                args: Calculation::from_text("$x"),
+            },
+        ]);
+    }
+
+    #[test]
+    fn save_as_xml_test() {
+        check_relation_file("test_data/xml_files/relation_save_as.xml");
+    }
+
+    #[test]
+    fn ddr_test() {
+        check_relation_file("test_data/xml_files/relation_ddr.xml");
+    }
+
+    /* relation_schema.xml is not a FileMaker export: it was written to hold
+     * what the hbam reader decodes from relation.fmp12. Replace it with a
+     * real Save a Copy as XML of that file once one is available. */
+    #[test]
+    fn save_as_xml_matches_hbam_test() {
+        let xml = file_from_xml_path(Path::new("test_data/xml_files/relation_schema.xml")).unwrap();
+        let mut cache = common::hbam2::page_store::PageStore::new();
+        let hbam = common::hbam2::get_schema_contents(&mut cache, "../common/test_data/fmp_files/relation.fmp12");
+
+        let xml_tables = xml.schema.tables.iter()
+            .map(|table| (table.id, table.name.clone(), table.comment.clone(), table.fields.values()
+                .map(|field| (field.id, field.name.clone(), format!("{:?}", field.dtype), field.global, field.repetitions, field.comment.clone()))
+                .collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let hbam_tables = hbam.schema.tables.iter()
+            .map(|table| (table.id, table.name.clone(), table.comment.clone(), table.fields.values()
+                .map(|field| (field.id, field.name.clone(), format!("{:?}", field.dtype), field.global, field.repetitions, field.comment.clone()))
+                .collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(xml_tables, hbam_tables);
+
+        assert_eq!(serde_json::to_value(&xml.schema.relation_graph).unwrap(),
+            serde_json::to_value(&hbam.schema.relation_graph).unwrap());
+
+        assert_eq!(xml.layouts.iter().map(|l| (l.id, l.name.clone(), l.occurrence.table_occurrence_id)).collect::<Vec<_>>(),
+            hbam.layouts.iter().map(|l| (l.id, l.name.clone(), l.occurrence.table_occurrence_id)).collect::<Vec<_>>());
+        assert_eq!(xml.data_sources.iter().map(|d| (d.id, d.name.clone(), d.paths.clone())).collect::<Vec<_>>(),
+            hbam.data_sources.iter().map(|d| (d.id, d.name.clone(), d.paths.clone())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unknown_format_test() {
+        assert!(matches!(file_from_xml("<fmxmlsnippet type=\"FMObjectList\"></fmxmlsnippet>"),
+            Err(Error::UnknownFormat(root)) if root == "fmxmlsnippet"));
+        assert!(matches!(file_from_xml("<FMPReport>"), Err(Error::Xml(..))));
+    }
+}
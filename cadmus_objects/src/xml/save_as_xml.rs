@@ -0,0 +1,253 @@
+/* FMSaveAsXML, as written by "Save a Copy as XML". All catalogs live under
+ * <Structure><AddAction>, and objects refer to each other by id. */
+
+use std::collections::BTreeMap;
+
+use roxmltree::Node;
+
+use crate::{
+    data_source::{DataSource, DataSourceType},
+    file::File,
+    layout::Layout,
+    metadata::Metadata,
+    reference::{TableOccurrenceReference, TableReference},
+    schema::{field::{Field, Validation, ValidationTrigger, ValidationType}, relationgraph::{graph::RelationGraph, relation::RelationCriteria, table_occurrence::TableOccurrence}, table::Table, Schema},
+    scripting::script::Script,
+};
+
+use super::{
+    add_relation, attribute, bool_attribute, child, children, descendants, expect_child,
+    grouped, id_attribute, local_occurrence_id, local_table_id, parse_comparison, parse_datatype,
+    parse_relation_options,
+    step::{parse_steps, StepContext}, Result,
+};
+
+pub(crate) fn parse(root: Node) -> Result<File> {
+    let name = attribute(root, "File")?.to_string();
+    let actions = expect_child(expect_child(root, "Structure")?, "AddAction")?;
+
+    let mut tables = match child(actions, "BaseTableCatalog") {
+        Some(catalog) => parse_tables(catalog)?,
+        None => vec![],
+    };
+    if let Some(catalog) = child(actions, "FieldsForTables") {
+        parse_fields(catalog, &mut tables)?;
+    }
+
+    let mut occurrences = match child(actions, "TableOccurrenceCatalog") {
+        Some(catalog) => parse_occurrences(catalog)?,
+        None => vec![],
+    };
+    if let Some(catalog) = child(actions, "RelationshipCatalog") {
+        parse_relationships(catalog, &mut occurrences)?;
+    }
+
+    let layouts = match child(actions, "LayoutCatalog") {
+        Some(catalog) => parse_layouts(catalog)?,
+        None => vec![],
+    };
+
+    let data_sources = match child(actions, "ExternalDataSourceCatalog") {
+        Some(catalog) => parse_data_sources(catalog)?,
+        None => vec![],
+    };
+
+    let ctx = StepContext {
+        file_name: &name,
+        occurrences: &occurrences,
+    };
+    let scripts = match child(actions, "ScriptCatalog") {
+        Some(catalog) => parse_scripts(catalog, child(actions, "StepsForScripts"), &ctx)?,
+        None => vec![],
+    };
+
+    Ok(File {
+        name,
+        schema: Schema {
+            tables,
+            relation_graph: RelationGraph {
+                nodes: occurrences,
+            },
+        },
+        layouts,
+        data_sources,
+        scripts,
+        tests: vec![],
+        triggers: vec![],
+        working_dir: String::new(),
+    })
+}
+
+fn parse_tables(catalog: Node) -> Result<Vec<Table>> {
+    let mut result = vec![];
+    for table in children(catalog, "BaseTable") {
+        let mut tmp = Table::new(local_table_id(id_attribute(table, "id")?));
+        tmp.name = attribute(table, "name")?.to_string();
+        tmp.comment = table.attribute("comment").unwrap_or_default().to_string();
+        result.push(tmp);
+    }
+    Ok(result)
+}
+
+fn parse_fields(catalog: Node, tables: &mut [Table]) -> Result<()> {
+    for field_catalog in children(catalog, "FieldCatalog") {
+        let table_id = local_table_id(id_attribute(expect_child(field_catalog, "BaseTableReference")?, "id")?);
+        let table = match tables.iter_mut().find(|t| t.id == table_id) {
+            Some(inner) => inner,
+            None => continue,
+        };
+
+        for field in descendants(field_catalog, "Field") {
+            let id_ = id_attribute(field, "id")?;
+            let mut tmp = Field::new(id_, attribute(field, "name")?.to_string())
+                .datatype(parse_datatype(field, "datatype")?)
+                .comment(field.attribute("comment").unwrap_or_default().to_string());
+
+            if let Some(storage) = child(field, "Storage") {
+                tmp.global = bool_attribute(storage, "global")?;
+                tmp = tmp.repetitions(storage.attribute("maxRepetitions")
+                    .and_then(|r| r.parse().ok())
+                    .unwrap_or(1));
+            }
+
+            if let Some(validation) = child(field, "Validation") {
+                tmp = tmp.validation(parse_validation(validation)?);
+            }
+            table.fields.insert(id_, tmp);
+        }
+    }
+    Ok(())
+}
+
+fn parse_validation(validation: Node) -> Result<Validation> {
+    let mut checks = vec![];
+    if bool_attribute(validation, "notEmpty")? {
+        checks.push(ValidationType::NotEmpty);
+    }
+    if bool_attribute(validation, "unique")? {
+        checks.push(ValidationType::Unique);
+    }
+    if bool_attribute(validation, "existing")? {
+        checks.push(ValidationType::Required);
+    }
+
+    Ok(Validation {
+        trigger: match validation.attribute("type") {
+            Some("OnlyDuringDataEntry") => ValidationTrigger::OnEntry,
+            _ => ValidationTrigger::OnCommit,
+        },
+        user_override: bool_attribute(validation, "allowOverride")?,
+        checks,
+        message: String::new(),
+    })
+}
+
+fn parse_occurrences(catalog: Node) -> Result<Vec<TableOccurrence>> {
+    let mut result = vec![];
+    for occurrence in children(catalog, "TableOccurrence") {
+        let source = expect_child(occurrence, "BaseTableSourceReference")?;
+        result.push(TableOccurrence {
+            id: local_occurrence_id(id_attribute(occurrence, "id")?),
+            name: attribute(occurrence, "name")?.to_string(),
+            base: TableReference {
+                data_source: 0,
+                table_id: local_table_id(id_attribute(expect_child(source, "BaseTableReference")?, "id")?),
+            },
+            relations: vec![],
+        });
+    }
+    Ok(result)
+}
+
+fn occurrence_reference(node: Node) -> Result<u32> {
+    Ok(local_occurrence_id(id_attribute(expect_child(node, "TableOccurrenceReference")?, "id")?))
+}
+
+fn parse_relationships(catalog: Node, occurrences: &mut [TableOccurrence]) -> Result<()> {
+    for relationship in children(catalog, "Relationship") {
+        let left_side = expect_child(relationship, "LeftTableOccurrence")?;
+        let right_side = expect_child(relationship, "RightTableOccurrence")?;
+        let left = (occurrence_reference(left_side)?, parse_relation_options(left_side, "FieldReference")?);
+        let right = (occurrence_reference(right_side)?, parse_relation_options(right_side, "FieldReference")?);
+
+        let mut criteria = vec![];
+        for predicate in descendants(expect_child(relationship, "JoinPredicateList")?, "JoinPredicate") {
+            let left_field = expect_child(expect_child(predicate, "LeftField")?, "FieldReference")?;
+            let right_field = expect_child(expect_child(predicate, "RightField")?, "FieldReference")?;
+            criteria.push(RelationCriteria {
+                field_self: id_attribute(left_field, "id")?,
+                field_other: id_attribute(right_field, "id")?,
+                comparison: parse_comparison(predicate)?,
+            });
+        }
+
+        add_relation(occurrences, id_attribute(relationship, "id")?, left, right, criteria)?;
+    }
+    Ok(())
+}
+
+fn parse_layouts(catalog: Node) -> Result<Vec<Layout>> {
+    let mut result = vec![];
+    for layout in grouped(catalog, "Layout") {
+        result.push(Layout {
+            id: id_attribute(layout, "id")?,
+            name: attribute(layout, "name")?.to_string(),
+            occurrence: TableOccurrenceReference {
+                data_source: 0,
+                table_occurrence_id: occurrence_reference(layout)?,
+            },
+            triggers: vec![],
+        });
+    }
+    Ok(result)
+}
+
+fn parse_data_sources(catalog: Node) -> Result<Vec<DataSource>> {
+    let mut result = vec![];
+    for source in children(catalog, "ExternalDataSource") {
+        let dstype = match source.attribute("type") {
+            Some("ODBC") => DataSourceType::ODBC,
+            _ => DataSourceType::FileMaker,
+        };
+        let paths = descendants(source, "PathList")
+            .flat_map(|list| list.text().unwrap_or_default().lines().map(|p| p.trim().to_string()).collect::<Vec<_>>())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.strip_prefix("file:").map(|s| s.to_string()).unwrap_or(p))
+            .collect();
+
+        result.push(DataSource {
+            id: id_attribute(source, "id")?,
+            name: attribute(source, "name")?.to_string(),
+            dstype,
+            paths,
+        });
+    }
+    Ok(result)
+}
+
+fn parse_scripts(catalog: Node, steps: Option<Node>, ctx: &StepContext) -> Result<Vec<Script>> {
+    let mut step_lists = BTreeMap::new();
+    if let Some(steps) = steps {
+        for script in children(steps, "Script") {
+            let id_ = id_attribute(expect_child(script, "ScriptReference")?, "id")?;
+            step_lists.insert(id_, parse_steps(expect_child(script, "ObjectList")?, ctx)?);
+        }
+    }
+
+    let mut result = vec![];
+    for script in grouped(catalog, "Script") {
+        let id_ = id_attribute(script, "id")?;
+        result.push(Script {
+            id: id_,
+            name: attribute(script, "name")?.to_string(),
+            args: vec![],
+            instructions: step_lists.remove(&id_).unwrap_or_default(),
+            metadata: Metadata {
+                created_by: String::new(),
+                modified_by: String::new(),
+                modified_at: None,
+            },
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,222 @@
+/* fmxmlsnippet is the format FileMaker Pro puts on the clipboard when copying
+ * objects out of Manage Database or the Script Workspace:
+ * <fmxmlsnippet type="FMObjectList"> ...objects... </fmxmlsnippet>
+ * Tables, fields, scripts and loose script steps are supported. Snippets do
+ * not name the file they came from, so references inside them are resolved
+ * by name against the file they are being pasted into. */
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    file::File,
+    metadata::Metadata,
+    schema::{field::Field, table::Table},
+    scripting::script::{Script, ScriptStep},
+};
+
+use super::{
+    attribute, children, escape, field::{parse_field, write_field}, id_attribute, local_table_id,
+    step::{parse_step, parse_steps, write_step, StepContext}, xml_bool, Error, Result,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnippetObject {
+    Table(Table),
+    Field(Field),
+    Script(Script),
+    Step(ScriptStep),
+}
+
+pub fn to_snippet(objects: &[SnippetObject], file: &File) -> String {
+    let mut buffer = String::from("<fmxmlsnippet type=\"FMObjectList\">\n");
+    for object in objects {
+        match object {
+            SnippetObject::Table(table) => write_table(table, file, &mut buffer),
+            SnippetObject::Field(field) => write_field(field, file, &mut buffer),
+            SnippetObject::Script(script) => write_script(script, file, &mut buffer),
+            SnippetObject::Step(step) => write_step(step, file, &mut buffer),
+        }
+    }
+    buffer.push_str("</fmxmlsnippet>\n");
+    buffer
+}
+
+pub fn from_snippet(text: &str, file: &File) -> Result<Vec<SnippetObject>> {
+    let document = Document::parse(text)?;
+    let root = document.root_element();
+    if !root.has_tag_name("fmxmlsnippet") {
+        return Err(Error::UnknownFormat(root.tag_name().name().to_string()));
+    }
+
+    let ctx = StepContext {
+        file_name: &file.name,
+        occurrences: &file.schema.relation_graph.nodes,
+    };
+
+    let mut result = vec![];
+    for node in root.children().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            "BaseTable" => result.push(SnippetObject::Table(parse_table(node)?)),
+            "Field" => result.push(SnippetObject::Field(parse_field(node)?)),
+            "Script" => result.push(SnippetObject::Script(parse_script(node, &ctx)?)),
+            "Step" => {
+                if let Some(instruction) = parse_step(node, &ctx)? {
+                    let id = result.iter().filter(|o| matches!(o, SnippetObject::Step(..))).count() as u32;
+                    result.push(SnippetObject::Step(ScriptStep { id, instruction }));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+fn write_table(table: &Table, file: &File, buffer: &mut String) {
+    buffer.push_str(&format!("<BaseTable id=\"{}\" name=\"{}\" comment=\"{}\">\n",
+        table.id + 128, escape(&table.name), escape(&table.comment)));
+    for field in table.fields.values() {
+        write_field(field, file, buffer);
+    }
+    buffer.push_str("</BaseTable>\n");
+}
+
+fn parse_table(node: Node) -> Result<Table> {
+    let mut result = Table::new(match node.attribute("id") {
+        Some(..) => local_table_id(id_attribute(node, "id")?),
+        None => 0,
+    });
+    result.name = attribute(node, "name")?.to_string();
+    result.comment = node.attribute("comment").unwrap_or_default().to_string();
+    for field in children(node, "Field") {
+        let field = parse_field(field)?;
+        result.fields.insert(field.id, field);
+    }
+    Ok(result)
+}
+
+fn write_script(script: &Script, file: &File, buffer: &mut String) {
+    buffer.push_str(&format!("<Script includeInMenu=\"{}\" runFullAccess=\"{}\" id=\"{}\" name=\"{}\">\n",
+        xml_bool(true), xml_bool(false), script.id, escape(&script.name)));
+    for step in &script.instructions {
+        write_step(step, file, buffer);
+    }
+    buffer.push_str("</Script>\n");
+}
+
+fn parse_script(node: Node, ctx: &StepContext) -> Result<Script> {
+    Ok(Script {
+        id: match node.attribute("id") {
+            Some(..) => id_attribute(node, "id")?,
+            None => 0,
+        },
+        name: attribute(node, "name")?.to_string(),
+        args: vec![],
+        instructions: parse_steps(node, ctx)?,
+        metadata: Metadata {
+            created_by: String::new(),
+            modified_by: String::new(),
+            modified_at: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        calculation::{token::Token, Calculation},
+        reference::*,
+        schema::field::{AutoEntryDataPresets, AutoEntryType, DataType, Field, SerialTrigger, Validation, ValidationTrigger, ValidationType},
+        scripting::{arguments::*, instructions::Instruction, script::ScriptStep},
+        xml::file_from_xml_path,
+    };
+    use super::{from_snippet, to_snippet, SnippetObject};
+
+    #[test]
+    fn field_roundtrip_test() {
+        let file = file_from_xml_path(Path::new("test_data/xml_files/relation_ddr.xml")).unwrap();
+        let fields = vec![
+            Field::new(1, String::from("id"))
+                .datatype(DataType::Number)
+                .autoentry(AutoEntryType::Serial { next: 10, increment: 2, trigger: SerialTrigger::OnCommit }, true),
+            Field::new(2, String::from("created"))
+                .datatype(DataType::Timestamp)
+                .autoentry(AutoEntryType::Creation(AutoEntryDataPresets::Timestamp), false),
+            Field::new(3, String::from("full_name"))
+                .autoentry(AutoEntryType::Calculation { code: Calculation::from_text("$first & \" \" & $last"), noreplace: true }, false)
+                .validation(Validation {
+                    trigger: ValidationTrigger::OnCommit,
+                    user_override: false,
+                    checks: vec![ValidationType::NotEmpty, ValidationType::Unique, ValidationType::MaxChars(20)],
+                    message: String::from("Names must be <20 & unique"),
+                }),
+            Field::new(4, String::from("status"))
+                .comment(String::from("One of \"new\", \"open\" & \"closed\""))
+                .repetitions(3)
+                .autoentry(AutoEntryType::Data(String::from("new")), false),
+        ];
+        let objects = fields.into_iter().map(SnippetObject::Field).collect::<Vec<_>>();
+
+        let text = to_snippet(&objects, &file);
+        assert!(text.starts_with("<fmxmlsnippet type=\"FMObjectList\">"));
+        assert_eq!(from_snippet(&text, &file).unwrap(), objects);
+    }
+
+    #[test]
+    fn table_and_script_roundtrip_test() {
+        let file = file_from_xml_path(Path::new("test_data/xml_files/relation_ddr.xml")).unwrap();
+        let mut objects = file.schema.tables.iter().cloned().map(SnippetObject::Table).collect::<Vec<_>>();
+        objects.extend(file.scripts.iter().cloned().map(SnippetObject::Script));
+
+        let text = to_snippet(&objects, &file);
+        assert!(text.contains("<Field table=\"other_table\" id=\"3\" name=\"CreatedBy\"></Field>"));
+        assert!(text.contains("<Layout id=\"2\" name=\"other_table\"></Layout>"));
+        assert_eq!(from_snippet(&text, &file).unwrap(), objects);
+    }
+
+    #[test]
+    fn step_snippet_test() {
+        let file = file_from_xml_path(Path::new("test_data/xml_files/relation_ddr.xml")).unwrap();
+        let text = "<fmxmlsnippet type=\"FMObjectList\">
+            <Step enable=\"True\" id=\"68\" name=\"If\"><Calculation><![CDATA[$x ≥ 10]]></Calculation></Step>
+            <Step enable=\"True\" id=\"89\" name=\"# (comment)\"></Step>
+            <Step enable=\"True\" id=\"1\" name=\"Perform Script\"><Script id=\"1\" name=\"New Script\"></Script></Step>
+            <Step enable=\"True\" id=\"70\" name=\"End If\"></Step>
+        </fmxmlsnippet>";
+
+        let objects = from_snippet(text, &file).unwrap();
+        assert_eq!(objects, vec![
+            SnippetObject::Step(ScriptStep { id: 0, instruction: Instruction::If { condition: Calculation::from_text("$x ≥ 10") } }),
+            SnippetObject::Step(ScriptStep { id: 1, instruction: Instruction::PerformScript {
+                script: ScriptSelection::FromList(ScriptReference { data_source: 0, script_id: 1 }),
+                args: Calculation(vec![]),
+            }}),
+            SnippetObject::Step(ScriptStep { id: 2, instruction: Instruction::EndIf }),
+        ]);
+        assert_eq!(from_snippet(&to_snippet(&objects, &file), &file).unwrap(), objects);
+
+        assert!(from_snippet("<FMPReport></FMPReport>", &file).is_err());
+    }
+
+    #[test]
+    fn step_snippet_names_test() {
+        let file = file_from_xml_path(Path::new("test_data/xml_files/relation_ddr.xml")).unwrap();
+        let occurrence = file.schema.relation_graph.nodes.iter()
+            .find(|o| o.name == "other_table")
+            .unwrap();
+        let reference = FieldReference { data_source: 0, table_occurrence_id: occurrence.id, field_id: 3 };
+        let objects = vec![
+            SnippetObject::Step(ScriptStep { id: 0, instruction: Instruction::SetVariable {
+                name: String::from("$by"),
+                value: Calculation::from_tokens(&vec![Token::ResolvedFieldReference(reference)]),
+                repetition: Calculation(vec![]),
+            }}),
+            SnippetObject::Step(ScriptStep { id: 1, instruction: Instruction::Beep }),
+        ];
+
+        let text = to_snippet(&objects, &file);
+        assert!(text.contains("<![CDATA[other_table::CreatedBy]]>"));
+        assert!(text.contains("<Step enable=\"True\" id=\"89\" name=\"# (comment)\">\n<Text>Unsupported step: Beep</Text>"));
+    }
+}
@@ -0,0 +1,246 @@
+/* Script steps share one representation across the DDR, "Save a Copy as XML"
+ * and the clipboard (fmxmlsnippet) formats:
+ * <Step enable="True" id="141" name="Set Variable"> ... </Step>
+ * where id is FileMaker's script step id. Steps that are not yet modelled
+ * are skipped, the same as the hbam reader does. */
+
+use roxmltree::Node;
+
+use crate::{
+    calculation::Calculation,
+    file::File,
+    reference::*,
+    schema::relationgraph::table_occurrence::TableOccurrence,
+    scripting::{arguments::*, instructions::Instruction, script::ScriptStep},
+};
+
+use super::{attribute, cdata, child, children, escape, expect_child, id_attribute, invalid_value, Error, Result};
+
+pub(crate) const PERFORM_SCRIPT: u32 = 1;
+pub(crate) const GO_TO_LAYOUT: u32 = 6;
+pub(crate) const NEW_RECORD_REQUEST: u32 = 7;
+pub(crate) const IF: u32 = 68;
+pub(crate) const ELSE: u32 = 69;
+pub(crate) const END_IF: u32 = 70;
+pub(crate) const LOOP: u32 = 71;
+pub(crate) const EXIT_LOOP_IF: u32 = 72;
+pub(crate) const END_LOOP: u32 = 73;
+pub(crate) const COMMIT_RECORDS_REQUESTS: u32 = 75;
+pub(crate) const SET_FIELD: u32 = 76;
+pub(crate) const COMMENT: u32 = 89;
+pub(crate) const EXIT_SCRIPT: u32 = 103;
+pub(crate) const ELSE_IF: u32 = 125;
+pub(crate) const SET_VARIABLE: u32 = 141;
+
+/* Names are resolved against the file the steps belong to. Snippets carry no
+ * file, so field references to unknown occurrences resolve to occurrence 0. */
+pub(crate) struct StepContext<'a> {
+    pub file_name: &'a str,
+    pub occurrences: &'a [TableOccurrence],
+}
+
+pub(crate) fn parse_steps(list: Node, ctx: &StepContext) -> Result<Vec<ScriptStep>> {
+    let mut result = vec![];
+    for step in children(list, "Step") {
+        if let Some(instruction) = parse_step(step, ctx)? {
+            result.push(ScriptStep {
+                id: result.len() as u32,
+                instruction,
+            });
+        }
+    }
+    Ok(result)
+}
+
+fn calculation(node: Node) -> Calculation {
+    match child(node, "Calculation") {
+        Some(calc) => Calculation::from_text(calc.text().unwrap_or_default().trim()),
+        None => Calculation(vec![]),
+    }
+}
+
+fn nested_calculation(node: Node, name: &str) -> Calculation {
+    match child(node, name) {
+        Some(inner) => calculation(inner),
+        None => Calculation(vec![]),
+    }
+}
+
+fn field_reference(node: Node, ctx: &StepContext) -> Result<FieldReference> {
+    let field = expect_child(node, "Field")?;
+    let table = attribute(field, "table")?;
+    let occurrence = ctx.occurrences.iter()
+        .find(|o| o.name == table)
+        .map(|o| o.id);
+    if occurrence.is_none() && !ctx.occurrences.is_empty() {
+        return Err(Error::UnresolvedReference {
+            construct: String::from("table occurrence"),
+            name: table.to_string(),
+        });
+    }
+
+    Ok(FieldReference {
+        data_source: 0,
+        table_occurrence_id: occurrence.unwrap_or(0),
+        field_id: id_attribute(field, "id")?,
+    })
+}
+
+fn layout_selection(node: Node, ctx: &StepContext) -> Result<LayoutSelection> {
+    let destination = expect_child(node, "LayoutDestination")?;
+    match attribute(destination, "value")? {
+        "OriginalLayout" => Ok(LayoutSelection::Current),
+        "SelectedLayout" => Ok(LayoutSelection::FromList(LayoutReference {
+            file_name: ctx.file_name.to_string(),
+            layout_id: id_attribute(expect_child(node, "Layout")?, "id")?,
+        })),
+        "LayoutNameByCalc" => Ok(LayoutSelection::NameByCalculation(calculation(node))),
+        "LayoutNumberByCalc" => Ok(LayoutSelection::NumberByCalculation(calculation(node))),
+        other => Err(invalid_value(destination, "value", other)),
+    }
+}
+
+pub(crate) fn parse_step(step: Node, ctx: &StepContext) -> Result<Option<Instruction>> {
+    let instruction = match id_attribute(step, "id")? {
+        PERFORM_SCRIPT => Instruction::PerformScript {
+            script: ScriptSelection::FromList(ScriptReference {
+                data_source: 0,
+                script_id: id_attribute(expect_child(step, "Script")?, "id")?,
+            }),
+            args: calculation(step),
+        },
+        GO_TO_LAYOUT => Instruction::GoToLayout {
+            layout: layout_selection(step, ctx)?,
+            animation: LayoutAnimation::None,
+        },
+        NEW_RECORD_REQUEST => Instruction::NewRecordRequest,
+        IF => Instruction::If { condition: calculation(step) },
+        ELSE => Instruction::Else,
+        END_IF => Instruction::EndIf,
+        LOOP => Instruction::Loop,
+        EXIT_LOOP_IF => Instruction::ExitLoopIf { condition: calculation(step) },
+        END_LOOP => Instruction::EndLoop,
+        COMMIT_RECORDS_REQUESTS => Instruction::CommitRecordsRequests,
+        SET_FIELD => Instruction::SetField {
+            field: field_reference(step, ctx)?,
+            value: calculation(step),
+            repetition: nested_calculation(step, "Repetition"),
+        },
+        EXIT_SCRIPT => Instruction::ExitScript { value: calculation(step) },
+        ELSE_IF => Instruction::ElseIf { condition: calculation(step) },
+        SET_VARIABLE => Instruction::SetVariable {
+            name: expect_child(step, "Name")?.text().unwrap_or_default().to_string(),
+            value: nested_calculation(step, "Value"),
+            repetition: nested_calculation(step, "Repetition"),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(instruction))
+}
+
+fn write_calculation(calc: &Calculation, file: &File, buffer: &mut String) {
+    if !calc.0.is_empty() {
+        buffer.push_str(&format!("<Calculation>{}</Calculation>\n", cdata(&calc.to_text_in(file))));
+    }
+}
+
+fn write_nested_calculation(name: &str, calc: &Calculation, file: &File, buffer: &mut String) {
+    if !calc.0.is_empty() {
+        buffer.push_str(&format!("<{}>\n", name));
+        write_calculation(calc, file, buffer);
+        buffer.push_str(&format!("</{}>\n", name));
+    }
+}
+
+fn write_field_reference(field: &FieldReference, file: &File, buffer: &mut String) {
+    let occurrence = file.schema.relation_graph.nodes.iter()
+        .find(|o| o.id == field.table_occurrence_id);
+    let name = occurrence
+        .and_then(|o| file.schema.tables.iter().find(|t| t.id == o.base.table_id))
+        .and_then(|t| t.fields.get(&field.field_id))
+        .map(|f| f.name.as_str())
+        .unwrap_or_default();
+    buffer.push_str(&format!("<Field table=\"{}\" id=\"{}\" name=\"{}\"></Field>\n",
+        escape(occurrence.map(|o| o.name.as_str()).unwrap_or_default()), field.field_id, escape(name)));
+}
+
+fn write_layout_selection(layout: &LayoutSelection, file: &File, buffer: &mut String) {
+    match layout {
+        LayoutSelection::Current => buffer.push_str("<LayoutDestination value=\"OriginalLayout\"></LayoutDestination>\n"),
+        LayoutSelection::FromList(reference) => {
+            let name = file.layouts.iter()
+                .find(|l| l.id == reference.layout_id)
+                .map(|l| l.name.as_str())
+                .unwrap_or_default();
+            buffer.push_str("<LayoutDestination value=\"SelectedLayout\"></LayoutDestination>\n");
+            buffer.push_str(&format!("<Layout id=\"{}\" name=\"{}\"></Layout>\n", reference.layout_id, escape(name)));
+        }
+        LayoutSelection::NameByCalculation(calc) => {
+            buffer.push_str("<LayoutDestination value=\"LayoutNameByCalc\"></LayoutDestination>\n");
+            write_calculation(calc, file, buffer);
+        }
+        LayoutSelection::NumberByCalculation(calc) => {
+            buffer.push_str("<LayoutDestination value=\"LayoutNumberByCalc\"></LayoutDestination>\n");
+            write_calculation(calc, file, buffer);
+        }
+    }
+}
+
+/* Writes the step in the same shape parse_step reads. Names are looked up in
+ * the file the step belongs to, as FileMaker resolves references by name when
+ * pasting. Steps that are not yet modelled are written as a comment naming
+ * the step, so the pasted script keeps its shape and shows what is missing. */
+pub(crate) fn write_step(step: &ScriptStep, file: &File, buffer: &mut String) {
+    let (id_, name) = match &step.instruction {
+        Instruction::PerformScript { script: ScriptSelection::FromList(..), .. } => (PERFORM_SCRIPT, "Perform Script"),
+        Instruction::GoToLayout { .. } => (GO_TO_LAYOUT, "Go to Layout"),
+        Instruction::NewRecordRequest => (NEW_RECORD_REQUEST, "New Record/Request"),
+        Instruction::If { .. } => (IF, "If"),
+        Instruction::Else => (ELSE, "Else"),
+        Instruction::EndIf => (END_IF, "End If"),
+        Instruction::Loop => (LOOP, "Loop"),
+        Instruction::ExitLoopIf { .. } => (EXIT_LOOP_IF, "Exit Loop If"),
+        Instruction::EndLoop => (END_LOOP, "End Loop"),
+        Instruction::CommitRecordsRequests => (COMMIT_RECORDS_REQUESTS, "Commit Records/Requests"),
+        Instruction::SetField { .. } => (SET_FIELD, "Set Field"),
+        Instruction::ExitScript { .. } => (EXIT_SCRIPT, "Exit Script"),
+        Instruction::ElseIf { .. } => (ELSE_IF, "Else If"),
+        Instruction::SetVariable { .. } => (SET_VARIABLE, "Set Variable"),
+        _ => {
+            let debug = format!("{:?}", step.instruction);
+            let name = debug.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default();
+            buffer.push_str(&format!("<Step enable=\"True\" id=\"{}\" name=\"# (comment)\">\n", COMMENT));
+            buffer.push_str(&format!("<Text>Unsupported step: {}</Text>\n</Step>\n", escape(name)));
+            return;
+        }
+    };
+
+    buffer.push_str(&format!("<Step enable=\"True\" id=\"{}\" name=\"{}\">\n", id_, name));
+    match &step.instruction {
+        Instruction::PerformScript { script: ScriptSelection::FromList(reference), args } => {
+            let name = file.scripts.iter()
+                .find(|s| s.id == reference.script_id)
+                .map(|s| s.name.as_str())
+                .unwrap_or_default();
+            write_calculation(args, file, buffer);
+            buffer.push_str(&format!("<Script id=\"{}\" name=\"{}\"></Script>\n", reference.script_id, escape(name)));
+        }
+        Instruction::GoToLayout { layout, .. } => write_layout_selection(layout, file, buffer),
+        Instruction::If { condition }
+            | Instruction::ElseIf { condition }
+            | Instruction::ExitLoopIf { condition } => write_calculation(condition, file, buffer),
+        Instruction::ExitScript { value } => write_calculation(value, file, buffer),
+        Instruction::SetField { field, value, repetition } => {
+            write_calculation(value, file, buffer);
+            write_nested_calculation("Repetition", repetition, file, buffer);
+            write_field_reference(field, file, buffer);
+        }
+        Instruction::SetVariable { name, value, repetition } => {
+            write_nested_calculation("Value", value, file, buffer);
+            write_nested_calculation("Repetition", repetition, file, buffer);
+            buffer.push_str(&format!("<Name>{}</Name>\n", escape(name)));
+        }
+        _ => {}
+    }
+    buffer.push_str("</Step>\n");
+}
@@ -1,6 +1,10 @@
 
+use std::collections::HashSet;
+
 use super::database_mgr::DatabaseMgr;
+use crate::dbobjects::file::File;
 use crate::dbobjects::reference::{FieldReference, TableOccurrenceReference};
+use crate::dbobjects::schema::field::{FieldKind, SummaryKind};
 use super::script_mgr::ScriptMgr;
 use super::window_mgr::WindowMgr;
 use super::EmulatorState;
@@ -11,6 +15,8 @@ pub struct EmulatorContext<'a> {
     pub database_mgr: &'a DatabaseMgr,
     pub variables: &'a Vec<(String, String)>,
     pub globals: &'a Vec<(String, String)>,
+    pub script_parameter: &'a str,
+    pub script_result: &'a str,
     pub window_mgr: &'a WindowMgr,
     pub state: &'a EmulatorState,
 }
@@ -28,12 +34,16 @@ impl CalculationContext for EmulatorContext<'_> {
             .map(|var| var.1.clone())
     }
     fn get_global_var(&self, name: &str) -> Option<String> { todo!() }
+    fn get_script_parameter(&self) -> String { self.script_parameter.to_string() }
+    fn get_script_result(&self) -> String { self.script_result.to_string() }
 
-    fn lookup_field(&self, reference: FieldReference) -> Result<Option<String>, String> {
+    fn lookup_field(&self, reference: FieldReference, repetition: u32) -> Result<Option<String>, String> {
         let cur_window = self.window_mgr.windows.get(&self.state.active_window).unwrap();
         let cur_layout_id = cur_window.layout_id;
         let db = self.database_mgr.databases.get(&self.state.active_database).unwrap();
-        let cur_occurrence_ref = db.file.layouts.get(cur_layout_id as usize).unwrap().occurrence.clone();
+        let cur_occurrence_ref = db.file.layouts.iter()
+            .find(|layout| layout.id == cur_layout_id).unwrap()
+            .occurrence.clone();
 
         let cur_set = cur_window.found_sets.iter()
             .find(|set| set.table_occurrence_ref == TableOccurrenceReference { 
@@ -41,10 +51,197 @@ impl CalculationContext for EmulatorContext<'_> {
                 table_occurrence_id: cur_occurrence_ref.table_occurrence_id
             }).unwrap();
 
+        let local_field = db.file.schema.relation_graph.nodes.iter()
+            .find(|node| reference.data_source == 0 && node.id == reference.table_occurrence_id)
+            .and_then(|node| db.file.schema.tables.iter().find(|table| table.id == node.base.table_id))
+            .and_then(|table| table.fields.get(&reference.field_id).map(|field| (table.id, field)));
+
+        /* Globals don't need a record to be read. */
+        if let Some((table, field)) = local_field.filter(|(_, field)| field.global) {
+            return Ok(Some(db.records.get_repetition(table, 0, field.id, repetition)))
+        }
         if cur_set.cursor.is_none() {
             return Ok(None)
         }
         let record_id = cur_set.records[cur_set.cursor.unwrap() as usize];
-        return Ok(self.database_mgr.get_field((cur_occurrence_ref, record_id), reference, &self.state.active_database))
+
+        match local_field {
+            /* Calculations and summaries only have a first repetition. */
+            Some((_, field)) if repetition > 1 && field.kind != FieldKind::Simple => return Ok(Some(String::new())),
+            /* Calculations are evaluated from the current record. One that
+             * reads itself would never finish, so is an error instead. */
+            Some((table, field)) if matches!(field.kind, FieldKind::Calculation { .. }) => {
+                let FieldKind::Calculation { code, .. } = &field.kind else { unreachable!() };
+                if reads_itself(&db.file, table, field.id) {
+                    return Err(format!("Calculation for field \"{}\" refers back to itself.", field.name))
+                }
+                return code.eval(self).map(Some)
+            }
+            /* Summaries on the layout's own occurrence run over the found
+             * set, and over the related records otherwise. */
+            Some((table, field)) if matches!(field.kind, FieldKind::Summary { .. }) => {
+                let FieldKind::Summary { kind, field: summarised } = field.kind else { unreachable!() };
+                let records = if reference.table_occurrence_id == cur_occurrence_ref.table_occurrence_id {
+                    cur_set.records.clone()
+                } else {
+                    let to = TableOccurrenceReference { data_source: 0, table_occurrence_id: reference.table_occurrence_id };
+                    self.database_mgr.get_related_records((cur_occurrence_ref, record_id), &to, &self.state.active_database)
+                        .unwrap_or_default()
+                };
+                let values = records.iter()
+                    .map(|id| db.records.get_field(table, *id, summarised))
+                    .collect::<Vec<_>>();
+                let current = db.records.get_field(table, record_id, summarised);
+                return Ok(Some(summarise(kind, &values, &current)))
+            }
+            _ => {}
+        }
+        return Ok(self.database_mgr.get_field((cur_occurrence_ref, record_id), reference, repetition, &self.state.active_database))
+    }
+}
+
+/* Whether the calculation field reaches itself through the calculation
+ * fields of this file it reads. */
+fn reads_itself(file: &File, table: u32, field: u32) -> bool {
+    let reads = |(table, field): (u32, u32)| file.schema.tables.iter()
+        .find(|search| search.id == table)
+        .and_then(|table| table.fields.get(&field))
+        .map(|field| match &field.kind {
+            FieldKind::Calculation { code, .. } => code.field_references(),
+            _ => vec![],
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|reference| file.schema.relation_graph.nodes.iter()
+            .find(|node| node.id == reference.table_occurrence_id && node.base.data_source == 0)
+            .map(|node| (node.base.table_id, reference.field_id)))
+        .collect::<Vec<_>>();
+
+    let mut seen = HashSet::new();
+    let mut stack = reads((table, field));
+    while let Some(next) = stack.pop() {
+        if next == (table, field) {
+            return true
+        }
+        if seen.insert(next) {
+            stack.extend(reads(next));
+        }
+    }
+    false
+}
+
+/* Empty and non numeric values are skipped, except by list_of. */
+fn summarise(kind: SummaryKind, values: &[String], current: &str) -> String {
+    let numbers = values.iter()
+        .filter_map(|value| value.parse::<f64>().ok())
+        .collect::<Vec<_>>();
+    let count = numbers.len() as f64;
+    let total: f64 = numbers.iter().sum();
+    let result = match kind {
+        SummaryKind::ListOf => return values.iter()
+            .filter(|value| !value.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n"),
+        SummaryKind::Count => values.iter().filter(|value| !value.is_empty()).count() as f64,
+        SummaryKind::Total => total,
+        SummaryKind::Average if count == 0.0 => return String::new(),
+        SummaryKind::Average => total / count,
+        SummaryKind::Minimum => return numbers.iter().cloned().reduce(f64::min).map(|n| n.to_string()).unwrap_or_default(),
+        SummaryKind::Maximum => return numbers.iter().cloned().reduce(f64::max).map(|n| n.to_string()).unwrap_or_default(),
+        SummaryKind::StdDev if count < 2.0 => 0.0,
+        SummaryKind::StdDev => {
+            let mean = total / count;
+            (numbers.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / (count - 1.0)).sqrt()
+        }
+        SummaryKind::Fraction if total == 0.0 => return String::new(),
+        SummaryKind::Fraction => current.parse::<f64>().unwrap_or(0.0) / total,
+    };
+    result.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Emulator;
+    use super::*;
+    use crate::dbobjects::calculation::context::CalculationContext;
+    use crate::dbobjects::calculation::{token::Token, Calculation};
+    use crate::dbobjects::reference::TableReference;
+
+    #[test]
+    fn summarise_values() {
+        let values = ["2", "4", "", "9"].map(String::from);
+        assert_eq!(summarise(SummaryKind::Total, &values, "4"), "15");
+        assert_eq!(summarise(SummaryKind::Count, &values, "4"), "3");
+        assert_eq!(summarise(SummaryKind::Average, &values, "4"), "5");
+        assert_eq!(summarise(SummaryKind::Minimum, &values, "4"), "2");
+        assert_eq!(summarise(SummaryKind::Maximum, &values, "4"), "9");
+        assert_eq!(summarise(SummaryKind::StdDev, &["1", "3"].map(String::from), ""), 2f64.sqrt().to_string());
+        assert_eq!(summarise(SummaryKind::Fraction, &values, "3"), "0.2");
+        assert_eq!(summarise(SummaryKind::ListOf, &values, "4"), "2\n4\n9");
+    }
+
+    #[test]
+    fn calculation_and_summary_fields() {
+        let mut env = Emulator::new();
+        let path = "test_data/cad_files/summary.cad";
+        env.load_file(path);
+        env.state.active_database = path.to_string();
+
+        for amount in [2, 4, 9] {
+            let record = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, path);
+            let db = env.database_mgr.databases.get_mut(path).unwrap();
+            db.records.set_field(1, record, 1, amount.to_string());
+            env.window_mgr.windows.get_mut(&env.state.active_window).unwrap()
+                .append_record_to_found_sets(record, 1, db);
+        }
+
+        let variables = vec![];
+        let ctx = EmulatorContext {
+            database_mgr: &env.database_mgr,
+            variables: &variables,
+            globals: &variables,
+            script_parameter: "",
+            script_result: "",
+            window_mgr: &env.window_mgr,
+            state: &env.state,
+        };
+        let field = |field_id| FieldReference { data_source: 0, table_occurrence_id: 1, field_id };
+        assert_eq!(ctx.lookup_field(field(2), 1), Ok(Some(String::from("18"))));
+        assert_eq!(ctx.lookup_field(field(3), 1), Ok(Some(String::from("15"))));
+        assert_eq!(ctx.lookup_field(field(4), 1), Ok(Some(String::from("5"))));
+    }
+
+    #[test]
+    fn calculation_reading_itself() {
+        let mut env = Emulator::new();
+        let path = "test_data/cad_files/summary.cad";
+        env.load_file(path);
+        env.state.active_database = path.to_string();
+
+        /* The compiler rejects this, but a file from elsewhere may not. */
+        let doubled = FieldReference { data_source: 0, table_occurrence_id: 1, field_id: 2 };
+        let db = env.database_mgr.databases.get_mut(path).unwrap();
+        db.file.schema.tables[0].fields.get_mut(&2).unwrap().kind = FieldKind::Calculation {
+            code: Calculation::from_tokens(&vec![Token::ResolvedFieldReference(doubled.clone()), Token::Add, Token::Number(1.0)]),
+            stored: false,
+        };
+        let record = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, path);
+        let db = env.database_mgr.databases.get_mut(path).unwrap();
+        env.window_mgr.windows.get_mut(&env.state.active_window).unwrap()
+            .append_record_to_found_sets(record, 1, db);
+
+        let variables = vec![];
+        let ctx = EmulatorContext {
+            database_mgr: &env.database_mgr,
+            variables: &variables,
+            globals: &variables,
+            script_parameter: "",
+            script_result: "",
+            window_mgr: &env.window_mgr,
+            state: &env.state,
+        };
+        assert_eq!(ctx.lookup_field(doubled, 1),
+            Err(String::from("Calculation for field \"doubled\" refers back to itself.")));
     }
 }
@@ -1,13 +1,14 @@
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::Path;
 
 use crate::emulator3::record_store::*;
 use crate::dbobjects::reference::{TableReference, FieldReference, TableOccurrenceReference};
-use crate::dbobjects::schema::{Schema, relationgraph::{graph::*, relation::*}};
+use crate::dbobjects::schema::{Schema, field::AutoEntryType, relationgraph::{graph::*, relation::*}};
 use crate::dbobjects::file::File;
 
-use crate::hbam2::{page_store::*, api::*};
+use crate::hbam2::{page_store::*, *};
 
 use super::database::Database;
 use super::record_store::Record;
@@ -36,8 +37,8 @@ impl DatabaseMgr {
                         let cur_field = record.fields.iter().find(|field| field.0 == criteria.field_self).unwrap();
                         let next_field = other.fields.iter().find(|field| field.0 == criteria.field_other).unwrap();
                         if match criteria.comparison {
-                            RelationComparison::Equal => cur_field == next_field,
-                            RelationComparison::NotEqual => cur_field != next_field,
+                            RelationComparison::Equal => cur_field.1[0] == next_field.1[0],
+                            RelationComparison::NotEqual => cur_field.1[0] != next_field.1[0],
                             // TODO: Implement the other comparisons
                             _ => todo!("Other comparisons to be implemented"),
                         } {
@@ -55,13 +56,19 @@ impl DatabaseMgr {
         active_database: &str) 
         -> Option<Vec<u32>> {
 
+            /* A field on the record's own occurrence comes from the record itself. */
+            if from.0 == *to {
+                return Some(vec![from.1])
+            }
             let db = self.databases.get(active_database).unwrap();
-            let cur_table_ref = &db.file.schema.relation_graph.nodes.iter()
+            let mut cur_table = db.file.schema.relation_graph.nodes.iter()
                 .find(|source| source.id == from.0.table_occurrence_id)
                 .unwrap().base.table_id;
+            let mut cur_db = self.databases.get(&self.source_path(active_database, from.0.data_source)).unwrap();
+            let mut sort: &[RelationSort] = &[];
 
-            let cur_records: &Vec<Record> = db.records.records_by_table
-                .get(cur_table_ref).unwrap();
+            let cur_records: &Vec<Record> = cur_db.records.records_by_table
+                .get(&cur_table).unwrap();
 
             let mut cur_records: Vec<&Record> = vec![&cur_records.iter()
                 .find(|record| record.id == from.1).unwrap()];
@@ -108,11 +115,11 @@ impl DatabaseMgr {
                     for other in next_records {
                         for criteria in &relation.criteria {
                             // TODO: current field value can be cached
-                            let cur_field = record.fields.iter().find(|field| field.0 == criteria.field_self).unwrap();
-                            let next_field = other.fields.iter().find(|field| field.0 == criteria.field_other).unwrap();
+                            let cur_value = cur_db.records.match_value(cur_table, record, criteria.field_self);
+                            let next_value = next_db.records.match_value(next_table_ref.table_id, other, criteria.field_other);
                             if match criteria.comparison {
-                                RelationComparison::Equal => cur_field.1 == next_field.1,
-                                RelationComparison::NotEqual => cur_field.1 != next_field.1,
+                                RelationComparison::Equal => cur_value == next_value,
+                                RelationComparison::NotEqual => cur_value != next_value,
                                 _ => todo!("Other comparisons to be implemented"),
                             } {
                                 related_records.push(other)
@@ -124,8 +131,22 @@ impl DatabaseMgr {
                     return None
                 }
                 cur_records = related_records.clone();
+                cur_table = next_table_ref.table_id;
+                cur_db = next_db;
+                sort = &relation.options.sort;
             }
 
+            /* Related records come in the order of the last relation's sort,
+             * and in creation order without one. */
+            cur_records.sort_by(|a, b| sort.iter()
+                .map(|key| {
+                    let order = sort_order(
+                        cur_db.records.match_value(cur_table, a, key.field),
+                        cur_db.records.match_value(cur_table, b, key.field));
+                    if key.descending { order.reverse() } else { order }
+                })
+                .find(|order| order.is_ne())
+                .unwrap_or(Ordering::Equal));
             Some(cur_records.iter().map(|record| record.id).collect())
     }
 
@@ -136,6 +157,7 @@ impl DatabaseMgr {
     pub fn get_field(&self,
         from: (TableOccurrenceReference, u32),
         to: FieldReference,
+        repetition: u32,
         active_database: &str
         ) -> Option<String> {
 
@@ -143,26 +165,31 @@ impl DatabaseMgr {
             data_source: to.data_source,
             table_occurrence_id: to.table_occurrence_id
         };
-        let record_ids = match self.get_related_records(from, &to_occurrence_ref, active_database) {
-            Some(inner) => inner,
-            None => return None,
-        };
-
         let cur_db = self.databases.get(active_database).unwrap();
 
-        let other_db = self.databases.get(
-            &cur_db.file.data_sources
+        let other_db = if to.data_source == 0 {
+            cur_db
+        } else {
+            let other_db_path = cur_db.file.working_dir.clone() + "/" + &cur_db.file.data_sources
                 .iter()
                 .find(|source| source.id == to.data_source)
-                .unwrap().paths[0]
-            ).unwrap();
+                .unwrap().paths[0];
+            self.databases.get(&other_db_path).unwrap()
+        };
 
         let other_table = cur_db.file.schema.relation_graph.nodes
             .iter()
             .find(|node| node.id == to.table_occurrence_id)
             .unwrap().base.table_id;
 
-        Some(other_db.records.get_field(other_table, record_ids[0], to.field_id))
+        /* Globals are read through any occurrence of their table, related
+         * or not. */
+        if other_db.records.is_global(other_table, to.field_id) {
+            return Some(other_db.records.get_repetition(other_table, 0, to.field_id, repetition))
+        }
+        let record_ids = self.get_related_records(from, &to_occurrence_ref, active_database)?;
+
+        Some(other_db.records.get_repetition(other_table, record_ids[0], to.field_id, repetition))
     }
 
     pub fn set_field(&mut self,
@@ -176,24 +203,149 @@ impl DatabaseMgr {
             data_source: to.data_source,
             table_occurrence_id: to.table_occurrence_id
         };
-        let record_ids = match self.get_related_records(from, &to_occurrence_ref, active_database) {
-            Some(inner) => inner,
-            None => return Err("No related record exists"),
-        };
-
-        let cur_db = self.databases.get(active_database).unwrap();
-        let other_db_path = cur_db.file.working_dir.clone() + "/" + &cur_db.file.data_sources.iter().find(|source| source.id == to.data_source).unwrap()
-            .paths[0].clone();
-        let other_table = cur_db.file.schema.relation_graph.nodes
+        let other_db_path = self.source_path(active_database, to.data_source);
+        let other_table = self.databases.get(active_database).unwrap()
+            .file.schema.relation_graph.nodes
             .iter()
             .find(|node| node.id == to.table_occurrence_id)
             .unwrap().base.table_id;
 
+        let record_id = match self.get_related_records(from.clone(), &to_occurrence_ref, active_database) {
+            Some(inner) => inner[0],
+            None => match self.create_related_record(from, &to_occurrence_ref, active_database) {
+                Some(created) => created,
+                None => return Err("No related record exists"),
+            },
+        };
+
         let other_db = self.databases.get_mut(&other_db_path).unwrap();
-        other_db.records.set_field(other_table, record_ids[0], to.field_id, value.to_string());
+        other_db.records.set_field(other_table, record_id, to.field_id, value.to_string());
         Ok(())
     }
 
+    /* Setting a field through a relation which allows creation makes the
+     * related record when there is none, with its match fields taken from
+     * `from`. Only direct relations, matching on equality, create records. */
+    fn create_related_record(&mut self,
+        from: (TableOccurrenceReference, u32),
+        to: &TableOccurrenceReference,
+        active_database: &str) -> Option<u32> {
+
+        let db = self.databases.get(active_database).unwrap();
+        let graph = &db.file.schema.relation_graph;
+        let from_node = graph.nodes.iter().find(|node| node.id == from.0.table_occurrence_id)?;
+        let to_node = graph.nodes.iter().find(|node| node.id == to.table_occurrence_id)?;
+        let relation = from_node.relations.iter()
+            .find(|relation| relation.other_occurrence == to_node.id)
+            .filter(|relation| relation.options.allow_creation)
+            .filter(|relation| relation.criteria.iter().all(|criteria| criteria.comparison == RelationComparison::Equal))?;
+
+        let from_db = self.databases.get(&self.source_path(active_database, from.0.data_source)).unwrap();
+        let from_table = from_node.base.table_id;
+        let to_table = to_node.base.table_id;
+        let record = from_db.records.records_by_table.get(&from_table)?
+            .iter().find(|record| record.id == from.1)?;
+        let matched = relation.criteria.iter()
+            .map(|criteria| (criteria.field_other, from_db.records.match_value(from_table, record, criteria.field_self).to_string()))
+            .collect::<Vec<_>>();
+
+        let other_db = self.databases.get_mut(&self.source_path(active_database, to.data_source)).unwrap();
+        let table = other_db.file.schema.tables.iter()
+            .find(|table| table.id == to_table)?
+            .clone();
+        let created = other_db.records.new_record(&table);
+        for (field, value) in matched {
+            other_db.records.set_field(table.id, created, field, value);
+        }
+        Some(created)
+    }
+
+    /* Deletes `from` along with the records related through relations which
+     * delete them, returning each deleted occurrence and record. Records
+     * already gone are skipped, so a relation deleting both ways ends. */
+    pub fn delete_record(&mut self,
+        from: (TableOccurrenceReference, u32),
+        active_database: &str) -> Vec<(TableOccurrenceReference, u32)> {
+
+        let mut deleted = vec![];
+        let mut pending = vec![from];
+        while let Some((occurrence, record)) = pending.pop() {
+            let db = self.databases.get(active_database).unwrap();
+            let node = db.file.schema.relation_graph.nodes.iter()
+                .find(|node| node.id == occurrence.table_occurrence_id)
+                .unwrap();
+            let table = node.base.table_id;
+            let path = self.source_path(active_database, occurrence.data_source);
+            let exists = self.databases.get(&path).unwrap()
+                .records.records_by_table.get(&table)
+                .is_some_and(|records| records.iter().any(|search| search.id == record));
+            if !exists {
+                continue;
+            }
+
+            for relation in node.relations.iter().filter(|relation| relation.options.delete_related) {
+                let other = db.file.schema.relation_graph.nodes.iter()
+                    .find(|node| node.id == relation.other_occurrence)
+                    .unwrap();
+                let other_ref = TableOccurrenceReference {
+                    data_source: other.base.data_source,
+                    table_occurrence_id: other.id,
+                };
+                for id in self.get_related_records((occurrence.clone(), record), &other_ref, active_database).unwrap_or_default() {
+                    pending.push((other_ref.clone(), id));
+                }
+            }
+
+            self.databases.get_mut(&path).unwrap().records.delete_record(table, record);
+            deleted.push((occurrence, record));
+        }
+        deleted
+    }
+
+    /* Path of the database a data source of the active one is loaded from,
+     * with 0 being the active database itself. */
+    fn source_path(&self, active_database: &str, data_source: u32) -> String {
+        if data_source == 0 {
+            return active_database.to_string()
+        }
+        let db = self.databases.get(active_database).unwrap();
+        db.file.working_dir.clone() + "/" + &db.file.data_sources.iter()
+            .find(|source| source.id == data_source)
+            .unwrap().paths[0]
+    }
+
+    /* Copies looked up values into `record` for the lookups matching on the
+     * `changed` field, as FileMaker does when a match field is edited. A
+     * lookup with no related record leaves its field as it was. */
+    pub fn apply_lookups(&mut self, table: u32, record: u32, changed: u32, active_database: &str) {
+        let db = self.databases.get(active_database).unwrap();
+        let graph = &db.file.schema.relation_graph;
+
+        let looked_up = db.file.schema.tables.iter()
+            .find(|search| search.id == table).unwrap()
+            .fields.values()
+            .filter_map(|field| match &field.autoentry.definition {
+                AutoEntryType::Lookup { from, to } => Some((field.id, from, to)),
+                _ => None,
+            })
+            .filter(|(_, from, to)| {
+                let path = graph.get_path(from.table_occurrence_id, to.table_occurrence_id).unwrap_or_default();
+                path.len() > 1 && graph.nodes.iter()
+                    .find(|node| node.id == path[0]).unwrap()
+                    .relations.iter()
+                    .filter(|relation| relation.other_occurrence == path[1])
+                    .any(|relation| relation.criteria.iter().any(|criteria| criteria.field_self == changed))
+            })
+            .filter_map(|(id, from, to)| self.get_field((from.clone(), record), to.clone(), 1, active_database)
+                .map(|value| (id, value)))
+            .collect::<Vec<_>>();
+
+        let db = self.databases.get_mut(active_database).unwrap();
+        for (field, value) in looked_up {
+            db.records.set_field(table, record, field, value);
+        }
+    }
+
     pub fn new_record(&mut self,
         table_ref: TableReference,
         active_database: &str) -> u32 {
@@ -211,7 +363,15 @@ impl DatabaseMgr {
             // load cad file
             println!("path: {}", path);
             let cadcode = read_to_string(&path).unwrap();
-            let mut file = crate::cadlang::compiler::compile_to_file(Path::new(path)).unwrap();
+            let mut file = match crate::cadlang::compiler::compile_to_file(Path::new(path)) {
+                Ok(file) => file,
+                Err(errors) => {
+                    for (module, e) in &errors {
+                        eprintln!("error: {}: {}", module.display(), e);
+                    }
+                    panic!("Unable to compile {}", path);
+                }
+            };
             file.name = path.to_string();
             let database = Database::from_file(file);
             self.databases.insert(path.to_string(), database);
@@ -239,10 +399,13 @@ impl DatabaseMgr {
                 .into_iter()
                 .map(|node| node.1)
                 .collect::<Vec<_>>();
+            /* Where the file keeps saved global values isn't known yet, so
+             * globals start empty as for .cad files. */
+            let records_ = RecordStore::new(&tables_);
 
             let parent_dir = Path::new(path).parent().unwrap().to_str().unwrap().to_string();
             let database = Database {
-                records: RecordStore::new(&tables_),
+                records: records_,
                 file: File {
                     name: String::new(),
                     working_dir: parent_dir,
@@ -256,6 +419,8 @@ impl DatabaseMgr {
                     layouts: layouts_,
                     scripts: scripts_,
                     tests: vec![],
+                    /* Triggers aren't read from .fmp12 files yet. */
+                    triggers: vec![],
                 },
             };
             self.databases.insert(path.to_string(), database);
@@ -266,6 +431,14 @@ impl DatabaseMgr {
     }
 }
 
+/* Values which are both numbers sort as numbers, and as text otherwise. */
+fn sort_order(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::read_to_string;
@@ -349,6 +522,83 @@ mod tests {
 
         assert_eq!(related_records, None);
     }
+
+    #[test]
+    fn extern_field() {
+        let mut env = Emulator::new();
+        let quotes_path = "test_data/cad_files/multi_file_solution/quotes.cad";
+        let customers_path = "test_data/cad_files/multi_file_solution/customers.cad";
+        env.load_file(quotes_path);
+
+        let quote = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, quotes_path);
+        env.database_mgr.databases.get_mut(quotes_path).unwrap()
+            .records.set_field(1, quote, 2, 2.to_string());
+        for i in 1..=3 {
+            let customer = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, customers_path);
+            let records = &mut env.database_mgr.databases.get_mut(customers_path).unwrap().records;
+            records.set_field(1, customer, 1, i.to_string());
+            records.set_field(1, customer, 2, format!("customer {}", i));
+        }
+
+        /* Customers is opened relative to the quotes file, and keyed by that path. */
+        let quotes_ref = TableOccurrenceReference { data_source: 0, table_occurrence_id: 1 };
+        let name = FieldReference { data_source: 1, table_occurrence_id: 2, field_id: 2 };
+        assert_eq!(env.database_mgr.get_field((quotes_ref, quote), name, 1, quotes_path), Some(String::from("customer 2")));
+    }
+
+    #[test]
+    fn relation_options() {
+        let mut env = Emulator::new();
+        let path = "test_data/cad_files/relation_options.cad";
+        env.load_file(path);
+        let person_ref = TableOccurrenceReference { data_source: 0, table_occurrence_id: 1 };
+        let phone_ref = TableOccurrenceReference { data_source: 0, table_occurrence_id: 2 };
+
+        let person = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, path);
+        env.database_mgr.databases.get_mut(path).unwrap()
+            .records.set_field(1, person, 1, 1.to_string());
+        for label in ["home", "work", "mobile"] {
+            let phone = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 2 }, path);
+            let records = &mut env.database_mgr.databases.get_mut(path).unwrap().records;
+            records.set_field(2, phone, 1, 1.to_string());
+            records.set_field(2, phone, 2, label.to_string());
+        }
+
+        /* Sorted by label, descending. */
+        let related = env.database_mgr.get_related_records((person_ref.clone(), person), &phone_ref, path);
+        assert_eq!(related, Some(vec![2, 3, 1]));
+
+        /* Setting a field through the relation creates the related record. */
+        let other = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, path);
+        env.database_mgr.databases.get_mut(path).unwrap()
+            .records.set_field(1, other, 1, 2.to_string());
+        let label = FieldReference { data_source: 0, table_occurrence_id: 2, field_id: 2 };
+        assert_eq!(env.database_mgr.set_field((person_ref.clone(), other), label, path, "fax"), Ok(()));
+        let records = &env.database_mgr.databases.get(path).unwrap().records;
+        assert_eq!(records.get_field(2, 4, 1), "2");
+        assert_eq!(records.get_field(2, 4, 2), "fax");
+    }
+
+    #[test]
+    fn lookup_on_match_field() {
+        let mut env = Emulator::new();
+        let path = "test_data/cad_files/lookup.cad";
+        env.load_file(path);
+
+        let address = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 2 }, path);
+        let records = &mut env.database_mgr.databases.get_mut(path).unwrap().records;
+        records.set_field(2, address, 1, 1.to_string());
+        records.set_field(2, address, 2, 6000.to_string());
+
+        let person = env.database_mgr.new_record(TableReference { data_source: 0, table_id: 1 }, path);
+        env.database_mgr.apply_lookups(1, person, 1, path);
+        assert_eq!(env.database_mgr.databases.get(path).unwrap().records.get_field(1, person, 2), "");
+
+        env.database_mgr.databases.get_mut(path).unwrap()
+            .records.set_field(1, person, 1, 1.to_string());
+        env.database_mgr.apply_lookups(1, person, 1, path);
+        assert_eq!(env.database_mgr.databases.get(path).unwrap().records.get_field(1, person, 2), "6000");
+    }
 }
 
 
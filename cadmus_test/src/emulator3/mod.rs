@@ -10,11 +10,12 @@ mod window;
 
 use super::emulator3::database_mgr::DatabaseMgr;
 use super::emulator3::database::Database;
-use super::emulator3::script_mgr::ScriptMgr;
+use super::emulator3::script_mgr::{file_triggers, ScriptMgr};
 use super::emulator3::window_mgr::WindowMgr;
 
 use std::path::{Path, PathBuf};
 
+use crate::dbobjects::scripting::trigger::TriggerEvent;
 use crate::shell::Host;
 
 struct ManagerRefs<'a> {
@@ -78,6 +79,7 @@ impl Emulator {
 
     pub fn run_test_on_file(&mut self, test_name: &str, path: &str) -> Result<(), EmulatorErr>{
         self.state.active_database = path.to_string();
+        let first_window = !self.window_mgr.windows.values().any(|window| window.database == path);
         let tests = &self.load_file(path).file.tests;
         let test = match tests
             .iter()
@@ -85,6 +87,16 @@ impl Emulator {
                 Some(inner) => inner.clone(),
                 None => return Err(EmulatorErr::UnknownTest(test_name.to_string()))
         };
+        /* Globals only last the session, so each test starts with them
+         * empty. */
+        for database in self.database_mgr.databases.values_mut() {
+            database.records.reset_globals();
+        }
+        if first_window {
+            let file = &self.database_mgr.databases.get(path).unwrap().file;
+            self.script_mgr.pending.extend(file_triggers(file, TriggerEvent::OnFirstWindowOpen));
+            self.script_mgr.pending.extend(file_triggers(file, TriggerEvent::OnWindowOpen));
+        }
         println!("Running test: {} on file: {}", test.name, path);
         let status = self.script_mgr.run_script(test.clone(), ManagerRefs { window_mgr: &mut self.window_mgr, database_mgr: &mut self.database_mgr },  &mut self.state);
         Ok(())
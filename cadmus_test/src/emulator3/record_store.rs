@@ -2,66 +2,180 @@
 use std::collections::HashMap;
 use std::cmp::Ordering;
 
+use crate::dbobjects::schema::field::{ValidationTrigger, ValidationType};
 use crate::dbobjects::schema::table::{Table, TableID};
 
+/* Each field holds one value per repetition, the first being the field's
+ * own value. */
 #[derive(Debug, PartialEq)]
 pub struct Record {
     pub id: u32,
-    pub fields: Vec<(u32, String)>,
+    pub fields: Vec<(u32, Vec<String>)>,
 }
 
+/* A check a record failed on commit, with the error FileMaker reports for
+ * it. */
+#[derive(Debug, PartialEq)]
+pub struct ValidationFailure {
+    pub field: u32,
+    pub code: u32,
+}
+
+/* Global fields are kept out of the records, as they hold one value per
+ * table for the session whichever record they're read through. They start
+ * each test empty. */
 #[derive(Debug, PartialEq)]
 pub struct RecordStore {
     pub records_by_table: HashMap<TableID, Vec<Record>>,
+    pub globals: HashMap<(TableID, u32), Vec<String>>,
 }
 
 impl RecordStore {
     pub fn new(tables: &Vec<Table>) -> Self {
+        let globals = tables.iter()
+            .flat_map(|table| table.fields.values()
+                .filter(|field| field.global)
+                .map(|field| ((table.id, field.id), vec![String::new(); field.repetitions.max(1) as usize])))
+            .collect::<HashMap<_, _>>();
         Self {
             records_by_table: tables.iter().map(|table| (table.id, vec![])).collect(),
+            globals,
         }
     }
 
+    pub fn reset_globals(&mut self) {
+        for values in self.globals.values_mut() {
+            values.iter_mut().for_each(|value| value.clear());
+        }
+    }
+
+    pub fn is_global(&self, table: u32, field: u32) -> bool {
+        self.globals.contains_key(&(table, field))
+    }
+
     pub fn new_record(&mut self, table: &Table) -> u32 {
         let records = self.records_by_table.get_mut(&table.id).unwrap();
 
         records.push(Record {
             id: records.iter().max_by(|a, b| a.id.cmp(&b.id)).unwrap_or(&Record { id: 0, fields: vec![] }).id + 1,
-            fields: table.fields.iter().map(|field| (field.1.id, String::new())).collect(),
+            fields: table.fields.values()
+                .filter(|field| !field.global)
+                .map(|field| (field.id, vec![String::new(); field.repetitions.max(1) as usize]))
+                .collect(),
         });
 
         records.iter().max_by(|a, b| a.id.cmp(&b.id)).unwrap().id
     }
 
-    pub fn set_field(&mut self, table: u32, record: u32, field: u32, value: String) {
-        let records = self.records_by_table.get_mut(&table).unwrap();
-        let record = &mut records.iter_mut().find(|search| search.id == record).unwrap();
-        let field = record.fields.iter_mut().find(|search| search.0 == field).unwrap();
+    /* Returns false if there was no such record. */
+    pub fn delete_record(&mut self, table: u32, record: u32) -> bool {
+        let Some(records) = self.records_by_table.get_mut(&table) else {
+            return false
+        };
+        let before = records.len();
+        records.retain(|search| search.id != record);
+        records.len() != before
+    }
 
-        field.1 = value;
+    pub fn set_field(&mut self, table: u32, record: u32, field: u32, value: String) {
+        self.set_repetition(table, record, field, 1, value);
     }
 
     pub fn get_field(&self, table: u32, record: u32, field: u32) -> String {
-        let records = self.records_by_table.get(&table).unwrap();
-        let record = &mut records.iter().find(|search| search.id == record).unwrap();
-        let field = record.fields.iter().find(|search| search.0 == field).unwrap();
-        field.1.clone()
+        self.get_repetition(table, record, field, 1)
+    }
+
+    /* Returns false when the field has fewer repetitions, as FileMaker
+     * leaves the record unchanged. Repetitions count from 1, and the record
+     * is ignored for globals. */
+    pub fn set_repetition(&mut self, table: u32, record: u32, field: u32, repetition: u32, value: String) -> bool {
+        let values = match self.globals.get_mut(&(table, field)) {
+            Some(global) => global,
+            None => {
+                let records = self.records_by_table.get_mut(&table).unwrap();
+                let record = records.iter_mut().find(|search| search.id == record).unwrap();
+                &mut record.fields.iter_mut().find(|search| search.0 == field).unwrap().1
+            }
+        };
+
+        match (repetition as usize).checked_sub(1).and_then(|index| values.get_mut(index)) {
+            Some(slot) => { *slot = value; true }
+            None => false,
+        }
+    }
+
+    /* Repetitions past the last are empty. */
+    pub fn get_repetition(&self, table: u32, record: u32, field: u32, repetition: u32) -> String {
+        let values = match self.globals.get(&(table, field)) {
+            Some(global) => global,
+            None => {
+                let records = self.records_by_table.get(&table).unwrap();
+                let record = records.iter().find(|search| search.id == record).unwrap();
+                &record.fields.iter().find(|search| search.0 == field).unwrap().1
+            }
+        };
+        (repetition as usize).checked_sub(1)
+            .and_then(|index| values.get(index))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /* The value relations match `record` on, which is only the first
+     * repetition. */
+    pub fn match_value<'a>(&'a self, table: u32, record: &'a Record, field: u32) -> &'a str {
+        match self.globals.get(&(table, field)) {
+            Some(global) => &global[0],
+            None => &record.fields.iter().find(|search| search.0 == field).unwrap().1[0],
+        }
+    }
+
+    /* Runs the checks of fields validated on commit against `record`. Empty
+     * values only fail `not_empty`, and calculations and value lists are left
+     * to the caller, as they need more than the stored records. */
+    pub fn validate_record(&self, table: &Table, record: u32) -> Result<(), ValidationFailure> {
+        let records = self.records_by_table.get(&table.id).unwrap();
+        for (id, field) in &table.fields {
+            if field.validation.trigger != ValidationTrigger::OnCommit {
+                continue;
+            }
+            let value = self.get_field(table.id, record, *id);
+            let others = records.iter()
+                .filter(|other| other.id != record)
+                .filter_map(|other| other.fields.iter().find(|search| search.0 == *id))
+                .map(|other| &other.1[0]);
+
+            for check in &field.validation.checks {
+                let code = match check {
+                    ValidationType::NotEmpty if value.is_empty() => 509,
+                    _ if value.is_empty() => continue,
+                    ValidationType::Unique if others.clone().any(|other| *other == value) => 504,
+                    ValidationType::Required if !others.clone().any(|other| *other == value) => 505,
+                    ValidationType::Range { start, end } => match value.parse::<f64>() {
+                        Ok(n) if n >= *start as f64 && n <= *end as f64 => continue,
+                        _ => 503,
+                    },
+                    ValidationType::MaxChars(max) if value.chars().count() > *max => 511,
+                    _ => continue,
+                };
+                return Err(ValidationFailure { field: *id, code });
+            }
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{RecordStore, Table};
-    use crate::dbobjects::schema::field::*;
-    use std::collections::BTreeMap;
+    use super::{RecordStore, Table, ValidationFailure};
+    use crate::dbobjects::{metadata::Metadata, schema::field::*};
+    use std::collections::{BTreeMap, HashMap};
     #[test]
     fn records_test() {
         let mut tables = vec![];
         let mut table = Table {
             id: 1,
             name: "Person".to_string(),
-            created_by: String::new(),
-            modified_by: String::new(),
+            metadata: Metadata::default(),
             comment: String::new(),
             fields: BTreeMap::new(),
         };
@@ -99,4 +213,95 @@ mod tests {
         records.set_field(1, 1, 2, "Jeff".to_string());
         assert_eq!(records.get_field(1, 1, 2), "Jeff");
     }
+
+    #[test]
+    fn repeating_fields() {
+        let mut table = Table {
+            id: 1,
+            name: "Person".to_string(),
+            metadata: Metadata::default(),
+            comment: String::new(),
+            fields: BTreeMap::new(),
+        };
+        table.fields.insert(1, Field::new(1, "phones".to_string()).repetitions(3));
+        let tables = vec![table];
+
+        let mut records = RecordStore::new(&tables);
+        records.new_record(&tables[0]);
+        assert!(records.set_repetition(1, 1, 1, 3, "555".to_string()));
+        assert!(!records.set_repetition(1, 1, 1, 4, "556".to_string()));
+        assert!(!records.set_repetition(1, 1, 1, 0, "557".to_string()));
+        records.set_field(1, 1, 1, "554".to_string());
+        assert_eq!(records.get_repetition(1, 1, 1, 1), "554");
+        assert_eq!(records.get_repetition(1, 1, 1, 2), "");
+        assert_eq!(records.get_repetition(1, 1, 1, 3), "555");
+        assert_eq!(records.get_repetition(1, 1, 1, 4), "");
+    }
+
+    #[test]
+    fn global_fields() {
+        let mut table = Table {
+            id: 1,
+            name: "Person".to_string(),
+            metadata: Metadata::default(),
+            comment: String::new(),
+            fields: BTreeMap::new(),
+        };
+        table.fields.insert(1, Field::new(1, "name".to_string()));
+        table.fields.insert(2, Field::new(2, "theme".to_string()).global(true));
+        let tables = vec![table];
+
+        let mut records = RecordStore::new(&tables);
+        assert_eq!(records.get_field(1, 0, 2), "");
+
+        records.new_record(&tables[0]);
+        records.new_record(&tables[0]);
+        assert_eq!(records.records_by_table.get(&1).unwrap()[0].fields.len(), 1);
+        records.set_field(1, 1, 2, "dark".to_string());
+        assert_eq!(records.get_field(1, 2, 2), "dark");
+        assert_eq!(records.get_field(1, 0, 2), "dark");
+
+        records.reset_globals();
+        assert_eq!(records.get_field(1, 2, 2), "");
+    }
+
+    #[test]
+    fn validate_on_commit() {
+        let mut table = Table {
+            id: 1,
+            name: "Person".to_string(),
+            metadata: Metadata::default(),
+            comment: String::new(),
+            fields: BTreeMap::new(),
+        };
+        let validation = |checks| Validation {
+            trigger: ValidationTrigger::OnCommit,
+            user_override: false,
+            checks,
+            message: String::new(),
+        };
+        table.fields.insert(1, Field::new(1, "id".to_string())
+            .validation(validation(vec![ValidationType::NotEmpty, ValidationType::Unique])));
+        table.fields.insert(2, Field::new(2, "age".to_string())
+            .validation(validation(vec![ValidationType::Range { start: 0, end: 150 }])));
+        table.fields.insert(3, Field::new(3, "initials".to_string())
+            .validation(validation(vec![ValidationType::MaxChars(3)])));
+        let tables = vec![table];
+
+        let mut records = RecordStore::new(&tables);
+        records.new_record(&tables[0]);
+        assert_eq!(records.validate_record(&tables[0], 1), Err(ValidationFailure { field: 1, code: 509 }));
+        records.set_field(1, 1, 1, "1".to_string());
+        assert_eq!(records.validate_record(&tables[0], 1), Ok(()));
+        records.set_field(1, 1, 2, "200".to_string());
+        assert_eq!(records.validate_record(&tables[0], 1), Err(ValidationFailure { field: 2, code: 503 }));
+        records.set_field(1, 1, 2, "20".to_string());
+        records.set_field(1, 1, 3, "ABCD".to_string());
+        assert_eq!(records.validate_record(&tables[0], 1), Err(ValidationFailure { field: 3, code: 511 }));
+        records.set_field(1, 1, 3, "ABC".to_string());
+
+        records.new_record(&tables[0]);
+        records.set_field(1, 2, 1, "1".to_string());
+        assert_eq!(records.validate_record(&tables[0], 2), Err(ValidationFailure { field: 1, code: 504 }));
+    }
 }
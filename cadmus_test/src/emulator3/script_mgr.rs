@@ -7,17 +7,29 @@ use super::ManagerRefs;
 
 use std::path::Path;
 
-use crate::dbobjects::scripting::arguments::ScriptSelection;
-use crate::dbobjects::scripting::{script::Script, instructions::Instruction};
+use crate::dbobjects::file::File;
+use crate::dbobjects::layout::{LayoutObject, LayoutObjectKind};
+use crate::dbobjects::reference::FieldReference;
+use crate::dbobjects::scripting::arguments::{LayoutSelection, RecordSelection, ScriptSelection};
+use crate::dbobjects::scripting::{script::Script, instructions::Instruction, trigger::{ScriptTrigger, TriggerEvent}};
 use crate::dbobjects::calculation::Calculation;
+use crate::dbobjects::schema::field::{ValidationTrigger, ValidationType};
 
 
 pub struct ScriptMgr {
     pub program_counters: Vec<(u32, Script)>,
     pub variables: Vec<Vec<(String, String)>>,
+    /* Parameter of each running script, alongside its locals. */
+    pub parameters: Vec<String>,
     pub globals: Vec<(String, String)>,
+    /* Value passed to Exit Script by the last script to finish. */
+    pub last_result: String,
+    /* Scripts fired by triggers, run in order once the current step is done. */
+    pub pending: Vec<Script>,
 
     pub loop_stack: Vec<u32>,
+    /* Error number of the last step, as returned by Get(LastError). */
+    pub last_error: u32,
 }
 
 #[derive(Debug)]
@@ -29,8 +41,12 @@ impl ScriptMgr {
         Self {
             program_counters: vec![],
             variables: vec![],
+            parameters: vec![],
             globals: vec![],
+            last_result: String::new(),
+            pending: vec![],
             loop_stack: vec![],
+            last_error: 0,
         }
     }
 
@@ -64,13 +80,30 @@ impl ScriptMgr {
     pub fn run_script(&mut self, test: Script, managers: ManagerRefs, state: &mut EmulatorState) -> Result<(), ScriptErr> {
         self.program_counters.push((0, test));
         self.variables.push(vec![]);
+        self.parameters.push(String::new());
+        self.run_pending();
 
         let (window_mgr, db_mgr) = (managers.window_mgr, managers.database_mgr);
 
         while let Some(mut ip) = self.program_counters.pop() {
+            /* A caller whose last step was the call has nothing to resume. */
+            if ip.0 as usize >= ip.1.instructions.len() {
+                self.finish_script();
+                continue;
+            }
             let cur_instr = &ip.1.instructions[ip.0 as usize];
             match &cur_instr.instruction {
                 Instruction::PerformScript { script, args } => {
+                    let parameter = args.eval(&EmulatorContext {
+                        database_mgr: &db_mgr,
+                        variables: self.variables.last().unwrap(),
+                        globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
+                        window_mgr: &window_mgr,
+                        state: &state,
+                    }).unwrap_or_default();
+
                     /* Case where the script is in another data_source:
                      * 1. check if there is a window open for that data source.
                      * 2a. if not, open one, and change the context of the emulator to be looking
@@ -113,6 +146,8 @@ impl ScriptMgr {
                                 database_mgr: &db_mgr,
                                 variables: self.variables.last().unwrap(),
                                 globals: &self.globals,
+                                script_parameter: self.parameters.last().unwrap(),
+                                script_result: &self.last_result,
                                 window_mgr: &window_mgr,
                                 state: &state,
                             }).unwrap();
@@ -127,6 +162,7 @@ impl ScriptMgr {
                     ip.0 += 1;
                     self.program_counters.push(ip.clone());
                     self.variables.push(vec![]);
+                    self.parameters.push(parameter);
                     self.program_counters.push((0, script.clone()));
                     ip = self.program_counters.pop().unwrap();
                 },
@@ -153,8 +189,109 @@ impl ScriptMgr {
                     let record_id = db_mgr.databases.get_mut(&state.active_database).unwrap()
                         .records.new_record(&table);
 
-                    window.append_record_to_found_sets(record_id, table_id, db_mgr.databases.get(&state.active_database).unwrap());
+                    let database = db_mgr.databases.get(&state.active_database).unwrap();
+                    window.append_record_to_found_sets(record_id, table_id, database);
+                    self.pending.extend(layout_triggers(&database.file, window.layout_id, TriggerEvent::OnRecordLoad));
+
+                    ip.0 += 1;
+                },
+                Instruction::DeleteRecordRequest => {
+                    let database = db_mgr.databases.get(&state.active_database).unwrap();
+                    let window = window_mgr.windows.get(&state.active_window).unwrap();
+                    let occ = database.file.layouts.iter()
+                        .find(|search| search.id == window.layout_id).unwrap()
+                        .occurrence.table_occurrence_id;
+                    let current_set = window.found_sets
+                        .iter()
+                        .find(|set| set.table_occurrence_ref.table_occurrence_id == occ)
+                        .unwrap();
 
+                    self.last_error = 0;
+                    if let Some(cursor) = current_set.cursor {
+                        let from = (current_set.table_occurrence_ref.clone(), current_set.records[cursor as usize]);
+                        let deleted = db_mgr.delete_record(from, &state.active_database);
+
+                        /* Related records deleted with it leave the found
+                         * sets too. */
+                        let database = db_mgr.databases.get(&state.active_database).unwrap();
+                        let window = window_mgr.windows.get_mut(&state.active_window).unwrap();
+                        for (occurrence, record) in deleted {
+                            let base = database.file.schema.relation_graph.nodes.iter()
+                                .find(|node| node.id == occurrence.table_occurrence_id)
+                                .map(|node| node.base.clone())
+                                .unwrap();
+                            window.remove_record_from_found_sets(record, &base, database);
+                        }
+                        if current_cursor(&database.file, window).is_some() {
+                            self.pending.extend(layout_triggers(&database.file, window.layout_id, TriggerEvent::OnRecordLoad));
+                        }
+                    } else {
+                        self.last_error = 101;
+                    }
+                    ip.0 += 1;
+                },
+                Instruction::CommitRecordsRequests => {
+                    let window = window_mgr.windows.get(&state.active_window).unwrap();
+                    let database = db_mgr.databases.get(&state.active_database).unwrap();
+
+                    let occ = database.file.layouts.iter()
+                        .find(|search| search.id == window.layout_id).unwrap()
+                        .occurrence.table_occurrence_id;
+
+                    let table = database.file.schema.relation_graph.nodes.iter()
+                        .find(|search| search.id == occ)
+                        .and_then(|found| database.file.schema.tables.iter()
+                            .find(|search| search.id == found.base.table_id))
+                        .unwrap();
+
+                    let current_set = window.found_sets
+                        .iter()
+                        .find(|set| set.table_occurrence_ref.table_occurrence_id == occ)
+                        .unwrap();
+
+                    self.last_error = 0;
+                    if let Some(cursor) = current_set.cursor {
+                        let record = current_set.records[cursor as usize];
+                        let context = EmulatorContext {
+                            database_mgr: &*db_mgr,
+                            variables: self.variables.last().unwrap(),
+                            globals: &self.globals,
+                            script_parameter: self.parameters.last().unwrap(),
+                            script_result: &self.last_result,
+                            window_mgr: &*window_mgr,
+                            state: &*state,
+                        };
+
+                        /* Calculations need the emulator's context, so they
+                         * are checked once the stored values pass. */
+                        let failure = match database.records.validate_record(table, record) {
+                            Err(failure) => Some((&table.fields[&failure.field], failure.code)),
+                            Ok(()) => table.fields.values()
+                                .filter(|field| field.validation.trigger == ValidationTrigger::OnCommit)
+                                .find_map(|field| field.validation.checks.iter().find_map(|check| match check {
+                                    /* A calculation that can't be evaluated is
+                                     * a calculation error (1200), not a failed
+                                     * validation. */
+                                    ValidationType::Calculation(calc) => match calc.eval(&context) {
+                                        Ok(result) if result == "true" => None,
+                                        Ok(_) => Some((field, 507)),
+                                        Err(_) => Some((field, 1200)),
+                                    },
+                                    _ => None,
+                                })),
+                        };
+
+                        if let Some((field, code)) = failure {
+                            if field.validation.message.is_empty() {
+                                eprintln!("Validation failed for field \"{}\".", field.name);
+                            } else {
+                                eprintln!("{}", field.validation.message);
+                            }
+                            self.last_error = code;
+                        } else {
+                            self.pending.extend(layout_triggers(&database.file, window.layout_id, TriggerEvent::OnRecordCommit));
+                        }
+                    }
                     ip.0 += 1;
                 },
                 Instruction::SetVariable { name, value, repetition } => {
@@ -162,14 +299,20 @@ impl ScriptMgr {
                         database_mgr: &*db_mgr,
                         variables: self.variables.last().unwrap(),
                         globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
                         window_mgr: &*window_mgr,
                         state: &*state,
                     };
 
                     let val = value.eval(&context).unwrap();
-                    let rep = repetition.eval(&context).unwrap();
-
-                    self.set_var(name, val);
+                    /* Later repetitions are kept as `$name[n]`, and the
+                     * first is the variable itself. */
+                    match repetition_number(repetition, &context) {
+                        Some(1) => self.set_var(name, val),
+                        Some(rep) => self.set_var(&format!("{}[{}]", name, rep), val),
+                        None => eprintln!("Invalid repetition for variable {}.", name),
+                    }
                     ip.0 += 1;
                 },
                 Instruction::SetField { field, value, repetition } => {
@@ -177,10 +320,13 @@ impl ScriptMgr {
                         database_mgr: &*db_mgr,
                         variables: self.variables.last().unwrap(),
                         globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
                         window_mgr: &*window_mgr,
                         state: &*state,
                     };
                     let val = value.eval(&context).unwrap();
+                    let rep = repetition_number(repetition, &context);
 
 
                     let occurrence = db_mgr.databases.get(&state.active_database)
@@ -198,12 +344,30 @@ impl ScriptMgr {
                         .find(|set| set.table_occurrence_ref.table_occurrence_id == occurrence.id)
                         .unwrap();
 
-                    if current_set.cursor.is_none() { eprintln!("Unable to set field, as there are no records present for this table."); ip.0 += 1; continue; }
-
-                    if ds == 0 {
+                    /* The step is skipped on failure rather than ending the
+                     * script. Globals can be set without a record. */
+                    let global = ds == 0 && db_mgr.databases.get(&state.active_database).unwrap()
+                        .records.is_global(source_table, field.field_id);
+                    if current_set.cursor.is_none() && !global {
+                        eprintln!("Unable to set field, as there are no records present for this table.");
+                    } else if rep.is_none() {
+                        eprintln!("Invalid repetition for field.");
+                    } else if ds == 0 {
                         // Same data source as starting table
-                        db_mgr.databases.get_mut(&state.active_database).unwrap()
-                            .records.set_field(source_table, current_set.records[current_set.cursor.unwrap() as usize], field.field_id, val)
+                        let rep = rep.unwrap();
+                        let record = current_set.cursor
+                            .map(|cursor| current_set.records[cursor as usize])
+                            .unwrap_or(0);
+                        if db_mgr.databases.get_mut(&state.active_database).unwrap()
+                            .records.set_repetition(source_table, record, field.field_id, rep, val) {
+                            if !global {
+                                db_mgr.apply_lookups(source_table, record, field.field_id, &state.active_database);
+                            }
+                            let file = &db_mgr.databases.get(&state.active_database).unwrap().file;
+                            self.pending.extend(field_triggers(file, window.layout_id, field, TriggerEvent::OnObjectModify));
+                        } else {
+                            eprintln!("Unable to set repetition {} of a field with fewer repetitions.", rep);
+                        }
                     } else {
                         let source = db_mgr.databases.get(&state.active_database).unwrap()
                             .file.data_sources.iter()
@@ -212,7 +376,7 @@ impl ScriptMgr {
                             .unwrap();
 
                         let other_handle = db_mgr.databases.get_mut(&source).unwrap();
-                        other_handle.records.set_field(source_table, 1, field.field_id, val);
+                        other_handle.records.set_repetition(source_table, 1, field.field_id, rep.unwrap(), val);
                     }
                     // println!("{:?}", db_mgr.databases.get(&state.active_database).unwrap()
                     //     .records.records_by_table.get(&source_table));
@@ -230,11 +394,14 @@ impl ScriptMgr {
                         database_mgr: &*db_mgr,
                         variables: self.variables.last().unwrap(),
                         globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
                         window_mgr: &*window_mgr,
                         state: &*state,
                     };
                     let exit = condition.eval(&context).unwrap();
                     if exit == "true" {
+                        self.loop_stack.pop();
                         let mut depth = 1;
                         while ip.0 < ip.1.instructions.len() as u32 && depth != 0 {
                             ip.0 += 1;
@@ -249,6 +416,152 @@ impl ScriptMgr {
                         ip.0 += 1;
                     }
                 },
+                Instruction::If { condition } => {
+                    let context = EmulatorContext {
+                        database_mgr: &*db_mgr,
+                        variables: self.variables.last().unwrap(),
+                        globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
+                        window_mgr: &*window_mgr,
+                        state: &*state,
+                    };
+                    let mut branch = ip.0;
+                    let mut condition = condition.clone();
+                    ip.0 = loop {
+                        if condition.eval(&context).unwrap() == "true" {
+                            break branch + 1;
+                        }
+                        branch = next_branch(&ip.1, branch);
+                        match ip.1.instructions.get(branch as usize).map(|step| &step.instruction) {
+                            Some(Instruction::ElseIf { condition: next }) => condition = next.clone(),
+                            _ => break branch + 1,
+                        }
+                    };
+                }
+                /* Reaching another branch means the one before it ran. */
+                Instruction::ElseIf { .. } | Instruction::Else => {
+                    let mut branch = ip.0;
+                    while !matches!(ip.1.instructions.get(branch as usize).map(|step| &step.instruction),
+                        Some(Instruction::EndIf) | None) {
+                        branch = next_branch(&ip.1, branch);
+                    }
+                    ip.0 = branch + 1;
+                }
+                Instruction::EndIf => {
+                    ip.0 += 1;
+                }
+                Instruction::ExitScript { value } => {
+                    let context = EmulatorContext {
+                        database_mgr: &*db_mgr,
+                        variables: self.variables.last().unwrap(),
+                        globals: &self.globals,
+                        script_parameter: self.parameters.last().unwrap(),
+                        script_result: &self.last_result,
+                        window_mgr: &*window_mgr,
+                        state: &*state,
+                    };
+                    let result = value.eval(&context).unwrap_or_default();
+                    self.last_result = result;
+                    ip.0 = ip.1.instructions.len() as u32;
+                }
+                Instruction::GoToLayout { layout, .. } => {
+                    let database = db_mgr.databases.get(&state.active_database).unwrap();
+                    let current = window_mgr.windows.get(&state.active_window).unwrap().layout_id;
+                    let target = match layout {
+                        LayoutSelection::Current => Some(current),
+                        LayoutSelection::FromList(reference) => database.file.layouts.iter()
+                            .any(|search| search.id == reference.layout_id)
+                            .then_some(reference.layout_id),
+                        LayoutSelection::NameByCalculation(calc) | LayoutSelection::NumberByCalculation(calc) => {
+                            let context = EmulatorContext {
+                                database_mgr: &*db_mgr,
+                                variables: self.variables.last().unwrap(),
+                                globals: &self.globals,
+                                script_parameter: self.parameters.last().unwrap(),
+                                script_result: &self.last_result,
+                                window_mgr: &*window_mgr,
+                                state: &*state,
+                            };
+                            let value = calc.eval(&context).unwrap_or_default();
+                            database.file.layouts.iter()
+                                .find(|search| match layout {
+                                    LayoutSelection::NameByCalculation(_) => search.name == value,
+                                    _ => value.parse::<u32>() == Ok(search.id),
+                                })
+                                .map(|found| found.id)
+                        }
+                    };
+                    let window = window_mgr.windows.get_mut(&state.active_window).unwrap();
+
+                    self.last_error = 0;
+                    match target {
+                        /* Going to the layout already shown runs no triggers. */
+                        Some(target) if target == current => {}
+                        Some(target) => {
+                            self.pending.extend(layout_triggers(&database.file, current, TriggerEvent::OnLayoutExit));
+                            window.layout_id = target;
+                            self.pending.extend(layout_triggers(&database.file, target, TriggerEvent::OnLayoutEnter));
+                            if current_cursor(&database.file, window).is_some() {
+                                self.pending.extend(layout_triggers(&database.file, target, TriggerEvent::OnRecordLoad));
+                            }
+                        }
+                        None => self.last_error = 105,
+                    }
+                    ip.0 += 1;
+                }
+                Instruction::GoToRecordRequestPage { record } => {
+                    let database = db_mgr.databases.get(&state.active_database).unwrap();
+                    let target = match record {
+                        RecordSelection::ByCalc(calc) => {
+                            let context = EmulatorContext {
+                                database_mgr: &*db_mgr,
+                                variables: self.variables.last().unwrap(),
+                                globals: &self.globals,
+                                script_parameter: self.parameters.last().unwrap(),
+                                script_result: &self.last_result,
+                                window_mgr: &*window_mgr,
+                                state: &*state,
+                            };
+                            calc.eval(&context).ok().and_then(|value| value.parse::<u32>().ok())
+                        }
+                        _ => None,
+                    };
+                    let window = window_mgr.windows.get_mut(&state.active_window).unwrap();
+                    let occurrence = database.file.layouts.iter()
+                        .find(|search| search.id == window.layout_id).unwrap()
+                        .occurrence.table_occurrence_id;
+                    let current_set = window.found_sets.iter_mut()
+                        .find(|set| set.table_occurrence_ref.table_occurrence_id == occurrence)
+                        .unwrap();
+
+                    self.last_error = 0;
+                    if let Some(cursor) = current_set.cursor {
+                        let last = current_set.records.len() as u32 - 1;
+                        /* Record numbers given by calculation count from 1. */
+                        let moved = match record {
+                            RecordSelection::First => Some(0),
+                            RecordSelection::Last => Some(last),
+                            RecordSelection::Next => (cursor < last).then(|| cursor + 1),
+                            RecordSelection::Previous => cursor.checked_sub(1),
+                            RecordSelection::ByCalc(_) => target
+                                .and_then(|n| n.checked_sub(1))
+                                .map(|n| n.min(last)),
+                        };
+                        match moved {
+                            Some(moved) => {
+                                current_set.cursor = Some(moved);
+                                if moved != cursor {
+                                    self.pending.extend(layout_triggers(&database.file, window.layout_id, TriggerEvent::OnRecordLoad));
+                                }
+                            }
+                            None => self.last_error = 101,
+                        }
+                    } else {
+                        self.last_error = 101;
+                    }
+                    ip.0 += 1;
+                }
                 _ => {
                     ip.0 += 1;
                 }
@@ -256,14 +569,114 @@ impl ScriptMgr {
 
             if ip.0 < ip.1.instructions.len() as u32 {
                 self.program_counters.push(ip);
-            } else if self.program_counters.len() == 1 {
-                break;
+            } else {
+                self.finish_script();
             }
-
+            self.run_pending();
         }
 
         Ok(())
     }
+
+    /* Starts the scripts fired by triggers. The first fired runs first, and
+     * the interrupted script resumes once they have all finished. */
+    fn run_pending(&mut self) {
+        for script in self.pending.drain(..).rev() {
+            self.variables.push(vec![]);
+            self.parameters.push(String::new());
+            self.program_counters.push((0, script));
+        }
+    }
+
+    /* Drops the locals and parameter of a script that has finished. The first
+     * script's are kept so they can be inspected once it has run. */
+    fn finish_script(&mut self) {
+        if self.variables.len() > 1 {
+            self.variables.pop();
+            self.parameters.pop();
+        }
+    }
+}
+
+/* Repetition given to Set Field or Set Variable, where an empty
+ * calculation means the first. */
+fn repetition_number(repetition: &Calculation, context: &EmulatorContext) -> Option<u32> {
+    match repetition.eval(context).ok()?.as_str() {
+        "" => Some(1),
+        value => value.parse::<f64>().ok()
+            .filter(|n| *n >= 1.0 && n.fract() == 0.0)
+            .map(|n| n as u32),
+    }
+}
+
+/* Scripts run by `triggers` for `event`. Only scripts in the same file are
+ * run for now. */
+fn trigger_scripts(file: &File, triggers: &[ScriptTrigger], event: TriggerEvent) -> Vec<Script> {
+    triggers.iter()
+        .filter(|trigger| trigger.event == event && trigger.script.data_source == 0)
+        .filter_map(|trigger| file.scripts.iter().find(|script| script.id == trigger.script.script_id))
+        .cloned()
+        .collect()
+}
+
+pub fn file_triggers(file: &File, event: TriggerEvent) -> Vec<Script> {
+    trigger_scripts(file, &file.triggers, event)
+}
+
+fn layout_triggers(file: &File, layout_id: u32, event: TriggerEvent) -> Vec<Script> {
+    file.layouts.iter()
+        .find(|layout| layout.id == layout_id)
+        .map(|layout| trigger_scripts(file, &layout.triggers, event))
+        .unwrap_or_default()
+}
+
+/* Scripts fired by every object on the layout showing `field`, including
+ * those inside portals and tab panels. */
+fn field_triggers(file: &File, layout_id: u32, field: &FieldReference, event: TriggerEvent) -> Vec<Script> {
+    fn walk(objects: &[LayoutObject], field: &FieldReference, found: &mut Vec<ScriptTrigger>) {
+        for object in objects {
+            match &object.kind {
+                LayoutObjectKind::Field { field: shown } if shown == field => found.extend(object.triggers.iter().cloned()),
+                LayoutObjectKind::Portal { objects, .. } => walk(objects, field, found),
+                LayoutObjectKind::TabControl { tabs } => tabs.iter().for_each(|tab| walk(&tab.objects, field, found)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut found = vec![];
+    if let Some(layout) = file.layouts.iter().find(|layout| layout.id == layout_id) {
+        for part in &layout.parts {
+            walk(&part.objects, field, &mut found);
+        }
+    }
+    trigger_scripts(file, &found, event)
+}
+
+fn current_cursor(file: &File, window: &super::window::Window) -> Option<u32> {
+    let occurrence = file.layouts.iter()
+        .find(|layout| layout.id == window.layout_id)?
+        .occurrence.table_occurrence_id;
+    window.found_sets.iter()
+        .find(|set| set.table_occurrence_ref.table_occurrence_id == occurrence)?
+        .cursor
+}
+
+/* Index of the `Else If`, `Else` or `End If` ending the branch which starts
+ * at `from`. */
+fn next_branch(script: &Script, from: u32) -> u32 {
+    let mut depth = 0;
+    let mut ip = from + 1;
+    while (ip as usize) < script.instructions.len() {
+        match script.instructions[ip as usize].instruction {
+            Instruction::If { .. } => depth += 1,
+            Instruction::EndIf if depth > 0 => depth -= 1,
+            Instruction::ElseIf { .. } | Instruction::Else | Instruction::EndIf if depth == 0 => return ip,
+            _ => {}
+        }
+        ip += 1;
+    }
+    ip
 }
 
 #[cfg(test)]
@@ -271,6 +684,10 @@ mod tests {
     use super::super::*;
     use std::path::Path;
     use std::fs::read_to_string;
+    use crate::dbobjects::reference::LayoutReference;
+    use crate::dbobjects::scripting::arguments::{LayoutAnimation, LayoutSelection};
+    use crate::dbobjects::scripting::instructions::Instruction;
+    use crate::dbobjects::scripting::script::ScriptStep;
     #[test]
     fn quotes_make_10_quotes() {
         let mut emulator = Emulator::new();
@@ -289,7 +706,7 @@ mod tests {
             assert_eq!(db.records.records_by_table.get(&1).unwrap()
                 .iter()
                 .find(|record| record.id == quotes_set.records[x as usize])
-                .unwrap().fields[0].1, x.to_string())
+                .unwrap().fields[0].1[0], x.to_string())
         }
 
         println!("Running next test.");
@@ -301,7 +718,125 @@ mod tests {
                 .iter()
                 // .inspect(|rec| println!("{:?}", rec))
                 .find(|record| record.id == x + 1)
-                .unwrap().fields[0].1, (x+1).to_string())
+                .unwrap().fields[0].1[0], (x+1).to_string())
         }
     }
+
+    #[test]
+    fn nested_loops_and_branches() {
+        let mut emulator = Emulator::new();
+        let _ = emulator.run_test_on_file("nested_loops", "test_data/cad_files/control_flow.cad");
+        assert_eq!(emulator.script_mgr.get_var("$total"), Some(String::from("6")));
+        assert_eq!(emulator.script_mgr.get_var("$branch"), Some(String::from("2")));
+    }
+
+    #[test]
+    fn script_parameters_and_results() {
+        let mut emulator = Emulator::new();
+        let _ = emulator.run_test_on_file("call_with_params", "test_data/cad_files/script_params.cad");
+        assert_eq!(emulator.script_mgr.get_var("$result"), Some(String::from("5")));
+        /* The called script's $sum is its own. */
+        assert_eq!(emulator.script_mgr.get_var("$sum"), Some(String::from("1")));
+    }
+
+    #[test]
+    fn repeating_fields() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/repetitions.cad";
+        let _ = emulator.run_test_on_file("fill_phones", path);
+
+        let records = &emulator.database_mgr.databases.get(path).unwrap().records;
+        let phones = (1..=4).map(|rep| records.get_repetition(1, 1, 1, rep)).collect::<Vec<_>>();
+        assert_eq!(phones, vec!["111", "333!", "333", ""]);
+        assert_eq!(emulator.script_mgr.get_var("$second"), Some(String::from("333!")));
+        assert_eq!(emulator.script_mgr.get_var("$tags"), Some(String::from("a")));
+        assert_eq!(emulator.script_mgr.get_var("$tag"), Some(String::from("b")));
+    }
+
+    #[test]
+    fn global_fields() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/globals.cad";
+        let _ = emulator.run_test_on_file("set_theme", path);
+
+        /* Set before any Settings record exists, and read from every
+         * Person record and through the relation. */
+        assert_eq!(emulator.script_mgr.get_var("$direct"), Some(String::from("dark")));
+        assert_eq!(emulator.script_mgr.get_var("$related"), Some(String::from("dark")));
+        assert_eq!(emulator.script_mgr.get_var("$other"), Some(String::from("dark")));
+
+        /* The next test starts with the global empty again. */
+        let _ = emulator.run_test_on_file("append_theme", path);
+        let records = &emulator.database_mgr.databases.get(path).unwrap().records;
+        assert_eq!(records.get_field(2, 0, 2), "light");
+    }
+
+    #[test]
+    fn delete_related_records() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/relation_options.cad";
+        let _ = emulator.run_test_on_file("delete_person", path);
+
+        /* Deleting the second person takes its phones with it. */
+        let records = &emulator.database_mgr.databases.get(path).unwrap().records;
+        let ids = |table: u32| records.records_by_table[&table].iter().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(ids(1), vec![1]);
+        assert_eq!(ids(2), vec![1]);
+        let window = emulator.window_mgr.windows.get(&emulator.state.active_window).unwrap();
+        assert!(window.found_sets.iter().all(|set| set.records == vec![1] && set.cursor == Some(0)));
+    }
+
+    #[test]
+    fn triggers_fire_on_events() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/triggers.cad";
+        let _ = emulator.run_test_on_file("fire_triggers", path);
+
+        /* Each trigger script appends its own letter to the current record. */
+        let records = &emulator.database_mgr.databases.get(path).unwrap().records;
+        assert_eq!(records.get_field(1, 1, 2), "lomclxe");
+        assert_eq!(records.get_field(1, 2, 2), "l");
+        let window = emulator.window_mgr.windows.get(&emulator.state.active_window).unwrap();
+        assert_eq!(window.layout_id, 2);
+
+        /* The file is already open the second time round. */
+        let _ = emulator.run_test_on_file("fire_triggers", path);
+        let records = &emulator.database_mgr.databases.get(path).unwrap().records;
+        assert_eq!(records.records_by_table.get(&1).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn validation_calculations_on_commit() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/validation.cad";
+        let _ = emulator.run_test_on_file("commit_valid", path);
+        assert_eq!(emulator.script_mgr.last_error, 0);
+        let _ = emulator.run_test_on_file("commit_invalid", path);
+        assert_eq!(emulator.script_mgr.last_error, 507);
+        /* Dividing by the empty ratio can't be evaluated. */
+        let _ = emulator.run_test_on_file("commit_calculation_error", path);
+        assert_eq!(emulator.script_mgr.last_error, 1200);
+    }
+
+    #[test]
+    fn go_to_missing_layout() {
+        let mut emulator = Emulator::new();
+        let path = "test_data/cad_files/triggers.cad";
+        let _ = emulator.run_test_on_file("fire_triggers", path);
+
+        let mut script = emulator.database_mgr.databases.get(path).unwrap().file.tests[0].clone();
+        script.instructions = vec![ScriptStep {
+            id: 0,
+            instruction: Instruction::GoToLayout {
+                layout: LayoutSelection::FromList(LayoutReference { file_name: String::new(), layout_id: 99 }),
+                animation: LayoutAnimation::None,
+            },
+        }];
+        let _ = emulator.script_mgr.run_script(script, ManagerRefs { window_mgr: &mut emulator.window_mgr, database_mgr: &mut emulator.database_mgr }, &mut emulator.state);
+
+        /* A layout missing from the file is an error, not a move. */
+        assert_eq!(emulator.script_mgr.last_error, 105);
+        let window = emulator.window_mgr.windows.get(&emulator.state.active_window).unwrap();
+        assert_eq!(window.layout_id, 2);
+    }
 }
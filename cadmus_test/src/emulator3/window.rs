@@ -1,6 +1,7 @@
 
 use super::{database::Database, find::*};
 
+use crate::dbobjects::reference::TableReference;
 use crate::dbobjects::schema::table::{Table, TableID};
 
 pub struct Window {
@@ -63,4 +64,29 @@ impl Window {
 
 
     }
+
+    /* The cursor stays on the record after the deleted one, or moves back
+     * to the new last record. */
+    pub fn remove_record_from_found_sets(&mut self, record_id: u32, table: &TableReference, database: &Database) {
+        let effected_occurrences = database.file.schema.relation_graph
+            .nodes
+            .iter()
+            .filter(|occ| occ.base == *table)
+            .map(|occ| occ.id)
+            .collect::<Vec<_>>();
+
+        for set in self.found_sets.iter_mut()
+            .filter(|set| effected_occurrences.contains(&set.table_occurrence_ref.table_occurrence_id)) {
+            let Some(index) = set.records.iter().position(|id| *id == record_id) else {
+                continue;
+            };
+            set.records.remove(index);
+            set.cursor = match set.cursor {
+                _ if set.records.is_empty() => None,
+                Some(cursor) if cursor as usize > index => Some(cursor - 1),
+                Some(cursor) => Some(cursor.min(set.records.len() as u32 - 1)),
+                None => None,
+            };
+        }
+    }
 }
@@ -1,3 +1,7 @@
+use common::{cadlang, dbobjects, hbam2, shell};
+
+mod emulator3;
+
 fn main() {
     println!("Hello, world!");
 }
@@ -42,6 +42,42 @@ pub fn put_int(mut n: usize) -> Vec<u8> {
     res
 }
 
+/* Variable length values are stored as a length byte, followed by that
+ * many bytes. Integers stored this way are big-endian. */
+pub fn get_var_int(bytes: &[u8]) -> Option<u64> {
+    let (len, rest) = bytes.split_first()?;
+    let len = *len as usize;
+    if len > 8 || rest.len() < len { return None }
+    Some(rest[..len].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+pub fn put_var_int(n: u64) -> Vec<u8> {
+    let bytes = n.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let mut res = vec![(8 - skip) as u8];
+    res.extend(&bytes[skip..]);
+    res
+}
+
+/* Timestamps (e.g key 64515 in object metadata) are variable length ints,
+ * counting 1/16ths of a nanosecond since 2001-01-01 00:00:00 UTC.
+ * Converted to and from unix epoch seconds, sub-second precision is dropped.
+ * 8 bytes only reach 2037-07-14, so later times are written as the last one. */
+pub const FM_EPOCH_OFFSET: i64 = 978_307_200;
+pub const FM_TICKS_PER_SECOND: u64 = 16_000_000_000;
+
+pub fn get_timestamp(bytes: &[u8]) -> Option<i64> {
+    let ticks = get_var_int(bytes)?;
+    Some((ticks / FM_TICKS_PER_SECOND) as i64 + FM_EPOCH_OFFSET)
+}
+
+pub fn put_timestamp(epoch_seconds: i64) -> Vec<u8> {
+    let ticks = (epoch_seconds.saturating_sub(FM_EPOCH_OFFSET).max(0) as u64).saturating_mul(FM_TICKS_PER_SECOND);
+    let mut res = vec![8];
+    res.extend(ticks.to_be_bytes());
+    res
+}
+
 pub fn fm_string_encrypt(text: &str) -> Vec<u8> {
     text
         .bytes()
@@ -92,6 +128,34 @@ mod tests {
         assert_eq!(put_int(1024), &[0, 0, 4, 0]);
     }
 
+    #[test]
+    fn var_int_testing() {
+        assert_eq!(get_var_int(&[1, 67]), Some(67));
+        assert_eq!(get_var_int(&[2, 4, 0]), Some(1024));
+        assert_eq!(get_var_int(&[4, 0, 0, 4, 0]), Some(1024));
+        assert_eq!(get_var_int(&[2, 4]), None);
+        assert_eq!(get_var_int(&[]), None);
+        assert_eq!(put_var_int(0), &[1, 0]);
+        assert_eq!(put_var_int(67), &[1, 67]);
+        assert_eq!(put_var_int(1024), &[2, 4, 0]);
+        for n in [1, 255, 256, 65536, u32::MAX as u64, u64::MAX] {
+            assert_eq!(get_var_int(&put_var_int(n)), Some(n));
+        }
+    }
+
+    #[test]
+    fn timestamp_testing() {
+        /* Table metadata from test_data/fmp_files/mixed.fmp12,
+         * 2023-10-13 23:11:57 and 2023-10-13 23:24:33 UTC. */
+        assert_eq!(get_timestamp(&[8, 159, 162, 134, 128, 5, 135, 69, 16]), Some(1697238717));
+        assert_eq!(get_timestamp(&[8, 159, 162, 145, 128, 5, 147, 52, 144]), Some(1697239473));
+        assert_eq!(get_timestamp(&put_timestamp(1697238717)), Some(1697238717));
+        assert_eq!(put_timestamp(FM_EPOCH_OFFSET), &[8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(get_timestamp(&[4, 0, 0]), None);
+        assert_eq!(put_timestamp(4_000_000_000), &[8, 255, 255, 255, 255, 255, 255, 255, 255]);
+        assert_eq!(put_timestamp(i64::MIN), put_timestamp(FM_EPOCH_OFFSET));
+    }
+
     #[test]
     fn string_testing() {
         assert_eq!(fm_string_decrypt(&[0x7e, 0x22]), "$x");
@@ -3,31 +3,34 @@ use std::path::Component;
 
 use crate::dbobjects::{
     file::*,
-    layout::Layout, 
+    layout::{Layout, LayoutObject, LayoutObjectKind, LayoutPart, LayoutTab},
     scripting::{
         script::*,
         instructions::*,
-        arguments::*
+        arguments::*,
+        trigger::ScriptTrigger,
     },
     reference::{
         FieldReference,
+        LayoutReference,
         ScriptReference,
         TableReference,
         TableOccurrenceReference,
     },
-    calculation::Calculation,
+    calculation::{Calculation, token::{Function, GetArgument, Token as CalcToken}},
     schema::{
         field::*, relationgraph::{
             graph::RelationGraph,
             relation::{
-                Relation, RelationComparison, RelationCriteria
+                Relation, RelationComparison, RelationCriteria, RelationOptions, RelationSort
             },
             table_occurrence::TableOccurrence
         }, table::Table, Schema
     },
 };
-use super::{cadscript::proto_instruction::ProtoScriptSelection, staging::*};
-use super::cadscript::proto_instruction::{ProtoInstruction, ProtoFieldSelection};
+use super::{cadscript::proto_instruction::ProtoScriptSelection, staging::*, token::Token};
+use super::cadscript::proto_instruction::{ProtoInstruction, ProtoFieldSelection, ProtoLayoutSelection, ProtoRecordSelection};
+use super::cadscript::steps::lower_unit_step;
 
 use std::collections::{HashSet, HashMap, BTreeMap};
 
@@ -111,11 +114,13 @@ pub fn generate_relation_refs(stage: &Stage, live_occurrences: &mut Vec<TableOcc
             id: (*i) as u32,
             other_occurrence: 0,
             criteria: vec![],
+            options: RelationOptions::default(),
         };
         let mut tmp2 = Relation {
             id: (*i) as u32,
             other_occurrence: 0,
             criteria: vec![],
+            options: RelationOptions::default(),
         };
 
         let mut occ1_id = 0;
@@ -192,11 +197,41 @@ pub fn generate_relation_refs(stage: &Stage, live_occurrences: &mut Vec<TableOcc
             tmp1.criteria.push(crit1);
             tmp2.criteria.push(crit2);
         }
+
+        /* Each side's options live on the relation leading to it. */
+        let occurrence2 = live_occurrences.iter().find(|occ| occ.id == occ2_id).unwrap();
+        let occurrence1 = live_occurrences.iter().find(|occ| occ.id == occ1_id).unwrap();
+        tmp1.options = generate_relation_options(relation, occurrence2, stage, externs);
+        tmp2.options = generate_relation_options(relation, occurrence1, stage, externs);
         live_occurrences.iter_mut().find(|occ| occ.id == occ1_id).unwrap().relations.push(tmp1);
         live_occurrences.iter_mut().find(|occ| occ.id == occ2_id).unwrap().relations.push(tmp2);
     }
 }
 
+fn generate_relation_options(relation: &StagedRelation, side: &TableOccurrence, stage: &Stage, externs: &HashMap<u32, Stage>) -> RelationOptions {
+    let table = if side.base.data_source == 0 {
+        stage.tables.values().find(|table| table.id as u32 == side.base.table_id)
+    } else {
+        externs.get(&side.base.data_source)
+            .and_then(|source| source.tables.values().find(|table| table.id as u32 == side.base.table_id))
+    }.unwrap();
+
+    RelationOptions {
+        allow_creation: relation.allow_creation.iter().any(|occ| occ.value == side.name),
+        delete_related: relation.delete_related.iter().any(|occ| occ.value == side.name),
+        sort: relation.sort.iter()
+            .filter(|sort| sort.occurrence.value == side.name)
+            .map(|sort| RelationSort {
+                field: table.fields.iter()
+                    .find(|field| field.1.name.value == sort.field.value)
+                    .map(|field| *field.0 as u32)
+                    .unwrap(),
+                descending: sort.descending,
+            })
+            .collect(),
+    }
+}
+
 pub fn generate_table_occurrences(stage: &Stage, externs: &HashMap<u32, Stage> ) -> Vec<TableOccurrence> {
     let mut live_occurrences = generate_table_occurrence_refs(stage, externs);
     generate_relation_refs(stage, &mut live_occurrences, &externs);
@@ -270,9 +305,12 @@ fn generate_tables(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Relatio
         let mut tmp = Table {
             id: table.id as u32,
             name: table.name.value.clone(),
-            created_by: String::from("admin"),
-            modified_by: String::from("admin"),
-            comment: String::new(),
+            metadata: crate::dbobjects::metadata::Metadata {
+                created_by: String::from("admin"),
+                modified_by: String::from("admin"),
+                modified_at: None,
+            },
+            comment: table.comments.join("\n"),
             fields: BTreeMap::new(),
         };
 
@@ -291,9 +329,15 @@ fn generate_tables(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Relatio
                     increment: *increment,
                     trigger: trigger.clone(),
                 },
-                StagedAutoEntryType::Lookup { from, to } => {
-                    AutoEntryType::NA
-                }
+                StagedAutoEntryType::Lookup { from, occurrence, field } => AutoEntryType::Lookup {
+                    from: TableOccurrenceReference {
+                        data_source: 0,
+                        table_occurrence_id: graph.nodes.iter()
+                            .find(|node| node.name == from.value)
+                            .unwrap().id,
+                    },
+                    to: resolve_field_reference(&occurrence.value, &field.value, stage, externs, graph),
+                },
                 StagedAutoEntryType::Creation(preset) => AutoEntryType::Creation(preset.clone()),
                 StagedAutoEntryType::Modification(preset) => AutoEntryType::Modification(preset.clone()),
                 StagedAutoEntryType::Calculation { code, noreplace } => {
@@ -304,9 +348,39 @@ fn generate_tables(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Relatio
                 }
             };
 
+            let checks_ = field.validation.checks.iter()
+                .map(|check| match check {
+                    StagedValidationType::NotEmpty => ValidationType::NotEmpty,
+                    StagedValidationType::Unique => ValidationType::Unique,
+                    StagedValidationType::Required => ValidationType::Required,
+                    StagedValidationType::MemberOf(value_list) => ValidationType::MemberOf(value_list.value.clone()),
+                    StagedValidationType::Range { start, end } => ValidationType::Range { start: *start, end: *end },
+                    StagedValidationType::Calculation(code) => {
+                        ValidationType::Calculation(encode_calculation(code, stage, externs, graph))
+                    }
+                    StagedValidationType::MaxChars(max) => ValidationType::MaxChars(*max),
+                })
+                .collect();
+
+            let kind_ = match &field.kind {
+                StagedFieldKind::Simple => FieldKind::Simple,
+                StagedFieldKind::Calculation { code, stored } => FieldKind::Calculation {
+                    code: encode_calculation(code, stage, externs, graph),
+                    stored: *stored,
+                },
+                StagedFieldKind::Summary { kind, field: summarised } => FieldKind::Summary {
+                    kind: *kind,
+                    field: table.fields.values()
+                        .find(|f| f.name.value == summarised.value)
+                        .map(|f| f.id as u32)
+                        .expect("Summarised field should be validated."),
+                },
+            };
+
             tmp.fields.insert((*j) as u32, Field {
                 id: (*j) as u32,
                 name: field.name.value.clone(),
+                kind: kind_,
                 repetitions: field.repetitions as u8,
                 dtype: field.dtype.clone(),
                 autoentry: AutoEntry {
@@ -314,7 +388,7 @@ fn generate_tables(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Relatio
                     nomodify: nomodify_,
                 },
                 validation: Validation {
-                    checks: vec![],
+                    checks: checks_,
                     message: field.validation.message.clone(),
                     trigger: field.validation.trigger.clone(),
                     user_override: field.validation.user_override,
@@ -322,6 +396,11 @@ fn generate_tables(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Relatio
                 created_by: String::new(),
                 modified_by: String::new(),
                 global: field.global,
+                comment: if field.comment.is_empty() {
+                    field.comments.join("\n")
+                } else {
+                    field.comment.clone()
+                },
             });
         }
         tables.push(tmp)
@@ -338,25 +417,82 @@ pub fn build_schema(stage: &Stage, externs: &HashMap<u32, Stage>) -> Schema {
     result
 }
 
+/* Resolves `occurrence::field` to the ids it names, looking in the external
+ * file for occurrences of tables from other files. */
+fn resolve_field_reference(occurrence: &str, field: &str, stage: &Stage, externs: &HashMap<u32, Stage>, graph: &RelationGraph) -> FieldReference {
+    let node = graph.nodes
+        .iter()
+        .find(|search_occ| search_occ.name == *occurrence)
+        .unwrap();
+
+    let occ_id = node.id;
+    let (external_ds, table_id) = (node.base.data_source, node.base.table_id);
+
+    let field_id_ = if external_ds == 0 {
+        stage.tables.get(&(table_id as u16)).unwrap()
+            .fields.iter()
+            .find(|search| search.1.name.value == *field)
+            .map(|f| f.1.id)
+            .unwrap()
+    } else {
+        let data_source = externs.get(&external_ds).unwrap();
+        data_source.tables.get(&(table_id as u16)).unwrap()
+            .fields.iter()
+            .find(|search| search.1.name.value == *field)
+            .map(|f| f.1.id)
+            .unwrap()
+    };
+
+    FieldReference { 
+        data_source: external_ds,
+        table_occurrence_id: occ_id, 
+        field_id: field_id_ as u32,
+    }
+}
+
 fn build_script_objects(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &RelationGraph) -> Vec<(u32, Script)> {
     let mut finished_scripts = vec![];
     for (t, (i, script)) in stage.scripts.iter().map(|script| (0, script)).chain(stage.tests.iter().map(|test| (1, test)).into_iter()) {
         let mut tmp = Script {
             id: (*i) as u32,
             name: script.name.clone(),
-            args: vec![],
+            args: script.params.clone(),
             instructions: vec![],
             metadata: crate::dbobjects::metadata::Metadata {
                 created_by: String::new(),
                 modified_by: String::new(),
+                modified_at: None,
             }
         };
 
-        tmp.instructions = script.instructions.iter().enumerate().map(|(i, instr)| {
-           ScriptStep { id: i as u32, instruction: match instr {
+        /* Parameters arrive as one JSON object, and each is read into the
+         * local of the same name before the script body runs. */
+        let prologue = script.params.iter().map(|param| Instruction::SetVariable {
+            name: format!("${}", param),
+            value: Calculation::from_tokens(&vec![
+                CalcToken::Function(Function::JSONGetElement),
+                CalcToken::OpenParen,
+                CalcToken::Function(Function::Get(GetArgument::ScriptParameter)),
+                CalcToken::SemiColon,
+                CalcToken::String(param.clone()),
+                CalcToken::CloseParen,
+            ]),
+            repetition: Calculation::from_text("1"),
+        });
+
+        tmp.instructions = prologue.chain(script.instructions.iter().map(|instr| {
+           match instr {
                 ProtoInstruction::PerformScript { script, args } => {
-                    println!("DOes get here into the PERFORM SCRIPT SCIRPTIPSIPCISPCIPSCSCPI");
                     let script_ = match script {
+                        ProtoScriptSelection::UnresolvedReference { data_source, script } if data_source.is_empty() => {
+                            ScriptSelection::FromList(ScriptReference {
+                                data_source: 0,
+                                script_id: stage.scripts.iter()
+                                    .find(|script_| script_.1.name == *script)
+                                    .map(|script_| (*script_.0) as u32)
+                                    .expect("Script names are checked in validation."),
+                            })
+                        }
                         ProtoScriptSelection::UnresolvedReference { data_source, script } => {
                             let ds = stage.data_sources.iter()
                                 .find(|ds| ds.1.name == *data_source)
@@ -382,40 +518,40 @@ fn build_script_objects(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Re
                         args: encode_calculation(&args.0, stage, externs, graph) 
                     }
                 },
-                ProtoInstruction::NewRecordRequest => Instruction::NewRecordRequest,
+                ProtoInstruction::GoToLayout { layout, animation } => Instruction::GoToLayout {
+                    layout: match layout {
+                        ProtoLayoutSelection::UnresolvedName(name) => LayoutSelection::FromList(LayoutReference {
+                            file_name: String::new(),
+                            layout_id: stage.layouts.values()
+                                .find(|layout| layout.name.value == *name)
+                                .map(|layout| layout.id as u32)
+                                .expect("Layout names are checked in validation."),
+                        }),
+                        ProtoLayoutSelection::Calculation(calc) =>
+                            LayoutSelection::NameByCalculation(encode_calculation(calc.0.as_str(), stage, externs, graph)),
+                    },
+                    animation: animation.clone(),
+                },
+                ProtoInstruction::GoToRecordRequestPage { record } => Instruction::GoToRecordRequestPage {
+                    record: match record {
+                        ProtoRecordSelection::First => RecordSelection::First,
+                        ProtoRecordSelection::Last => RecordSelection::Last,
+                        ProtoRecordSelection::Next => RecordSelection::Next,
+                        ProtoRecordSelection::Previous => RecordSelection::Previous,
+                        ProtoRecordSelection::ByCalc(calc) =>
+                            RecordSelection::ByCalc(encode_calculation(calc.0.as_str(), stage, externs, graph)),
+                    },
+                },
+                ProtoInstruction::ExitScript { value } => Instruction::ExitScript {
+                    value: encode_calculation(value.0.as_str(), stage, externs, graph),
+                },
+                ProtoInstruction::Print { expr } => Instruction::Print {
+                    expr: encode_calculation(expr.0.as_str(), stage, externs, graph),
+                },
                 ProtoInstruction::SetField { field, value, repetition } => Instruction::SetField {
                     field: match field {
-                        ProtoFieldSelection::UnresolvedReference { occurrence, field } => {
-                            let node = graph.nodes
-                                .iter()
-                                .find(|search_occ| search_occ.name == *occurrence)
-                                .unwrap();
-
-                            let occ_id = node.id;
-                            let (external_ds, table_id) = (node.base.data_source, node.base.table_id);
-
-                            let field_id_ = if external_ds == 0 {
-                                stage.tables.get(&(table_id as u16)).unwrap()
-                                    .fields.iter()
-                                    .find(|search| search.1.name.value == *field)
-                                    .map(|f| f.1.id)
-                                    .unwrap()
-                            } else {
-                                let data_source = externs.get(&external_ds).unwrap();
-                                data_source.tables.get(&(table_id as u16)).unwrap()
-                                    .fields.iter()
-                                    .find(|search| search.1.name.value == *field)
-                                    .map(|f| f.1.id)
-                                    .unwrap()
-                            };
-
-                            FieldReference { 
-                                data_source: external_ds,
-                                table_occurrence_id: occ_id, 
-                                field_id: field_id_ as u32,
-                            }
-
-                        },
+                        ProtoFieldSelection::UnresolvedReference { occurrence, field } =>
+                            resolve_field_reference(occurrence, field, stage, externs, graph),
                     },
                     value: encode_calculation(value.0.as_str(), stage, externs, graph),
                     repetition: encode_calculation(repetition.0.as_str(), stage, externs, graph),
@@ -425,42 +561,130 @@ fn build_script_objects(stage: &Stage, externs: &HashMap<u32, Stage>, graph: &Re
                     value: encode_calculation(value.0.as_str(), stage, externs, graph),
                     repetition: encode_calculation(repetition.0.as_str(), stage, externs, graph)
                 },
+                ProtoInstruction::If { condition } => Instruction::If {
+                    condition: encode_calculation(condition.0.as_str(), stage, externs, graph),
+                },
+                ProtoInstruction::ElseIf { condition } => Instruction::ElseIf {
+                    condition: encode_calculation(condition.0.as_str(), stage, externs, graph),
+                },
+                ProtoInstruction::Else => Instruction::Else,
+                ProtoInstruction::EndIf => Instruction::EndIf,
                 ProtoInstruction::Loop => Instruction::Loop,
                 ProtoInstruction::EndLoop => Instruction::EndLoop,
                 ProtoInstruction::ExitLoopIf { condition } => Instruction::ExitLoopIf {
                     condition: encode_calculation(condition.0.as_str(), stage, externs, graph),
                 },
-                _ => Instruction::Print,
-           }
+                ProtoInstruction::Assert { expr } => Instruction::Assert {
+                    expr: encode_calculation(expr.0.as_str(), stage, externs, graph),
+                },
+                other => lower_unit_step(other).expect("Every step without arguments is in the step table."),
            }
-        }).collect();
+        })).enumerate().map(|(i, instruction)| ScriptStep { id: i as u32, instruction }).collect();
         finished_scripts.push((t, tmp));
     }
     finished_scripts
 }
 
+/* Finds a script named in a layout, locally or in another file's stage. */
+fn resolve_script_reference(data_source: Option<&Token>, script: &Token, stage: &Stage, externs: &HashMap<u32, Stage>) -> ScriptReference {
+    let (data_source_, scripts) = match data_source {
+        Some(data_source) => {
+            let ds = stage.data_sources.values()
+                .find(|ds| ds.name == data_source.value)
+                .unwrap();
+            (ds.id, &externs.get(&ds.id).unwrap().scripts)
+        }
+        None => (0, &stage.scripts),
+    };
+    let script_id_ = scripts.iter()
+        .find(|s| s.1.name == script.value)
+        .map(|s| *s.0)
+        .expect("Script names are checked in validation.");
+    ScriptReference {
+        data_source: data_source_,
+        script_id: script_id_ as u32,
+    }
+}
+
+fn build_triggers(triggers: &[StagedTrigger], stage: &Stage, externs: &HashMap<u32, Stage>) -> Vec<ScriptTrigger> {
+    triggers.iter().map(|trigger| ScriptTrigger {
+        event: trigger.event,
+        script: resolve_script_reference(trigger.data_source.as_ref(), &trigger.script, stage, externs),
+    }).collect()
+}
+
+fn build_layout_objects(objects: &[StagedLayoutObject], stage: &Stage, externs: &HashMap<u32, Stage>, graph: &RelationGraph) -> Vec<LayoutObject> {
+    objects.iter().map(|object| LayoutObject {
+        bounds: object.bounds,
+        kind: match &object.kind {
+            StagedLayoutObjectKind::Field { occurrence, field } => LayoutObjectKind::Field {
+                field: resolve_field_reference(&occurrence.value, &field.value, stage, externs, graph),
+            },
+            StagedLayoutObjectKind::Button { label, data_source, script } => LayoutObjectKind::Button {
+                label: label.clone(),
+                script: resolve_script_reference(data_source.as_ref(), script, stage, externs),
+            },
+            StagedLayoutObjectKind::Portal { occurrence, rows, objects } => LayoutObjectKind::Portal {
+                occurrence: TableOccurrenceReference {
+                    data_source: 0,
+                    table_occurrence_id: graph.nodes.iter()
+                        .find(|node| node.name == occurrence.value)
+                        .unwrap().id,
+                },
+                rows: *rows,
+                objects: build_layout_objects(objects, stage, externs, graph),
+            },
+            StagedLayoutObjectKind::TabControl { tabs } => LayoutObjectKind::TabControl {
+                tabs: tabs.iter().map(|tab| LayoutTab {
+                    name: tab.name.value.clone(),
+                    objects: build_layout_objects(&tab.objects, stage, externs, graph),
+                }).collect(),
+            },
+        },
+        triggers: build_triggers(&object.triggers, stage, externs),
+    }).collect()
+}
+
 pub fn build_file(stage: &Stage, working_dir: &Path) -> File {
+    build_file_with_externs(stage, working_dir, &load_externs(stage, working_dir))
+}
+
+/* `externs` are the compiled files the stage's data sources point at, see
+ * `load_externs`. References into them must already be validated. */
+pub fn build_file_with_externs(stage: &Stage, working_dir: &Path, externs: &HashMap<u32, Stage>) -> File {
     let mut layouts_ = vec![];
-    let externs = load_externs(stage, working_dir);
-    let schema_ = build_schema(&stage, &externs);
+    let schema_ = build_schema(&stage, externs);
 
     for (i, layout) in &stage.layouts {
         let occurrence_id = schema_.relation_graph.nodes
             .iter()
             .find(|l| l.name == layout.base_occurrence.value).unwrap().id;
 
+        let mut parts_ = layout.parts.iter()
+            .map(|part| LayoutPart {
+                kind: part.kind,
+                height: part.height,
+                objects: build_layout_objects(&part.objects, stage, externs, &schema_.relation_graph),
+            })
+            .collect::<Vec<_>>();
+        /* Parts always stack in the same order, whatever order they were
+         * written in. */
+        parts_.sort_by_key(|part| part.kind);
+
         let tmp = Layout {
             id: (*i) as u32,
             name: layout.name.value.clone(),
             occurrence: TableOccurrenceReference {
                 data_source: 0,
                 table_occurrence_id: occurrence_id,
-            }
+            },
+            parts: parts_,
+            triggers: build_triggers(&layout.triggers, stage, externs),
         };
         layouts_.push(tmp);
     }
 
-    let (scripts_, tests_): (Vec<_>, Vec<_>) = build_script_objects(stage, &externs, &schema_.relation_graph)
+    let (scripts_, tests_): (Vec<_>, Vec<_>) = build_script_objects(stage, externs, &schema_.relation_graph)
         .into_iter().partition(|(t, _)| *t == 0);
 
     File {
@@ -471,13 +695,15 @@ pub fn build_file(stage: &Stage, working_dir: &Path) -> File {
         layouts: layouts_,
         scripts: scripts_.into_iter().map(|(_, script)| script).collect(),
         tests: tests_.into_iter().map(|(_, script)| script).collect(),
+        triggers: build_triggers(&stage.triggers, stage, externs),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::dbobjects::{schema::relationgraph::graph::*, data_source::*};
+    use crate::dbobjects::{schema::relationgraph::graph::*, data_source::*, layout::{Bounds, LayoutPartKind}};
+    use crate::dbobjects::scripting::trigger::TriggerEvent;
     use std::fs::read_to_string;
     #[test]
     fn basic_multi_file() {
@@ -513,7 +739,9 @@ mod tests {
                 occurrence: TableOccurrenceReference {
                     data_source: 0,
                     table_occurrence_id: 1,
-                }
+                },
+                parts: vec![],
+                triggers: vec![],
             },
             Layout {
                 id: 2,
@@ -521,7 +749,9 @@ mod tests {
                 occurrence: TableOccurrenceReference {
                     data_source: 0,
                     table_occurrence_id: 5,
-                }
+                },
+                parts: vec![],
+                triggers: vec![],
             }
         ];
         assert_eq!(file.layouts, expected_layouts);
@@ -531,17 +761,17 @@ mod tests {
                 id: 1,
                 name: String::from("Quotes"),
                 comment: String::new(),
-                created_by: String::from("admin"),
-                modified_by: String::from("admin"),
+                metadata: crate::dbobjects::metadata::Metadata { created_by: String::from("admin"), modified_by: String::from("admin"), modified_at: None },
                 fields: BTreeMap::from([
                     (1, Field {
                         id: 1,
                         name: String::from("id"),
+                        kind: FieldKind::Simple,
                         dtype: DataType::Number,
                         created_by: String::new(),
                         modified_by: String::new(),
                         validation: Validation {
-                            checks: vec![],
+                            checks: vec![ValidationType::Unique, ValidationType::Required],
                             message: String::new(),
                             trigger: ValidationTrigger::OnEntry,
                             user_override: true,
@@ -556,10 +786,12 @@ mod tests {
                         },
                         repetitions: 1,
                         global: false,
+                        comment: String::new(),
                     }),
                     (2, Field {
                         id: 2,
                         name: String::from("customer_id"),
+                        kind: FieldKind::Simple,
                         dtype: DataType::Text,
                         created_by: String::new(),
                         modified_by: String::new(),
@@ -575,10 +807,12 @@ mod tests {
                         },
                         repetitions: 1,
                         global: false,
+                        comment: String::new(),
                     }),
                     (3, Field {
                         id: 3,
                         name: String::from("price"),
+                        kind: FieldKind::Simple,
                         dtype: DataType::Number,
                         created_by: String::new(),
                         modified_by: String::new(),
@@ -594,6 +828,7 @@ mod tests {
                         },
                         repetitions: 1,
                         global: false,
+                        comment: String::new(),
                     }),
                 ]),
             },
@@ -601,12 +836,12 @@ mod tests {
                 id: 2,
                 name: String::from("MaterialJoin"),
                 comment: String::new(),
-                created_by: String::from("admin"),
-                modified_by: String::from("admin"),
+                metadata: crate::dbobjects::metadata::Metadata { created_by: String::from("admin"), modified_by: String::from("admin"), modified_at: None },
                 fields: BTreeMap::from([
                     (1, Field {
                         id: 1,
                         name: String::from("quote_id"),
+                        kind: FieldKind::Simple,
                         dtype: DataType::Number,
                         created_by: String::new(),
                         modified_by: String::new(),
@@ -622,10 +857,12 @@ mod tests {
                         },
                         repetitions: 1,
                         global: false,
+                        comment: String::new(),
                     }),
                     (2, Field {
                         id: 2,
                         name: String::from("material_id"),
+                        kind: FieldKind::Simple,
                         dtype: DataType::Number,
                         created_by: String::new(),
                         modified_by: String::new(),
@@ -641,6 +878,7 @@ mod tests {
                         },
                         repetitions: 1,
                         global: false,
+                        comment: String::new(),
                     }),
                 ]),
             },
@@ -674,6 +912,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         },
                         Relation {
                             id: 2,
@@ -685,6 +924,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         }
                     ],
                 },
@@ -706,6 +946,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         }
                     ],
                 },
@@ -736,6 +977,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         },
                     ],
                 },
@@ -757,6 +999,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         },
                         Relation {
                             id: 3,
@@ -768,6 +1011,7 @@ mod tests {
                                     comparison: RelationComparison::Equal,
                                 }
                             ],
+                            options: RelationOptions::default(),
                         }
                     ],
                 },
@@ -775,7 +1019,346 @@ mod tests {
         };
         assert_eq!(file.schema.relation_graph, expected_graph);
     }
-}
 
+    #[test]
+    fn comment_round_trip() {
+        let code = "
+            // Everyone we have ever quoted.
+            /* Imported from the
+             * old system. */
+            table %1 Person = {
+                // Surrogate key.
+                field %1 id = {
+                    datatype = Number,
+                },
+                field %2 name = {
+                    datatype = Text,
+                    comment = \"Full name\",
+                }
+            }";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let table = &file.schema.tables[0];
+        assert_eq!(table.comment, "Everyone we have ever quoted.\nImported from the\nold system.");
+        assert_eq!(table.fields[&1].comment, "Surrogate key.");
+        assert_eq!(table.fields[&2].comment, "Full name");
+
+        let mut file = file;
+        file.schema.tables[0].fields.get_mut(&2).unwrap().comment = String::from(r#"Full name, e.g. "Jo \ Jo""#);
+        let stage = parse(&lex(&file.to_cad()).unwrap()).unwrap();
+        assert_eq!(stage.tables[&1].comments, vec!["Everyone we have ever quoted.", "Imported from the", "old system."]);
+        assert_eq!(stage.tables[&1].fields[&1].comment, "Surrogate key.");
+        assert_eq!(stage.tables[&1].fields[&2].comment, r#"Full name, e.g. "Jo \ Jo""#);
+    }
+
+    #[test]
+    fn validation_checks() {
+        let code = "
+            table %1 Person = {
+                field %1 id = {
+                    datatype = Number,
+                    validate = always,
+                    override = false,
+                    not_empty = true,
+                    unique = true,
+                    range = { start = 1, end = 500 },
+                    validation_calc = |People::id > 0|,
+                    validation_message = \"Invalid ID chosen.\",
+                },
+                field %2 name = {
+                    datatype = Text,
+                    required = true,
+                    member_of = names,
+                    max_chars = 30,
+                },
+            }
+            table_occurrence %1 People : Person
+            ";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let fields = &file.schema.tables[0].fields;
+        assert_eq!(fields[&1].validation, Validation {
+            trigger: ValidationTrigger::OnCommit,
+            user_override: false,
+            checks: vec![
+                ValidationType::NotEmpty,
+                ValidationType::Unique,
+                ValidationType::Range { start: 1, end: 500 },
+                ValidationType::Calculation(encode_calculation("People::id > 0", &stage, &HashMap::new(), &file.schema.relation_graph)),
+            ],
+            message: String::from("Invalid ID chosen."),
+        });
+        assert_eq!(fields[&2].validation.checks, vec![
+            ValidationType::Required,
+            ValidationType::MemberOf(String::from("names")),
+            ValidationType::MaxChars(30),
+        ]);
+
+        let mut reparsed = parse(&lex(&file.to_cad()).unwrap()).unwrap();
+        let rebuilt = build_file(&mut reparsed, Path::new("."));
+        let rebuilt_fields = &rebuilt.schema.tables[0].fields;
+        assert_eq!(rebuilt_fields[&1].validation.message, "Invalid ID chosen.");
+        assert_eq!(rebuilt_fields[&1].validation.checks, fields[&1].validation.checks);
+        assert_eq!(rebuilt_fields[&2].validation, fields[&2].validation);
+    }
+
+    #[test]
+    fn lookup_autoentry() {
+        let code = "
+            table %1 Person = {
+                field %1 address_id = {
+                    datatype = Number,
+                },
+                field %2 city = {
+                    lookup = { start = Person_occ, related = Address_occ::city },
+                },
+            }
+            table %2 Address = {
+                field %1 id = {
+                    datatype = Number,
+                },
+                field %2 city = {
+                    datatype = Text,
+                },
+            }
+            table_occurrence %1 Person_occ : Person
+            table_occurrence %2 Address_occ : Address
+            relation %1 = Person_occ::address_id == Address_occ::id
+            ";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        assert_eq!(file.schema.tables[0].fields[&2].autoentry.definition, AutoEntryType::Lookup {
+            from: TableOccurrenceReference { data_source: 0, table_occurrence_id: 1 },
+            to: FieldReference { data_source: 0, table_occurrence_id: 2, field_id: 2 },
+        });
+    }
 
+    #[test]
+    fn calculation_and_summary_fields() {
+        let code = "
+            table %1 Invoice = {
+                field %1 amount = {
+                    datatype = Number,
+                },
+                field %2 doubled = {
+                    datatype = Number,
+                    calculation = |Invoices::amount * 2|,
+                    stored = false,
+                },
+                field %3 total = {
+                    datatype = Number,
+                    summary = total_of(amount),
+                },
+            }
+            table_occurrence %1 Invoices : Invoice
+            ";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let fields = &file.schema.tables[0].fields;
+        assert_eq!(fields[&1].kind, FieldKind::Simple);
+        assert!(matches!(fields[&2].kind, FieldKind::Calculation { stored: false, .. }));
+        assert_eq!(fields[&3].kind, FieldKind::Summary { kind: SummaryKind::Total, field: 1 });
+
+        let mut reparsed = parse(&lex(&file.to_cad()).unwrap()).unwrap();
+        let rebuilt = build_file(&mut reparsed, Path::new("."));
+        assert_eq!(rebuilt.schema.tables[0].fields, *fields);
+    }
 
+    #[test]
+    fn summary_kinds_round_trip() {
+        let summaries = SummaryKind::ALL.iter().enumerate()
+            .map(|(i, kind)| format!("field %{} summary_{} = {{ datatype = Number, summary = {}(amount), }},",
+                i + 2, i, kind.to_cad()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let code = format!("
+            table %1 Invoice = {{
+                field %1 amount = {{
+                    datatype = Number,
+                }},
+                {}
+            }}
+            table_occurrence %1 Invoices : Invoice
+            ", summaries);
+        let mut stage = parse(&lex(&code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let kinds = file.schema.tables[0].fields.values()
+            .filter_map(|field| match field.kind {
+                FieldKind::Summary { kind, field } => Some((kind, field)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, SummaryKind::ALL.iter().map(|kind| (*kind, 1)).collect::<Vec<_>>());
+
+        let mut reparsed = parse(&lex(&file.to_cad()).unwrap()).unwrap();
+        let rebuilt = build_file(&mut reparsed, Path::new("."));
+        assert_eq!(rebuilt.schema.tables[0].fields, file.schema.tables[0].fields);
+    }
+
+    #[test]
+    fn script_steps() {
+        let code = "
+            table %1 Person = {
+                field %1 id = {
+                    datatype = Number,
+                },
+            }
+            table_occurrence %1 Person_occ : Person
+            layout %1 Start : Person_occ = {
+            }
+            layout %2 People : Person_occ = {
+            }
+            script %1 steps = {
+              go_to_layout(People)
+              go_to_record(last)
+              show_all_records()
+              beep()
+              exit_script(|1|)
+            }
+            ";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let steps = file.scripts[0].instructions.iter().map(|step| step.instruction.clone()).collect::<Vec<_>>();
+        assert_eq!(steps[0], Instruction::GoToLayout {
+            layout: LayoutSelection::FromList(LayoutReference { file_name: String::new(), layout_id: 2 }),
+            animation: LayoutAnimation::None,
+        });
+        assert_eq!(steps[1], Instruction::GoToRecordRequestPage { record: RecordSelection::Last });
+        assert_eq!(steps[2], Instruction::ShowAllRecords);
+        assert_eq!(steps[3], Instruction::Beep);
+        assert!(matches!(steps[4], Instruction::ExitScript { .. }));
+    }
+
+    #[test]
+    fn script_parameters() {
+        let code = "
+            script %1 add(left, right) = {
+              exit_script(|$left + $right|)
+            }
+            script %2 caller = {
+              perform_script(add, |1|)
+            }
+            ";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let add = &file.scripts[0];
+        assert_eq!(add.args, vec!["left", "right"]);
+        assert_eq!(add.instructions.len(), 3);
+        assert!(matches!(&add.instructions[1].instruction, Instruction::SetVariable { name, .. } if name == "$right"));
+        assert!(matches!(add.instructions[2].instruction, Instruction::ExitScript { .. }));
+        assert!(add.to_cad(&file, HashMap::new()).starts_with("script %1 add(left, right) = {\n    ExitScript"));
+        assert!(matches!(&file.scripts[1].instructions[0].instruction, Instruction::PerformScript {
+            script: ScriptSelection::FromList(ScriptReference { data_source: 0, script_id: 1 }), ..
+        }));
+    }
+
+    #[test]
+    fn script_triggers() {
+        let file = read_to_string("./test_data/cad_files/triggers.cad").unwrap();
+        let mut stage = parse(&lex(&file).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("./test_data/cad_files"));
+        let trigger = |event, script_id| ScriptTrigger { event, script: ScriptReference { data_source: 0, script_id } };
+        assert_eq!(file.triggers, vec![trigger(TriggerEvent::OnFirstWindowOpen, 1)]);
+        assert_eq!(file.layouts[0].triggers, vec![
+            trigger(TriggerEvent::OnRecordLoad, 2),
+            trigger(TriggerEvent::OnRecordCommit, 3),
+            trigger(TriggerEvent::OnLayoutExit, 5),
+        ]);
+        assert_eq!(file.layouts[0].parts[0].objects[0].triggers, vec![trigger(TriggerEvent::OnObjectModify, 4)]);
+
+        let cad = file.layouts[0].to_cad(&file);
+        let reparsed = parse(&lex(&cad).unwrap()).unwrap();
+        let names = |stage: &Stage| stage.layouts[&1].triggers.iter()
+            .map(|t| (t.event, t.script.value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(names(&reparsed), names(&stage));
+    }
+
+    #[test]
+    fn layout_objects() {
+        let code = "
+            table %1 Person = {
+                field %1 id = {
+                    datatype = Number,
+                },
+                field %2 name = {
+                    datatype = Text,
+                },
+            }
+            table %2 Invoice = {
+                field %1 person_id = {
+                    datatype = Number,
+                },
+            }
+            table_occurrence %1 Person_occ : Person
+            table_occurrence %2 Invoice_occ : Invoice
+            relation %1 = Person_occ::id == Invoice_occ::person_id
+            layout %1 People : Person_occ = {
+                footer = { height = 20 },
+                body = {
+                    height = 300,
+                    field Person_occ::name = { top = 10, left = 10, width = 200, height = 20 },
+                    portal Invoice_occ = {
+                        top = 40,
+                        rows = 3,
+                        field Invoice_occ::person_id = {},
+                    },
+                    tab_control = {
+                        tab \"Tools\" = {
+                            button \"Hi\" = { perform_script = hello },
+                        },
+                    },
+                },
+            }
+            script %1 hello = {
+                print(|\"hi\"|)
+            }";
+        let mut stage = parse(&lex(code).unwrap()).unwrap();
+        let file = build_file(&mut stage, Path::new("."));
+        let parts = &file.layouts[0].parts;
+        assert_eq!(parts.iter().map(|p| p.kind).collect::<Vec<_>>(), vec![LayoutPartKind::Body, LayoutPartKind::Footer]);
+
+        let objects = &parts[0].objects;
+        assert_eq!(objects[0], LayoutObject {
+            bounds: Bounds { top: 10, left: 10, width: 200, height: 20 },
+            kind: LayoutObjectKind::Field {
+                field: FieldReference { data_source: 0, table_occurrence_id: 1, field_id: 2 },
+            },
+            triggers: vec![],
+        });
+        assert_eq!(objects[1].kind, LayoutObjectKind::Portal {
+            occurrence: TableOccurrenceReference { data_source: 0, table_occurrence_id: 2 },
+            rows: 3,
+            objects: vec![LayoutObject {
+                bounds: Bounds::default(),
+                kind: LayoutObjectKind::Field {
+                    field: FieldReference { data_source: 0, table_occurrence_id: 2, field_id: 1 },
+                },
+                triggers: vec![],
+            }],
+        });
+        assert_eq!(objects[2].kind, LayoutObjectKind::TabControl {
+            tabs: vec![LayoutTab {
+                name: String::from("Tools"),
+                objects: vec![LayoutObject {
+                    bounds: Bounds::default(),
+                    kind: LayoutObjectKind::Button {
+                        label: String::from("Hi"),
+                        script: ScriptReference { data_source: 0, script_id: 1 },
+                    },
+                    triggers: vec![],
+                }],
+            }],
+        });
+
+        let cad = file.layouts[0].to_cad(&file);
+        let reparsed = parse(&lex(&cad).unwrap()).unwrap();
+        assert_eq!(reparsed.layouts[&1].parts.len(), 2);
+        let bounds = |parts: &[StagedLayoutPart]| parts.iter()
+            .map(|p| p.objects.iter().map(|o| o.bounds).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut original = stage.layouts[&1].parts.clone();
+        original.sort_by_key(|p| p.kind);
+        assert_eq!(bounds(&reparsed.layouts[&1].parts), bounds(&original));
+    }
+}
@@ -1,39 +1,317 @@
-
+// Generated by scripts/script_instruction_gen.py, do not edit by hand.
 
 // No indirection, we store duplicate instruction names.
 
-
-
-pub struct ArgLookupTable([(&'static str, u8, &'static str); 16]);
+/* (step, position, label, required) */
+pub struct ArgLookupTable([(&'static str, u8, &'static str, bool); 304]);
 
 impl ArgLookupTable {
     pub const fn new() -> Self {
         Self([
-
-            ("perform_script", 0, "script"),
-            ("perform_script", 1, "args"),
-
-            ("go_to_record", 0, "record"),
-
-            ("set_variable", 0, "var"),
-            ("set_variable", 1, "expr"),
-            ("set_variable", 2, "rep"),
-
-            ("set_field", 0, "field"),
-            ("set_field", 1, "expr"),
-            ("set_field", 2, "repetition"),
-
-            ("go_to_layout", 0, "layout"),
-            ("go_to_layout", 1, "animation"),
-
-            ("if", 0, "expr"),
-
-            ("elif", 0, "expr"),
-
-            ("exit_loop_if", 0, "expr"),
-
-            ("assert", 0, "expr"),
-            ("print", 0, "expr"),
+            ("perform_script", 0, "script", true),
+            ("perform_script", 1, "args", false),
+            ("save_a_copy_as_xml", 0, "window_name", false),
+            ("save_a_copy_as_xml", 1, "destination", false),
+            ("go_to_layout", 0, "layout", true),
+            ("go_to_layout", 1, "animation", false),
+            ("delete_record_request", 0, "with_dialog", false),
+            ("delete_all_records", 0, "with_dialog", false),
+            ("insert_from_index", 0, "select", false),
+            ("insert_from_index", 1, "target", false),
+            ("insert_from_last_visited", 0, "select", false),
+            ("insert_from_last_visited", 1, "target", false),
+            ("insert_current_date", 0, "select", false),
+            ("insert_current_date", 1, "target", false),
+            ("insert_current_time", 0, "select", false),
+            ("insert_current_time", 1, "target", false),
+            ("go_to_record", 0, "record", true),
+            ("go_to_request", 0, "record", true),
+            ("go_to_record_request_page", 0, "record", true),
+            ("go_to_field", 0, "select", false),
+            ("go_to_field", 1, "field", false),
+            ("check_selection", 0, "select", false),
+            ("check_selection", 1, "field", false),
+            ("enter_find_mode", 0, "pause", false),
+            ("enter_find_mode", 1, "requests", false),
+            ("omit_multiple_records", 0, "with_dialog", false),
+            ("omit_multiple_records", 1, "records", false),
+            ("perform_find", 0, "requests", false),
+            ("show_hide_toolbars", 0, "action", false),
+            ("show_hide_toolbars", 1, "lock", false),
+            ("view_as", 0, "view", false),
+            ("adjust_window", 0, "action", false),
+            ("open_file", 0, "file", false),
+            ("open_file", 1, "open_hidden", false),
+            ("close_file", 0, "file", false),
+            ("import_records", 0, "source", false),
+            ("import_records", 1, "with_dialog", false),
+            ("import_records", 2, "target", false),
+            ("export_records", 0, "output_file", false),
+            ("export_records", 1, "with_dialog", false),
+            ("save_a_copy_as", 0, "output_file", false),
+            ("save_a_copy_as", 1, "with_dialog", false),
+            ("save_a_copy_as", 2, "kind", false),
+            ("sort_records", 0, "with_dialog", false),
+            ("sort_records", 1, "order", false),
+            ("relookup_field_contents", 0, "with_dialog", false),
+            ("relookup_field_contents", 1, "field", false),
+            ("enter_preview_mode", 0, "pause", false),
+            ("print_setup", 0, "with_dialog", false),
+            ("print", 0, "expr", false),
+            ("undo_redo", 0, "action", false),
+            ("cut", 0, "select", false),
+            ("cut", 1, "field", false),
+            ("copy", 0, "select", false),
+            ("copy", 1, "field", false),
+            ("paste", 0, "select", false),
+            ("paste", 1, "field", false),
+            ("clear", 0, "select", false),
+            ("clear", 1, "field", false),
+            ("revert_record_request", 0, "with_dialog", false),
+            ("enter_browser_mode", 0, "pause", false),
+            ("insert_picture", 0, "file", false),
+            ("send_event", 0, "target", false),
+            ("send_event", 1, "event", false),
+            ("insert_current_user_name", 0, "select", false),
+            ("insert_current_user_name", 1, "target", false),
+            ("insert_text", 0, "select", false),
+            ("insert_text", 1, "target", false),
+            ("insert_text", 2, "text", false),
+            ("pause_resume_script", 0, "duration", false),
+            ("send_mail", 0, "to", false),
+            ("send_mail", 1, "cc", false),
+            ("send_mail", 2, "bcc", false),
+            ("send_mail", 3, "subject", false),
+            ("send_mail", 4, "message", false),
+            ("send_mail", 5, "attachment", false),
+            ("send_mail", 6, "with_dialog", false),
+            ("send_dde_execute", 0, "service", false),
+            ("send_dde_execute", 1, "topic", false),
+            ("send_dde_execute", 2, "command", false),
+            ("dial_phone", 0, "number", false),
+            ("dial_phone", 1, "with_dialog", false),
+            ("speak", 0, "text", false),
+            ("speak", 1, "voice", false),
+            ("speak", 2, "wait", false),
+            ("perform_applescript", 0, "script", false),
+            ("if", 0, "expr", true),
+            ("exit_loop_if", 0, "expr", true),
+            ("go_to_related_record", 0, "occurrence", false),
+            ("go_to_related_record", 1, "layout", false),
+            ("go_to_related_record", 2, "match_found_set", false),
+            ("commit_records_requests", 0, "with_dialog", false),
+            ("commit_records_requests", 1, "skip_validation", false),
+            ("commit_records", 0, "with_dialog", false),
+            ("commit_records", 1, "skip_validation", false),
+            ("commit_requests", 0, "with_dialog", false),
+            ("commit_requests", 1, "skip_validation", false),
+            ("set_field", 0, "field", true),
+            ("set_field", 1, "expr", true),
+            ("set_field", 2, "repetition", false),
+            ("insert_calculated_result", 0, "select", false),
+            ("insert_calculated_result", 1, "target", false),
+            ("insert_calculated_result", 2, "expr", false),
+            ("refresh_window", 0, "flush_join_results", false),
+            ("refresh_window", 1, "flush_sql_data", false),
+            ("scroll_window", 0, "direction", false),
+            ("change_password", 0, "old_password", false),
+            ("change_password", 1, "new_password", false),
+            ("change_password", 2, "with_dialog", false),
+            ("set_multi_user", 0, "state", false),
+            ("allow_user_abort", 0, "state", false),
+            ("set_error_capture", 0, "state", false),
+            ("show_custom_dialog", 0, "title", false),
+            ("show_custom_dialog", 1, "message", false),
+            ("show_custom_dialog", 2, "buttons", false),
+            ("blank_line_comment", 0, "text", false),
+            ("replace_field_contents", 0, "with_dialog", false),
+            ("replace_field_contents", 1, "field", false),
+            ("replace_field_contents", 2, "expr", false),
+            ("show_hide_text_ruler", 0, "action", false),
+            ("set_use_system_formats", 0, "state", false),
+            ("recover_file", 0, "file", false),
+            ("recover_file", 1, "with_dialog", false),
+            ("save_a_copy_as_add_on_package", 0, "window_name", false),
+            ("save_a_copy_as_add_on_package", 1, "replace_uuids", false),
+            ("set_zoom_level", 0, "level", false),
+            ("set_zoom_level", 1, "lock", false),
+            ("go_to_portal_row", 0, "row", false),
+            ("go_to_portal_row", 1, "select", false),
+            ("go_to_portal_row", 2, "with_dialog", false),
+            ("exit_script", 0, "expr", false),
+            ("delete_portal_row", 0, "with_dialog", false),
+            ("open_url", 0, "url", false),
+            ("open_url", 1, "with_dialog", false),
+            ("open_url", 2, "in_external_browser", false),
+            ("allow_formatting_bar", 0, "state", false),
+            ("set_next_serial_value", 0, "field", false),
+            ("set_next_serial_value", 1, "expr", false),
+            ("execute_sql", 0, "query", false),
+            ("execute_sql", 1, "with_dialog", false),
+            ("move_resize_window", 0, "window", false),
+            ("move_resize_window", 1, "height", false),
+            ("move_resize_window", 2, "width", false),
+            ("move_resize_window", 3, "top", false),
+            ("move_resize_window", 4, "left", false),
+            ("arrange_all_windows", 0, "action", false),
+            ("close_window", 0, "window", false),
+            ("new_window", 0, "name", false),
+            ("new_window", 1, "height", false),
+            ("new_window", 2, "width", false),
+            ("new_window", 3, "top", false),
+            ("new_window", 4, "left", false),
+            ("new_window", 5, "style", false),
+            ("select_window", 0, "window", false),
+            ("set_window_title", 0, "window", false),
+            ("set_window_title", 1, "title", false),
+            ("elif", 0, "expr", true),
+            ("constrain_found_set", 0, "requests", false),
+            ("extend_found_set", 0, "requests", false),
+            ("perform_find_replace", 0, "with_dialog", false),
+            ("perform_find_replace", 1, "find", false),
+            ("perform_find_replace", 2, "replace", false),
+            ("set_selection", 0, "field", false),
+            ("set_selection", 1, "start", false),
+            ("set_selection", 2, "end", false),
+            ("insert_file", 0, "target", false),
+            ("insert_file", 1, "file", false),
+            ("export_field_contents", 0, "field", false),
+            ("export_field_contents", 1, "output_file", false),
+            ("export_field_contents", 2, "create_folders", false),
+            ("add_account", 0, "name", false),
+            ("add_account", 1, "password", false),
+            ("add_account", 2, "privilege_set", false),
+            ("add_account", 3, "expire_password", false),
+            ("delete_account", 0, "name", false),
+            ("reset_account_password", 0, "name", false),
+            ("reset_account_password", 1, "password", false),
+            ("reset_account_password", 2, "expire_password", false),
+            ("enable_account", 0, "name", false),
+            ("enable_account", 1, "state", false),
+            ("relogin", 0, "name", false),
+            ("relogin", 1, "password", false),
+            ("relogin", 2, "with_dialog", false),
+            ("convert_file", 0, "source", false),
+            ("convert_file", 1, "with_dialog", false),
+            ("set_variable", 0, "var", true),
+            ("set_variable", 1, "expr", true),
+            ("set_variable", 2, "repetition", false),
+            ("install_menu_set", 0, "menu_set", false),
+            ("install_menu_set", 1, "use_as_file_default", false),
+            ("save_records_as_excel", 0, "output_file", false),
+            ("save_records_as_excel", 1, "records", false),
+            ("save_records_as_excel", 2, "with_dialog", false),
+            ("save_records_as_pdf", 0, "output_file", false),
+            ("save_records_as_pdf", 1, "records", false),
+            ("save_records_as_pdf", 2, "with_dialog", false),
+            ("save_records_as_pdf", 3, "append", false),
+            ("go_to_object", 0, "object", false),
+            ("go_to_object", 1, "repetition", false),
+            ("set_web_viewer", 0, "object", false),
+            ("set_web_viewer", 1, "action", false),
+            ("set_web_viewer", 2, "url", false),
+            ("set_field_by_name", 0, "field", false),
+            ("set_field_by_name", 1, "expr", false),
+            ("install_ontimer_script", 0, "script", false),
+            ("install_ontimer_script", 1, "args", false),
+            ("install_ontimer_script", 2, "interval", false),
+            ("perform_quick_find", 0, "expr", false),
+            ("save_records_as_snapshot_link", 0, "output_file", false),
+            ("save_records_as_snapshot_link", 1, "records", false),
+            ("sort_records_by_field", 0, "field", false),
+            ("sort_records_by_field", 1, "order", false),
+            ("find_matching_records", 0, "action", false),
+            ("find_matching_records", 1, "field", false),
+            ("install_plugin_file", 0, "field", false),
+            ("insert_pdf", 0, "target", false),
+            ("insert_pdf", 1, "file", false),
+            ("insert_audio_video", 0, "target", false),
+            ("insert_audio_video", 1, "file", false),
+            ("insert_from_url", 0, "target", false),
+            ("insert_from_url", 1, "url", false),
+            ("insert_from_url", 2, "with_dialog", false),
+            ("insert_from_url", 3, "curl_options", false),
+            ("insert_from_device", 0, "target", false),
+            ("insert_from_device", 1, "device", false),
+            ("perform_script_on_server", 0, "script", false),
+            ("perform_script_on_server", 1, "args", false),
+            ("perform_script_on_server", 2, "wait", false),
+            ("show_hide_menubar", 0, "action", false),
+            ("show_hide_menubar", 1, "lock", false),
+            ("refresh_object", 0, "object", false),
+            ("refresh_object", 1, "repetition", false),
+            ("set_layout_object_animation", 0, "state", false),
+            ("enable_touch_keyboard", 0, "state", false),
+            ("perform_javascript_in_web_viewer", 0, "object", false),
+            ("perform_javascript_in_web_viewer", 1, "function", false),
+            ("perform_javascript_in_web_viewer", 2, "args", false),
+            ("commented_out", 0, "text", false),
+            ("avplayer_play", 0, "source", false),
+            ("avplayer_play", 1, "presentation", false),
+            ("avplayer_play", 2, "start", false),
+            ("avplayer_play", 3, "end", false),
+            ("avplayer_set_playback_state", 0, "state", false),
+            ("avplayer_set_options", 0, "presentation", false),
+            ("avplayer_set_options", 1, "position", false),
+            ("avplayer_set_options", 2, "volume", false),
+            ("refresh_portal", 0, "object", false),
+            ("get_folder_path", 0, "target", false),
+            ("get_folder_path", 1, "dialog_title", false),
+            ("get_folder_path", 2, "default_location", false),
+            ("truncate_table", 0, "table", false),
+            ("truncate_table", 1, "with_dialog", false),
+            ("configure_region_monitor_script", 0, "monitor", false),
+            ("configure_region_monitor_script", 1, "name", false),
+            ("configure_region_monitor_script", 2, "script", false),
+            ("configure_local_notification", 0, "action", false),
+            ("configure_local_notification", 1, "name", false),
+            ("configure_local_notification", 2, "script", false),
+            ("configure_local_notification", 3, "text", false),
+            ("get_file_exists", 0, "file", false),
+            ("get_file_exists", 1, "target", false),
+            ("get_file_size", 0, "file", false),
+            ("get_file_size", 1, "target", false),
+            ("create_data_file", 0, "file", false),
+            ("create_data_file", 1, "create_folders", false),
+            ("open_data_file", 0, "file", false),
+            ("open_data_file", 1, "target", false),
+            ("write_to_data_file", 0, "file_id", false),
+            ("write_to_data_file", 1, "data", false),
+            ("write_to_data_file", 2, "encoding", false),
+            ("read_from_data_file", 0, "file_id", false),
+            ("read_from_data_file", 1, "amount", false),
+            ("read_from_data_file", 2, "target", false),
+            ("read_from_data_file", 3, "encoding", false),
+            ("get_data_file_position", 0, "file_id", false),
+            ("get_data_file_position", 1, "target", false),
+            ("set_data_file_position", 0, "file_id", false),
+            ("set_data_file_position", 1, "position", false),
+            ("close_data_file", 0, "file_id", false),
+            ("delete_file", 0, "file", false),
+            ("rename_file", 0, "file", false),
+            ("rename_file", 1, "name", false),
+            ("set_error_logging", 0, "state", false),
+            ("set_error_logging", 1, "expr", false),
+            ("configure_nfc_reading", 0, "action", false),
+            ("configure_nfc_reading", 1, "script", false),
+            ("configure_machine_learning_model", 0, "operation", false),
+            ("configure_machine_learning_model", 1, "name", false),
+            ("configure_machine_learning_model", 2, "from", false),
+            ("execute_filemaker_data_api", 0, "target", false),
+            ("execute_filemaker_data_api", 1, "expr", false),
+            ("revert_transaction", 0, "condition", false),
+            ("revert_transaction", 1, "error_code", false),
+            ("revert_transaction", 2, "error_message", false),
+            ("set_session_identifier", 0, "expr", false),
+            ("set_dictionary", 0, "language", false),
+            ("perform_script_on_server_with_callback", 0, "script", false),
+            ("perform_script_on_server_with_callback", 1, "args", false),
+            ("perform_script_on_server_with_callback", 2, "callback_script", false),
+            ("perform_script_on_server_with_callback", 3, "callback_args", false),
+            ("trigger_claris_connect_flow", 0, "flow", false),
+            ("trigger_claris_connect_flow", 1, "json", false),
+            ("trigger_claris_connect_flow", 2, "target", false),
+            ("assert", 0, "expr", true),
         ])
     }
 
@@ -46,6 +324,16 @@ impl ArgLookupTable {
             None => None,
         }
     }
+
+    pub fn has_arg(&self, instruction: &str, label: &str) -> bool {
+        self.0.iter().any(|entry| entry.0 == instruction && entry.2 == label)
+    }
+
+    pub fn required_args<'a>(&'a self, instruction: &'a str) -> impl Iterator<Item = &'static str> + 'a {
+        self.0.iter()
+            .filter(move |entry| entry.0 == instruction && entry.3)
+            .map(|entry| entry.2)
+    }
 }
 
 pub(crate) const ARG_LOOKUP: ArgLookupTable = ArgLookupTable::new();
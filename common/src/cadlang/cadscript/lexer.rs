@@ -1,54 +1,82 @@
-
 use super::token::*;
 
 pub fn lex(code: &str) -> Vec<TokenVal> {
+    lex_located(code).into_iter()
+        .map(|token| token.value)
+        .collect()
+}
+
+/* Line and column of a byte offset, both counted from 0. */
+fn location(code: &str, offset: usize) -> (usize, usize) {
+    let before = &code[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (before.matches('\n').count(), before[line_start..].chars().count())
+}
+
+pub fn lex_located(code: &str) -> Vec<Token> {
     let mut tokens = vec![];
-    let mut lex_iter = code.chars().peekable();
+    let mut lex_iter = code.char_indices().peekable();
     let mut buffer = String::new();
-    while let Some(mut c) = lex_iter.next() {
+    while let Some((offset, c)) = lex_iter.next() {
+        let mut push = |value: TokenVal| tokens.push(Token { value, location: location(code, offset) });
         match c {
-            c if c.is_alphabetic() || c == '$' => {
+            c if c.is_alphabetic() || c == '$' || c == '_' => {
                 buffer.push(c);
-                let ch = lex_iter.peek().unwrap();
-                if ch.is_alphanumeric() || *ch == '_' || *ch == ':' {
-                    buffer.push(*ch)
-                } else {
-                    break;
-                }
-                while let Some(c) = lex_iter.next() {
-                    let ch = lex_iter.peek().unwrap();
-                    if ch.is_alphanumeric() || *ch == '_' || *ch == ':' {
-                        buffer.push(*ch)
+                while let Some(&(_, ch)) = lex_iter.peek() {
+                    if ch.is_alphanumeric() || ch == '_' || ch == ':' || ch == '$' {
+                        buffer.push(ch);
+                        lex_iter.next();
                     } else {
                         break;
                     }
                 }
 
-                if buffer.ends_with("=") {
-                    buffer.pop();
-                    tokens.push(TokenVal::ArgLabel(buffer.clone()));
-                    tokens.push(TokenVal::Assignment);
-                } else if let [table, field] = buffer.split("::").collect::<Vec<_>>()[..] {
-                    println!("GETS HERE BRUH");
-                    tokens.push(TokenVal::FieldReference(table.to_string(), field.to_string()));
+                if let [table, field] = buffer.split("::").collect::<Vec<_>>()[..] {
+                    push(TokenVal::FieldReference(table.to_string(), field.to_string()));
                 } else {
-                    tokens.push(TokenVal::Identifier(buffer.clone()));
+                    push(TokenVal::Identifier(buffer.clone()));
+                }
+
+                buffer.clear();
+            }
+            c if c.is_ascii_digit() => {
+                buffer.push(c);
+                while let Some(&(_, ch)) = lex_iter.peek() {
+                    if ch.is_ascii_digit() || ch == '.' {
+                        buffer.push(ch);
+                        lex_iter.next();
+                    } else {
+                        break;
+                    }
                 }
 
+                match buffer.parse() {
+                    Ok(number) => push(TokenVal::NumberArg(number)),
+                    Err(_) => push(TokenVal::Identifier(buffer.clone())),
+                }
                 buffer.clear();
             }
+            /* `label = value` names an argument. */
+            '=' => {
+                if let Some(token) = tokens.last_mut() {
+                    if let TokenVal::Identifier(label) = &token.value {
+                        token.value = TokenVal::ArgLabel(label.clone());
+                    }
+                }
+                tokens.push(Token { value: TokenVal::Assignment, location: location(code, offset) });
+            }
             '$' => {
                 buffer.push(c);
-                while let Some(c) = lex_iter.next() {
+                while let Some((_, c)) = lex_iter.next() {
                     if [' ', ',', ')'].contains(&c) {
                         break;
                     }
                 }
 
                 if buffer.chars().nth(1).unwrap() == '$' {
-                    tokens.push(TokenVal::Global(buffer.clone()));
+                    push(TokenVal::Global(buffer.clone()));
                 } else {
-                    tokens.push(TokenVal::Variable(buffer.clone()));
+                    push(TokenVal::Variable(buffer.clone()));
                 }
                 buffer.clear();
             }
@@ -56,7 +84,7 @@ pub fn lex(code: &str) -> Vec<TokenVal> {
             '|' => {
                 let mut in_string = false;
                 let mut escaped = false;
-                while let Some(c) = lex_iter.next() {
+                while let Some((_, c)) = lex_iter.next() {
                     if c == '\"' {
                         if in_string && escaped {
                             escaped = false;
@@ -84,13 +112,13 @@ pub fn lex(code: &str) -> Vec<TokenVal> {
                     }
                 }
 
-                tokens.push(TokenVal::CalculationArg(buffer.clone()));
+                push(TokenVal::CalculationArg(buffer.clone()));
                 buffer.clear();
             }
 
             '\"' => {
                 let mut escaped = false;
-                while let Some(c) = lex_iter.next() {
+                while let Some((_, c)) = lex_iter.next() {
                     if c == '\"' && !escaped {
                         break;
                     } else if c == '\\' && !escaped {
@@ -102,15 +130,15 @@ pub fn lex(code: &str) -> Vec<TokenVal> {
                     }
                 }
 
-                tokens.push(TokenVal::StringArg(buffer.clone()));
+                push(TokenVal::StringArg(buffer.clone()));
                 buffer.clear();
             }
-            ';' => tokens.push(TokenVal::SemiColon),
-            '{' => tokens.push(TokenVal::OpenBrace),
-            '}' => tokens.push(TokenVal::CloseBrace),
-            '(' => tokens.push(TokenVal::OpenParen),
-            ')' => tokens.push(TokenVal::CloseParen),
-            ',' => tokens.push(TokenVal::Comma),
+            ';' => push(TokenVal::SemiColon),
+            '{' => push(TokenVal::OpenBrace),
+            '}' => push(TokenVal::CloseBrace),
+            '(' => push(TokenVal::OpenParen),
+            ')' => push(TokenVal::CloseParen),
+            ',' => push(TokenVal::Comma),
             _ => {}
         }
     }
@@ -182,4 +210,21 @@ mod tests {
        assert_eq!(lex(code), expected);
 
     }
+
+    #[test]
+    fn labels_and_numbers() {
+        assert_eq!(lex("show_custom_dialog(title = \"Hi\", buttons=2)\nx"), vec![
+            TokenVal::Identifier(String::from("show_custom_dialog")),
+            TokenVal::OpenParen,
+            TokenVal::ArgLabel(String::from("title")),
+            TokenVal::Assignment,
+            TokenVal::StringArg(String::from("Hi")),
+            TokenVal::Comma,
+            TokenVal::ArgLabel(String::from("buttons")),
+            TokenVal::Assignment,
+            TokenVal::NumberArg(2.0),
+            TokenVal::CloseParen,
+            TokenVal::Identifier(String::from("x")),
+        ]);
+    }
 }
@@ -4,16 +4,88 @@ pub(crate) mod parser;
 pub(crate) mod token;
 pub(crate) mod proto_script;
 pub(crate) mod proto_instruction;
+pub(crate) mod steps;
 mod arg_lookups;
 
 use proto_script::*;
+use parser::ParseErr;
+use token::TokenVal;
+use super::{error::CompileErr, token::{Location, Token, TokenType}};
 
-pub fn compile_cadscript(code: &str) -> ProtoScript {
-    let tokens = lexer::lex(code);
-    let script = parser::parse(tokens).unwrap();
+/* `start` is the location of the brace opening the script body, which
+ * errors and step locations are reported relative to. */
+pub fn compile_cadscript(code: &str, start: Location) -> Result<ProtoScript, CompileErr> {
+    let tokens = lexer::lex_located(code);
+    let script = parser::parse_located(tokens.iter().map(|token| token.value.clone()).collect());
 
-    ProtoScript {
+    let location = |index: usize| {
+        let (line, column) = tokens[index].location;
+        match line {
+            0 => Location { line: start.line, column: start.column + 1 + column as u32 },
+            _ => Location { line: start.line + line as u32, column: column as u32 + 1 },
+        }
+    };
+    let error_token = |index: usize| {
+        let (ttype, value) = token_kind(&tokens[index].value);
+        Token::with_value(ttype, location(index), value)
+    };
+    let steps = match script {
+        Ok(steps) => steps,
+        Err(ParseErr::UnexpectedToken { index, expected }) => return Err(CompileErr::UnexpectedToken {
+            token: error_token(index),
+            expected: expected.iter().map(|val| token_kind(val).0).collect(),
+        }),
+        Err(ParseErr::PositionalLabelMix(index)) => return Err(CompileErr::MixedArguments { token: error_token(index) }),
+        Err(ParseErr::InvalidLabel { step, index }) => return Err(CompileErr::UnknownAttribute { construct: step, token: error_token(index) }),
+        Err(ParseErr::MissingArgument { step, argument, index }) => {
+            return Err(CompileErr::MissingArgument { step, argument, token: error_token(index) })
+        }
+        Err(ParseErr::ExtraArgument { step, index }) => return Err(CompileErr::ExtraArgument { step, token: error_token(index) }),
+        Err(ParseErr::InvalidArgument { step, argument, index }) => {
+            return Err(CompileErr::InvalidArgument { step, argument, token: error_token(index) })
+        }
+        Err(ParseErr::UnsupportedArgument { step, argument, index }) => {
+            return Err(CompileErr::UnsupportedArgument { step, argument, token: error_token(index) })
+        }
+        Err(ParseErr::UnexpectedEOF) => return Err(CompileErr::UnexpectedEOF),
+        Err(ParseErr::UnclosedBlock(index)) => return Err(CompileErr::UnclosedBlock { token: error_token(index) }),
+        Err(ParseErr::UnmatchedBranch(index) | ParseErr::UnexpectedScopeCloser(index)) => {
+            return Err(CompileErr::UnmatchedBlock { token: error_token(index) })
+        }
+        Err(ParseErr::UnknownStep(index)) => return Err(CompileErr::UnknownScriptStep { token: error_token(index) }),
+    };
+
+    Ok(ProtoScript {
         name: String::new(),
-        instructions: script,
+        location: Location { line: 0, column: 0 },
+        params: vec![],
+        step_locations: steps.iter().map(|(_, index)| location(*index)).collect(),
+        instructions: steps.into_iter().map(|(instruction, _)| instruction).collect(),
+    })
+}
+
+/* The cadlang token type and text an error reports for a script token. */
+fn token_kind(value: &TokenVal) -> (TokenType, String) {
+    match value {
+        TokenVal::Identifier(name)
+            | TokenVal::Variable(name)
+            | TokenVal::Global(name)
+            | TokenVal::KeywordArg(name)
+            | TokenVal::ArgLabel(name) => (TokenType::Identifier, name.clone()),
+        TokenVal::FieldReference(occurrence, field) => (TokenType::Identifier, format!("{}::{}", occurrence, field)),
+        TokenVal::CalculationArg(calc) => (TokenType::Calculation, calc.clone()),
+        TokenVal::StringArg(text) => (TokenType::String, text.clone()),
+        TokenVal::NumberArg(number) => (TokenType::IntegerLiteral, number.to_string()),
+        TokenVal::Loop => (TokenType::Identifier, String::from("loop")),
+        TokenVal::EndLoop => (TokenType::Identifier, String::from("end_loop")),
+        TokenVal::If => (TokenType::Identifier, String::from("if")),
+        TokenVal::Else => (TokenType::Identifier, String::from("else")),
+        TokenVal::OpenBrace => (TokenType::OpenBrace, String::from("{")),
+        TokenVal::CloseBrace => (TokenType::CloseBrace, String::from("}")),
+        TokenVal::OpenParen => (TokenType::OpenParen, String::from("(")),
+        TokenVal::CloseParen => (TokenType::CloseParen, String::from(")")),
+        TokenVal::Assignment => (TokenType::Assignment, String::from("=")),
+        TokenVal::Comma => (TokenType::Comma, String::from(",")),
+        TokenVal::SemiColon => (TokenType::Identifier, String::from(";")),
     }
 }
@@ -1,7 +1,8 @@
 
 use crate::{cadlang::cadscript::proto_instruction::ProtoScriptSelection, dbobjects::{calculation::{Calculation, CalculationString}, scripting::{arguments::*, instructions::Instruction, script::Script}}};
 use super::token::TokenVal;
-use super::proto_instruction::{ProtoInstruction, ProtoFieldSelection, ProtoLayoutSelection};
+use super::proto_instruction::{ProtoInstruction, ProtoFieldSelection, ProtoLayoutSelection, ProtoRecordSelection};
+use super::steps::unit_step;
 
 use std::collections::HashMap;
 use super::arg_lookups::ARG_LOOKUP;
@@ -11,28 +12,39 @@ pub enum ArgFormat {
     Labeled,
 }
 
+/* `index` is the position of the value in the script's tokens. */
 #[derive(Clone, Debug)]
 pub struct ArgKeyVal {
     label: String,
     value: TokenVal,
+    index: usize,
 }
 
 impl ArgKeyVal {
-    pub fn new(label_: String, value_: TokenVal) -> Self {
+    pub fn new(label_: String, value_: TokenVal, index_: usize) -> Self {
         Self {
             label: label_,
             value: value_,
+            index: index_,
         }
     }
 }
 
+/* Errors hold the index of the offending token. */
 #[derive(Debug)]
 pub enum ParseErr {
-    UnexpectedToken { token: TokenVal, expected: Vec<TokenVal> },
-    PositionalLabelMix,
-    InvalidLabel(String),
+    UnexpectedToken { index: usize, expected: Vec<TokenVal> },
+    PositionalLabelMix(usize),
+    InvalidLabel { step: String, index: usize },
+    MissingArgument { step: String, argument: String, index: usize },
+    ExtraArgument { step: String, index: usize },
+    InvalidArgument { step: String, argument: String, index: usize },
+    UnsupportedArgument { step: String, argument: String, index: usize },
     UnexpectedEOF,
-    UnexpectedScopeCloser,
+    UnexpectedScopeCloser(usize),
+    UnclosedBlock(usize),
+    UnmatchedBranch(usize),
+    UnknownStep(usize),
 }
 
 pub struct ParseInfo {
@@ -51,7 +63,7 @@ fn expect<'a>(tokens: &'a [TokenVal], expected_: &Vec<TokenVal>, info: &mut Pars
         }
         info.cursor += 1;
         return Err(ParseErr::UnexpectedToken { 
-            token: token.clone(), 
+            index: info.cursor - 1,
             expected: expected_.to_vec(),
         });
     } else {
@@ -60,259 +72,291 @@ fn expect<'a>(tokens: &'a [TokenVal], expected_: &Vec<TokenVal>, info: &mut Pars
     }
 }
 
-pub fn parse_labeled_arg<'a>(label: &'a TokenVal, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<ArgKeyVal, ParseErr> {
-    expect(tokens, &vec![TokenVal::Assignment], info)?;
-
-    let value = expect(tokens, &vec![TokenVal::KeywordArg(String::new()),
-        TokenVal::StringArg(String::new()),
-        TokenVal::NumberArg(0.0),
-        TokenVal::CalculationArg(String::new())
-    ],
-    info)?;
-
-    return Ok(ArgKeyVal::new(label.get_value().unwrap(), value.clone()))
-}
-
-pub fn parse_labeled_args<'a>(tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<Vec<ArgKeyVal>, ParseErr> {
-    let mut args = vec![];
-    Ok(args)
-}
-
-pub fn parse_positional_args<'a>(instr: &'a str, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<Vec<ArgKeyVal>, ParseErr> {
-
-    let mut arg_n = 1;
-    let mut args = vec![];
-
-    while info.cursor < tokens.len() {
-        let val = expect(tokens, &vec![
-            TokenVal::CloseParen,
-            TokenVal::Identifier(String::new()),
-            TokenVal::KeywordArg(String::new()),
-            TokenVal::StringArg(String::new()),
-            TokenVal::NumberArg(0.0),
-            TokenVal::FieldReference(String::new(), String::new()),
-            TokenVal::CalculationArg(String::new())
-        ],
-        info)?;
-
-        let tmp_pair = match val {
-            TokenVal::CalculationArg(..) 
-                | TokenVal::Variable(..)
-                | TokenVal::Identifier(..)
-                | TokenVal::KeywordArg(..)
-                | TokenVal::StringArg(..)
-                | TokenVal::FieldReference(..) => ArgKeyVal {
-                label: ARG_LOOKUP.get_argname(instr, arg_n).unwrap().to_string(),
-                value: val.clone(),
-            },
-            TokenVal::ArgLabel(label) => return Err(ParseErr::PositionalLabelMix),
-            TokenVal::CloseParen => { break; }
-            _ => { eprintln!("{:?}", val); unreachable!() }
-        };
-        println!("{} :: {:?}", arg_n, tmp_pair);
-        args.push(tmp_pair);
-        arg_n += 1;
-    }
-
-    Ok(args)
-}
-
-pub fn parse_first_arg<'a>(instr: &'a str, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<Option<(ArgFormat, ArgKeyVal)>, ParseErr> {
-    let name_or_val = expect(tokens, &vec![
-        TokenVal::CloseParen,
+/* The kinds of token an argument's value may be. */
+fn value_tokens() -> Vec<TokenVal> {
+    vec![
         TokenVal::Identifier(String::new()),
+        TokenVal::Variable(String::new()),
+        TokenVal::Global(String::new()),
         TokenVal::KeywordArg(String::new()),
         TokenVal::StringArg(String::new()),
         TokenVal::NumberArg(0.0),
-        TokenVal::CalculationArg(String::new()),
         TokenVal::FieldReference(String::new(), String::new()),
-    ],
-    info)?;
-
-    println!("CALC: {:?}", name_or_val);
-
-    if [TokenVal::CloseParen, TokenVal::OpenBrace].contains(name_or_val) {
-        return Ok(None);
-    }
+        TokenVal::CalculationArg(String::new()),
+    ]
+}
 
-    match expect(tokens, &vec![TokenVal::Comma, TokenVal::Assignment, TokenVal::CloseParen], info)? {
-        TokenVal::Comma | TokenVal::CloseParen => {
-            let name = match ARG_LOOKUP.get_argname(instr, 0) {
-                Some(name) => name,
-                None => return Err(ParseErr::InvalidLabel(instr.to_string()))
-            };
-            Ok(Some((ArgFormat::Positional, ArgKeyVal::new(name.to_string(), name_or_val.clone()))))
-        }
-        TokenVal::Assignment => {
-            let value = expect(tokens, &vec![TokenVal::KeywordArg(String::new()),
-                TokenVal::StringArg(String::new()),
-                TokenVal::NumberArg(0.0),
-                TokenVal::CalculationArg(String::new())
-            ],
-            info)?;
-
-            Ok(Some((ArgFormat::Labeled, ArgKeyVal::new(name_or_val.get_value().unwrap(), value.clone()))))
-        }
+pub fn parse_labeled_arg<'a>(instr: &'a str, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<ArgKeyVal, ParseErr> {
+    let label_index = info.cursor;
+    let label = match expect(tokens, &vec![TokenVal::ArgLabel(String::new())], info)? {
+        TokenVal::ArgLabel(label) => label,
         _ => unreachable!()
+    };
+    if !ARG_LOOKUP.has_arg(instr, label) {
+        return Err(ParseErr::InvalidLabel { step: instr.to_string(), index: label_index });
     }
-}
+    expect(tokens, &vec![TokenVal::Assignment], info)?;
+    let value = expect(tokens, &value_tokens(), info)?;
 
-pub fn parse_args<'a>(instr: &'a str, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<Vec<ArgKeyVal>, ParseErr> {
-    // Return an empty Vec early if there are no args.
-    let early = expect(&tokens, &vec![TokenVal::OpenParen, TokenVal::OpenBrace], info)?;
-    if *early == TokenVal::OpenBrace { return Ok(vec![]) }
+    Ok(ArgKeyVal::new(label.to_string(), value.clone(), info.cursor - 1))
+}
 
-    let mut result = vec![];
+pub fn parse_positional_arg<'a>(instr: &'a str, position: u8, tokens: &'a [TokenVal], info: &mut ParseInfo) -> Result<ArgKeyVal, ParseErr> {
+    let value = expect(tokens, &value_tokens(), info)?;
+    match ARG_LOOKUP.get_argname(instr, position) {
+        Some(label) => Ok(ArgKeyVal::new(label.to_string(), value.clone(), info.cursor - 1)),
+        None => Err(ParseErr::ExtraArgument { step: instr.to_string(), index: info.cursor - 1 }),
+    }
+}
 
-    match parse_first_arg(instr, tokens, info).unwrap() {
-        Some((format, first_arg)) => {
-            result.push(first_arg);
+/* Arguments are either all positional or all labelled, and are checked
+ * against the step's schema in ARG_LOOKUP. `start` is the index of the
+ * step's name, where missing arguments are reported. */
+pub fn parse_args<'a>(instr: &'a str, tokens: &'a [TokenVal], info: &mut ParseInfo, start: usize) -> Result<Vec<ArgKeyVal>, ParseErr> {
+    let mut args: Vec<ArgKeyVal> = vec![];
+
+    // Blocks may open without an argument list.
+    let early = expect(tokens, &vec![TokenVal::OpenParen, TokenVal::OpenBrace], info)?;
+    if *early == TokenVal::OpenParen {
+        let format = match tokens.get(info.cursor) {
+            Some(TokenVal::ArgLabel(..)) => ArgFormat::Labeled,
+            _ => ArgFormat::Positional,
+        };
+        if tokens.get(info.cursor) == Some(&TokenVal::CloseParen) {
+            info.cursor += 1;
+        } else {
+            loop {
+                let arg = match (&format, tokens.get(info.cursor)) {
+                    (ArgFormat::Labeled, Some(TokenVal::ArgLabel(..))) => parse_labeled_arg(instr, tokens, info)?,
+                    (ArgFormat::Positional, Some(TokenVal::ArgLabel(..))) | (ArgFormat::Labeled, Some(_)) => {
+                        return Err(ParseErr::PositionalLabelMix(info.cursor))
+                    }
+                    (ArgFormat::Positional, _) | (ArgFormat::Labeled, None) => {
+                        parse_positional_arg(instr, args.len() as u8, tokens, info)?
+                    }
+                };
+                if args.iter().any(|existing| existing.label == arg.label) {
+                    return Err(ParseErr::ExtraArgument { step: instr.to_string(), index: arg.index });
+                }
+                args.push(arg);
 
-            if tokens[info.cursor - 1] != TokenVal::CloseParen {
-                result.extend(match format {
-                    ArgFormat::Positional => parse_positional_args(instr, tokens, info)?,
-                    ArgFormat::Labeled => parse_labeled_args(tokens, info)?,
-                });
+                if *expect(tokens, &vec![TokenVal::Comma, TokenVal::CloseParen], info)? == TokenVal::CloseParen {
+                    break;
+                }
             }
-
-            Ok(result)
-        },
-        None => {
-            return Ok(vec![]);
         }
     }
+
+    if let Some(missing) = ARG_LOOKUP.required_args(instr).find(|label| !args.iter().any(|arg| arg.label == *label)) {
+        return Err(ParseErr::MissingArgument { step: instr.to_string(), argument: missing.to_string(), index: start });
+    }
+    Ok(args)
 }
 
+/* Steps with fields, which `parse` builds by hand. */
+fn field_step(name: &str) -> bool {
+    matches!(name, "perform_script" | "set_variable" | "loop" | "if" | "elif" | "else" | "exit_loop_if"
+        | "assert" | "exit_script" | "go_to_record" | "go_to_request" | "go_to_record_request_page"
+        | "set_field" | "go_to_layout" | "print")
+}
+
+#[allow(dead_code)]
 pub fn parse(tokens: Vec<TokenVal>) -> Result<Vec<ProtoInstruction>, ParseErr> {
-    for tok in &tokens {
-        println!("{:?}", tok);
-    }
+    parse_located(tokens).map(|steps| steps.into_iter()
+        .map(|(instruction, _)| instruction)
+        .collect())
+}
+
+/* Each instruction comes with the index of the token it was written at. */
+pub fn parse_located(tokens: Vec<TokenVal>) -> Result<Vec<(ProtoInstruction, usize)>, ParseErr> {
     let mut info = ParseInfo { cursor: 0 };
     let mut instructions = vec![];
-    let mut scope_stack = vec![TokenVal::EOF];
+    /* Open blocks, with the index of the instruction that opened each. */
+    let mut scope_stack: Vec<(TokenVal, usize)> = vec![];
+    /* Set when the last block closed was an `if` or `elif`, which is the
+     * only place an `elif` or `else` may follow. */
+    let mut branch_open = false;
     while info.cursor < tokens.len() {
-        println!("cursor: {} :: {:?}", info.cursor, tokens[info.cursor]);
-        let instr = match expect(&tokens, &vec![TokenVal::Identifier(String::new()), TokenVal::OpenBrace, TokenVal::CloseBrace], &mut info)? {
+        let start = info.cursor;
+        let instr = match expect(&tokens, &vec![TokenVal::Identifier(String::new()), TokenVal::CloseBrace], &mut info)? {
             TokenVal::Identifier(inner) => inner,
-            TokenVal::OpenBrace => continue,
             TokenVal::CloseBrace => {
-                match scope_stack.last() {
-                    Some(TokenVal::Loop) => { 
-                        instructions.push(ProtoInstruction::EndLoop); 
-                        scope_stack.pop();
-                        continue 
-                    },
-                    Some(TokenVal::EOF) => {
-                        break;
-                    }
-                    None => {
-                        return Err(ParseErr::UnexpectedScopeCloser)
-                    }
-                    _ => unreachable!(),
+                if branch_open {
+                    instructions.push((ProtoInstruction::EndIf, start));
+                    branch_open = false;
                 }
+                match scope_stack.pop() {
+                    Some((TokenVal::Loop, _)) => instructions.push((ProtoInstruction::EndLoop, start)),
+                    Some((TokenVal::If, _)) => branch_open = true,
+                    Some((TokenVal::Else, _)) => instructions.push((ProtoInstruction::EndIf, start)),
+                    _ => return Err(ParseErr::UnexpectedScopeCloser(start)),
+                }
+                continue
             }
             _ => unreachable!()
         };
 
-        let arguments = parse_args(instr, &tokens, &mut info).unwrap();
-        instructions.push(match instr.as_str() {
+        let continues_branch = ["elif", "else"].contains(&instr.as_str());
+        if branch_open && !continues_branch {
+            instructions.push((ProtoInstruction::EndIf, start));
+        } else if !branch_open && continues_branch {
+            return Err(ParseErr::UnmatchedBranch(start));
+        }
+        branch_open = false;
+
+        if !field_step(instr) && unit_step(instr).is_none() {
+            return Err(ParseErr::UnknownStep(start));
+        }
+        let arguments = parse_args(instr, &tokens, &mut info, start)?;
+        /* Steps without fields have nowhere to keep their options yet. */
+        if let (false, Some(arg)) = (field_step(instr), arguments.first()) {
+            return Err(ParseErr::UnsupportedArgument { step: instr.to_string(), argument: arg.label.clone(), index: arg.index });
+        }
+        let block = match instr.as_str() {
+            "loop" => Some(TokenVal::Loop),
+            "if" | "elif" => Some(TokenVal::If),
+            "else" => Some(TokenVal::Else),
+            _ => None,
+        };
+        if let Some(block) = block {
+            if tokens[info.cursor - 1] != TokenVal::OpenBrace
+                && tokens.get(info.cursor) != Some(&TokenVal::OpenBrace) {
+                return Err(ParseErr::UnclosedBlock(start));
+            }
+            if tokens[info.cursor - 1] != TokenVal::OpenBrace {
+                info.cursor += 1;
+            }
+            scope_stack.push((block, start));
+        }
+        instructions.push((match instr.as_str() {
             "perform_script" => {
-                println!("FOUND ARGS: {:?}", arguments);
-                let script_ = match &arguments.iter().find(|arg| arg.label == "script").unwrap().value {
+                let script_arg = required(instr, &arguments, "script", start)?;
+                let script = match &script_arg.value {
                     TokenVal::CalculationArg(calc) => ProtoScriptSelection::Calculation(CalculationString(calc.to_string())),
                     TokenVal::FieldReference(file, script_name) => ProtoScriptSelection::UnresolvedReference { 
                         data_source: file.to_string(),
                         script: script_name.to_string() 
                     },
-                    _ => todo!("Error handling.")
-                };
-                let args = arguments.iter().find(|arg| arg.label == "args");
-                let args_ = match args {
-                    Some(args) => args,
-                    None => &ArgKeyVal { 
-                        label: String::from("args"),
-                        value: TokenVal::CalculationArg(String::new()) 
+                    /* Scripts in the same file are named without a data source. */
+                    TokenVal::Identifier(script_name) | TokenVal::StringArg(script_name) => ProtoScriptSelection::UnresolvedReference {
+                        data_source: String::new(),
+                        script: script_name.to_string()
                     },
+                    _ => return Err(invalid_argument(instr, script_arg)),
                 };
                 ProtoInstruction::PerformScript { 
-                    script: script_,
-                    args: CalculationString(args_.value.get_value().unwrap()) }
+                    script,
+                    args: optional_calculation(instr, &arguments, "args", "")?,
+                }
             }
             "set_variable" => {
-                let name = arguments.iter().find(|arg| arg.label == "var").unwrap();
-                let val = arguments.iter().find(|arg| arg.label == "expr").unwrap();
-                let rep = arguments.iter().find(|arg| arg.label == "rep");
-                let rep = match rep {
-                    Some(rep) => rep,
-                    None => &ArgKeyVal { 
-                        label: String::from("rep"),
-                        value: TokenVal::CalculationArg(String::from("1")) 
-                    },
+                let name_arg = required(instr, &arguments, "var", start)?;
+                let name = match &name_arg.value {
+                    TokenVal::Identifier(name) if name.starts_with('$') => name.clone(),
+                    TokenVal::Variable(name) | TokenVal::Global(name) => name.clone(),
+                    _ => return Err(invalid_argument(instr, name_arg)),
                 };
 
                 ProtoInstruction::SetVariable { 
-                    name: name.value.get_value().unwrap(),
-                    value: CalculationString(val.value.get_value().unwrap()),
-                    repetition: CalculationString(rep.value.get_value().unwrap()), 
+                    name,
+                    value: calculation(instr, required(instr, &arguments, "expr", start)?)?,
+                    repetition: optional_calculation(instr, &arguments, "repetition", "1")?,
                 }
             },
-            "loop" => { 
-                scope_stack.push(TokenVal::Loop); 
-                ProtoInstruction::Loop 
-            },
-            "exit_loop_if" => ProtoInstruction::ExitLoopIf { condition: CalculationString(arguments.iter()
-                .find(|arg| arg.label == "expr")
-                .unwrap().value
-                    .get_value()
-                    .unwrap()
-                    .clone()) 
-            },
-            "new_record" | "new_request" => ProtoInstruction::NewRecordRequest,
-            "set_field" => {
-                let name = arguments.iter().find(|arg| arg.label == "field").unwrap();
-                let val = arguments.iter().find(|arg| arg.label == "expr").unwrap();
-                let rep = arguments.iter().find(|arg| arg.label == "rep");
-                let rep = match rep {
-                    Some(rep) => rep,
-                    None => &ArgKeyVal { 
-                        label: String::from("rep"),
-                        value: TokenVal::CalculationArg(String::from("1")) 
+            "loop" => ProtoInstruction::Loop,
+            "if" => ProtoInstruction::If { condition: calculation(instr, required(instr, &arguments, "expr", start)?)? },
+            "elif" => ProtoInstruction::ElseIf { condition: calculation(instr, required(instr, &arguments, "expr", start)?)? },
+            "else" => ProtoInstruction::Else,
+            "exit_loop_if" => ProtoInstruction::ExitLoopIf { condition: calculation(instr, required(instr, &arguments, "expr", start)?)? },
+            "assert" => ProtoInstruction::Assert { expr: calculation(instr, required(instr, &arguments, "expr", start)?)? },
+            "exit_script" => ProtoInstruction::ExitScript { value: optional_calculation(instr, &arguments, "expr", "")? },
+            "print" => ProtoInstruction::Print { expr: optional_calculation(instr, &arguments, "expr", "")? },
+            "go_to_record" | "go_to_request" | "go_to_record_request_page" => {
+                let record_arg = required(instr, &arguments, "record", start)?;
+                let record = match &record_arg.value {
+                    TokenVal::CalculationArg(calc) => ProtoRecordSelection::ByCalc(CalculationString(calc.to_string())),
+                    TokenVal::Identifier(ident) | TokenVal::StringArg(ident) => match ident.as_str() {
+                        "first" => ProtoRecordSelection::First,
+                        "last" => ProtoRecordSelection::Last,
+                        "next" => ProtoRecordSelection::Next,
+                        "previous" => ProtoRecordSelection::Previous,
+                        _ => return Err(invalid_argument(instr, record_arg)),
                     },
+                    _ => return Err(invalid_argument(instr, record_arg)),
                 };
+                ProtoInstruction::GoToRecordRequestPage { record }
+            }
+            "set_field" => {
+                let field_arg = required(instr, &arguments, "field", start)?;
                 ProtoInstruction::SetField {
-                    field: match &name.value { 
+                    field: match &field_arg.value { 
                         TokenVal::FieldReference(table_, field_) => ProtoFieldSelection::UnresolvedReference { occurrence: table_.to_string(), field: field_.to_string() },
-                        _ => unreachable!(),
+                        _ => return Err(invalid_argument(instr, field_arg)),
                     },
-                    value: CalculationString(val.value.get_value().unwrap()),
-                    repetition: CalculationString(rep.value.get_value().unwrap()),
+                    value: calculation(instr, required(instr, &arguments, "expr", start)?)?,
+                    repetition: optional_calculation(instr, &arguments, "repetition", "1")?,
                 }
             },
             "go_to_layout" => {
-                let layout_ = match &arguments.iter().find(|arg| arg.label == "layout").unwrap().value {
+                let layout_arg = required(instr, &arguments, "layout", start)?;
+                let layout = match &layout_arg.value {
                     TokenVal::CalculationArg(calc) => ProtoLayoutSelection::Calculation(CalculationString(calc.to_string())),
-                    TokenVal::Identifier(ident) => ProtoLayoutSelection::UnresolvedName(ident.to_string()),
-                    _ => unreachable!()
+                    TokenVal::Identifier(ident) | TokenVal::StringArg(ident) => ProtoLayoutSelection::UnresolvedName(ident.to_string()),
+                    _ => return Err(invalid_argument(instr, layout_arg)),
                 };
+
+                /* Only the default animation is supported so far. */
                 let animation = match arguments.iter().find(|arg| arg.label == "animation") {
-                    Some(inner) => inner,
-                    None => &ArgKeyVal { label: "animation".to_string(), value: TokenVal::KeywordArg("None".to_string()) },
+                    None => LayoutAnimation::None,
+                    Some(ArgKeyVal { value: TokenVal::Identifier(name), .. }) if name == "none" => LayoutAnimation::None,
+                    Some(arg) => return Err(ParseErr::UnsupportedArgument {
+                        step: instr.to_string(),
+                        argument: arg.label.clone(),
+                        index: arg.index,
+                    }),
                 };
+                ProtoInstruction::GoToLayout { layout, animation }
+            }
+            _ => unit_step(instr).ok_or(ParseErr::UnknownStep(start))?,
+        }, start));
 
-                let animation_encoded = match animation.value.get_value().unwrap().as_str() {
-                    "None" => LayoutAnimation::None,
-                    _ => LayoutAnimation::None,
-                };
+    }
 
-                ProtoInstruction::GoToLayout { layout: layout_, animation: animation_encoded }
+    if branch_open {
+        instructions.push((ProtoInstruction::EndIf, tokens.len() - 1));
+    }
+    match scope_stack.pop() {
+        Some((_, opened_at)) => Err(ParseErr::UnclosedBlock(opened_at)),
+        None => Ok(instructions),
+    }
+}
 
-            }
-            _ => ProtoInstruction::Print
-        });
+fn required<'a>(instr: &str, arguments: &'a [ArgKeyVal], label: &str, start: usize) -> Result<&'a ArgKeyVal, ParseErr> {
+    arguments.iter()
+        .find(|arg| arg.label == label)
+        .ok_or_else(|| ParseErr::MissingArgument { step: instr.to_string(), argument: label.to_string(), index: start })
+}
+
+fn invalid_argument(instr: &str, arg: &ArgKeyVal) -> ParseErr {
+    ParseErr::InvalidArgument { step: instr.to_string(), argument: arg.label.clone(), index: arg.index }
+}
 
+fn calculation(instr: &str, arg: &ArgKeyVal) -> Result<CalculationString, ParseErr> {
+    match &arg.value {
+        TokenVal::CalculationArg(calc) => Ok(CalculationString(calc.clone())),
+        TokenVal::NumberArg(number) => Ok(CalculationString(number.to_string())),
+        /* A string stands for the calculation giving that text. The lexer
+         * keeps its escapes, which calculations share. */
+        TokenVal::StringArg(text) => Ok(CalculationString(format!("\"{}\"", text))),
+        _ => Err(invalid_argument(instr, arg)),
     }
+}
 
-    Ok(instructions)
+fn optional_calculation(instr: &str, arguments: &[ArgKeyVal], label: &str, default: &str) -> Result<CalculationString, ParseErr> {
+    match arguments.iter().find(|arg| arg.label == label) {
+        Some(arg) => calculation(instr, arg),
+        None => Ok(CalculationString(default.to_string())),
+    }
 }
 
 #[cfg(test)]
@@ -362,16 +406,147 @@ mod tests {
         }
     }
 
+    #[test]
+    fn if_chains_and_nested_loops() {
+        let code = "loop {
+                      if(|$x = 1|) {
+                        loop {
+                          exit_loop_if(|$y = 2|)
+                        }
+                      } elif(|$x = 2|) {
+                        new_record()
+                      } else {
+                        if(|$y = 1|) {
+                          new_record()
+                        }
+                      }
+                      exit_loop_if(|$x = 3|)
+                    }";
+
+        let instrs = parser::parse(lexer::lex(code)).unwrap();
+        let condition = |code: &str| CalculationString(code.to_string());
+        assert_eq!(instrs, vec![
+            ProtoInstruction::Loop,
+            ProtoInstruction::If { condition: condition("$x = 1") },
+            ProtoInstruction::Loop,
+            ProtoInstruction::ExitLoopIf { condition: condition("$y = 2") },
+            ProtoInstruction::EndLoop,
+            ProtoInstruction::ElseIf { condition: condition("$x = 2") },
+            ProtoInstruction::NewRecordRequest,
+            ProtoInstruction::Else,
+            ProtoInstruction::If { condition: condition("$y = 1") },
+            ProtoInstruction::NewRecordRequest,
+            ProtoInstruction::EndIf,
+            ProtoInstruction::EndIf,
+            ProtoInstruction::ExitLoopIf { condition: condition("$x = 3") },
+            ProtoInstruction::EndLoop,
+        ]);
+    }
+
+    #[test]
+    fn repetition_args() {
+        let instrs = parser::parse(lexer::lex("set_field(Person::phones, |\"a\"|, |$i + 1|)\nset_variable($x, |0|, |2|)")).unwrap();
+        assert_eq!(instrs, vec![
+            ProtoInstruction::SetField {
+                field: proto_instruction::ProtoFieldSelection::UnresolvedReference { occurrence: "Person".to_string(), field: "phones".to_string() },
+                value: CalculationString("\"a\"".to_string()),
+                repetition: CalculationString("$i + 1".to_string()),
+            },
+            ProtoInstruction::SetVariable {
+                name: "$x".to_string(),
+                value: CalculationString("0".to_string()),
+                repetition: CalculationString("2".to_string()),
+            },
+        ]);
+    }
+
+    #[test]
+    fn unbalanced_blocks() {
+        let parse = |code: &str| parser::parse(lexer::lex(code));
+        assert!(matches!(parse("new_record()\nloop {\nnew_record()\n"), Err(parser::ParseErr::UnclosedBlock(3))));
+        assert!(matches!(parse("if(|$x = 1|) new_record()\n"), Err(parser::ParseErr::UnclosedBlock(0))));
+        assert!(matches!(parse("new_record()\nelse {\n}\n"), Err(parser::ParseErr::UnmatchedBranch(3))));
+        assert!(matches!(parse("if(|$x = 1|) {\n} else {\n} else {\n}\n"), Err(parser::ParseErr::UnmatchedBranch(9))));
+        assert!(matches!(parse("loop {\n}\n}\n"), Err(parser::ParseErr::UnexpectedScopeCloser(3))));
+    }
+
+    #[test]
+    fn generated_steps() {
+        for name in steps::UNIT_STEPS {
+            let instrs = parser::parse(lexer::lex(&format!("{}()", name))).unwrap();
+            assert!(steps::lower_unit_step(&instrs[0]).is_some(), "{}", name);
+        }
+
+        let instrs = parser::parse(lexer::lex("go_to_record(first)\ngo_to_request(|$n|)\nexit_script(|1|)\n")).unwrap();
+        assert_eq!(instrs, vec![
+            ProtoInstruction::GoToRecordRequestPage { record: proto_instruction::ProtoRecordSelection::First },
+            ProtoInstruction::GoToRecordRequestPage {
+                record: proto_instruction::ProtoRecordSelection::ByCalc(CalculationString("$n".to_string()))
+            },
+            ProtoInstruction::ExitScript { value: CalculationString("1".to_string()) },
+        ]);
+        assert!(matches!(parser::parse(lexer::lex("new_record()\nfly_to_moon()\n")), Err(parser::ParseErr::UnknownStep(3))));
+    }
+
+    #[test]
     fn basic_parse_labels() {
-        let code = "set_variable(variable=$x, expr=|0|)
+        let code = "set_variable(var=$x, expr=|0|)
                     go_to_layout(layout=\"Person\")
                     loop {
                       exit_loop_if(expr=|$x == 10|)
                       new_record()
-                      set_variable(variable=$x, expr=|$x + 1|)
-                    }";
+                      set_variable(expr=|$x + 1|, var=$x, repetition=2)
+                    }
+                    go_to_layout(layout=Person, animation=none)";
 
         let tokens = lexer::lex(code);
         let instrs = parser::parse(tokens).unwrap();
+        assert_eq!(instrs, vec![
+            ProtoInstruction::SetVariable {
+                name: "$x".to_string(),
+                value: CalculationString("0".to_string()),
+                repetition: CalculationString("1".to_string()),
+            },
+            ProtoInstruction::GoToLayout {
+                layout: proto_instruction::ProtoLayoutSelection::UnresolvedName("Person".to_string()),
+                animation: LayoutAnimation::None,
+            },
+            ProtoInstruction::Loop,
+            ProtoInstruction::ExitLoopIf { condition: CalculationString("$x == 10".to_string()) },
+            ProtoInstruction::NewRecordRequest,
+            ProtoInstruction::SetVariable {
+                name: "$x".to_string(),
+                value: CalculationString("$x + 1".to_string()),
+                repetition: CalculationString("2".to_string()),
+            },
+            ProtoInstruction::EndLoop,
+            ProtoInstruction::GoToLayout {
+                layout: proto_instruction::ProtoLayoutSelection::UnresolvedName("Person".to_string()),
+                animation: LayoutAnimation::None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn argument_errors() {
+        let parse = |code: &str| parser::parse(lexer::lex(code));
+        assert!(matches!(parse("beep(|1|)"), Err(parser::ParseErr::ExtraArgument { index: 2, .. })));
+        assert!(matches!(parse("exit_script(|1|, |2|)"), Err(parser::ParseErr::ExtraArgument { index: 4, .. })));
+        assert!(matches!(parse("set_variable(var=$x, var=$y, expr=|1|)"), Err(parser::ParseErr::ExtraArgument { index: 8, .. })));
+        assert!(matches!(parse("new_record()\nset_field(Person::name)"), Err(parser::ParseErr::MissingArgument { index: 3, .. })));
+        assert!(matches!(parse("if() {\n}"), Err(parser::ParseErr::MissingArgument { index: 0, .. })));
+        assert!(matches!(parse("set_variable($x, expr=|1|)"), Err(parser::ParseErr::PositionalLabelMix(4))));
+        assert!(matches!(parse("set_variable(var=$x, |1|)"), Err(parser::ParseErr::PositionalLabelMix(6))));
+        assert!(matches!(parse("if(cond=|1|) {\n}"), Err(parser::ParseErr::InvalidLabel { index: 2, .. })));
+        assert!(matches!(parse("set_field($x, |1|)"), Err(parser::ParseErr::InvalidArgument { index: 2, .. })));
+        assert!(matches!(parse("set_variable($x, |1|"), Err(parser::ParseErr::UnexpectedEOF)));
+        /* Options of steps without fields are known, but can't be kept. */
+        assert!(matches!(parse("show_custom_dialog(title=\"Done\", message=|$x|)"),
+            Err(parser::ParseErr::UnsupportedArgument { index: 4, .. })));
+        assert!(matches!(parse("set_error_capture(on)"), Err(parser::ParseErr::UnsupportedArgument { index: 2, .. })));
+        assert_eq!(parse("print(\"say \\\"hi\\\"\")").unwrap(), vec![
+            ProtoInstruction::Print { expr: CalculationString("\"say \\\"hi\\\"\"".to_string()) },
+        ]);
+        assert!(matches!(parse("go_to_layout(Person, slide_left)"), Err(parser::ParseErr::UnsupportedArgument { index: 4, .. })));
     }
 }
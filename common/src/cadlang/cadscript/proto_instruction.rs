@@ -13,6 +13,15 @@ pub enum ProtoFieldSelection {
     UnresolvedReference { occurrence: String, field: String },
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtoRecordSelection {
+    First,
+    Last,
+    Next,
+    Previous,
+    ByCalc(CalculationString),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProtoScriptSelection {
     UnresolvedReference { data_source: String, script: String },
@@ -21,20 +30,21 @@ pub enum ProtoScriptSelection {
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(u16)] pub enum ProtoInstruction {
+	// Generated by scripts/script_instruction_gen.py, do not edit by hand.
 	PerformScript { script: ProtoScriptSelection, args: CalculationString },
 	SaveACopyAsXml,
 	GoToNextField,
 	GoToPreviousField,
 	GoToLayout { layout: ProtoLayoutSelection, animation: LayoutAnimation },
 	NewRecordRequest,
-        DuplicateRecordRequest,
+	DuplicateRecordRequest,
 	DeleteRecordRequest,
 	DeleteAllRecords,
 	InsertFromIndex,
 	InsertFromLastVisited,
 	InsertCurrentDate,
 	InsertCurrentTime,
-	GoToRecordRequestPage { record: RecordSelection },
+	GoToRecordRequestPage { record: ProtoRecordSelection },
 	GoToField,
 	CheckSelection,
 	CheckRecord,
@@ -61,7 +71,7 @@ pub enum ProtoScriptSelection {
 	RelookupFieldContents,
 	EnterPreviewMode,
 	PrintSetup,
-	Print,
+	Print { expr: CalculationString },
 	ExitApplication,
 	UndoRedo,
 	Cut,
@@ -85,7 +95,7 @@ pub enum ProtoScriptSelection {
 	Else,
 	EndIf,
 	Loop,
-	ExitLoopIf { condition: CalculationString } ,
+	ExitLoopIf { condition: CalculationString },
 	EndLoop,
 	GoToRelatedRecord,
 	CommitRecordsRequests,
@@ -180,7 +190,7 @@ pub enum ProtoScriptSelection {
 	OpenUploadToHost,
 	EnableTouchKeyboard,
 	PerformJavascriptInWebViewer,
-        CommentedOut,
+	CommentedOut,
 	AvplayerPlay,
 	AvplayerSetPlaybackState,
 	AvplayerSetOptions,
@@ -212,5 +222,5 @@ pub enum ProtoScriptSelection {
 	SetDictionary,
 	PerformScriptOnServerWithCallback,
 	TriggerClarisConnectFlow,
-        Assert { expr: CalculationString },
+	Assert { expr: CalculationString },
 }
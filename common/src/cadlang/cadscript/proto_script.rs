@@ -1,10 +1,15 @@
 
 use crate::dbobjects::{calculation::CalculationString, scripting::{instructions::Instruction, script::Script}};
 
-use super::proto_instruction::*;
+use super::{proto_instruction::*, super::token::Location};
 
 #[derive(Debug, Clone)]
 pub struct ProtoScript {
     pub name: String,
+    pub location: Location,
+    /* Parameter names, without the `$` of the local each is read into. */
+    pub params: Vec<String>,
     pub instructions: Vec<ProtoInstruction>,
+    /* Where each instruction was written, in step with `instructions`. */
+    pub step_locations: Vec<Location>,
 }
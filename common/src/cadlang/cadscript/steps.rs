@@ -0,0 +1,553 @@
+// Generated by scripts/script_instruction_gen.py, do not edit by hand.
+
+use crate::dbobjects::scripting::instructions::Instruction;
+use super::proto_instruction::ProtoInstruction;
+
+/* Canonical spellings of the steps which take no arguments. */
+#[allow(dead_code)]
+pub(crate) const UNIT_STEPS: [&str; 177] = [
+    "save_a_copy_as_xml",
+    "go_to_next_field",
+    "go_to_previous_field",
+    "new_record_request",
+    "duplicate_record_request",
+    "delete_record_request",
+    "delete_all_records",
+    "insert_from_index",
+    "insert_from_last_visited",
+    "insert_current_date",
+    "insert_current_time",
+    "go_to_field",
+    "check_selection",
+    "check_record",
+    "check_found_set",
+    "unsort_records",
+    "enter_find_mode",
+    "show_all_records",
+    "modify_last_find",
+    "omit_record",
+    "omit_multiple_records",
+    "show_omitted_only",
+    "perform_find",
+    "show_hide_toolbars",
+    "view_as",
+    "adjust_window",
+    "open_help",
+    "open_file",
+    "close_file",
+    "import_records",
+    "export_records",
+    "save_a_copy_as",
+    "open_manage_database",
+    "sort_records",
+    "relookup_field_contents",
+    "enter_preview_mode",
+    "print_setup",
+    "exit_application",
+    "undo_redo",
+    "cut",
+    "copy",
+    "paste",
+    "clear",
+    "select_all",
+    "revert_record_request",
+    "enter_browser_mode",
+    "insert_picture",
+    "send_event",
+    "insert_current_user_name",
+    "insert_text",
+    "pause_resume_script",
+    "send_mail",
+    "send_dde_execute",
+    "dial_phone",
+    "speak",
+    "perform_applescript",
+    "go_to_related_record",
+    "commit_records_requests",
+    "insert_calculated_result",
+    "freeze_window",
+    "refresh_window",
+    "scroll_window",
+    "new_file",
+    "change_password",
+    "set_multi_user",
+    "allow_user_abort",
+    "set_error_capture",
+    "show_custom_dialog",
+    "open_script_workspace",
+    "blank_line_comment",
+    "halt_script",
+    "replace_field_contents",
+    "show_hide_text_ruler",
+    "beep",
+    "set_use_system_formats",
+    "recover_file",
+    "save_a_copy_as_add_on_package",
+    "set_zoom_level",
+    "copy_all_records_requests",
+    "go_to_portal_row",
+    "copy_record_request",
+    "flush_cache_to_disk",
+    "delete_portal_row",
+    "open_preferences",
+    "correct_word",
+    "spelling_options",
+    "select_dictionaries",
+    "edit_user_dictionary",
+    "open_url",
+    "open_manage_value_lists",
+    "open_sharing",
+    "open_file_options",
+    "allow_formatting_bar",
+    "set_next_serial_value",
+    "execute_sql",
+    "open_hosts",
+    "move_resize_window",
+    "arrange_all_windows",
+    "close_window",
+    "new_window",
+    "select_window",
+    "set_window_title",
+    "constrain_found_set",
+    "extend_found_set",
+    "perform_find_replace",
+    "open_find_replace",
+    "set_selection",
+    "insert_file",
+    "export_field_contents",
+    "open_record_request",
+    "add_account",
+    "delete_account",
+    "reset_account_password",
+    "enable_account",
+    "relogin",
+    "convert_file",
+    "open_manage_data_sources",
+    "install_menu_set",
+    "save_records_as_excel",
+    "save_records_as_pdf",
+    "go_to_object",
+    "set_web_viewer",
+    "set_field_by_name",
+    "install_ontimer_script",
+    "open_edit_saved_finds",
+    "perform_quick_find",
+    "open_manage_layouts",
+    "save_records_as_snapshot_link",
+    "sort_records_by_field",
+    "find_matching_records",
+    "manage_containers",
+    "install_plugin_file",
+    "insert_pdf",
+    "insert_audio_video",
+    "insert_from_url",
+    "insert_from_device",
+    "perform_script_on_server",
+    "open_manage_themes",
+    "show_hide_menubar",
+    "refresh_object",
+    "set_layout_object_animation",
+    "close_popover",
+    "open_upload_to_host",
+    "enable_touch_keyboard",
+    "perform_javascript_in_web_viewer",
+    "commented_out",
+    "avplayer_play",
+    "avplayer_set_playback_state",
+    "avplayer_set_options",
+    "refresh_portal",
+    "get_folder_path",
+    "truncate_table",
+    "open_favorites",
+    "configure_region_monitor_script",
+    "configure_local_notification",
+    "get_file_exists",
+    "get_file_size",
+    "create_data_file",
+    "open_data_file",
+    "write_to_data_file",
+    "read_from_data_file",
+    "get_data_file_position",
+    "set_data_file_position",
+    "close_data_file",
+    "delete_file",
+    "rename_file",
+    "set_error_logging",
+    "configure_nfc_reading",
+    "configure_machine_learning_model",
+    "execute_filemaker_data_api",
+    "open_transaction",
+    "commit_transaction",
+    "revert_transaction",
+    "set_session_identifier",
+    "set_dictionary",
+    "perform_script_on_server_with_callback",
+    "trigger_claris_connect_flow",
+];
+
+/* Steps which take no arguments, by any of their spellings. */
+pub(crate) fn unit_step(name: &str) -> Option<ProtoInstruction> {
+    match name {
+        "save_a_copy_as_xml" => Some(ProtoInstruction::SaveACopyAsXml),
+        "go_to_next_field" => Some(ProtoInstruction::GoToNextField),
+        "go_to_previous_field" => Some(ProtoInstruction::GoToPreviousField),
+        "new_record_request" | "new_record" | "new_request" => Some(ProtoInstruction::NewRecordRequest),
+        "duplicate_record_request" => Some(ProtoInstruction::DuplicateRecordRequest),
+        "delete_record_request" => Some(ProtoInstruction::DeleteRecordRequest),
+        "delete_all_records" => Some(ProtoInstruction::DeleteAllRecords),
+        "insert_from_index" => Some(ProtoInstruction::InsertFromIndex),
+        "insert_from_last_visited" => Some(ProtoInstruction::InsertFromLastVisited),
+        "insert_current_date" => Some(ProtoInstruction::InsertCurrentDate),
+        "insert_current_time" => Some(ProtoInstruction::InsertCurrentTime),
+        "go_to_field" => Some(ProtoInstruction::GoToField),
+        "check_selection" => Some(ProtoInstruction::CheckSelection),
+        "check_record" => Some(ProtoInstruction::CheckRecord),
+        "check_found_set" => Some(ProtoInstruction::CheckFoundSet),
+        "unsort_records" => Some(ProtoInstruction::UnsortRecords),
+        "enter_find_mode" => Some(ProtoInstruction::EnterFindMode),
+        "show_all_records" => Some(ProtoInstruction::ShowAllRecords),
+        "modify_last_find" => Some(ProtoInstruction::ModifyLastFind),
+        "omit_record" => Some(ProtoInstruction::OmitRecord),
+        "omit_multiple_records" => Some(ProtoInstruction::OmitMultipleRecords),
+        "show_omitted_only" => Some(ProtoInstruction::ShowOmmitedOnly),
+        "perform_find" => Some(ProtoInstruction::PerformFind),
+        "show_hide_toolbars" => Some(ProtoInstruction::ShowHideToolbars),
+        "view_as" => Some(ProtoInstruction::ViewAs),
+        "adjust_window" => Some(ProtoInstruction::AdjustWindow),
+        "open_help" => Some(ProtoInstruction::OpenHelp),
+        "open_file" => Some(ProtoInstruction::OpenFile),
+        "close_file" => Some(ProtoInstruction::CloseFile),
+        "import_records" => Some(ProtoInstruction::ImportRecords),
+        "export_records" => Some(ProtoInstruction::ExportRecords),
+        "save_a_copy_as" => Some(ProtoInstruction::SaveACopyAs),
+        "open_manage_database" => Some(ProtoInstruction::OpenManageDatabase),
+        "sort_records" => Some(ProtoInstruction::SortRecords),
+        "relookup_field_contents" => Some(ProtoInstruction::RelookupFieldContents),
+        "enter_preview_mode" => Some(ProtoInstruction::EnterPreviewMode),
+        "print_setup" => Some(ProtoInstruction::PrintSetup),
+        "exit_application" => Some(ProtoInstruction::ExitApplication),
+        "undo_redo" => Some(ProtoInstruction::UndoRedo),
+        "cut" => Some(ProtoInstruction::Cut),
+        "copy" => Some(ProtoInstruction::Copy),
+        "paste" => Some(ProtoInstruction::Paste),
+        "clear" => Some(ProtoInstruction::Clear),
+        "select_all" => Some(ProtoInstruction::SelectAll),
+        "revert_record_request" => Some(ProtoInstruction::RevertRecordRequest),
+        "enter_browser_mode" => Some(ProtoInstruction::EnterBrowserMode),
+        "insert_picture" => Some(ProtoInstruction::InsertPicture),
+        "send_event" => Some(ProtoInstruction::SendEvent),
+        "insert_current_user_name" => Some(ProtoInstruction::InsertCurrentUserName),
+        "insert_text" => Some(ProtoInstruction::InsertText),
+        "pause_resume_script" => Some(ProtoInstruction::PauseResumeScript),
+        "send_mail" => Some(ProtoInstruction::SendMail),
+        "send_dde_execute" => Some(ProtoInstruction::SendDdeExecute),
+        "dial_phone" => Some(ProtoInstruction::DialPhone),
+        "speak" => Some(ProtoInstruction::Speak),
+        "perform_applescript" => Some(ProtoInstruction::PerformApplescript),
+        "go_to_related_record" => Some(ProtoInstruction::GoToRelatedRecord),
+        "commit_records_requests" | "commit_records" | "commit_requests" => Some(ProtoInstruction::CommitRecordsRequests),
+        "insert_calculated_result" => Some(ProtoInstruction::InsertCalculatedResult),
+        "freeze_window" => Some(ProtoInstruction::FreezeWindow),
+        "refresh_window" => Some(ProtoInstruction::RefreshWindow),
+        "scroll_window" => Some(ProtoInstruction::ScrollWindow),
+        "new_file" => Some(ProtoInstruction::NewFile),
+        "change_password" => Some(ProtoInstruction::ChangePassword),
+        "set_multi_user" => Some(ProtoInstruction::SetMultiUser),
+        "allow_user_abort" => Some(ProtoInstruction::AllowUserAbort),
+        "set_error_capture" => Some(ProtoInstruction::SetErrorCapture),
+        "show_custom_dialog" => Some(ProtoInstruction::ShowCustomDialog),
+        "open_script_workspace" => Some(ProtoInstruction::OpenScriptWorkspace),
+        "blank_line_comment" => Some(ProtoInstruction::BlankLineComment),
+        "halt_script" => Some(ProtoInstruction::HaltScript),
+        "replace_field_contents" => Some(ProtoInstruction::ReplaceFieldContents),
+        "show_hide_text_ruler" => Some(ProtoInstruction::ShowHideTextRuler),
+        "beep" => Some(ProtoInstruction::Beep),
+        "set_use_system_formats" => Some(ProtoInstruction::SetUseSystemFormats),
+        "recover_file" => Some(ProtoInstruction::RecoverFile),
+        "save_a_copy_as_add_on_package" => Some(ProtoInstruction::SaveACopyAsAddOnPackage),
+        "set_zoom_level" => Some(ProtoInstruction::SetZoomLevel),
+        "copy_all_records_requests" => Some(ProtoInstruction::CopyAllRecordsRequests),
+        "go_to_portal_row" => Some(ProtoInstruction::GoToPortalRow),
+        "copy_record_request" => Some(ProtoInstruction::CopyRecordRequest),
+        "flush_cache_to_disk" => Some(ProtoInstruction::FluchCacheToDisk),
+        "delete_portal_row" => Some(ProtoInstruction::DeletePortalRow),
+        "open_preferences" => Some(ProtoInstruction::OpenPreferences),
+        "correct_word" => Some(ProtoInstruction::CorrectWord),
+        "spelling_options" => Some(ProtoInstruction::SpellingOptions),
+        "select_dictionaries" => Some(ProtoInstruction::SelectDictionaries),
+        "edit_user_dictionary" => Some(ProtoInstruction::EditUserDictionary),
+        "open_url" => Some(ProtoInstruction::OpenUrl),
+        "open_manage_value_lists" => Some(ProtoInstruction::OpenManageValueLists),
+        "open_sharing" => Some(ProtoInstruction::OpenSharing),
+        "open_file_options" => Some(ProtoInstruction::OpenFileOptions),
+        "allow_formatting_bar" => Some(ProtoInstruction::AllowFormattingBar),
+        "set_next_serial_value" => Some(ProtoInstruction::SetNextSerialValue),
+        "execute_sql" => Some(ProtoInstruction::ExecuteSql),
+        "open_hosts" => Some(ProtoInstruction::OpenHosts),
+        "move_resize_window" => Some(ProtoInstruction::MoveResizeWindow),
+        "arrange_all_windows" => Some(ProtoInstruction::ArrangeAllWindows),
+        "close_window" => Some(ProtoInstruction::CloseWindow),
+        "new_window" => Some(ProtoInstruction::NewWindow),
+        "select_window" => Some(ProtoInstruction::SelectWindow),
+        "set_window_title" => Some(ProtoInstruction::SetWindowTitle),
+        "constrain_found_set" => Some(ProtoInstruction::ConstrainFoundSet),
+        "extend_found_set" => Some(ProtoInstruction::ExtendFoundSet),
+        "perform_find_replace" => Some(ProtoInstruction::PerformFindReplace),
+        "open_find_replace" => Some(ProtoInstruction::OpenFindReplace),
+        "set_selection" => Some(ProtoInstruction::SetSelection),
+        "insert_file" => Some(ProtoInstruction::InsertFile),
+        "export_field_contents" => Some(ProtoInstruction::ExportFieldContents),
+        "open_record_request" => Some(ProtoInstruction::OpenRecordRequest),
+        "add_account" => Some(ProtoInstruction::AddAccount),
+        "delete_account" => Some(ProtoInstruction::DeleteAccount),
+        "reset_account_password" => Some(ProtoInstruction::ResetAccountPassword),
+        "enable_account" => Some(ProtoInstruction::EnableAccount),
+        "relogin" => Some(ProtoInstruction::Relogin),
+        "convert_file" => Some(ProtoInstruction::ConvertFile),
+        "open_manage_data_sources" => Some(ProtoInstruction::OpenManageDataSources),
+        "install_menu_set" => Some(ProtoInstruction::InstallMenuSet),
+        "save_records_as_excel" => Some(ProtoInstruction::SaveRecordsAsExcel),
+        "save_records_as_pdf" => Some(ProtoInstruction::SaveRecordsAsPdf),
+        "go_to_object" => Some(ProtoInstruction::GoToObject),
+        "set_web_viewer" => Some(ProtoInstruction::SetWebViewer),
+        "set_field_by_name" => Some(ProtoInstruction::SetFieldByName),
+        "install_ontimer_script" => Some(ProtoInstruction::InstallOntimerScript),
+        "open_edit_saved_finds" => Some(ProtoInstruction::OpenEditSavedFinds),
+        "perform_quick_find" => Some(ProtoInstruction::PerformQuickFind),
+        "open_manage_layouts" => Some(ProtoInstruction::OpenManageLayouts),
+        "save_records_as_snapshot_link" => Some(ProtoInstruction::SaveRecordsAsSnapshotLink),
+        "sort_records_by_field" => Some(ProtoInstruction::SortRecordsByField),
+        "find_matching_records" => Some(ProtoInstruction::FindMatchingRecords),
+        "manage_containers" => Some(ProtoInstruction::ManageContainers),
+        "install_plugin_file" => Some(ProtoInstruction::InstallPluginFile),
+        "insert_pdf" => Some(ProtoInstruction::InsertPdf),
+        "insert_audio_video" => Some(ProtoInstruction::InsertAudioVideo),
+        "insert_from_url" => Some(ProtoInstruction::InsertFromUrl),
+        "insert_from_device" => Some(ProtoInstruction::InsertFromDevice),
+        "perform_script_on_server" => Some(ProtoInstruction::PerformScriptOnServer),
+        "open_manage_themes" => Some(ProtoInstruction::OpenManageThemes),
+        "show_hide_menubar" => Some(ProtoInstruction::ShowHideMenubar),
+        "refresh_object" => Some(ProtoInstruction::RefreshObject),
+        "set_layout_object_animation" => Some(ProtoInstruction::SetLayoutObjectAnimation),
+        "close_popover" => Some(ProtoInstruction::ClosePopover),
+        "open_upload_to_host" => Some(ProtoInstruction::OpenUploadToHost),
+        "enable_touch_keyboard" => Some(ProtoInstruction::EnableTouchKeyboard),
+        "perform_javascript_in_web_viewer" => Some(ProtoInstruction::PerformJavascriptInWebViewer),
+        "commented_out" => Some(ProtoInstruction::CommentedOut),
+        "avplayer_play" => Some(ProtoInstruction::AvplayerPlay),
+        "avplayer_set_playback_state" => Some(ProtoInstruction::AvplayerSetPlaybackState),
+        "avplayer_set_options" => Some(ProtoInstruction::AvplayerSetOptions),
+        "refresh_portal" => Some(ProtoInstruction::RefreshPortal),
+        "get_folder_path" => Some(ProtoInstruction::GetFolderPath),
+        "truncate_table" => Some(ProtoInstruction::TruncateTable),
+        "open_favorites" => Some(ProtoInstruction::OpenFavorites),
+        "configure_region_monitor_script" => Some(ProtoInstruction::ConfigureRegionMonitorScript),
+        "configure_local_notification" => Some(ProtoInstruction::ConfigureLocalNotification),
+        "get_file_exists" => Some(ProtoInstruction::GetFileExists),
+        "get_file_size" => Some(ProtoInstruction::GetFileSize),
+        "create_data_file" => Some(ProtoInstruction::CreateDataFile),
+        "open_data_file" => Some(ProtoInstruction::OpenDataFile),
+        "write_to_data_file" => Some(ProtoInstruction::WriteToDataFile),
+        "read_from_data_file" => Some(ProtoInstruction::ReadFromDataFile),
+        "get_data_file_position" => Some(ProtoInstruction::GetDataFilePosition),
+        "set_data_file_position" => Some(ProtoInstruction::SetDataFilePosition),
+        "close_data_file" => Some(ProtoInstruction::CloseDataFile),
+        "delete_file" => Some(ProtoInstruction::DeleteFile),
+        "rename_file" => Some(ProtoInstruction::RenameFile),
+        "set_error_logging" => Some(ProtoInstruction::SetErrorLogging),
+        "configure_nfc_reading" => Some(ProtoInstruction::ConfigureNfcReading),
+        "configure_machine_learning_model" => Some(ProtoInstruction::ConfigureMachineLearningModel),
+        "execute_filemaker_data_api" => Some(ProtoInstruction::ExecuteFilemakerDataApi),
+        "open_transaction" => Some(ProtoInstruction::OpenTransaction),
+        "commit_transaction" => Some(ProtoInstruction::CommitTransaction),
+        "revert_transaction" => Some(ProtoInstruction::RevertTransaction),
+        "set_session_identifier" => Some(ProtoInstruction::SetSessionIdentifier),
+        "set_dictionary" => Some(ProtoInstruction::SetDictionary),
+        "perform_script_on_server_with_callback" => Some(ProtoInstruction::PerformScriptOnServerWithCallback),
+        "trigger_claris_connect_flow" => Some(ProtoInstruction::TriggerClarisConnectFlow),
+        _ => None,
+    }
+}
+
+pub(crate) fn lower_unit_step(step: &ProtoInstruction) -> Option<Instruction> {
+    match step {
+        ProtoInstruction::SaveACopyAsXml => Some(Instruction::SaveACopyAsXml),
+        ProtoInstruction::GoToNextField => Some(Instruction::GoToNextField),
+        ProtoInstruction::GoToPreviousField => Some(Instruction::GoToPreviousField),
+        ProtoInstruction::NewRecordRequest => Some(Instruction::NewRecordRequest),
+        ProtoInstruction::DuplicateRecordRequest => Some(Instruction::DuplicateRecordRequest),
+        ProtoInstruction::DeleteRecordRequest => Some(Instruction::DeleteRecordRequest),
+        ProtoInstruction::DeleteAllRecords => Some(Instruction::DeleteAllRecords),
+        ProtoInstruction::InsertFromIndex => Some(Instruction::InsertFromIndex),
+        ProtoInstruction::InsertFromLastVisited => Some(Instruction::InsertFromLastVisited),
+        ProtoInstruction::InsertCurrentDate => Some(Instruction::InsertCurrentDate),
+        ProtoInstruction::InsertCurrentTime => Some(Instruction::InsertCurrentTime),
+        ProtoInstruction::GoToField => Some(Instruction::GoToField),
+        ProtoInstruction::CheckSelection => Some(Instruction::CheckSelection),
+        ProtoInstruction::CheckRecord => Some(Instruction::CheckRecord),
+        ProtoInstruction::CheckFoundSet => Some(Instruction::CheckFoundSet),
+        ProtoInstruction::UnsortRecords => Some(Instruction::UnsortRecords),
+        ProtoInstruction::EnterFindMode => Some(Instruction::EnterFindMode),
+        ProtoInstruction::ShowAllRecords => Some(Instruction::ShowAllRecords),
+        ProtoInstruction::ModifyLastFind => Some(Instruction::ModifyLastFind),
+        ProtoInstruction::OmitRecord => Some(Instruction::OmitRecord),
+        ProtoInstruction::OmitMultipleRecords => Some(Instruction::OmitMultipleRecords),
+        ProtoInstruction::ShowOmmitedOnly => Some(Instruction::ShowOmmitedOnly),
+        ProtoInstruction::PerformFind => Some(Instruction::PerformFind),
+        ProtoInstruction::ShowHideToolbars => Some(Instruction::ShowHideToolbars),
+        ProtoInstruction::ViewAs => Some(Instruction::ViewAs),
+        ProtoInstruction::AdjustWindow => Some(Instruction::AdjustWindow),
+        ProtoInstruction::OpenHelp => Some(Instruction::OpenHelp),
+        ProtoInstruction::OpenFile => Some(Instruction::OpenFile),
+        ProtoInstruction::CloseFile => Some(Instruction::CloseFile),
+        ProtoInstruction::ImportRecords => Some(Instruction::ImportRecords),
+        ProtoInstruction::ExportRecords => Some(Instruction::ExportRecords),
+        ProtoInstruction::SaveACopyAs => Some(Instruction::SaveACopyAs),
+        ProtoInstruction::OpenManageDatabase => Some(Instruction::OpenManageDatabase),
+        ProtoInstruction::SortRecords => Some(Instruction::SortRecords),
+        ProtoInstruction::RelookupFieldContents => Some(Instruction::RelookupFieldContents),
+        ProtoInstruction::EnterPreviewMode => Some(Instruction::EnterPreviewMode),
+        ProtoInstruction::PrintSetup => Some(Instruction::PrintSetup),
+        ProtoInstruction::ExitApplication => Some(Instruction::ExitApplication),
+        ProtoInstruction::UndoRedo => Some(Instruction::UndoRedo),
+        ProtoInstruction::Cut => Some(Instruction::Cut),
+        ProtoInstruction::Copy => Some(Instruction::Copy),
+        ProtoInstruction::Paste => Some(Instruction::Paste),
+        ProtoInstruction::Clear => Some(Instruction::Clear),
+        ProtoInstruction::SelectAll => Some(Instruction::SelectAll),
+        ProtoInstruction::RevertRecordRequest => Some(Instruction::RevertRecordRequest),
+        ProtoInstruction::EnterBrowserMode => Some(Instruction::EnterBrowserMode),
+        ProtoInstruction::InsertPicture => Some(Instruction::InsertPicture),
+        ProtoInstruction::SendEvent => Some(Instruction::SendEvent),
+        ProtoInstruction::InsertCurrentUserName => Some(Instruction::InsertCurrentUserName),
+        ProtoInstruction::InsertText => Some(Instruction::InsertText),
+        ProtoInstruction::PauseResumeScript => Some(Instruction::PauseResumeScript),
+        ProtoInstruction::SendMail => Some(Instruction::SendMail),
+        ProtoInstruction::SendDdeExecute => Some(Instruction::SendDdeExecute),
+        ProtoInstruction::DialPhone => Some(Instruction::DialPhone),
+        ProtoInstruction::Speak => Some(Instruction::Speak),
+        ProtoInstruction::PerformApplescript => Some(Instruction::PerformApplescript),
+        ProtoInstruction::GoToRelatedRecord => Some(Instruction::GoToRelatedRecord),
+        ProtoInstruction::CommitRecordsRequests => Some(Instruction::CommitRecordsRequests),
+        ProtoInstruction::InsertCalculatedResult => Some(Instruction::InsertCalculatedResult),
+        ProtoInstruction::FreezeWindow => Some(Instruction::FreezeWindow),
+        ProtoInstruction::RefreshWindow => Some(Instruction::RefreshWindow),
+        ProtoInstruction::ScrollWindow => Some(Instruction::ScrollWindow),
+        ProtoInstruction::NewFile => Some(Instruction::NewFile),
+        ProtoInstruction::ChangePassword => Some(Instruction::ChangePassword),
+        ProtoInstruction::SetMultiUser => Some(Instruction::SetMultiUser),
+        ProtoInstruction::AllowUserAbort => Some(Instruction::AllowUserAbort),
+        ProtoInstruction::SetErrorCapture => Some(Instruction::SetErrorCapture),
+        ProtoInstruction::ShowCustomDialog => Some(Instruction::ShowCustomDialog),
+        ProtoInstruction::OpenScriptWorkspace => Some(Instruction::OpenScriptWorkspace),
+        ProtoInstruction::BlankLineComment => Some(Instruction::BlankLineComment),
+        ProtoInstruction::HaltScript => Some(Instruction::HaltScript),
+        ProtoInstruction::ReplaceFieldContents => Some(Instruction::ReplaceFieldContents),
+        ProtoInstruction::ShowHideTextRuler => Some(Instruction::ShowHideTextRuler),
+        ProtoInstruction::Beep => Some(Instruction::Beep),
+        ProtoInstruction::SetUseSystemFormats => Some(Instruction::SetUseSystemFormats),
+        ProtoInstruction::RecoverFile => Some(Instruction::RecoverFile),
+        ProtoInstruction::SaveACopyAsAddOnPackage => Some(Instruction::SaveACopyAsAddOnPackage),
+        ProtoInstruction::SetZoomLevel => Some(Instruction::SetZoomLevel),
+        ProtoInstruction::CopyAllRecordsRequests => Some(Instruction::CopyAllRecordsRequests),
+        ProtoInstruction::GoToPortalRow => Some(Instruction::GoToPortalRow),
+        ProtoInstruction::CopyRecordRequest => Some(Instruction::CopyRecordRequest),
+        ProtoInstruction::FluchCacheToDisk => Some(Instruction::FluchCacheToDisk),
+        ProtoInstruction::DeletePortalRow => Some(Instruction::DeletePortalRow),
+        ProtoInstruction::OpenPreferences => Some(Instruction::OpenPreferences),
+        ProtoInstruction::CorrectWord => Some(Instruction::CorrectWord),
+        ProtoInstruction::SpellingOptions => Some(Instruction::SpellingOptions),
+        ProtoInstruction::SelectDictionaries => Some(Instruction::SelectDictionaries),
+        ProtoInstruction::EditUserDictionary => Some(Instruction::EditUserDictionary),
+        ProtoInstruction::OpenUrl => Some(Instruction::OpenUrl),
+        ProtoInstruction::OpenManageValueLists => Some(Instruction::OpenManageValueLists),
+        ProtoInstruction::OpenSharing => Some(Instruction::OpenSharing),
+        ProtoInstruction::OpenFileOptions => Some(Instruction::OpenFileOptions),
+        ProtoInstruction::AllowFormattingBar => Some(Instruction::AllowFormattingBar),
+        ProtoInstruction::SetNextSerialValue => Some(Instruction::SetNextSerialValue),
+        ProtoInstruction::ExecuteSql => Some(Instruction::ExecuteSql),
+        ProtoInstruction::OpenHosts => Some(Instruction::OpenHosts),
+        ProtoInstruction::MoveResizeWindow => Some(Instruction::MoveResizeWindow),
+        ProtoInstruction::ArrangeAllWindows => Some(Instruction::ArrangeAllWindows),
+        ProtoInstruction::CloseWindow => Some(Instruction::CloseWindow),
+        ProtoInstruction::NewWindow => Some(Instruction::NewWindow),
+        ProtoInstruction::SelectWindow => Some(Instruction::SelectWindow),
+        ProtoInstruction::SetWindowTitle => Some(Instruction::SetWindowTitle),
+        ProtoInstruction::ConstrainFoundSet => Some(Instruction::ConstrainFoundSet),
+        ProtoInstruction::ExtendFoundSet => Some(Instruction::ExtendFoundSet),
+        ProtoInstruction::PerformFindReplace => Some(Instruction::PerformFindReplace),
+        ProtoInstruction::OpenFindReplace => Some(Instruction::OpenFindReplace),
+        ProtoInstruction::SetSelection => Some(Instruction::SetSelection),
+        ProtoInstruction::InsertFile => Some(Instruction::InsertFile),
+        ProtoInstruction::ExportFieldContents => Some(Instruction::ExportFieldContents),
+        ProtoInstruction::OpenRecordRequest => Some(Instruction::OpenRecordRequest),
+        ProtoInstruction::AddAccount => Some(Instruction::AddAccount),
+        ProtoInstruction::DeleteAccount => Some(Instruction::DeleteAccount),
+        ProtoInstruction::ResetAccountPassword => Some(Instruction::ResetAccountPassword),
+        ProtoInstruction::EnableAccount => Some(Instruction::EnableAccount),
+        ProtoInstruction::Relogin => Some(Instruction::Relogin),
+        ProtoInstruction::ConvertFile => Some(Instruction::ConvertFile),
+        ProtoInstruction::OpenManageDataSources => Some(Instruction::OpenManageDataSources),
+        ProtoInstruction::InstallMenuSet => Some(Instruction::InstallMenuSet),
+        ProtoInstruction::SaveRecordsAsExcel => Some(Instruction::SaveRecordsAsExcel),
+        ProtoInstruction::SaveRecordsAsPdf => Some(Instruction::SaveRecordsAsPdf),
+        ProtoInstruction::GoToObject => Some(Instruction::GoToObject),
+        ProtoInstruction::SetWebViewer => Some(Instruction::SetWebViewer),
+        ProtoInstruction::SetFieldByName => Some(Instruction::SetFieldByName),
+        ProtoInstruction::InstallOntimerScript => Some(Instruction::InstallOntimerScript),
+        ProtoInstruction::OpenEditSavedFinds => Some(Instruction::OpenEditSavedFinds),
+        ProtoInstruction::PerformQuickFind => Some(Instruction::PerformQuickFind),
+        ProtoInstruction::OpenManageLayouts => Some(Instruction::OpenManageLayouts),
+        ProtoInstruction::SaveRecordsAsSnapshotLink => Some(Instruction::SaveRecordsAsSnapshotLink),
+        ProtoInstruction::SortRecordsByField => Some(Instruction::SortRecordsByField),
+        ProtoInstruction::FindMatchingRecords => Some(Instruction::FindMatchingRecords),
+        ProtoInstruction::ManageContainers => Some(Instruction::ManageContainers),
+        ProtoInstruction::InstallPluginFile => Some(Instruction::InstallPluginFile),
+        ProtoInstruction::InsertPdf => Some(Instruction::InsertPdf),
+        ProtoInstruction::InsertAudioVideo => Some(Instruction::InsertAudioVideo),
+        ProtoInstruction::InsertFromUrl => Some(Instruction::InsertFromUrl),
+        ProtoInstruction::InsertFromDevice => Some(Instruction::InsertFromDevice),
+        ProtoInstruction::PerformScriptOnServer => Some(Instruction::PerformScriptOnServer),
+        ProtoInstruction::OpenManageThemes => Some(Instruction::OpenManageThemes),
+        ProtoInstruction::ShowHideMenubar => Some(Instruction::ShowHideMenubar),
+        ProtoInstruction::RefreshObject => Some(Instruction::RefreshObject),
+        ProtoInstruction::SetLayoutObjectAnimation => Some(Instruction::SetLayoutObjectAnimation),
+        ProtoInstruction::ClosePopover => Some(Instruction::ClosePopover),
+        ProtoInstruction::OpenUploadToHost => Some(Instruction::OpenUploadToHost),
+        ProtoInstruction::EnableTouchKeyboard => Some(Instruction::EnableTouchKeyboard),
+        ProtoInstruction::PerformJavascriptInWebViewer => Some(Instruction::PerformJavascriptInWebViewer),
+        ProtoInstruction::CommentedOut => Some(Instruction::CommentedOut),
+        ProtoInstruction::AvplayerPlay => Some(Instruction::AvplayerPlay),
+        ProtoInstruction::AvplayerSetPlaybackState => Some(Instruction::AvplayerSetPlaybackState),
+        ProtoInstruction::AvplayerSetOptions => Some(Instruction::AvplayerSetOptions),
+        ProtoInstruction::RefreshPortal => Some(Instruction::RefreshPortal),
+        ProtoInstruction::GetFolderPath => Some(Instruction::GetFolderPath),
+        ProtoInstruction::TruncateTable => Some(Instruction::TruncateTable),
+        ProtoInstruction::OpenFavorites => Some(Instruction::OpenFavorites),
+        ProtoInstruction::ConfigureRegionMonitorScript => Some(Instruction::ConfigureRegionMonitorScript),
+        ProtoInstruction::ConfigureLocalNotification => Some(Instruction::ConfigureLocalNotification),
+        ProtoInstruction::GetFileExists => Some(Instruction::GetFileExists),
+        ProtoInstruction::GetFileSize => Some(Instruction::GetFileSize),
+        ProtoInstruction::CreateDataFile => Some(Instruction::CreateDataFile),
+        ProtoInstruction::OpenDataFile => Some(Instruction::OpenDataFile),
+        ProtoInstruction::WriteToDataFile => Some(Instruction::WriteToDataFile),
+        ProtoInstruction::ReadFromDataFile => Some(Instruction::ReadFromDataFile),
+        ProtoInstruction::GetDataFilePosition => Some(Instruction::GetDataFilePosition),
+        ProtoInstruction::SetDataFilePosition => Some(Instruction::SetDataFilePosition),
+        ProtoInstruction::CloseDataFile => Some(Instruction::CloseDataFile),
+        ProtoInstruction::DeleteFile => Some(Instruction::DeleteFile),
+        ProtoInstruction::RenameFile => Some(Instruction::RenameFile),
+        ProtoInstruction::SetErrorLogging => Some(Instruction::SetErrorLogging),
+        ProtoInstruction::ConfigureNfcReading => Some(Instruction::ConfigureNfcReading),
+        ProtoInstruction::ConfigureMachineLearningModel => Some(Instruction::ConfigureMachineLearningModel),
+        ProtoInstruction::ExecuteFilemakerDataApi => Some(Instruction::ExecuteFilemakerDataApi),
+        ProtoInstruction::OpenTransaction => Some(Instruction::OpenTransaction),
+        ProtoInstruction::CommitTransaction => Some(Instruction::CommitTransaction),
+        ProtoInstruction::RevertTransaction => Some(Instruction::RevertTransaction),
+        ProtoInstruction::SetSessionIdentifier => Some(Instruction::SetSessionIdentifier),
+        ProtoInstruction::SetDictionary => Some(Instruction::SetDictionary),
+        ProtoInstruction::PerformScriptOnServerWithCallback => Some(Instruction::PerformScriptOnServerWithCallback),
+        ProtoInstruction::TriggerClarisConnectFlow => Some(Instruction::TriggerClarisConnectFlow),
+        _ => None,
+    }
+}
@@ -14,6 +14,8 @@ pub(crate) enum TokenVal {
 
     Loop,
     EndLoop,
+    If,
+    Else,
 
     OpenBrace,
     CloseBrace,
@@ -24,29 +26,11 @@ pub(crate) enum TokenVal {
 
     Comma,
     SemiColon,
-
-    EOF,
-}
-
-impl TokenVal {
-    pub fn get_value(&self) -> Option<String> {
-        match self {
-            TokenVal::Identifier(val) => Some(val.to_string()),
-            TokenVal::CalculationArg(val) => Some(val.to_string()),
-            TokenVal::StringArg(val) => Some(val.to_string()),
-            TokenVal::NumberArg(val) => Some(val.to_string()),
-            TokenVal::KeywordArg(val) => Some(val.to_string()),
-            TokenVal::ArgLabel(val) => Some(val.to_string()),
-
-            _ => None,
-        }
-    }
 }
 
+/* Locations are (line, column) within the script body, counted from 0. */
+#[derive(Debug, Clone)]
 pub struct Token {
     pub value: TokenVal,
     pub location: (usize, usize),
 }
-
-impl Token {
-}
@@ -2,11 +2,14 @@ use crate::cadlang::lexer::lex;
 
 use crate::dbobjects::schema::Schema;
 
-use std::path::Path;
-use std::fs::read_to_string;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::fs::{read_dir, read_to_string};
 
-use super::{parser::parse, error::CompileErr}; 
-use super::backend::build_file;
+use super::{parser::{parse, parse_partial}, error::CompileErr, validate::{validate, validate_externs}};
+use super::lockfile::{allocate_ids, lock_path, Lockfile};
+use super::{staging::Stage, token::{Location, Token, TokenType}};
+use super::backend::{build_file_with_externs, load_externs};
 use crate::dbobjects::file::File;
 
 #[deprecated]
@@ -30,20 +33,224 @@ pub fn compile_to_schema(code: String) -> Result<Schema, CompileErr> {
     Ok(Schema::new())
 }
 
-pub fn compile_to_file(path: &Path) -> Result<File, CompileErr> {
+/* A compile error along with the module it was found in. */
+pub type ModuleErr = (PathBuf, CompileErr);
+
+fn find_modules(dir: &Path, result: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    let mut paths = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            find_modules(&path, result);
+        } else if path.extension().is_some_and(|ext| ext == "cad") {
+            result.push(path);
+        }
+    }
+}
+
+/* One .cad file making up part of a solution file. */
+pub struct Module {
+    pub path: PathBuf,
+    pub tokens: Vec<Token>,
+}
+
+fn lex_module(path: &Path, errors: &mut Vec<ModuleErr>) -> Option<Vec<Token>> {
+    let Ok(code) = read_to_string(path) else {
+        let name = Token::with_value(TokenType::String, Location { line: 0, column: 0 }, path.display().to_string());
+        errors.push((path.to_path_buf(), CompileErr::UnknownModule { path: name }));
+        return None;
+    };
+    match lex(&code) {
+        Ok(tokens) => Some(tokens),
+        Err(..) => {
+            errors.push((path.to_path_buf(), CompileErr::UnexpectedEOF));
+            None
+        }
+    }
+}
+
+fn load_imports(module: &Module, loaded: &mut HashSet<PathBuf>, result: &mut Vec<Module>, errors: &mut Vec<ModuleErr>) {
+    let dir = module.path.parent().unwrap_or(Path::new("."));
+    let imports = module.tokens.windows(2)
+        .filter(|pair| pair[0].ttype == TokenType::Import && pair[1].ttype == TokenType::String)
+        .map(|pair| &pair[1]);
+    for import in imports {
+        let target = dir.join(&import.value);
+        let paths = if target.is_dir() {
+            let mut paths = vec![];
+            find_modules(&target, &mut paths);
+            paths
+        } else if target.is_file() {
+            vec![target]
+        } else {
+            errors.push((module.path.clone(), CompileErr::UnknownModule { path: import.clone() }));
+            continue;
+        };
+        for path in paths {
+            /* Shared modules and import cycles are only loaded once. */
+            if !loaded.insert(path.canonicalize().unwrap_or(path.clone())) {
+                continue;
+            }
+            if let Some(tokens) = lex_module(&path, errors) {
+                let imported = Module { path, tokens };
+                load_imports(&imported, loaded, result, errors);
+                result.push(imported);
+            }
+        }
+    }
+}
+
+/* Finds every module imported by the file at `path`, whose tokens are
+ * given, returning them all with the root first. Import paths are relative
+ * to the importing file, and importing a directory imports every .cad file
+ * below it. */
+pub fn collect_modules(path: &Path, tokens: Vec<Token>, errors: &mut Vec<ModuleErr>) -> Vec<Module> {
+    let root = Module { path: path.to_path_buf(), tokens };
+    let mut loaded = HashSet::from([path.canonicalize().unwrap_or(path.to_path_buf())]);
+    let mut imported = vec![];
+    load_imports(&root, &mut loaded, &mut imported, errors);
+    let mut result = vec![root];
+    result.extend(imported);
+    result
+}
+
+/* Parses every module, merging them into one Stage. */
+pub fn merge_modules(modules: &[Module], errors: &mut Vec<ModuleErr>) -> Stage {
+    let mut stage = Stage::new();
+    for module in modules {
+        let (parsed, parse_errors) = parse_partial(&module.tokens);
+        errors.extend(parse_errors.into_iter().map(|e| (module.path.clone(), e)));
+        errors.extend(stage.merge(parsed, &module.path.display().to_string())
+            .into_iter()
+            .map(|e| (module.path.clone(), e)));
+    }
+    stage
+}
+
+/* Parses a file together with every module it imports, giving objects
+ * without an id one from `lock`. */
+pub fn load_stage(path: &Path, lock: &mut Lockfile) -> Result<Stage, Vec<ModuleErr>> {
+    let mut errors = vec![];
+    let Some(tokens) = lex_module(path, &mut errors) else {
+        return Err(errors);
+    };
+    let mut modules = collect_modules(path, tokens, &mut errors);
+    allocate_ids(modules.iter_mut().map(|m| &mut m.tokens), lock);
+    let stage = merge_modules(&modules, &mut errors);
+    if errors.is_empty() {
+        Ok(stage)
+    } else {
+        Err(errors)
+    }
+}
+
+/* Errors are returned for the caller to report. Validation runs on the
+ * merged modules, so its errors are given against `path` itself. */
+pub fn compile_to_file(path: &Path) -> Result<File, Vec<ModuleErr>> {
     let working_dir = path.parent().unwrap();
 
     println!("working directory: {:?}", working_dir);
 
     println!("PATH: {:?}", path);
-    let code = read_to_string(path).unwrap();
-    let tokens = lex(&code).expect("Unable to lex cadmus code.");
-    let staging = match parse(&tokens) {
-        Ok(s) => s,
-        Err(e) => { eprintln!("{:?}", e); panic!(); }
-    };
+    let lock_path = lock_path(path);
+    let mut lock = Lockfile::load(&lock_path);
+    let before = lock.clone();
+    let staging = load_stage(path, &mut lock)?;
+
+    let in_root = |errors: Vec<CompileErr>| errors.into_iter()
+        .map(|e| (path.to_path_buf(), e))
+        .collect::<Vec<_>>();
+    let errors = validate(&staging);
+    if !errors.is_empty() {
+        return Err(in_root(errors));
+    }
+    let externs = load_externs(&staging, working_dir);
+    let errors = validate_externs(&staging, &externs);
+    if !errors.is_empty() {
+        return Err(in_root(errors));
+    }
 
-    let file = build_file(&staging, working_dir);
+    /* The lockfile only changes when an id was allocated, so files which
+     * give every id themselves never get one. */
+    if lock != before {
+        if let Err(e) = lock.save(&lock_path) {
+            eprintln!("warning: unable to write {}: {}", lock_path.display(), e);
+        }
+    }
+
+    let file = build_file_with_externs(&staging, working_dir, &externs);
 
     Ok(file)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::cadlang::lockfile::{lock_path, Lockfile};
+
+    use super::{compile_to_file, load_stage};
+
+    #[test]
+    fn modules_are_merged() {
+        let stage = load_stage(Path::new("test_data/cad_files/module_solution/main.cad"), &mut Lockfile::default()).unwrap();
+        let tables = stage.tables.values().map(|t| t.name.value.as_str()).collect::<Vec<_>>();
+        assert_eq!(tables, vec!["Person", "Invoice"]);
+        assert_eq!(stage.scripts[&1].name, "hello");
+
+        let file = compile_to_file(Path::new("test_data/cad_files/module_solution/main.cad")).unwrap();
+        assert_eq!(file.schema.tables.len(), 2);
+        assert_eq!(file.layouts.len(), 1);
+    }
+
+    #[test]
+    fn id_clashes_across_modules() {
+        let errors = load_stage(Path::new("test_data/cad_files/module_clash/main.cad"), &mut Lockfile::default()).unwrap_err();
+        let messages = errors.iter()
+            .map(|(module, e)| format!("{}: {}", module.file_name().unwrap().to_string_lossy(), e))
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "main.cad: Unable to find module \"missing.cad\" @ 2, 8",
+            "other.cad: table \"Company\" in test_data/cad_files/module_clash/other.cad @ 1, 10 reuses the id %1 of a table in another module",
+        ]);
+    }
+
+    #[test]
+    fn ids_are_allocated_across_modules() {
+        let dir = std::env::temp_dir().join(format!("cadmus_ids_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.cad");
+        std::fs::write(&main, "import \"person.cad\"\ntable %1 Invoice = {\n    field id = {\n        datatype = Number,\n    },\n}\n").unwrap();
+        std::fs::write(dir.join("person.cad"), "table Person = {\n    field id = {\n        datatype = Number,\n    },\n}\n").unwrap();
+
+        let file = compile_to_file(&main).unwrap();
+        let mut tables = file.schema.tables.iter().map(|t| (t.id, t.name.as_str())).collect::<Vec<_>>();
+        tables.sort();
+        assert_eq!(tables, vec![(1, "Invoice"), (2, "Person")]);
+
+        let saved = std::fs::read_to_string(lock_path(&main)).unwrap();
+        assert!(saved.contains("table\tPerson\t2\n"));
+        compile_to_file(&main).unwrap();
+        assert_eq!(std::fs::read_to_string(lock_path(&main)).unwrap(), saved);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn every_error_is_returned() {
+        let errors = compile_to_file(Path::new("test_data/cad_files/module_clash/main.cad")).unwrap_err();
+        assert_eq!(errors.len(), 2);
+
+        let dir = std::env::temp_dir().join(format!("cadmus_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.cad");
+        std::fs::write(&main, "table %1 Person = {\n    field %1 id = {\n        datatype = Number,\n    },\n}\n\ntable_occurrence %1 People : Missing\ntable_occurrence %2 Others : AlsoMissing\n").unwrap();
+
+        let errors = compile_to_file(&main).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|(module, _)| module == &main));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
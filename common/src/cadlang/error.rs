@@ -8,11 +8,33 @@ pub enum CompileErr {
     UnknownTable { token: Token },
     UnknownTableOccurrence { token: Token },
     UnknownField { token: Token },
+    UnrelatedOccurrence { from: String, token: Token },
+    WrongBaseTable { table: String, token: Token },
     InvalidAssert { token: Token }, // Asserts can only be used in tests
     MissingAttribute { base_object: String, construct: String, specifier: String },
+    UnknownAttribute { construct: String, token: Token },
     UnimplementedLanguageFeauture { feature: String, token: Token },
     UndefinedReference { construct: FMObjType, token: Token },
     UnknownFileType { filename: Token },
+    DuplicateId { construct: FMObjType, token: Token },
+    DuplicateName { construct: FMObjType, token: Token },
+    ModuleIdClash { construct: FMObjType, id: u16, token: Token, module: String },
+    UnknownModule { path: Token },
+    UnclosedBlock { token: Token },
+    UnmatchedBlock { token: Token },
+    UnknownScriptStep { token: Token },
+    MixedArguments { token: Token }, // script step arguments are all positional or all labelled.
+    MissingArgument { step: String, argument: String, token: Token },
+    ExtraArgument { step: String, token: Token },
+    InvalidArgument { step: String, argument: String, token: Token },
+    UnsupportedArgument { step: String, argument: String, token: Token }, // known to FileMaker, but not kept by the compiler.
+    UnassignedVariable { token: Token },
+    InvalidRepetitions { token: Token },
+    IntegerOutOfRange { token: Token },
+    RelationOptionSide { token: Token }, // options apply to one of the joined occurrences.
+    RelationCycle { path: Vec<String>, token: Token },
+    CalculationCycle { path: Vec<String>, token: Token },
+    FormatChangedMeaning { detail: String }, // formatter bug; the file is left as it was.
     UnexpectedEOF,
 }
 
@@ -63,9 +85,30 @@ impl<'a> fmt::Display for CompileErr {
                     token.location.line,
                     token.location.column)
             }
+            Self::UnrelatedOccurrence { from, token } => {
+                write!(f, "No relation between {} and {} @ {}, {}",
+                    from,
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::WrongBaseTable { table, token } => {
+                write!(f, "Table occurrence {} is not an occurrence of {} @ {}, {}",
+                    token.value,
+                    table,
+                    token.location.line,
+                    token.location.column)
+            }
             Self::MissingAttribute { base_object, construct, specifier } => {
                 write!(f, "Missing attribute {} for {} in {}", specifier, construct, base_object)
             }
+            Self::UnknownAttribute { construct, token } => {
+                write!(f, "Unknown attribute \"{}\" for {} @ {}, {}",
+                    token.value,
+                    construct,
+                    token.location.line,
+                    token.location.column)
+            }
             Self::UnimplementedLanguageFeauture { feature, token } => {
                 write!(f, "Unimplemented language feature: {} used @ {},{}",
                     feature,
@@ -75,7 +118,139 @@ impl<'a> fmt::Display for CompileErr {
             Self::UndefinedReference { construct, token } => {
                 write!(f, "Undefined reference to {} \"{}\"", construct, token.value)
             }
-            _ => write!(f, "nah not compiling.")
+            Self::InvalidAssert { token } => {
+                write!(f, "Asserts can only be used in tests @ {}, {}",
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::UnknownFileType { filename } => {
+                write!(f, "Unknown file type for \"{}\" @ {}, {}",
+                    filename.value,
+                    filename.location.line,
+                    filename.location.column)
+            }
+            Self::DuplicateId { construct, token } => {
+                write!(f, "Duplicate {} id %{} @ {}, {}",
+                    construct,
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::DuplicateName { construct, token } => {
+                write!(f, "Duplicate {} name \"{}\" @ {}, {}",
+                    construct,
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::ModuleIdClash { construct, id, token, module } => {
+                write!(f, "{} \"{}\" in {} @ {}, {} reuses the id %{} of a {} in another module",
+                    construct,
+                    token.value,
+                    module,
+                    token.location.line,
+                    token.location.column,
+                    id,
+                    construct)
+            }
+            Self::UnknownModule { path } => {
+                write!(f, "Unable to find module \"{}\" @ {}, {}",
+                    path.value,
+                    path.location.line,
+                    path.location.column)
+            }
+            Self::UnclosedBlock { token } => {
+                write!(f, "Unclosed {} block @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::UnmatchedBlock { token } => {
+                write!(f, "Unmatched {} @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::UnknownScriptStep { token } => {
+                write!(f, "Unknown script step {} @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::MixedArguments { token } => {
+                write!(f, "Positional and labelled arguments can't be mixed @ {}, {}",
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::MissingArgument { step, argument, token } => {
+                write!(f, "Missing argument \"{}\" for {} @ {}, {}",
+                    argument,
+                    step,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::ExtraArgument { step, token } => {
+                write!(f, "Unexpected argument \"{}\" for {} @ {}, {}",
+                    token.value,
+                    step,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::InvalidArgument { step, argument, token } => {
+                write!(f, "Invalid value \"{}\" for argument \"{}\" of {} @ {}, {}",
+                    token.value,
+                    argument,
+                    step,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::UnsupportedArgument { step, argument, token } => {
+                write!(f, "Argument \"{}\" of {} isn't supported yet @ {}, {}",
+                    argument,
+                    step,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::UnassignedVariable { token } => {
+                write!(f, "Variable {} used before assignment @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::InvalidRepetitions { token } => {
+                write!(f, "Repetitions must be from 1 to 255, found {} @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::IntegerOutOfRange { token } => {
+                write!(f, "Integer {} is too large @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::RelationOptionSide { token } => {
+                write!(f, "Relation option names \"{}\", which is not joined by the relation. @ {}, {}",
+                    token.value,
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::RelationCycle { path, token } => {
+                write!(f, "Relation closes a cycle: {} @ {}, {}",
+                    path.join(" -> "),
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::CalculationCycle { path, token } => {
+                write!(f, "Calculation refers back to itself: {} @ {}, {}",
+                    path.join(" -> "),
+                    token.location.line,
+                    token.location.column)
+            }
+            Self::FormatChangedMeaning { detail } => {
+                write!(f, "Formatting would change the meaning of the file ({}), so it was not formatted.", detail)
+            }
+            Self::UnexpectedEOF => write!(f, "Unexpected end of file."),
         }
     }
 
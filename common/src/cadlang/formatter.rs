@@ -0,0 +1,588 @@
+/* Canonical printer for .cad files, used by `cadmus fmt`.
+ *
+ * The formatter works on the token stream rather than the Stage, as the Stage
+ * drops comments and compiles script bodies. Tokens are grouped into a tree
+ * of entries and blocks, which is then reprinted:
+ * - two space indentation, one entry per line, and a trailing comma after
+ *   every entry inside a block.
+ * - attributes of fields, serials and value lists in a fixed order.
+ * - top level objects with a block are separated by a blank line, otherwise
+ *   a single blank line is kept wherever the source had one or more.
+ * - comments stay attached to the entry that follows them, or the entry on
+ *   the same line for trailing comments.
+ * - script bodies are reindented by brace depth.
+ * Only files which parse are formatted, and the output is checked to parse
+ * into the same objects. */
+
+use super::{
+    error::CompileErr,
+    lexer::lex_with_comments,
+    lockfile::{allocate_ids, Lockfile},
+    parser::parse,
+    token::{quote, Token, TokenType},
+};
+
+const INDENT: &str = "  ";
+
+/* Attribute order inside blocks, keys not listed keep their relative order
+ * after the listed ones. */
+const ATTRIBUTE_ORDER: [&str; 26] = [
+    "datatype", "calculation", "stored", "summary", "global", "repetitions",
+    "serial", "generate", "next", "increment",
+    "calculated_val", "do_not_replace", "lookup", "data",
+    "validate", "override", "required", "unique", "not_empty", "member_of", "range", "max_chars",
+    "validation_calc", "validation_message",
+    "from", "sort",
+];
+
+#[derive(Debug)]
+struct Block {
+    items: Vec<Item>,
+    script: Option<String>,
+}
+
+#[derive(Debug)]
+enum Item {
+    Comment { token: Token, blank_before: bool },
+    Entry {
+        tokens: Vec<Token>,
+        block: Option<Block>,
+        trailing: Option<Token>,
+        blank_before: bool,
+    },
+}
+
+impl Item {
+    fn blank_before(&self) -> bool {
+        match self {
+            Self::Comment { blank_before, .. } | Self::Entry { blank_before, .. } => *blank_before,
+        }
+    }
+
+    fn set_blank_before(&mut self, value: bool) {
+        match self {
+            Self::Comment { blank_before, .. } | Self::Entry { blank_before, .. } => *blank_before = value,
+        }
+    }
+}
+
+fn is_top_level(ttype: TokenType) -> bool {
+    matches!(ttype,
+        TokenType::Table | TokenType::TableOccurrence | TokenType::Relation | TokenType::ValueList
+        | TokenType::Layout | TokenType::Script | TokenType::Test | TokenType::Extern | TokenType::Import)
+}
+
+/* Tokens which can end an entry that has no comma after it. */
+fn is_terminal(ttype: TokenType) -> bool {
+    !matches!(ttype,
+        TokenType::Assignment | TokenType::Colon | TokenType::ScopeResolution | TokenType::Exclamation
+        | TokenType::Eq | TokenType::Neq | TokenType::Lt | TokenType::Lte | TokenType::Gt | TokenType::Gte
+        | TokenType::Field | TokenType::ObjectNumber | TokenType::OpenParen)
+}
+
+fn last_line(token: &Token) -> u32 {
+    token.location.line + token.value.matches('\n').count() as u32
+}
+
+fn token_text(token: &Token) -> String {
+    let text = match token.ttype {
+        TokenType::ObjectNumber => return format!("%{}", token.value),
+        TokenType::String => return quote(&token.value),
+        TokenType::Calculation => return format!("|{}|", token.value),
+        TokenType::Comment => return token.value.trim_end().to_string(),
+        TokenType::Assignment => "=",
+        TokenType::Eq => "==",
+        TokenType::Neq => "!=",
+        TokenType::Lt => "<",
+        TokenType::Lte => "<=",
+        TokenType::Gt => ">",
+        TokenType::Gte => ">=",
+        TokenType::Colon => ":",
+        TokenType::ScopeResolution => "::",
+        TokenType::Comma => ",",
+        TokenType::Exclamation => "!",
+        TokenType::OpenBrace => "{",
+        TokenType::OpenParen => "(",
+        TokenType::CloseParen => ")",
+        TokenType::CloseBrace => "}",
+        TokenType::CalculatedVal => "calculated_val",
+        TokenType::Datatype => "datatype",
+        TokenType::DoNotReplace => "do_not_replace",
+        TokenType::Extern => "extern",
+        TokenType::Import => "import",
+        TokenType::False => "false",
+        TokenType::Field => "field",
+        TokenType::FirstField => "first_field",
+        TokenType::From => "from",
+        TokenType::Generate => "generate",
+        TokenType::Increment => "increment",
+        TokenType::Layout => "layout",
+        TokenType::Lookup => "lookup",
+        TokenType::MaxChars => "max_chars",
+        TokenType::MemberOf => "member_of",
+        TokenType::Next => "next",
+        TokenType::NotEmpty => "not_empty",
+        TokenType::Number => "Number",
+        TokenType::OnCreation => "on_creation",
+        TokenType::OnCommit => "on_commit",
+        TokenType::AllowOverride => "override",
+        TokenType::Range => "range",
+        TokenType::Relation => "relation",
+        TokenType::Required => "required",
+        TokenType::Script => "script",
+        TokenType::SecondField => "second_field",
+        TokenType::Serial => "serial",
+        TokenType::Sort => "sort",
+        TokenType::Table => "table",
+        TokenType::TableOccurrence => "table_occurrence",
+        TokenType::Test => "test",
+        TokenType::Text => "Text",
+        TokenType::True => "true",
+        TokenType::Unique => "unique",
+        TokenType::Validate => "validate",
+        TokenType::ValidationMessage => "validation_message",
+        TokenType::ValidationCalc => "validation_calc",
+        TokenType::ValueList => "value_list",
+        _ => return token.value.clone(),
+    };
+    text.to_string()
+}
+
+fn join_tokens(tokens: &[Token]) -> String {
+    let mut buffer = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let tight = i == 0
+            || token.ttype == TokenType::Comma
+            || token.ttype == TokenType::ScopeResolution
+            || token.ttype == TokenType::OpenParen
+            || token.ttype == TokenType::CloseParen
+            || tokens[i - 1].ttype == TokenType::ScopeResolution
+            || tokens[i - 1].ttype == TokenType::OpenParen
+            || tokens[i - 1].ttype == TokenType::Exclamation;
+        if !tight {
+            buffer.push(' ');
+        }
+        buffer.push_str(&token_text(token));
+    }
+    buffer
+}
+
+struct TreeBuilder<'a> {
+    tokens: &'a [Token],
+    cursor: usize,
+    /* Last source line the previous token was on, for blank lines and
+     * trailing comments. */
+    line: u32,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.cursor).filter(|t| t.ttype != TokenType::EOF)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek()?;
+        self.cursor += 1;
+        self.line = last_line(token);
+        Some(token)
+    }
+
+    fn items(&mut self, top_level: bool) -> Vec<Item> {
+        let mut items = vec![];
+        while let Some(token) = self.peek() {
+            match token.ttype {
+                TokenType::CloseBrace if !top_level => break,
+                TokenType::Comma => { self.next(); }
+                TokenType::Comment => {
+                    let same_line = token.location.line == self.line;
+                    if let (true, Some(Item::Entry { trailing: trailing @ None, .. })) = (same_line, items.last_mut()) {
+                        *trailing = Some(token.clone());
+                    } else {
+                        let blank_before = token.location.line > self.line + 1;
+                        items.push(Item::Comment { token: token.clone(), blank_before });
+                    }
+                    self.next();
+                }
+                _ => {
+                    let blank_before = token.location.line > self.line + 1;
+                    items.push(self.entry(top_level, blank_before));
+                }
+            }
+        }
+        items
+    }
+
+    fn entry(&mut self, top_level: bool, blank_before: bool) -> Item {
+        let mut tokens: Vec<Token> = vec![];
+        let mut block = None;
+        while let Some(token) = self.peek() {
+            let boundary = match token.ttype {
+                TokenType::Comment | TokenType::EOF => true,
+                TokenType::CloseBrace => true,
+                TokenType::Comma => !top_level,
+                ttype if is_top_level(ttype) => !tokens.is_empty(),
+                ttype => !top_level && tokens.last().is_some_and(|last| {
+                    token.location.line > self.line && is_terminal(last.ttype) && is_terminal(ttype)
+                }),
+            };
+            if boundary {
+                break;
+            }
+
+            self.next();
+            if token.ttype == TokenType::OpenBrace {
+                block = Some(self.block());
+                break;
+            }
+            tokens.push(token.clone());
+        }
+        Item::Entry { tokens, block, trailing: None, blank_before }
+    }
+
+    fn block(&mut self) -> Block {
+        let result = match self.peek() {
+            Some(token) if token.ttype == TokenType::ScriptContent => {
+                self.next();
+                Block { items: vec![], script: Some(token.value.clone()) }
+            }
+            _ => Block { items: self.items(false), script: None },
+        };
+        /* The closing brace */
+        self.next();
+        result
+    }
+}
+
+fn attribute_rank(item: &Item) -> usize {
+    match item {
+        Item::Entry { tokens, .. } if tokens.len() > 1 && tokens[1].ttype == TokenType::Assignment => {
+            let key = token_text(&tokens[0]).to_lowercase();
+            ATTRIBUTE_ORDER.iter().position(|k| *k == key).unwrap_or(ATTRIBUTE_ORDER.len())
+        }
+        _ => ATTRIBUTE_ORDER.len(),
+    }
+}
+
+/* Sorts attribute entries, moving the comments in front of each entry with
+ * it. Blocks which are not made of attributes are left as they are. */
+fn order_attributes(items: Vec<Item>) -> Vec<Item> {
+    let is_attribute = |item: &Item| matches!(item,
+        Item::Entry { tokens, .. } if tokens.len() > 1 && tokens[1].ttype == TokenType::Assignment);
+    if !items.iter().any(is_attribute)
+        || items.iter().any(|item| matches!(item, Item::Entry { .. }) && !is_attribute(item)) {
+        return items;
+    }
+
+    let mut groups: Vec<Vec<Item>> = vec![];
+    let mut pending = vec![];
+    for item in items {
+        let entry = matches!(item, Item::Entry { .. });
+        pending.push(item);
+        if entry {
+            groups.push(std::mem::take(&mut pending));
+        }
+    }
+    groups.sort_by_key(|group| attribute_rank(group.last().unwrap()));
+    let mut result = groups.into_iter().flatten().collect::<Vec<_>>();
+    result.extend(pending);
+    result
+}
+
+/* Counts braces outside of strings and calculations. Returns the number of
+ * leading closing braces, and the change in depth over the line. */
+fn script_line_depth(line: &str) -> (usize, isize) {
+    let leading = line.chars().take_while(|c| *c == '}').count();
+    let mut delta = 0;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (None, '"' | '|') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '{') => delta += 1,
+            (None, '}') => delta -= 1,
+            _ => {}
+        }
+    }
+    (leading, delta)
+}
+
+fn format_script(script: &str, depth: usize, buffer: &mut String) {
+    let lines = script.lines().map(str::trim).collect::<Vec<_>>();
+    let start = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !l.is_empty()).map(|i| i + 1).unwrap_or(start);
+
+    let mut script_depth: isize = 0;
+    let mut previous_blank = false;
+    for line in &lines[start..end] {
+        if line.is_empty() {
+            if !previous_blank {
+                buffer.push('\n');
+            }
+            previous_blank = true;
+            continue;
+        }
+        previous_blank = false;
+        let (leading, delta) = script_line_depth(line);
+        let indent = depth + (script_depth - leading as isize).max(0) as usize;
+        buffer.push_str(&INDENT.repeat(indent));
+        buffer.push_str(line);
+        buffer.push('\n');
+        script_depth = (script_depth + delta).max(0);
+    }
+}
+
+fn format_items(items: Vec<Item>, depth: usize, buffer: &mut String) {
+    let top_level = depth == 0;
+    let mut items = if top_level { items } else { order_attributes(items) };
+
+    if top_level {
+        /* Objects with a block always get a blank line, in front of any
+         * comments leading up to them. */
+        let mut group_start = 0;
+        let mut previous_block = false;
+        for i in 0..items.len() {
+            let has_block = match &items[i] {
+                Item::Entry { block, .. } => block.is_some(),
+                Item::Comment { .. } => continue,
+            };
+            if group_start > 0 && (previous_block || has_block) {
+                items[group_start].set_blank_before(true);
+            }
+            previous_block = has_block;
+            group_start = i + 1;
+        }
+    }
+
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 && item.blank_before() {
+            buffer.push('\n');
+        }
+        let indent = INDENT.repeat(depth);
+        match item {
+            Item::Comment { token, .. } => {
+                buffer.push_str(&format!("{}{}\n", indent, token_text(&token)));
+            }
+            Item::Entry { tokens, block, trailing, .. } => {
+                buffer.push_str(&indent);
+                buffer.push_str(&join_tokens(&tokens));
+                if let Some(block) = block {
+                    if !tokens.is_empty() {
+                        buffer.push(' ');
+                    }
+                    match block.script {
+                        Some(script) => {
+                            buffer.push_str("{\n");
+                            format_script(&script, depth + 1, buffer);
+                            buffer.push_str(&format!("{}}}", indent));
+                        }
+                        None if block.items.is_empty() => buffer.push_str("{}"),
+                        None => {
+                            buffer.push_str("{\n");
+                            format_items(block.items, depth + 1, buffer);
+                            buffer.push_str(&format!("{}}}", indent));
+                        }
+                    }
+                }
+                if !top_level {
+                    buffer.push(',');
+                }
+                if let Some(comment) = trailing {
+                    buffer.push_str(&format!(" {}", token_text(&comment)));
+                }
+                buffer.push('\n');
+            }
+        }
+    }
+}
+
+/* What a file declares, for checking the formatter did not change it. Script
+ * bodies are compared as text with whitespace removed rather than compiled,
+ * as compiling them is not needed to format them. */
+fn meaning(tokens: Vec<Token>) -> Result<Vec<String>, Vec<CompileErr>> {
+    let mut result = vec![];
+    let mut tokens = tokens.into_iter()
+        .filter(|t| t.ttype != TokenType::Comment)
+        .map(|mut t| {
+            if t.ttype == TokenType::ScriptContent {
+                result.push(t.value.split_whitespace().collect::<String>());
+                t.value.clear();
+            }
+            t
+        })
+        .collect::<Vec<_>>();
+    /* Objects without an id are keyed by the same allocated one on both
+     * sides, as the formatter never reorders them. */
+    allocate_ids([&mut tokens], &mut Lockfile::default());
+    let stage = parse(&tokens)?;
+
+    for table in stage.tables.values() {
+        result.push(format!("table {} {}", table.id, table.name.value));
+        for field in table.fields.values() {
+            /* Reordering attributes reorders validation checks, which has no
+             * effect. */
+            let mut checks = field.validation.checks.iter().map(|c| format!("{:?}", c)).collect::<Vec<_>>();
+            checks.sort();
+            result.push(format!("field {} {} {:?} {:?} {:?} {:?} {:?}", field.id, field.name.value, field.kind, field.dtype, field.autoentry, checks, field.validation.message));
+        }
+    }
+    result.extend(stage.table_occurrences.values().map(|o| format!("occurrence {} {} {}", o.id, o.name.value, o.base_table.value)));
+    result.extend(stage.relations.values().map(|r| format!("relation {} {}", r.id, r.criterias.len())));
+    result.extend(stage.value_lists.values().map(|v| format!("value_list {} {}", v.id, v.name.value)));
+    result.extend(stage.layouts.values().map(|l| format!("layout {} {}", l.id, l.name.value)));
+    result.extend(stage.scripts.iter().map(|(id, s)| format!("script {} {}", id, s.name)));
+    result.extend(stage.tests.iter().map(|(id, s)| format!("test {} {}", id, s.name)));
+    result.extend(stage.data_sources.values().map(|d| format!("extern {} {}", d.id, d.name)));
+    Ok(result)
+}
+
+/* Refuses formatted output that no longer lexes, parses or declares the same
+ * objects as the original did. */
+fn check_meaning(before: &[String], formatted: &str) -> Result<(), Vec<CompileErr>> {
+    let changed = |detail: String| vec![CompileErr::FormatChangedMeaning { detail }];
+    let tokens = lex_with_comments(formatted).map_err(|_| changed(String::from("output does not lex")))?;
+    let after = meaning(tokens).map_err(|errs| changed(format!("output does not parse: {}", errs[0])))?;
+    if let Some((was, now)) = before.iter().zip(after.iter()).find(|(was, now)| was != now) {
+        return Err(changed(format!("\"{}\" became \"{}\"", was, now)));
+    }
+    if before.len() != after.len() {
+        return Err(changed(format!("{} declarations became {}", before.len(), after.len())));
+    }
+    Ok(())
+}
+
+pub fn format(code: &str) -> Result<String, Vec<CompileErr>> {
+    let tokens = lex_with_comments(code).map_err(|_| vec![CompileErr::UnexpectedEOF])?;
+    let before = meaning(tokens.clone())?;
+
+    let mut builder = TreeBuilder { tokens: &tokens, cursor: 0, line: 1 };
+    let mut items = builder.items(true);
+    if let Some(first) = items.first_mut() {
+        first.set_blank_before(false);
+    }
+    let mut buffer = String::new();
+    format_items(items, 0, &mut buffer);
+
+    /* A formatter bug must never change what a file means. */
+    check_meaning(&before, &buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{check_meaning, format, lex_with_comments, meaning, CompileErr};
+
+    fn cad_files(dir: &Path, result: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                cad_files(&path, result);
+            } else if path.extension().is_some_and(|e| e == "cad") {
+                result.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn changed_meaning_is_an_error() {
+        let code = "table %1 Person = {\n  field %1 id = {\n    datatype = Number,\n  },\n}\n";
+        let before = meaning(lex_with_comments(code).unwrap()).unwrap();
+        assert!(check_meaning(&before, code).is_ok());
+
+        let errs = check_meaning(&before, &code.replace("Number", "Text")).unwrap_err();
+        assert!(matches!(&errs[..], [CompileErr::FormatChangedMeaning { .. }]));
+        let errs = check_meaning(&before, "table %1 Person = {").unwrap_err();
+        assert!(matches!(&errs[..], [CompileErr::FormatChangedMeaning { .. }]));
+        let errs = check_meaning(&before, "").unwrap_err();
+        assert_eq!(errs[0].to_string(),
+            "Formatting would change the meaning of the file (2 declarations became 0), so it was not formatted.");
+    }
+
+    #[test]
+    fn idempotent_on_test_data() {
+        let mut paths = vec![];
+        cad_files(Path::new("test_data/cad_files"), &mut paths);
+        paths.sort();
+
+        let mut formatted_count = 0;
+        for path in paths {
+            let code = std::fs::read_to_string(&path).unwrap();
+            let Ok(once) = format(&code) else {
+                assert!(path.ends_with("malformed.cad"), "{:?} failed to format", path);
+                continue;
+            };
+            let twice = format(&once).unwrap();
+            assert_eq!(once, twice, "{:?} is not stable", path);
+            formatted_count += 1;
+        }
+        assert!(formatted_count >= 5);
+    }
+
+    #[test]
+    fn canonical_layout() {
+        let code = "
+// People
+table %1 Person = {
+    /* Surrogate key */
+    field %1 id = {
+        required = true, // must exist
+        datatype = Number,
+        serial = { increment = 1, next = 1, generate = on_creation }
+    }
+    field %2 name = {
+      datatype = Text
+    },
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Person
+
+
+
+relation %1 = { Person_occ::id == Person_occ2::id }
+script %1 hello = {
+set_variable($x, |0|)
+      loop {
+  exit_loop_if(|$x == 10|)
+}
+}
+";
+        assert_eq!(format(code).unwrap(), "\
+// People
+table %1 Person = {
+  /* Surrogate key */
+  field %1 id = {
+    datatype = Number,
+    serial = {
+      generate = on_creation,
+      next = 1,
+      increment = 1,
+    },
+    required = true, // must exist
+  },
+  field %2 name = {
+    datatype = Text,
+  },
+}
+
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Person
+
+relation %1 = {
+  Person_occ::id == Person_occ2::id,
+}
+
+script %1 hello = {
+  set_variable($x, |0|)
+  loop {
+    exit_loop_if(|$x == 10|)
+  }
+}
+");
+    }
+
+    #[test]
+    fn refuses_broken_files() {
+        assert!(format("table %1 Person = { field }").is_err());
+    }
+}
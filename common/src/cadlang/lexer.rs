@@ -48,15 +48,30 @@ fn decode_buffer(buffer: &str, start: Location) -> Token {
         "generate" => {
             Token::new(TokenType::Generate, start)
         },
+        "import" => {
+            Token::new(TokenType::Import, start)
+        }
         "increment" => {
             Token::new(TokenType::Increment, start)
         }
         "layout" => {
             Token::new(TokenType::Layout, start)
         }
+        "lookup" => {
+            Token::new(TokenType::Lookup, start)
+        }
+        "max_chars" => {
+            Token::new(TokenType::MaxChars, start)
+        }
+        "member_of" => {
+            Token::new(TokenType::MemberOf, start)
+        }
         "next" => {
             Token::new(TokenType::Next, start)
         }
+        "not_empty" => {
+            Token::new(TokenType::NotEmpty, start)
+        }
         "Number" => {
             Token::new(TokenType::Number, start) 
         }
@@ -66,6 +81,12 @@ fn decode_buffer(buffer: &str, start: Location) -> Token {
         "on_commit" => {
             Token::new(TokenType::OnCommit, start) 
         }
+        "override" => {
+            Token::new(TokenType::AllowOverride, start)
+        }
+        "range" => {
+            Token::new(TokenType::Range, start)
+        }
         "relation" => { 
             Token::new(TokenType::Relation, start) 
         },
@@ -102,9 +123,15 @@ fn decode_buffer(buffer: &str, start: Location) -> Token {
         "unique" => {
             Token::new(TokenType::Unique, start) 
         }
+        "validate" => {
+            Token::new(TokenType::Validate, start)
+        }
         "validation_message" => {
             Token::new(TokenType::ValidationMessage, start) 
         }
+        "validation_calc" => {
+            Token::new(TokenType::ValidationCalc, start)
+        }
         "value_list" => {
             Token::new(TokenType::ValueList, start)
         }
@@ -113,8 +140,36 @@ fn decode_buffer(buffer: &str, start: Location) -> Token {
 
 }
 
+/* Moves the cursor over text which was consumed as a single token. */
+fn advance_over(cursor: &mut Location, text: &str) {
+    match text.rfind('\n') {
+        Some(last) => {
+            cursor.line += text.matches('\n').count() as u32;
+            cursor.column = text[last + 1..].chars().count() as u32;
+        }
+        None => cursor.column += text.chars().count() as u32,
+    }
+}
+
+/* Comments are attached as trivia to the token following them. */
 pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
     let mut tokens = vec![];
+    let mut comments = vec![];
+    for mut token in lex_with_comments(code)? {
+        if token.ttype == TokenType::Comment {
+            comments.push(token.value);
+        } else {
+            token.comments = std::mem::take(&mut comments);
+            tokens.push(token);
+        }
+    }
+    Ok(tokens)
+}
+
+/* Comments are kept in the token stream as `TokenType::Comment` tokens, with
+ * their delimiters, for tooling such as the formatter. */
+pub fn lex_with_comments(code: &str) -> Result<Vec<Token>, LexErr> {
+    let mut tokens = vec![];
 
     let mut cursor = Location { line: 1, column: 1 };
 
@@ -161,11 +216,18 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
                     buffer.clear();
                 }
                 cursor.column += 1;
-                if let Some(number) = lex_iter.next() {
-                    tokens.push(Token::with_value(TokenType::ObjectNumber, cursor, number.to_string()));
-                } else {
-                    return Err(LexErr::UnexpectedEOF)
+                let mut number = String::new();
+                while let Some(digit) = lex_iter.next_if(|c| c.is_ascii_digit()) {
+                    number.push(digit);
+                }
+                if number.is_empty() {
+                    match lex_iter.next() {
+                        Some(c) => number.push(c),
+                        None => return Err(LexErr::UnexpectedEOF),
+                    }
                 }
+                tokens.push(Token::with_value(TokenType::ObjectNumber, cursor, number.clone()));
+                cursor.column += number.chars().count() as u32 - 1;
             },
             '{' => {
                 if !buffer.is_empty() {
@@ -199,8 +261,10 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
                     buffer.pop();
                     tokens.push(Token::new(TokenType::OpenBrace, cursor));
                     tokens.push(Token::with_value(TokenType::ScriptContent, cursor, buffer.clone()));
+                    advance_over(&mut cursor, &buffer);
                     tokens.push(Token::new(TokenType::CloseBrace, cursor));
                     buffer.clear();
+                    in_script = false;
                 } else {
                     tokens.push(Token::new(TokenType::OpenBrace, cursor));
                 }
@@ -232,19 +296,36 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
                 }
                 tokens.push(Token::new(TokenType::Comma, cursor));
             }
+            '(' | ')' => {
+                if !buffer.is_empty() {
+                    tokens.push(decode_buffer(&buffer, token_start));
+                    buffer.clear();
+                }
+                let ttype = if c == '(' { TokenType::OpenParen } else { TokenType::CloseParen };
+                tokens.push(Token::new(ttype, cursor));
+            }
             '"' => {
                 if !buffer.is_empty() {
                     tokens.push(decode_buffer(&buffer, token_start));
                     buffer.clear();
                 }
-                for c in lex_iter.by_ref() {
+                /* The source text is kept alongside the value so that escapes
+                 * still count towards the column. */
+                let mut raw = String::new();
+                while let Some(c) = lex_iter.next() {
                     if c == '"' {
                         tokens.push(Token::with_value(TokenType::String, cursor, buffer.clone()));
-                        cursor.column += buffer.len() as u32;
+                        advance_over(&mut cursor, &raw);
                         buffer.clear();
                         break;
-                    } else {
-                        buffer.push(c);
+                    }
+                    raw.push(c);
+                    match c {
+                        '\\' => if let Some(escaped) = lex_iter.next() {
+                            raw.push(escaped);
+                            buffer.push(escaped);
+                        },
+                        _ => buffer.push(c),
                     }
                 }
             }
@@ -283,7 +364,7 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
                     if *c == '|' {
                         if !in_string {
                             tokens.push(Token::with_value(TokenType::Calculation, cursor, buffer.clone()));
-                            cursor.column += buffer.len() as u32;
+                            advance_over(&mut cursor, &buffer);
                             buffer.clear();
                             break;
                         } else {
@@ -311,14 +392,34 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
                 }
                 let next = lex_iter.next();
                 if next == Some('/') {
+                    let mut comment = String::from("//");
+                    let start = cursor;
                     for c in lex_iter.by_ref() {
                         if c == '\n' {
                             cursor.line += 1;
                             cursor.column = 0;
                             break;
                         }
+                        comment.push(c);
                     }
+                    tokens.push(Token::with_value(TokenType::Comment, start, comment));
                     continue;
+                } else if next == Some('*') {
+                    let mut comment = String::from("/*");
+                    let start = cursor;
+                    loop {
+                        match lex_iter.next() {
+                            Some('*') if lex_iter.peek() == Some(&'/') => {
+                                lex_iter.next();
+                                comment.push_str("*/");
+                                break;
+                            }
+                            Some(c) => comment.push(c),
+                            None => return Err(LexErr::UnexpectedEOF),
+                        }
+                    }
+                    advance_over(&mut cursor, &comment[1..]);
+                    tokens.push(Token::with_value(TokenType::Comment, start, comment));
                 }
             },
             '<' => {
@@ -356,7 +457,7 @@ pub fn lex(code: &str) -> Result<Vec<Token>, LexErr> {
 mod tests {
     use crate::cadlang::token::{Location, Token, TokenType};
 
-    use super::lex;
+    use super::{lex, LexErr};
 
     #[test]
     fn table_test() {
@@ -420,7 +521,25 @@ table %1 Person {
             assert_eq!(*pair.0, pair.1);
         }
     }
-}
 
+    #[test]
+    fn comments_as_trivia() {
+        let code = "// Line comment\n/* Block\n   comment */ table %12 Person = {}";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        assert_eq!(tokens[0], Token::new(TokenType::Table, Location { line: 3, column: 15 }));
+        assert_eq!(tokens[0].comments, vec!["// Line comment", "/* Block\n   comment */"]);
+        assert_eq!(tokens[1].value, "12");
+        assert!(tokens[1].comments.is_empty());
 
+        assert!(matches!(lex("table /* unterminated"), Err(LexErr::UnexpectedEOF)));
+    }
 
+    #[test]
+    fn string_escapes() {
+        let tokens = lex(r#"comment = "a \"b\" \\ c" ,"#).expect("Tokenisation failed.");
+        assert_eq!(tokens[2].value, r#"a "b" \ c"#);
+        assert_eq!(tokens[3], Token::new(TokenType::Comma, Location { line: 1, column: 25 }));
+        /* Same width without escapes. */
+        assert_eq!(lex(r#"comment = "abcdefghijkl" ,"#).unwrap()[3], tokens[3]);
+    }
+}
@@ -0,0 +1,399 @@
+use core::fmt;
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::{Path, PathBuf}};
+
+use crate::dbobjects::file::File;
+
+use super::token::{Token, TokenType};
+
+/* Records the id allocated to every object written without an `%id`, so
+ * that it keeps the same one from build to build, and ids are never handed
+ * out twice. Lines are tab separated, as names may contain spaces:
+ *
+ *   table<TAB>Person<TAB>1
+ *   field:Person<TAB>id<TAB>1
+ *   reserved<TAB>table<TAB>1 2 5
+ *
+ * The first column names the id space: one per kind of object, and one per
+ * table for its fields. `reserved` lines hold the ids present in the last
+ * synced .fmp12, which stay off limits even once their object is deleted.
+ * Tabs, newlines and backslashes in names are escaped with a backslash. */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    pub entries: BTreeMap<(String, String), u16>,
+    pub reserved: BTreeMap<String, BTreeSet<u16>>,
+}
+
+/* The lockfile belonging to a root .cad file, e.g quotes.cad -> quotes.lock */
+pub fn lock_path(path: &Path) -> PathBuf {
+    path.with_extension("lock")
+}
+
+fn field_space(table: &str) -> String {
+    format!("field:{}", table)
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(name: &str) -> String {
+    let mut result = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+impl Lockfile {
+    pub fn parse(text: &str) -> Self {
+        let mut result = Self::default();
+        for line in text.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let columns = line.split('\t').collect::<Vec<_>>();
+            match columns[..] {
+                ["reserved", space, ids] => {
+                    result.reserved.entry(unescape(space))
+                        .or_default()
+                        .extend(ids.split_whitespace().filter_map(|id| id.parse::<u16>().ok()));
+                }
+                [space, name, id] => {
+                    if let Ok(id) = id.parse() {
+                        result.entries.insert((unescape(space), unescape(name)), id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /* A missing lockfile is an empty one. */
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /* Records the ids of a file read from an .fmp12, both as the ids of the
+     * objects by name and as reserved. */
+    pub fn sync_file(&mut self, file: &File) {
+        for table in &file.schema.tables {
+            self.record(String::from("table"), &table.name, table.id);
+            for field in table.fields.values() {
+                self.record(field_space(&table.name), &field.name, field.id);
+            }
+        }
+        for occurrence in &file.schema.relation_graph.nodes {
+            self.record(String::from("table_occurrence"), &occurrence.name, occurrence.id);
+            for relation in &occurrence.relations {
+                if let Ok(id) = u16::try_from(relation.id) {
+                    self.reserved.entry(String::from("relation")).or_default().insert(id);
+                }
+            }
+        }
+        for layout in &file.layouts {
+            self.record(String::from("layout"), &layout.name, layout.id);
+        }
+        for script in &file.scripts {
+            self.record(String::from("script"), &script.name, script.id);
+        }
+        for data_source in &file.data_sources {
+            self.record(String::from("extern"), &data_source.name, data_source.id);
+        }
+    }
+
+    /* Ids past u16::MAX can't be written as an `%id`, so they are not
+     * recorded. */
+    fn record(&mut self, space: String, name: &str, id: u32) {
+        let Ok(id) = u16::try_from(id) else { return };
+        self.reserved.entry(space.clone()).or_default().insert(id);
+        self.entries.insert((space, name.to_string()), id);
+    }
+
+    /* The id after every one taken in the space. Once u16::MAX is taken, the
+     * lowest id never taken is used instead, or None if there is none. */
+    fn next_id(&self, space: &str, used: Option<&BTreeSet<u16>>) -> Option<u16> {
+        let locked = self.entries.iter()
+            .filter(|((s, _), _)| s == space)
+            .map(|(_, id)| *id);
+        let reserved = self.reserved.get(space).into_iter().flatten().copied();
+        let used = used.into_iter().flatten().copied();
+        let taken = locked.chain(reserved).chain(used).collect::<BTreeSet<_>>();
+        match taken.last() {
+            None => Some(1),
+            Some(max) => max.checked_add(1)
+                .or_else(|| (1..u16::MAX).find(|id| !taken.contains(id))),
+        }
+    }
+}
+
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# Object ids allocated by cadmus, keep this file under version control.")?;
+        for ((space, name), id) in &self.entries {
+            writeln!(f, "{}\t{}\t{}", escape(space), escape(name), id)?;
+        }
+        for (space, ids) in &self.reserved {
+            let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+            writeln!(f, "reserved\t{}\t{}", escape(space), ids.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/* An object declaration found in a token stream. */
+struct Slot {
+    stream: usize,
+    index: usize,
+    space: String,
+    name: String,
+    id: Option<u16>,
+}
+
+fn space_name(ttype: TokenType) -> Option<&'static str> {
+    match ttype {
+        TokenType::Table => Some("table"),
+        TokenType::TableOccurrence => Some("table_occurrence"),
+        TokenType::Relation => Some("relation"),
+        TokenType::ValueList => Some("value_list"),
+        TokenType::Layout => Some("layout"),
+        TokenType::Script => Some("script"),
+        TokenType::Test => Some("test"),
+        TokenType::Extern => Some("extern"),
+        _ => None,
+    }
+}
+
+/* Relations have no name, so they are known by all of their criteria, e.g.
+ * "Person::id Eq Invoice::id". Options are left out. Relations with the same
+ * criteria are told apart by the order they are declared in, see find_slots. */
+fn relation_name(tokens: &[Token]) -> Option<String> {
+    let entries = match tokens.get(1)?.ttype {
+        TokenType::OpenBrace => {
+            let end = tokens.iter().position(|t| t.ttype == TokenType::CloseBrace)?;
+            &tokens[2..end]
+        }
+        _ => tokens.get(1..8)?,
+    };
+    let criteria = entries.split(|t| t.ttype == TokenType::Comma)
+        .filter(|c| c.len() == 7
+            && c[1].ttype == TokenType::ScopeResolution
+            && c[5].ttype == TokenType::ScopeResolution)
+        .map(|c| format!("{}::{} {} {}::{}", c[0].value, c[2].value, c[3].ttype, c[4].value, c[6].value))
+        .collect::<Vec<_>>();
+    match criteria.is_empty() {
+        true => None,
+        false => Some(criteria.join(", ")),
+    }
+}
+
+fn find_slots(stream: usize, tokens: &[Token], slots: &mut Vec<Slot>) {
+    let mut depth = 0;
+    let mut table: Option<String> = None;
+    let mut relation_names = HashMap::<String, usize>::new();
+    for (index, token) in tokens.iter().enumerate() {
+        match token.ttype {
+            TokenType::OpenBrace => { depth += 1; continue; }
+            TokenType::CloseBrace => { depth -= 1; continue; }
+            _ => {}
+        }
+        let space = match (depth, token.ttype) {
+            (0, ttype) => match space_name(ttype) {
+                Some(space) => space.to_string(),
+                None => continue,
+            },
+            (1, TokenType::Field) => match &table {
+                Some(table) => field_space(table),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        let id = tokens.get(index + 1)
+            .filter(|t| t.ttype == TokenType::ObjectNumber)
+            .and_then(|t| t.value.parse().ok());
+        let rest = &tokens[index + 1 + id.is_some() as usize..];
+        let name = if token.ttype == TokenType::Relation {
+            relation_name(rest).map(|name| {
+                let count = relation_names.entry(name.clone()).or_default();
+                *count += 1;
+                match count {
+                    1 => name,
+                    n => format!("{} #{}", name, n),
+                }
+            })
+        } else {
+            rest.first().filter(|t| t.ttype == TokenType::Identifier).map(|t| t.value.clone())
+        };
+        if token.ttype == TokenType::Table {
+            table = name.clone();
+        }
+        /* Anything else is left for the parser to complain about. */
+        if let Some(name) = name {
+            slots.push(Slot { stream, index, space, name, id });
+        }
+    }
+}
+
+/* Gives every object declared without an `%id` one, inserting it into the
+ * token stream. The streams are all the modules making up one file, as they
+ * share their ids. An object keeps the id the lockfile has for its name
+ * unless another object now claims it, new ids come after every id used in
+ * the sources, the lockfile, or the last synced .fmp12. */
+pub fn allocate_ids<'a>(streams: impl IntoIterator<Item = &'a mut Vec<Token>>, lock: &mut Lockfile) {
+    let mut streams = streams.into_iter().collect::<Vec<_>>();
+    let mut slots = vec![];
+    for (stream, tokens) in streams.iter().enumerate() {
+        find_slots(stream, tokens, &mut slots);
+    }
+
+    let mut used = HashMap::<String, BTreeSet<u16>>::new();
+    for slot in &slots {
+        if let Some(id) = slot.id {
+            used.entry(slot.space.clone()).or_default().insert(id);
+        }
+    }
+
+    let mut insertions = vec![];
+    for slot in slots.iter().filter(|s| s.id.is_none()) {
+        let key = (slot.space.clone(), slot.name.clone());
+        let id = match lock.entries.get(&key) {
+            Some(id) if !used.get(&slot.space).is_some_and(|u| u.contains(id)) => *id,
+            /* With every id taken, the object is left for the parser to
+             * report as missing its id. */
+            _ => match lock.next_id(&slot.space, used.get(&slot.space)) {
+                Some(id) => id,
+                None => continue,
+            },
+        };
+        used.entry(slot.space.clone()).or_default().insert(id);
+        lock.entries.insert(key, id);
+        insertions.push((slot.stream, slot.index, id));
+    }
+
+    /* Back to front, so earlier indices stay valid. */
+    insertions.sort();
+    for (stream, index, id) in insertions.into_iter().rev() {
+        let location = streams[stream][index].location;
+        streams[stream].insert(index + 1, Token::with_value(TokenType::ObjectNumber, location, id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cadlang::{lexer::lex, parser::parse};
+
+    use super::{allocate_ids, Lockfile};
+
+    const CODE: &str = "
+table Person = {
+    field %2 id = {
+        datatype = Number,
+    },
+    field name = {
+        datatype = Text,
+    },
+}
+table %3 Invoice = {
+    field id = {
+        datatype = Number,
+    },
+}
+table_occurrence Person : Person
+table_occurrence Invoice : Invoice
+relation = Person::id == Invoice::id
+";
+
+    #[test]
+    fn allocation() {
+        let mut lock = Lockfile::parse("
+# comment
+table\tPerson\t1
+reserved\ttable\t1 2 7
+reserved\tfield:Person\t3
+");
+        let mut tokens = lex(CODE).unwrap();
+        allocate_ids([&mut tokens], &mut lock);
+        let stage = parse(&tokens).unwrap();
+
+        assert_eq!(stage.tables[&1].name.value, "Person");
+        assert_eq!(stage.tables[&1].fields[&4].name.value, "name");
+        assert_eq!(stage.tables[&3].fields[&1].name.value, "id");
+        assert_eq!(stage.table_occurrences[&1].name.value, "Person");
+        assert_eq!(stage.table_occurrences[&2].name.value, "Invoice");
+        assert!(stage.relations.contains_key(&1));
+        assert_eq!(lock.entries[&(String::from("relation"), String::from("Person::id Eq Invoice::id"))], 1);
+
+        /* The lockfile round trips, and building again changes nothing. */
+        let saved = lock.to_string();
+        assert_eq!(Lockfile::parse(&saved), lock);
+        let mut tokens = lex(CODE).unwrap();
+        allocate_ids([&mut tokens], &mut lock);
+        assert_eq!(lock.to_string(), saved);
+
+        /* A new table goes after the reserved ids, even with Invoice gone. */
+        let mut tokens = lex("table Person = {}\ntable Company = {}").unwrap();
+        allocate_ids([&mut tokens], &mut lock);
+        assert_eq!(lock.entries[&(String::from("table"), String::from("Company"))], 8);
+    }
+
+    #[test]
+    fn explicit_ids_take_precedence() {
+        let mut lock = Lockfile::parse("table\tPerson\t1\n");
+        let mut tokens = lex("table %1 Company = {}\ntable Person = {}").unwrap();
+        allocate_ids([&mut tokens], &mut lock);
+        let stage = parse(&tokens).unwrap();
+        assert_eq!(stage.tables[&1].name.value, "Company");
+        assert_eq!(stage.tables[&2].name.value, "Person");
+    }
+
+    #[test]
+    fn names_with_spaces_round_trip() {
+        /* Names from an .fmp12 may have spaces, such as the occurrence
+         * "blank 2" in relation.fmp12. */
+        let mut lock = Lockfile::default();
+        lock.record(String::from("table_occurrence"), "blank 2", 2);
+        lock.entries.insert((String::from("field:blank 2"), String::from("first\tname\\")), 4);
+        lock.reserved.entry(String::from("field:blank 2")).or_default().insert(4);
+        assert_eq!(Lockfile::parse(&lock.to_string()), lock);
+    }
+
+    #[test]
+    fn relations_with_the_same_criteria() {
+        let code = "
+relation = { Person::id == Invoice::id, sort = Invoice::id }
+relation = { Person::id == Invoice::id }
+relation = { Person::id == Invoice::id, Person::name == Invoice::name }
+";
+        let mut lock = Lockfile::default();
+        allocate_ids([&mut lex(code).unwrap()], &mut lock);
+        let relation = |name: &str| lock.entries[&(String::from("relation"), String::from(name))];
+        assert_eq!(relation("Person::id Eq Invoice::id"), 1);
+        assert_eq!(relation("Person::id Eq Invoice::id #2"), 2);
+        assert_eq!(relation("Person::id Eq Invoice::id, Person::name Eq Invoice::name"), 3);
+    }
+
+    #[test]
+    fn ids_past_the_last() {
+        let mut lock = Lockfile::parse("reserved\ttable\t1 65535\n");
+        let mut tokens = lex("table Person = {}").unwrap();
+        allocate_ids([&mut tokens], &mut lock);
+        assert_eq!(lock.entries[&(String::from("table"), String::from("Person"))], 2);
+
+        lock.record(String::from("table"), "Huge", 70000);
+        assert!(!lock.entries.contains_key(&(String::from("table"), String::from("Huge"))));
+    }
+}
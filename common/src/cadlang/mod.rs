@@ -1,10 +1,13 @@
 
 pub mod compiler;
 mod cadscript;
-mod lexer;
-mod parser;
-mod token;
-mod error;
-mod backend;
-mod staging;
+pub mod lexer;
+pub mod parser;
+pub mod token;
+pub mod error;
+pub mod formatter;
+pub(crate) mod backend;
+pub mod staging;
+pub mod validate;
+pub mod lockfile;
 //mod scripting;
@@ -8,23 +8,29 @@ use crate::dbobjects::{
             table_occurrence::TableOccurrence,
         },
         field::{
-            DataType, SerialTrigger, ValidationTrigger
+            DataType, SerialTrigger, SummaryKind, ValidationTrigger
         },
     },
-    layout::*,
+    layout::{Bounds, LayoutPartKind},
     reference::TableReference,
+    scripting::trigger::{TriggerEvent, TriggerScope},
     data_source::*
 };
 use super::{staging::*, error::CompileErr};
-use super::{cadscript::{compile_cadscript, proto_script::*}, token::{Token, TokenType}};
+use super::{cadscript::{compile_cadscript, proto_script::*}, token::{comment_text, Token, TokenType}};
 
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum FMObjType {
     Table,
     TableOccurrence,
     Field,
     ValueList,
+    Relation,
+    Layout,
+    Script,
+    Test,
+    DataSource,
 }
 
 impl<'a> fmt::Display for FMObjType {
@@ -33,7 +39,12 @@ impl<'a> fmt::Display for FMObjType {
             Self::Table => write!(f, "table"),
             Self::TableOccurrence => write!(f, "table occurrence"),
             Self::Field => write!(f, "field"),
-            Self::ValueList => write!(f, "valuelist")
+            Self::ValueList => write!(f, "valuelist"),
+            Self::Relation => write!(f, "relation"),
+            Self::Layout => write!(f, "layout"),
+            Self::Script => write!(f, "script"),
+            Self::Test => write!(f, "test"),
+            Self::DataSource => write!(f, "data source"),
         }
     }
 }
@@ -41,6 +52,49 @@ impl<'a> fmt::Display for FMObjType {
 
 pub struct ParseInfo {
     cursor: usize,
+    errors: Vec<CompileErr>,
+}
+
+const TOP_LEVEL: [TokenType; 9] = [
+    TokenType::Table, TokenType::TableOccurrence, TokenType::Relation, TokenType::ValueList,
+    TokenType::Layout, TokenType::Script, TokenType::Test, TokenType::Extern, TokenType::Import,
+];
+
+/* Panic mode recovery for an object starting at `start` which failed to parse.
+ * Skips forward from the point of failure until either the closing brace
+ * balancing the object's opening brace, or one of the `anchors`. Braces are
+ * counted from `start`, so nested blocks inside the object are skipped over.
+ * Leaves the cursor on the next token to be parsed. */
+fn synchronize(tokens: &[Token], info: &mut ParseInfo, start: usize, anchors: &[TokenType]) {
+    let failed_at = info.cursor.clamp(start + 1, tokens.len() - 1);
+    let mut depth = tokens[start..failed_at].iter()
+        .fold(0, |depth, token| match token.ttype {
+            TokenType::OpenBrace => depth + 1,
+            TokenType::CloseBrace => depth - 1,
+            _ => depth,
+        });
+
+    info.cursor = failed_at;
+    while let Some(token) = tokens.get(info.cursor) {
+        match token.ttype {
+            TokenType::EOF => return,
+            ttype if anchors.contains(&ttype) => return,
+            TokenType::OpenBrace => depth += 1,
+            TokenType::CloseBrace => {
+                /* Belongs to whatever encloses the object. */
+                if depth <= 0 {
+                    return;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    info.cursor += 1;
+                    return;
+                }
+            }
+            _ => {}
+        }
+        info.cursor += 1;
+    }
 }
 
 fn expect<'a>(tokens: &'a [Token], expected: &Vec<TokenType>, info: &mut ParseInfo) -> Result<&'a Token, CompileErr> {
@@ -61,16 +115,23 @@ fn expect<'a>(tokens: &'a [Token], expected: &Vec<TokenType>, info: &mut ParseIn
 
 pub fn parse_field(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, StagedField), CompileErr> {
     let mut checks_ = vec![];
-    let validation_msg = String::new();
-    let validation_trigger = ValidationTrigger::OnEntry;
-    let user_override_ = true;
+    let mut validation_msg = String::new();
+    let mut validation_trigger = ValidationTrigger::OnEntry;
+    let mut user_override_ = true;
     let mut autoentry_ = StagedAutoEntry {
         definition: StagedAutoEntryType::NA,
         nomodify: false,
     };
-    let repetitions_ = 1;
-    let global_ = false;
+    let mut repetitions_ = 1;
+    let mut global_ = false;
     let mut dtype_ = DataType::Text;
+    let mut calculation_: Option<String> = None;
+    let mut stored_ = true;
+    let mut summary_: Option<(SummaryKind, Token)> = None;
+    let mut comment_ = String::new();
+    let comments_ = tokens[info.cursor].comments.iter()
+        .map(|c| comment_text(c))
+        .collect();
 
     let id_ = expect(tokens, &vec![TokenType::ObjectNumber], info)?
         .value.parse().expect("Unable to parse object id.");
@@ -207,6 +268,46 @@ pub fn parse_field(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
 
             }
 
+            TokenType::Lookup => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                expect(tokens, &vec![TokenType::OpenBrace], info)?;
+                let mut from_: Option<Token> = None;
+                let mut related_: Option<(Token, Token)> = None;
+                parse_block(tokens, info, |key, info| {
+                    match (key.ttype, key.value.as_str()) {
+                        (TokenType::Identifier, "start") => {
+                            expect(tokens, &vec![TokenType::Assignment], info)?;
+                            from_ = Some(expect(tokens, &vec![TokenType::Identifier], info)?.clone());
+                        }
+                        (TokenType::Identifier, "related") => {
+                            expect(tokens, &vec![TokenType::Assignment], info)?;
+                            let occurrence = expect(tokens, &vec![TokenType::Identifier], info)?.clone();
+                            expect(tokens, &vec![TokenType::ScopeResolution], info)?;
+                            let field = expect(tokens, &vec![TokenType::Identifier], info)?.clone();
+                            related_ = Some((occurrence, field));
+                        }
+                        _ => return Err(unknown_attribute("lookup", key)),
+                    }
+                    Ok(())
+                })?;
+                info.cursor += 1;
+                let Some(from) = from_ else {
+                    return Err(CompileErr::MissingAttribute {
+                        base_object: name_.value.clone(),
+                        construct: String::from("Lookup"),
+                        specifier: String::from("start")
+                    })
+                };
+                let Some((occurrence, field)) = related_ else {
+                    return Err(CompileErr::MissingAttribute {
+                        base_object: name_.value.clone(),
+                        construct: String::from("Lookup"),
+                        specifier: String::from("related")
+                    })
+                };
+                autoentry_.definition = StagedAutoEntryType::Lookup { from, occurrence, field };
+            }
+
             // Validation Switches
             TokenType::NotEmpty => {
                 expect(tokens, &vec![TokenType::Assignment], info)?;
@@ -238,6 +339,108 @@ pub fn parse_field(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
                     _ => unreachable!()
                 };
             }
+            TokenType::MemberOf => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                let value_list = expect(tokens, &vec![TokenType::Identifier], info)?;
+                checks_.push(StagedValidationType::MemberOf(value_list.clone()));
+            }
+            TokenType::Range => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                let mut start_: Option<usize> = None;
+                let mut end_: Option<usize> = None;
+                expect(tokens, &vec![TokenType::OpenBrace], info)?;
+                parse_block(tokens, info, |key, info| {
+                    match (key.ttype, key.value.as_str()) {
+                        (TokenType::Identifier, "start") => {
+                            start_ = Some(parse_integer_attribute(tokens, info)? as usize);
+                        }
+                        (TokenType::Identifier, "end") => {
+                            end_ = Some(parse_integer_attribute(tokens, info)? as usize);
+                        }
+                        _ => return Err(unknown_attribute("range", key)),
+                    }
+                    Ok(())
+                })?;
+                info.cursor += 1;
+                for (value, specifier) in [(start_, "start"), (end_, "end")] {
+                    if value.is_none() {
+                        return Err(CompileErr::MissingAttribute {
+                            base_object: name_.value.clone(),
+                            construct: String::from("Range"),
+                            specifier: String::from(specifier)
+                        })
+                    }
+                }
+                checks_.push(StagedValidationType::Range { start: start_.unwrap(), end: end_.unwrap() });
+            }
+            TokenType::ValidationCalc => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                let calc = expect(tokens, &vec![TokenType::Calculation], info)?;
+                checks_.push(StagedValidationType::Calculation(calc.value.clone()));
+            }
+            TokenType::MaxChars => {
+                let max = parse_integer_attribute(tokens, info)?;
+                checks_.push(StagedValidationType::MaxChars(max as usize));
+            }
+            TokenType::ValidationMessage => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                validation_msg = expect(tokens, &vec![TokenType::String], info)?.value.clone();
+            }
+            TokenType::Validate => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                let trigger = expect(tokens, &vec![TokenType::Identifier], info)?;
+                validation_trigger = match trigger.value.as_str() {
+                    "always" => ValidationTrigger::OnCommit,
+                    "entry" => ValidationTrigger::OnEntry,
+                    _ => return Err(CompileErr::UnexpectedToken {
+                        token: trigger.clone(),
+                        expected: vec![TokenType::Always, TokenType::OnEntry]
+                    }),
+                };
+            }
+            TokenType::AllowOverride => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                user_override_ = expect(tokens, &vec![TokenType::True, TokenType::False], info)?
+                    .ttype == TokenType::True;
+            }
+            TokenType::Identifier if token.value == "calculation" => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                calculation_ = Some(expect(tokens, &vec![TokenType::Calculation], info)?.value.clone());
+            }
+            TokenType::Identifier if token.value == "stored" => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                stored_ = expect(tokens, &vec![TokenType::True, TokenType::False], info)?
+                    .ttype == TokenType::True;
+            }
+            TokenType::Identifier if token.value == "summary" => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                let kind_token = expect(tokens, &vec![TokenType::Identifier], info)?;
+                let Some(kind) = SummaryKind::from_cad(&kind_token.value) else {
+                    return Err(unknown_attribute("summary", kind_token));
+                };
+                expect(tokens, &vec![TokenType::OpenParen], info)?;
+                let field = expect(tokens, &vec![TokenType::Identifier], info)?.clone();
+                expect(tokens, &vec![TokenType::CloseParen], info)?;
+                summary_ = Some((kind, field));
+            }
+            TokenType::Identifier if token.value == "global" => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                global_ = expect(tokens, &vec![TokenType::True, TokenType::False], info)?
+                    .ttype == TokenType::True;
+            }
+            TokenType::Identifier if token.value == "repetitions" => {
+                repetitions_ = match parse_integer_attribute(tokens, info) {
+                    Err(CompileErr::IntegerOutOfRange { token }) => return Err(CompileErr::InvalidRepetitions { token }),
+                    result => result? as usize,
+                };
+                if !(1..=u8::MAX as usize).contains(&repetitions_) {
+                    return Err(CompileErr::InvalidRepetitions { token: tokens[info.cursor].clone() });
+                }
+            }
+            TokenType::Identifier if token.value == "comment" => {
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                comment_ = expect(tokens, &vec![TokenType::String], info)?.value.clone();
+            }
             _ => {
                 info.cursor += 1;
             }
@@ -247,9 +450,15 @@ pub fn parse_field(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
     if tokens.get(info.cursor).is_none() {
         return Err(CompileErr::UnexpectedEOF);
     }
+    let kind_ = match (summary_, calculation_) {
+        (Some((kind, field)), _) => StagedFieldKind::Summary { kind, field },
+        (None, Some(code)) => StagedFieldKind::Calculation { code, stored: stored_ },
+        (None, None) => StagedFieldKind::Simple,
+    };
     Ok((id_, StagedField {
         id: id_,
         name: name_.clone(),
+        kind: kind_,
         dtype: dtype_,
         validation: StagedValidation {
             checks: checks_,
@@ -260,10 +469,15 @@ pub fn parse_field(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
         autoentry: autoentry_,
         global: global_,
         repetitions: repetitions_,
+        comment: comment_,
+        comments: comments_,
     }))
 }
 
 pub fn parse_table(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, StagedTable), CompileErr> {
+    let comments_ = tokens[info.cursor].comments.iter()
+        .map(|c| comment_text(c))
+        .collect();
     let id_ = expect(tokens, &vec![TokenType::ObjectNumber], info)?
         .value.parse::<u16>().expect("Unable to parse object ID.");
     let name_ = expect(tokens, &vec![TokenType::Identifier], info)?;
@@ -275,8 +489,24 @@ pub fn parse_table(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
     while let Some(token) = tokens.get(info.cursor) {
         match token.ttype {
             TokenType::Field => {
-                let tmp = parse_field(tokens, info)?;
-                fields_.insert(tmp.0, tmp.1);
+                let start = info.cursor;
+                match parse_field(tokens, info) {
+                    Ok(tmp) => {
+                        if fields_.insert(tmp.0, tmp.1).is_some() {
+                            info.errors.push(CompileErr::DuplicateId {
+                                construct: FMObjType::Field,
+                                token: tokens[start + 1].clone(),
+                            });
+                        }
+                    },
+                    Err(e) => {
+                        info.errors.push(e);
+                        let mut anchors = TOP_LEVEL.to_vec();
+                        anchors.push(TokenType::Field);
+                        synchronize(tokens, info, start, &anchors);
+                        continue;
+                    }
+                }
             },
             TokenType::CloseBrace => {
                 break;
@@ -297,6 +527,7 @@ pub fn parse_table(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stage
         id: id_,
         name: name_.clone(),
         fields: fields_,
+        comments: comments_,
     }))
 }
 
@@ -364,15 +595,30 @@ pub fn parse_table_occurrence(tokens: &[Token], info: &mut ParseInfo) -> Result<
 pub fn parse_script(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, ProtoScript), CompileErr> {
     let id_ = expect(tokens, &vec![TokenType::ObjectNumber], info)?
         .value.parse::<u16>().expect("Unable to parse object number.");
-    let name_ = expect(tokens, &vec![TokenType::Identifier], info)?.value.clone();
+    let name_ = expect(tokens, &vec![TokenType::Identifier], info)?;
+
+    let mut params_ = vec![];
+    if tokens.get(info.cursor + 1).is_some_and(|token| token.ttype == TokenType::OpenParen) {
+        info.cursor += 1;
+        let mut param = expect(tokens, &vec![TokenType::Identifier, TokenType::CloseParen], info)?;
+        while param.ttype == TokenType::Identifier {
+            params_.push(param.value.clone());
+            if expect(tokens, &vec![TokenType::Comma, TokenType::CloseParen], info)?.ttype == TokenType::CloseParen {
+                break;
+            }
+            param = expect(tokens, &vec![TokenType::Identifier], info)?;
+        }
+    }
 
     expect(tokens, &vec![TokenType::Assignment], info)?;
     expect(tokens, &vec![TokenType::OpenBrace], info)?;
 
     let code = expect(tokens, &vec![TokenType::ScriptContent], info)?;
     //let mut script_ = BurnScriptCompiler::compile_burn_script(code.value.as_str());
-    let mut script_ = compile_cadscript(code.value.as_str());
-    script_.name = name_.clone();
+    let mut script_ = compile_cadscript(code.value.as_str(), code.location)?;
+    script_.name = name_.value.clone();
+    script_.location = name_.location;
+    script_.params = params_;
     expect(tokens, &vec![TokenType::CloseBrace], info)?;
 
     Ok((id_, script_))
@@ -609,8 +855,7 @@ pub fn parse_value_list(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16,
 pub fn parse_test(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, ProtoScript), CompileErr> {
     let id_ = expect(tokens, &vec![TokenType::ObjectNumber], info)?
         .value.parse::<u16>().expect("Unable to parse object id.");
-    let name_ = expect(tokens, &vec![TokenType::Identifier], info)?
-        .value.clone();
+    let name_ = expect(tokens, &vec![TokenType::Identifier], info)?;
 
     expect(tokens, &vec![TokenType::Assignment], info)?;
     expect(tokens, &vec![TokenType::OpenBrace], info)?;
@@ -619,8 +864,9 @@ pub fn parse_test(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, ProtoS
     //let mut script_ = BurnScriptCompiler::compile_burn_script(code.value.as_str());
     //script_[0].name = name_.clone();
     //expect(tokens, &vec![TokenType::CloseBrace], info)?;
-    let mut script_ = compile_cadscript(code.value.as_str());
-    script_.name = name_.clone();
+    let mut script_ = compile_cadscript(code.value.as_str(), code.location)?;
+    script_.name = name_.value.clone();
+    script_.location = name_.location;
     expect(tokens, &vec![TokenType::CloseBrace], info)?;
 
     Ok((id_, script_))
@@ -657,29 +903,60 @@ pub fn parse_relation_criteria<'a>(tokens: &'a [Token], info: &mut ParseInfo) ->
     })
 }
 
+/* Reads an option of a compound relation if the next entry is one,
+ * returning whether it was. */
+fn parse_relation_option(tokens: &[Token], relation: &mut StagedRelation, info: &mut ParseInfo) -> Result<bool, CompileErr> {
+    let Some(key) = tokens.get(info.cursor + 1) else { return Ok(false) };
+    let is_option = key.ttype == TokenType::Sort
+        || (key.ttype == TokenType::Identifier && ["allow_creation", "delete_related"].contains(&key.value.as_str()));
+    if !is_option || tokens.get(info.cursor + 2).map(|t| t.ttype) != Some(TokenType::Assignment) {
+        return Ok(false);
+    }
+    info.cursor += 2;
+    let occurrence = expect(tokens, &vec![TokenType::Identifier], info)?.clone();
+    match key.value.as_str() {
+        "allow_creation" => relation.allow_creation.push(occurrence),
+        "delete_related" => relation.delete_related.push(occurrence),
+        _ => {
+            expect(tokens, &vec![TokenType::ScopeResolution], info)?;
+            let field = expect(tokens, &vec![TokenType::Identifier], info)?.clone();
+            let descending = tokens.get(info.cursor + 1)
+                .is_some_and(|t| t.ttype == TokenType::Identifier && t.value == "descending");
+            if descending {
+                info.cursor += 1;
+            }
+            relation.sort.push(StagedRelationSort { occurrence, field, descending });
+        }
+    }
+    Ok(true)
+}
+
 pub fn parse_relation(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, StagedRelation), CompileErr> {
     let id_ = expect(tokens, &vec![TokenType::ObjectNumber], info)?
         .value.parse::<u16>().expect("Unable to parse object id.");
     expect(tokens, &vec![TokenType::Assignment], info)?;
     let token = expect(tokens, &vec![TokenType::OpenBrace, TokenType::Identifier], info)?;
 
-    let mut criterias_ = vec![];
+    let mut relation = StagedRelation {
+        id: id_,
+        table1: String::new(),
+        table2: String::new(),
+        criterias: vec![],
+        allow_creation: vec![],
+        delete_related: vec![],
+        sort: vec![],
+    };
     if token.ttype == TokenType::OpenBrace {
         while tokens.get(info.cursor).is_some() {
-            // parse attribute
-            let criteria = parse_relation_criteria(tokens, info)?;
-            //if tables.iter().any(|t| t.is_none()) {
-            //    tables[0] = Some(lhs_table);
-            //    tables[1] = Some(rhs_table);
-            //}
-            //if !tables.iter().any(|search| search.unwrap() == lhs_table) {
-            //    return Err(CompileErr::RelationCriteria { token: tokens[info.cursor - 2].clone() })
-            //}
-            //if !tables.iter().any(|search| search.unwrap() == rhs_table) {
-            //    return Err(CompileErr::RelationCriteria { token: tokens[info.cursor].clone() })
-            //}
-
-            criterias_.push(criteria.clone());
+            if !parse_relation_option(tokens, &mut relation, info)? {
+                let criteria = parse_relation_criteria(tokens, info)?;
+                if relation.criterias.is_empty() {
+                    relation.table1 = criteria.occurrence1.value.to_string();
+                    relation.table2 = criteria.occurrence2.value.to_string();
+                }
+                relation.criterias.push(criteria);
+            }
+
             let mut token = expect(tokens, &vec![TokenType::Comma, TokenType::CloseBrace], info)?;
             if token.ttype == TokenType::Comma {
                 if let Some(end) = tokens.get(info.cursor + 1) {
@@ -690,45 +967,242 @@ pub fn parse_relation(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, St
                 }
             }
             if token.ttype == TokenType::CloseBrace {
-                return Ok((id_, StagedRelation {
-                    id: id_,
-                    table1: criteria.occurrence1.value.to_string(),
-                    table2: criteria.occurrence2.value.to_string(),
-                    criterias: criterias_,
-                }))
+                /* Options alone don't say which occurrences are joined. */
+                if relation.criterias.is_empty() {
+                    return Err(CompileErr::UnexpectedToken {
+                        token: token.clone(),
+                        expected: vec![TokenType::Identifier],
+                    });
+                }
+                return Ok((id_, relation))
             }
         }
         unreachable!()
     } else {
         info.cursor -= 1;
         let criteria = parse_relation_criteria(tokens, info)?;
-        criterias_.push(criteria.clone());
-        Ok((id_, StagedRelation {
-            id: id_,
-            table1: criteria.occurrence1.value.to_string(),
-            table2: criteria.occurrence2.value.to_string(),
-            criterias: criterias_,
-        }))
+        relation.table1 = criteria.occurrence1.value.to_string();
+        relation.table2 = criteria.occurrence2.value.to_string();
+        relation.criterias.push(criteria);
+        Ok((id_, relation))
     }
 }
 
-pub fn parse_layout_attributes(tokens: &[Token], info: &mut ParseInfo) -> Result<Vec<LayoutAttribute>, CompileErr> {
-    let attributes = vec![];
+/* Walks the entries of a block, starting on its opening brace and leaving
+ * the cursor on its closing brace. `entry` is called on the first token of
+ * each entry, and leaves the cursor on the last. */
+fn parse_block(tokens: &[Token], info: &mut ParseInfo,
+    mut entry: impl FnMut(&Token, &mut ParseInfo) -> Result<(), CompileErr>) -> Result<(), CompileErr> {
+    info.cursor += 1;
     while let Some(token) = tokens.get(info.cursor) {
         match token.ttype {
-            TokenType::CloseBrace => {
-                return Ok(attributes);
-            }
-
-            _ => {
-                return Err(CompileErr::UnimplementedLanguageFeauture { 
-                    feature: String::from("Layout Attributes"),
-                    token: token.clone() 
-                })
-            }
+            TokenType::CloseBrace => return Ok(()),
+            TokenType::EOF => return Err(CompileErr::UnexpectedEOF),
+            TokenType::Comma => {}
+            _ => entry(token, info)?,
         }
+        info.cursor += 1;
     }
-    Ok(vec![])
+    Err(CompileErr::UnexpectedEOF)
+}
+
+fn parse_integer_attribute(tokens: &[Token], info: &mut ParseInfo) -> Result<u32, CompileErr> {
+    expect(tokens, &vec![TokenType::Assignment], info)?;
+    let token = expect(tokens, &vec![TokenType::IntegerLiteral], info)?;
+    token.value.parse::<u32>()
+        .map_err(|_| CompileErr::IntegerOutOfRange { token: token.clone() })
+}
+
+/* Reads `key = n` into `bounds` if `key` is one of its sides, returning
+ * whether it was. */
+fn parse_bounds_attribute(tokens: &[Token], key: &Token, bounds: &mut Bounds, info: &mut ParseInfo) -> Result<bool, CompileErr> {
+    let side = match (key.ttype, key.value.as_str()) {
+        (TokenType::Identifier, "top") => &mut bounds.top,
+        (TokenType::Identifier, "left") => &mut bounds.left,
+        (TokenType::Identifier, "width") => &mut bounds.width,
+        (TokenType::Identifier, "height") => &mut bounds.height,
+        _ => return Ok(false),
+    };
+    *side = parse_integer_attribute(tokens, info)?;
+    Ok(true)
+}
+
+/* Reads `= [data_source::]script` after an attribute naming a script. */
+fn parse_script_reference(tokens: &[Token], info: &mut ParseInfo) -> Result<(Option<Token>, Token), CompileErr> {
+    expect(tokens, &vec![TokenType::Assignment], info)?;
+    let name = expect(tokens, &vec![TokenType::Identifier], info)?;
+    if expect(tokens, &vec![TokenType::ScopeResolution], info).is_ok() {
+        let in_source = expect(tokens, &vec![TokenType::Identifier], info)?;
+        Ok((Some(name.clone()), in_source.clone()))
+    } else {
+        info.cursor -= 1;
+        Ok((None, name.clone()))
+    }
+}
+
+/* Reads `event = script` into `triggers` if `key` names an event of `scope`,
+ * returning whether it did. */
+fn parse_trigger_attribute(tokens: &[Token], key: &Token, scope: TriggerScope,
+    triggers: &mut Vec<StagedTrigger>, info: &mut ParseInfo) -> Result<bool, CompileErr> {
+    let event = match TriggerEvent::from_cad(&key.value) {
+        Some(event) if key.ttype == TokenType::Identifier && event.scope() == scope => event,
+        _ => return Ok(false),
+    };
+    let (data_source, script) = parse_script_reference(tokens, info)?;
+    triggers.push(StagedTrigger { event, data_source, script });
+    Ok(true)
+}
+
+fn unknown_attribute(construct: &str, token: &Token) -> CompileErr {
+    CompileErr::UnknownAttribute { construct: construct.to_string(), token: token.clone() }
+}
+
+/* Layout object keywords are only keywords inside layouts, so they are
+ * still free to use as names elsewhere. */
+fn is_layout_object(token: &Token) -> bool {
+    token.ttype == TokenType::Field
+        || (token.ttype == TokenType::Identifier
+            && ["button", "portal", "tab_control"].contains(&token.value.as_str()))
+}
+
+/* Parses the objects and bounds of a block holding layout objects, such as a
+ * portal's. */
+fn parse_layout_object_block(tokens: &[Token], info: &mut ParseInfo, construct: &str, bounds: &mut Bounds,
+    mut attribute: impl FnMut(&Token, &mut ParseInfo) -> Result<bool, CompileErr>) -> Result<Vec<StagedLayoutObject>, CompileErr> {
+    let mut objects = vec![];
+    parse_block(tokens, info, |key, info| {
+        if is_layout_object(key) {
+            objects.push(parse_layout_object(tokens, info)?);
+        } else if !parse_bounds_attribute(tokens, key, bounds, info)? && !attribute(key, info)? {
+            return Err(unknown_attribute(construct, key));
+        }
+        Ok(())
+    })?;
+    Ok(objects)
+}
+
+pub fn parse_layout_object(tokens: &[Token], info: &mut ParseInfo) -> Result<StagedLayoutObject, CompileErr> {
+    let start = &tokens[info.cursor];
+    let mut bounds = Bounds::default();
+    let mut triggers = vec![];
+    let kind = match (start.ttype, start.value.as_str()) {
+        (TokenType::Field, _) => {
+            let occurrence = expect(tokens, &vec![TokenType::Identifier], info)?;
+            expect(tokens, &vec![TokenType::ScopeResolution], info)?;
+            let field = expect(tokens, &vec![TokenType::Identifier], info)?;
+            expect(tokens, &vec![TokenType::Assignment], info)?;
+            expect(tokens, &vec![TokenType::OpenBrace], info)?;
+            parse_block(tokens, info, |key, info| {
+                if parse_bounds_attribute(tokens, key, &mut bounds, info)?
+                    || parse_trigger_attribute(tokens, key, TriggerScope::Object, &mut triggers, info)? {
+                    return Ok(());
+                }
+                Err(unknown_attribute("field object", key))
+            })?;
+            StagedLayoutObjectKind::Field { occurrence: occurrence.clone(), field: field.clone() }
+        }
+        (_, "button") => {
+            let label = expect(tokens, &vec![TokenType::String], info)?;
+            expect(tokens, &vec![TokenType::Assignment], info)?;
+            expect(tokens, &vec![TokenType::OpenBrace], info)?;
+            let mut script = None;
+            parse_block(tokens, info, |key, info| {
+                if parse_bounds_attribute(tokens, key, &mut bounds, info)?
+                    || parse_trigger_attribute(tokens, key, TriggerScope::Object, &mut triggers, info)? {
+                    return Ok(());
+                }
+                if key.ttype != TokenType::Identifier || key.value != "perform_script" {
+                    return Err(unknown_attribute("button", key));
+                }
+                script = Some(parse_script_reference(tokens, info)?);
+                Ok(())
+            })?;
+            let Some((data_source, script)) = script else {
+                return Err(CompileErr::MissingAttribute {
+                    base_object: label.value.clone(),
+                    construct: String::from("button"),
+                    specifier: String::from("perform_script"),
+                });
+            };
+            StagedLayoutObjectKind::Button { label: label.value.clone(), data_source, script }
+        }
+        (_, "portal") => {
+            let occurrence = expect(tokens, &vec![TokenType::Identifier], info)?;
+            expect(tokens, &vec![TokenType::Assignment], info)?;
+            expect(tokens, &vec![TokenType::OpenBrace], info)?;
+            let mut rows = 1;
+            let objects = parse_layout_object_block(tokens, info, "portal", &mut bounds, |key, info| {
+                if parse_trigger_attribute(tokens, key, TriggerScope::Object, &mut triggers, info)? {
+                    return Ok(true);
+                }
+                if key.ttype != TokenType::Identifier || key.value != "rows" {
+                    return Ok(false);
+                }
+                rows = parse_integer_attribute(tokens, info)?;
+                Ok(true)
+            })?;
+            StagedLayoutObjectKind::Portal { occurrence: occurrence.clone(), rows, objects }
+        }
+        (_, "tab_control") => {
+            expect(tokens, &vec![TokenType::Assignment], info)?;
+            expect(tokens, &vec![TokenType::OpenBrace], info)?;
+            let mut tabs = vec![];
+            parse_block(tokens, info, |key, info| {
+                if parse_bounds_attribute(tokens, key, &mut bounds, info)?
+                    || parse_trigger_attribute(tokens, key, TriggerScope::Object, &mut triggers, info)? {
+                    return Ok(());
+                }
+                if key.ttype != TokenType::Identifier || key.value != "tab" {
+                    return Err(unknown_attribute("tab_control", key));
+                }
+                let name = expect(tokens, &vec![TokenType::String], info)?;
+                expect(tokens, &vec![TokenType::Assignment], info)?;
+                expect(tokens, &vec![TokenType::OpenBrace], info)?;
+                /* Tab panels take the bounds of their tab control. */
+                let objects = parse_layout_object_block(tokens, info, "tab", &mut Bounds::default(),
+                    |_, _| Ok(false))?;
+                tabs.push(StagedLayoutTab { name: name.clone(), objects });
+                Ok(())
+            })?;
+            StagedLayoutObjectKind::TabControl { tabs }
+        }
+        _ => return Err(unknown_attribute("layout part", start)),
+    };
+    Ok(StagedLayoutObject { bounds, kind, triggers })
+}
+
+pub fn parse_layout_attributes(tokens: &[Token], info: &mut ParseInfo)
+    -> Result<(Vec<StagedLayoutPart>, Vec<StagedTrigger>), CompileErr> {
+    let mut parts = vec![];
+    let mut triggers = vec![];
+    parse_block(tokens, info, |key, info| {
+        if parse_trigger_attribute(tokens, key, TriggerScope::Layout, &mut triggers, info)? {
+            return Ok(());
+        }
+        let kind = match (key.ttype, key.value.as_str()) {
+            (TokenType::Identifier, "header") => LayoutPartKind::Header,
+            (TokenType::Identifier, "body") => LayoutPartKind::Body,
+            (TokenType::Identifier, "footer") => LayoutPartKind::Footer,
+            _ => return Err(unknown_attribute("layout", key)),
+        };
+        expect(tokens, &vec![TokenType::Assignment], info)?;
+        expect(tokens, &vec![TokenType::OpenBrace], info)?;
+        let mut height = 0;
+        let mut objects = vec![];
+        parse_block(tokens, info, |key, info| {
+            if is_layout_object(key) {
+                objects.push(parse_layout_object(tokens, info)?);
+            } else if key.ttype == TokenType::Identifier && key.value == "height" {
+                height = parse_integer_attribute(tokens, info)?;
+            } else {
+                return Err(unknown_attribute("layout part", key));
+            }
+            Ok(())
+        })?;
+        parts.push(StagedLayoutPart { kind, height, objects });
+        Ok(())
+    })?;
+    Ok((parts, triggers))
 }
 
 pub fn parse_layout(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, StagedLayout), CompileErr> {
@@ -744,72 +1218,96 @@ pub fn parse_layout(tokens: &[Token], info: &mut ParseInfo) -> Result<(u16, Stag
     expect(tokens, &vec![TokenType::Assignment], info)?;
     expect(tokens, &vec![TokenType::OpenBrace], info)?;
 
-    info.cursor += 1;
-    let _attrs = parse_layout_attributes(tokens, info)?;
+    let (parts_, triggers_) = parse_layout_attributes(tokens, info)?;
 
     Ok((id_, StagedLayout {
         id: id_,
         name: name_.clone(),
         base_occurrence: occurrence.clone(),
+        parts: parts_,
+        triggers: triggers_,
     }))
 }
 
-pub fn parse(tokens: &[Token]) -> Result<Stage, CompileErr> {
-    let mut result = Stage::new();
-    let mut info =  ParseInfo { cursor: 0 };
+pub fn parse_file_trigger(tokens: &[Token], info: &mut ParseInfo, triggers: &mut Vec<StagedTrigger>) -> Result<(), CompileErr> {
+    let event = expect(tokens, &vec![TokenType::Identifier], info)?;
+    match parse_trigger_attribute(tokens, event, TriggerScope::File, triggers, info)? {
+        true => Ok(()),
+        false => Err(unknown_attribute("trigger", event)),
+    }
+}
 
-    loop {
-        match &tokens[info.cursor].ttype {
-            TokenType::Table => {
-                let (id, table) = parse_table(tokens, &mut info)?;
-                result.tables.insert(id, table);
-            },
-            TokenType::TableOccurrence => {
-                let (id, table_occurrence) = parse_table_occurrence(tokens, &mut info)?;
-                result.table_occurrences.insert(id, table_occurrence);
-            }
-            TokenType::Relation => {
-                let (id, relation) = parse_relation(tokens, &mut info)?;
-                result.relations.insert(id, relation);
-            },
-            TokenType::ValueList => {
-                let (id, valuelist) = parse_value_list(tokens, &mut info)?;
-                result.value_lists.insert(id, valuelist);
-            },
-            TokenType::Script => {
-                let (id, script) = parse_script(tokens, &mut info)?;
-                result.scripts.insert(id, script);
-            },
-            TokenType::Layout => {
-                let (id, layout) = parse_layout(tokens, &mut info)?;
-                result.layouts.insert(id, layout);
-            },
-            TokenType::Test => {
-                let (id, test) = parse_test(tokens, &mut info)?;
-                result.tests.insert(id, test);
-            },
-            TokenType::Extern => {
-                let (id, datasource) = parse_extern(tokens, &mut info)?;
-                result.data_sources.insert(id, datasource);
-            }
+/* Parses as much of the file as possible. An object which fails to parse is
+ * skipped and its error recorded, so a file with several mistakes reports all
+ * of them, and tooling still gets a Stage holding every object that did parse. */
+pub fn parse_partial(tokens: &[Token]) -> (Stage, Vec<CompileErr>) {
+    let mut result = Stage::new();
+    let mut info =  ParseInfo { cursor: 0, errors: vec![] };
+
+    while info.cursor < tokens.len() {
+        let start = info.cursor;
+        /* Each arm reports the kind of object if its id was already taken. */
+        let parsed = match &tokens[info.cursor].ttype {
+            TokenType::Table => parse_table(tokens, &mut info)
+                .map(|(id, table)| result.tables.insert(id, table).map(|_| FMObjType::Table)),
+            TokenType::TableOccurrence => parse_table_occurrence(tokens, &mut info)
+                .map(|(id, table_occurrence)| result.table_occurrences.insert(id, table_occurrence)
+                    .map(|_| FMObjType::TableOccurrence)),
+            TokenType::Relation => parse_relation(tokens, &mut info)
+                .map(|(id, relation)| result.relations.insert(id, relation).map(|_| FMObjType::Relation)),
+            TokenType::ValueList => parse_value_list(tokens, &mut info)
+                .map(|(id, valuelist)| result.value_lists.insert(id, valuelist).map(|_| FMObjType::ValueList)),
+            TokenType::Script => parse_script(tokens, &mut info)
+                .map(|(id, script)| result.scripts.insert(id, script).map(|_| FMObjType::Script)),
+            TokenType::Layout => parse_layout(tokens, &mut info)
+                .map(|(id, layout)| result.layouts.insert(id, layout).map(|_| FMObjType::Layout)),
+            TokenType::Test => parse_test(tokens, &mut info)
+                .map(|(id, test)| result.tests.insert(id, test).map(|_| FMObjType::Test)),
+            TokenType::Extern => parse_extern(tokens, &mut info)
+                .map(|(id, datasource)| result.data_sources.insert(id, datasource).map(|_| FMObjType::DataSource)),
+            TokenType::Import => expect(tokens, &vec![TokenType::String], &mut info)
+                .map(|path| { result.imports.push(path.clone()); None }),
+            /* `trigger` is only a keyword at the top level. */
+            TokenType::Identifier if tokens[info.cursor].value == "trigger" =>
+                parse_file_trigger(tokens, &mut info, &mut result.triggers).map(|_| None),
             TokenType::EOF => {
                 break;
             }
-            _ => { return Err(CompileErr::UnexpectedToken { 
+            _ => Err(CompileErr::UnexpectedToken { 
                 token: tokens[info.cursor].clone(), 
                 expected: [
                     TokenType::Table, TokenType::TableOccurrence, TokenType::Relation,
                     TokenType::ValueList, TokenType::Script, TokenType::Test,
-                    TokenType::EOF,
+                    TokenType::Import, TokenType::EOF,
                 ].to_vec(),
-            }) }
+            })
+        };
+
+        match parsed {
+            Err(e) => {
+                info.errors.push(e);
+                synchronize(tokens, &mut info, start, &TOP_LEVEL);
+                continue;
+            }
+            Ok(Some(construct)) => info.errors.push(CompileErr::DuplicateId {
+                construct,
+                token: tokens[start + 1].clone(),
+            }),
+            Ok(None) => {}
         }
         if tokens[info.cursor].ttype == TokenType::EOF {
             break;
         }
         info.cursor += 1;
     };
-    Ok(result)
+    (result, info.errors)
+}
+
+pub fn parse(tokens: &[Token]) -> Result<Stage, Vec<CompileErr>> {
+    match parse_partial(tokens) {
+        (stage, errors) if errors.is_empty() => Ok(stage),
+        (_, errors) => Err(errors),
+    }
 }
 
 #[cfg(test)]
@@ -864,6 +1362,7 @@ mod tests {
                         Location { line: 3, column: 26 },
                         String::from("id")
                     ),
+                    kind: StagedFieldKind::Simple,
                     repetitions: 1,
                     dtype: DataType::Number,
                     global: false,
@@ -879,10 +1378,12 @@ mod tests {
                             StagedValidationType::Required,
                             StagedValidationType::Unique
                         ],
-                        message: String::new(),
+                        message: String::from("Invalid ID chosen."),
                         trigger: ValidationTrigger::OnEntry,
                         user_override: true,
-                    }
+                    },
+                    comment: String::new(),
+                    comments: vec![],
                 });
         expected_fields.insert(2, StagedField {
                     id: 2,
@@ -891,6 +1392,7 @@ mod tests {
                         Location { line: 10, column: 26 },
                         String::from("counter")
                     ),
+                    kind: StagedFieldKind::Simple,
                     dtype: DataType::Number,
                     repetitions: 1,
                     global: false,
@@ -908,7 +1410,9 @@ mod tests {
                         message: String::new(),
                         trigger: ValidationTrigger::OnEntry,
                         user_override: true,
-                    }
+                    },
+                    comment: String::new(),
+                    comments: vec![],
                 });
         let expected = StagedTable {
             id: 1,
@@ -917,6 +1421,7 @@ mod tests {
                 Location { line: 2, column: 22},
                 "Person".to_string()),
             fields: expected_fields,
+            comments: vec![],
         };
         println!("{:?}\n", schema.tables[&1]);
         println!("{:?}\n", expected);
@@ -1273,7 +1778,10 @@ mod tests {
                             TokenType::Identifier,
                             Location { line: 2, column: 53 },
                             String::from("id")),
-            }]
+            }],
+            allow_creation: vec![],
+            delete_related: vec![],
+            sort: vec![],
         };
         assert_eq!(expected, schema.relations[&1])
     }
@@ -1331,10 +1839,40 @@ mod tests {
                                 Location { line: 4, column: 47 },
                                 String::from("name")),
                 }
-            ]
+            ],
+            allow_creation: vec![],
+            delete_related: vec![],
+            sort: vec![],
         };
         assert_eq!(expected, schema.relations[&1])
     }
+
+    #[test]
+    fn relation_options_test() {
+        let code = "
+        relation %1 = {
+            Person_occ::id == Phone_occ::person_id,
+            allow_creation = Phone_occ,
+            delete_related = Phone_occ,
+            sort = Phone_occ::label descending,
+            sort = Phone_occ::number,
+        }
+        relation %2 = {
+            allow_creation = Phone_occ,
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+        let relation = &schema.relations[&1];
+        assert_eq!(relation.table2, "Phone_occ");
+        assert_eq!(relation.allow_creation.iter().map(|t| t.value.as_str()).collect::<Vec<_>>(), vec!["Phone_occ"]);
+        assert_eq!(relation.delete_related.len(), 1);
+        assert_eq!(relation.sort.iter().map(|s| (s.field.value.as_str(), s.descending)).collect::<Vec<_>>(),
+            vec![("label", true), ("number", false)]);
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Unexpected \"}\" @ 11,9. Expected: [\"Identifier\"]",
+        ]);
+    }
     
     #[test]
     fn extern_basic() {
@@ -1354,6 +1892,274 @@ mod tests {
 
     }
 
+    #[test]
+    fn layout_parts_and_objects() {
+        let code = "
+        layout %1 People : Person_occ = {
+            body = {
+                height = 200,
+                field Person_occ::name = { top = 10, left = 20, width = 120, height = 20 },
+                button \"Save\" = {
+                    top = 40,
+                    perform_script = Remote::save,
+                },
+                portal Invoice_occ = {
+                    rows = 5,
+                    field Invoice_occ::total = { width = 80 },
+                },
+                tab_control = {
+                    tab \"General\" = {
+                        field Person_occ::id = {},
+                    },
+                    tab \"Notes\" = {},
+                },
+            },
+            header = { height = 40 },
+        }
+        table_occurrence %1 Person_occ : Person
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let schema = parse(&tokens).expect("Parsing failed.");
+        assert_eq!(schema.table_occurrences.len(), 1);
+
+        let parts = &schema.layouts[&1].parts;
+        assert_eq!(parts.iter().map(|p| (p.kind, p.height)).collect::<Vec<_>>(),
+            vec![(LayoutPartKind::Body, 200), (LayoutPartKind::Header, 40)]);
+        let objects = &parts[0].objects;
+        assert_eq!(objects.len(), 4);
+        assert_eq!(objects[0].bounds, Bounds { top: 10, left: 20, width: 120, height: 20 });
+        assert!(matches!(&objects[0].kind, StagedLayoutObjectKind::Field { occurrence, field }
+            if occurrence.value == "Person_occ" && field.value == "name"));
+        assert!(matches!(&objects[1].kind, StagedLayoutObjectKind::Button { label, data_source: Some(ds), script }
+            if label == "Save" && ds.value == "Remote" && script.value == "save"));
+        assert!(matches!(&objects[2].kind, StagedLayoutObjectKind::Portal { occurrence, rows: 5, objects }
+            if occurrence.value == "Invoice_occ" && objects.len() == 1));
+        let StagedLayoutObjectKind::TabControl { tabs } = &objects[3].kind else {
+            panic!("expected a tab control");
+        };
+        assert_eq!(tabs.iter().map(|t| (t.name.value.as_str(), t.objects.len())).collect::<Vec<_>>(),
+            vec![("General", 1), ("Notes", 0)]);
+    }
+
+    #[test]
+    fn script_triggers() {
+        let code = "
+        trigger on_first_window_open = startup
+        layout %1 People : Person_occ = {
+            on_record_load = Remote::loaded,
+            body = {
+                field Person_occ::name = { on_object_modify = renamed },
+            },
+        }
+        trigger on_record_load = loaded
+        layout %2 Other : Person_occ = {
+            on_object_enter = entered,
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Unknown attribute \"on_record_load\" for trigger @ 9, 17",
+            "Unknown attribute \"on_object_enter\" for layout @ 11, 13",
+        ]);
+        let events = |triggers: &[StagedTrigger]| triggers.iter()
+            .map(|t| (t.event, t.data_source.as_ref().map(|ds| ds.value.clone()), t.script.value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(events(&schema.triggers), vec![(TriggerEvent::OnFirstWindowOpen, None, String::from("startup"))]);
+        let layout = &schema.layouts[&1];
+        assert_eq!(events(&layout.triggers), vec![(TriggerEvent::OnRecordLoad, Some(String::from("Remote")), String::from("loaded"))]);
+        assert_eq!(events(&layout.parts[0].objects[0].triggers), vec![(TriggerEvent::OnObjectModify, None, String::from("renamed"))]);
+    }
+
+    #[test]
+    fn layout_errors() {
+        let code = "
+        layout %1 People : Person_occ = {
+            body = {
+                button \"Save\" = { top = 1 },
+            },
+        }
+        layout %2 Other : Person_occ = {
+            sidebar = {},
+        }
+        table_occurrence %1 Person_occ : Person
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Missing attribute perform_script for button in Save",
+            "Unknown attribute \"sidebar\" for layout @ 8, 13",
+        ]);
+        assert!(schema.layouts.is_empty());
+        assert_eq!(schema.table_occurrences.len(), 1);
+    }
+
+    #[test]
+    fn field_repetitions() {
+        let code = "
+        table %1 Person = {
+            field %1 phones = {
+                datatype = Text,
+                repetitions = 3,
+            },
+            field %2 notes = {
+                datatype = Text,
+                repetitions = 0,
+            },
+            field %3 history = {
+                datatype = Text,
+                repetitions = 99999999999,
+            },
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+        assert_eq!(schema.tables[&1].fields[&1].repetitions, 3);
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Repetitions must be from 1 to 255, found 0 @ 9, 31",
+            "Repetitions must be from 1 to 255, found 99999999999 @ 13, 31",
+        ]);
+    }
+
+    #[test]
+    fn integers_out_of_range() {
+        let code = "
+        table %1 Person = {
+            field %1 name = {
+                datatype = Text,
+                max_chars = 99999999999,
+            },
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (_, errors) = parse_partial(&tokens);
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Integer 99999999999 is too large @ 5, 29",
+        ]);
+    }
+
+    #[test]
+    fn global_fields() {
+        let code = "
+        table %1 Settings = {
+            field %1 theme = {
+                datatype = Text,
+                global = true,
+            },
+            field %2 label = {
+                datatype = Text,
+                global = false,
+            },
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let schema = parse(&tokens).expect("Parsing failed.");
+        assert!(schema.tables[&1].fields[&1].global);
+        assert!(!schema.tables[&1].fields[&2].global);
+    }
+
+    #[test]
+    fn error_recovery() {
+        let code = "
+        table %1 Person = {
+            field %1 id = {
+                datatype = Number,
+            },
+            field %2 name = {
+                datatype = Txt,
+            },
+            field %3 age = {
+                datatype = Number,
+            }
+        }
+        table_occurrence %1 Person_occ : Person
+        table_occurrence %2 : Person
+        relation %1 = Person_occ::id == Person_occ::age
+        table %2 Job = {
+            field %1 id = {
+                datatype = Number,
+            }
+        }
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(&errors[0], CompileErr::UnexpectedToken { token, .. } 
+            if token.value == "Txt" && token.location.line == 7));
+        assert!(matches!(&errors[1], CompileErr::UnexpectedToken { token, .. } 
+            if token.ttype == TokenType::Colon && token.location.line == 14));
+
+        assert_eq!(schema.tables[&1].fields.keys().collect::<Vec<_>>(), vec![&1, &3]);
+        assert_eq!(schema.tables[&2].fields.len(), 1);
+        assert_eq!(schema.table_occurrences.keys().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(schema.relations.len(), 1);
+        assert!(parse(&tokens).is_err());
+    }
+
+    #[test]
+    fn unbalanced_script_blocks() {
+        let code = "
+script %1 check = {
+  if(|$x = 1|) new_record()
+}
+test %1 other = { elif(|$x = 2|) {
+  }
+}
+script %2 typo = {
+  new_recrod()
+}
+script %3 stray = {
+  new_record() (
+}
+script %4 sideways = {
+  go_to_record(sideways)
+}
+script %5 arguments = {
+  beep(|1|)
+}
+script %6 missing = {
+  set_field(Person::name)
+}
+script %7 options = {
+  show_custom_dialog(title=\"Done\")
+}
+";
+        let (_, errors) = parse_partial(&lex(code).unwrap());
+        assert_eq!(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Unclosed if block @ 3, 3",
+            "Unmatched elif @ 5, 19",
+            "Unknown script step new_recrod @ 9, 3",
+            "Unexpected \"(\" @ 12,16. Expected one of: [\"Identifier\", \"}\"]",
+            "Invalid value \"sideways\" for argument \"record\" of go_to_record @ 15, 16",
+            "Unexpected argument \"1\" for beep @ 18, 8",
+            "Missing argument \"expr\" for set_field @ 21, 3",
+            "Argument \"title\" of show_custom_dialog isn't supported yet @ 24, 28",
+        ]);
+    }
+
+    #[test]
+    fn error_recovery_stray_tokens() {
+        let code = "
+        table %1 Person = {
+            field %1 id = {
+                datatype = Number,
+            }
+        }
+        }
+        name = {
+            datatype = Text,
+        }
+        table_occurrence %1 Person_occ : Person
+        ";
+        let tokens = lex(code).expect("Tokenisation failed.");
+        let (schema, errors) = parse_partial(&tokens);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(schema.tables.len(), 1);
+        assert_eq!(schema.table_occurrences.len(), 1);
+    }
+
     #[test]
     fn compile_initial_cad() {
         let code = read_to_string("test_data/cad_files/initial.cad").expect("Unable to read file.");
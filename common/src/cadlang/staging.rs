@@ -1,5 +1,5 @@
-use std::collections::btree_map::BTreeMap;
-use super::{token::Token, cadscript::proto_script::ProtoScript, error::CompileErr, parser::FMObjType};
+use std::collections::btree_map::{BTreeMap, Entry};
+use super::{token::{Location, Token, TokenType}, cadscript::proto_script::ProtoScript, error::CompileErr, parser::FMObjType};
 
 use crate::dbobjects::{
     schema::{
@@ -8,9 +8,12 @@ use crate::dbobjects::{
             AutoEntryDataPresets,
             DataType,
             SerialTrigger,
+            SummaryKind,
             ValidationTrigger
         },
     },
+    layout::{Bounds, LayoutPartKind},
+    scripting::trigger::TriggerEvent,
     data_source::*
 };
 
@@ -19,6 +22,7 @@ pub struct StagedTable {
     pub id: u16,
     pub name: Token,
     pub fields: BTreeMap<u16, StagedField>,
+    pub comments: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -44,7 +48,8 @@ pub struct StagedValidation {
 pub enum StagedAutoEntryType {
     NA,
     Serial { next: usize, increment: usize, trigger: SerialTrigger },
-    Lookup { from: Token, to: Token },
+    /* Copies `occurrence::field` from the record related through `from`. */
+    Lookup { from: Token, occurrence: Token, field: Token },
     Creation(AutoEntryDataPresets),
     Modification(AutoEntryDataPresets),
     LastVisited,
@@ -58,15 +63,25 @@ pub struct StagedAutoEntry {
     pub definition: StagedAutoEntryType
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StagedFieldKind {
+    Simple,
+    Calculation { code: String, stored: bool },
+    Summary { kind: SummaryKind, field: Token },
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StagedField {
     pub id: u16,
     pub name: Token,
+    pub kind: StagedFieldKind,
     pub dtype: DataType,
     pub validation: StagedValidation,
     pub autoentry: StagedAutoEntry,
     pub global: bool,
     pub repetitions: usize,
+    pub comment: String,
+    pub comments: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -78,12 +93,23 @@ pub struct StagedRelationCriteria {
     pub comparison: RelationComparison,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StagedRelationSort {
+    pub occurrence: Token,
+    pub field: Token,
+    pub descending: bool,
+}
+
+/* `allow_creation` and `delete_related` name the sides they apply to. */
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StagedRelation {
     pub id: u16,
     pub table1: String,
     pub table2: String,
     pub criterias: Vec<StagedRelationCriteria>,
+    pub allow_creation: Vec<Token>,
+    pub delete_related: Vec<Token>,
+    pub sort: Vec<StagedRelationSort>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -119,11 +145,50 @@ pub struct StagedValueList {
     pub definition: StagedValueListDefinition,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StagedLayoutObjectKind {
+    Field { occurrence: Token, field: Token },
+    /* `data_source` is set for scripts in other files. */
+    Button { label: String, data_source: Option<Token>, script: Token },
+    Portal { occurrence: Token, rows: u32, objects: Vec<StagedLayoutObject> },
+    TabControl { tabs: Vec<StagedLayoutTab> },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StagedLayoutObject {
+    pub bounds: Bounds,
+    pub kind: StagedLayoutObjectKind,
+    pub triggers: Vec<StagedTrigger>,
+}
+
+/* `data_source` is set for scripts in other files. */
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StagedTrigger {
+    pub event: TriggerEvent,
+    pub data_source: Option<Token>,
+    pub script: Token,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StagedLayoutTab {
+    pub name: Token,
+    pub objects: Vec<StagedLayoutObject>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StagedLayoutPart {
+    pub kind: LayoutPartKind,
+    pub height: u32,
+    pub objects: Vec<StagedLayoutObject>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StagedLayout {
     pub id: u16,
     pub name: Token,
     pub base_occurrence: Token,
+    pub parts: Vec<StagedLayoutPart>,
+    pub triggers: Vec<StagedTrigger>,
 }
 
 #[derive(Debug, Clone)]
@@ -136,6 +201,26 @@ pub struct Stage {
     pub scripts: BTreeMap<u16, ProtoScript>,
     pub tests: BTreeMap<u16, ProtoScript>,
     pub data_sources: BTreeMap<u16, DataSource>,
+    /* Triggers declared on the file itself. */
+    pub triggers: Vec<StagedTrigger>,
+    pub imports: Vec<Token>,
+}
+
+/* Moves the objects of `from` into `into`, reporting the ones whose id is
+ * already taken. `name` gives the token to report the clash at. */
+fn merge_objects<T>(into: &mut BTreeMap<u16, T>, from: BTreeMap<u16, T>, construct: FMObjType,
+    name: impl Fn(&T) -> Token, module: &str, errors: &mut Vec<CompileErr>) {
+    for (id, object) in from {
+        match into.entry(id) {
+            Entry::Vacant(entry) => { entry.insert(object); }
+            Entry::Occupied(..) => errors.push(CompileErr::ModuleIdClash {
+                construct,
+                id,
+                token: name(&object),
+                module: module.to_string(),
+            }),
+        }
+    }
 }
 
 impl Stage {
@@ -149,9 +234,38 @@ impl Stage {
             scripts: BTreeMap::new(),
             tests: BTreeMap::new(),
             data_sources: BTreeMap::new(),
+            triggers: vec![],
+            imports: vec![],
         }
     }
 
+    /* Merges a module imported from `module` into this stage. Modules share
+     * one id space per kind of object, so clashing objects are reported and
+     * left out. */
+    pub fn merge(&mut self, other: Stage, module: &str) -> Vec<CompileErr> {
+        let mut errors = vec![];
+        let script_name = |s: &ProtoScript| Token::with_value(TokenType::Identifier, s.location, s.name.clone());
+        merge_objects(&mut self.tables, other.tables, FMObjType::Table,
+            |t| t.name.clone(), module, &mut errors);
+        merge_objects(&mut self.table_occurrences, other.table_occurrences, FMObjType::TableOccurrence,
+            |o| o.name.clone(), module, &mut errors);
+        merge_objects(&mut self.relations, other.relations, FMObjType::Relation,
+            |r| r.criterias[0].occurrence1.clone(), module, &mut errors);
+        merge_objects(&mut self.value_lists, other.value_lists, FMObjType::ValueList,
+            |v| v.name.clone(), module, &mut errors);
+        merge_objects(&mut self.layouts, other.layouts, FMObjType::Layout,
+            |l| l.name.clone(), module, &mut errors);
+        merge_objects(&mut self.scripts, other.scripts, FMObjType::Script,
+            script_name, module, &mut errors);
+        merge_objects(&mut self.tests, other.tests, FMObjType::Test,
+            script_name, module, &mut errors);
+        merge_objects(&mut self.data_sources, other.data_sources, FMObjType::DataSource,
+            |d| Token::with_value(TokenType::Identifier, Location { line: 0, column: 0 }, d.name.clone()),
+            module, &mut errors);
+        self.triggers.extend(other.triggers);
+        errors
+    }
+
     //pub fn to_schema(&self) -> Result<Schema, Vec<CompileErr>> {
     //    let mut result = Schema::new();
     //    let mut errs = Vec::<CompileErr>::new();
@@ -12,6 +12,7 @@ pub enum TokenType {
     Script,
     Test,
     Extern,
+    Import,
     // Second level objects
     Field,
     // thid level objects
@@ -98,6 +99,7 @@ pub enum TokenType {
     Comma,
     Colon,
     Exclamation,
+    Comment,
     EOF,
 }
 
@@ -121,11 +123,20 @@ pub struct Location {
     pub column: u32,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/* `comments` holds the comments directly in front of the token, as written
+ * in the source. They are trivia, and do not take part in comparisons. */
+#[derive(Clone, Debug, Eq)]
 pub struct Token {
     pub ttype: TokenType,
     pub value: String,
     pub location: Location,
+    pub comments: Vec<String>,
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.ttype == other.ttype && self.value == other.value && self.location == other.location
+    }
 }
 
 impl Token {
@@ -134,6 +145,7 @@ impl Token {
             ttype: ttype_,
             value: String::new(),
             location: location_,
+            comments: vec![],
         }
     }
 
@@ -142,6 +154,30 @@ impl Token {
             ttype: ttype_,
             value: value_,
             location: location_,
+            comments: vec![],
         }
     }
 }
+
+/* A string literal for `text`. Backslashes and double quotes are escaped
+ * with a backslash, which the lexer undoes. */
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/* The text of a comment without its delimiters, e.g "// note" and
+ * "/* note */" are both "note". Leading asterisks on the lines of block
+ * comments are dropped. */
+pub fn comment_text(comment: &str) -> String {
+    if let Some(line) = comment.strip_prefix("//") {
+        return line.trim().to_string();
+    }
+    let inner = comment.trim_start_matches("/*").trim_end_matches("*/");
+    inner.lines()
+        .map(|l| l.trim().trim_start_matches('*').trim())
+        .skip_while(|l| l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
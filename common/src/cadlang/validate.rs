@@ -1,110 +1,797 @@
-use crate::schema::{Schema, RelationCriteria, DBObjectReference};
-use super::parser::BindingList;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug)]
-pub enum ValidationErr {
-    UnknownTable(String),
-    UnknownTableOccurrence(String),
-    UnknownField(String),
+use super::{
+    cadscript::proto_instruction::{ProtoInstruction, ProtoLayoutSelection, ProtoRecordSelection, ProtoScriptSelection},
+    error::CompileErr,
+    parser::FMObjType,
+    staging::{
+        Stage, StagedAutoEntryType, StagedFieldKind, StagedLayoutObject, StagedLayoutObjectKind, StagedTable, StagedTrigger,
+        StagedValidationType, StagedValueListDefinition,
+    },
+    token::{Location, Token, TokenType},
+};
+use crate::dbobjects::{
+    calculation::{lex_text, token::Token as CalcToken, CalculationString},
+    reference::TableReference,
+    schema::relationgraph::{graph::RelationGraph, relation::{Relation, RelationOptions}, table_occurrence::TableOccurrence},
+};
+
+/* Semantic checks over a parsed Stage, run before the backend which assumes
+ * every reference resolves. Duplicate ids are caught by the parser, as the
+ * Stage keeps objects keyed by id. Occurrences of tables in other files are
+ * only checked as far as this file can see. */
+pub fn validate(stage: &Stage) -> Vec<CompileErr> {
+    let mut errors = vec![];
+    validate_names(stage, &mut errors);
+    validate_table_occurrences(stage, &mut errors);
+    validate_relations(stage, &mut errors);
+    validate_fields(stage, &mut errors);
+    validate_value_lists(stage, &mut errors);
+    validate_layouts(stage, &mut errors);
+    validate_scripts(stage, &mut errors);
+    validate_variables(stage, &mut errors);
+    errors
+}
+
+/* Scripts in other files can only be looked up once those files are
+ * compiled, so references to them are checked separately from `validate`,
+ * against the externs the backend builds with. */
+pub fn validate_externs(stage: &Stage, externs: &HashMap<u32, Stage>) -> Vec<CompileErr> {
+    let mut errors = vec![];
+    extern_triggers(stage, externs, &stage.triggers, &mut errors);
+    for layout in stage.layouts.values() {
+        extern_triggers(stage, externs, &layout.triggers, &mut errors);
+        for part in &layout.parts {
+            extern_layout_objects(stage, externs, &part.objects, &mut errors);
+        }
+    }
+    errors
+}
+
+fn check_extern_script(stage: &Stage, externs: &HashMap<u32, Stage>, data_source: Option<&Token>, script: &Token, errors: &mut Vec<CompileErr>) {
+    /* Unknown data sources are reported by `validate`. */
+    let Some(ds) = data_source.and_then(|ds| stage.data_sources.values().find(|d| d.name == ds.value)) else {
+        return;
+    };
+    let found = externs.get(&ds.id)
+        .is_some_and(|source| source.scripts.values().any(|s| s.name == script.value));
+    if !found {
+        errors.push(CompileErr::UndefinedReference { construct: FMObjType::Script, token: script.clone() });
+    }
+}
+
+fn extern_triggers(stage: &Stage, externs: &HashMap<u32, Stage>, triggers: &[StagedTrigger], errors: &mut Vec<CompileErr>) {
+    for trigger in triggers {
+        check_extern_script(stage, externs, trigger.data_source.as_ref(), &trigger.script, errors);
+    }
 }
 
-pub fn validate_table_occurrence_references(schema: &mut Schema, bindings: &BindingList) -> Result<(), ValidationErr> {
-    for ref mut table_occurrence in &mut schema.table_occurrences {
-        table_occurrence.1.base_table.top_id = match bindings.tables
-            .iter()
-            .find(|table| table.1 == table_occurrence.1.name) {
-                Some(inner) => inner.0 as u16,
-                None => {
-                    return Err(ValidationErr::UnknownTable(table_occurrence.1.name.clone()))
+fn extern_layout_objects(stage: &Stage, externs: &HashMap<u32, Stage>, objects: &[StagedLayoutObject], errors: &mut Vec<CompileErr>) {
+    for object in objects {
+        extern_triggers(stage, externs, &object.triggers, errors);
+        match &object.kind {
+            StagedLayoutObjectKind::Button { data_source, script, .. } => {
+                check_extern_script(stage, externs, data_source.as_ref(), script, errors);
+            }
+            StagedLayoutObjectKind::Portal { objects, .. } => extern_layout_objects(stage, externs, objects, errors),
+            StagedLayoutObjectKind::TabControl { tabs } => {
+                for tab in tabs {
+                    extern_layout_objects(stage, externs, &tab.objects, errors);
                 }
+            }
+            StagedLayoutObjectKind::Field { .. } => {}
+        }
+    }
+}
+
+fn duplicate_names<'a>(construct: FMObjType, names: impl Iterator<Item = &'a Token>, errors: &mut Vec<CompileErr>) {
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name.value.as_str()) {
+            errors.push(CompileErr::DuplicateName { construct, token: name.clone() });
         }
     }
+}
 
-    Ok(())
+fn script_name(name: &str, location: Location) -> Token {
+    Token::with_value(TokenType::Identifier, location, name.to_string())
 }
 
-pub fn validate_layout_references(schema: &mut Schema, bindings: &BindingList) -> Result<(), ValidationErr> {
-    for ref mut layout in &mut schema.layouts {
-        layout.1.table_occurrence = match bindings.table_occurrences
-            .iter()
-            .find(|occ| occ.1 == layout.1.name) {
-                Some(inner) => DBObjectReference { 
-                    data_source: 0,
-                    top_id: inner.0 as u16,
-                    inner_id: 0,
-                },
-                None => {
-                    return Err(ValidationErr::UnknownTableOccurrence(layout.1.table_occurrence_name.clone()));
+fn validate_names(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    duplicate_names(FMObjType::Table, stage.tables.values().map(|t| &t.name), errors);
+    for table in stage.tables.values() {
+        duplicate_names(FMObjType::Field, table.fields.values().map(|f| &f.name), errors);
+    }
+    duplicate_names(FMObjType::TableOccurrence, stage.table_occurrences.values().map(|o| &o.name), errors);
+    duplicate_names(FMObjType::ValueList, stage.value_lists.values().map(|v| &v.name), errors);
+    duplicate_names(FMObjType::Layout, stage.layouts.values().map(|l| &l.name), errors);
+
+    let scripts = stage.scripts.values().map(|s| script_name(&s.name, s.location)).collect::<Vec<_>>();
+    duplicate_names(FMObjType::Script, scripts.iter(), errors);
+    let tests = stage.tests.values().map(|s| script_name(&s.name, s.location)).collect::<Vec<_>>();
+    duplicate_names(FMObjType::Test, tests.iter(), errors);
+}
+
+fn find_table<'a>(stage: &'a Stage, name: &str) -> Option<&'a StagedTable> {
+    stage.tables.values().find(|t| t.name.value == name)
+}
+
+fn validate_table_occurrences(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    for occurrence in stage.table_occurrences.values() {
+        match &occurrence.data_source {
+            Some(data_source) => {
+                if !stage.data_sources.values().any(|ds| ds.name == data_source.value) {
+                    errors.push(CompileErr::UndefinedReference {
+                        construct: FMObjType::DataSource,
+                        token: data_source.clone(),
+                    });
+                }
+            }
+            None => {
+                if find_table(stage, &occurrence.base_table.value).is_none() {
+                    errors.push(CompileErr::UnknownTable { token: occurrence.base_table.clone() });
                 }
+            }
+        }
+    }
+}
+
+/* Checks `occurrence::field`, returning false if the occurrence is unknown.
+ * Fields on occurrences of external tables can't be checked here. */
+fn check_field_reference(stage: &Stage, occurrence: &Token, field: &Token, errors: &mut Vec<CompileErr>) -> bool {
+    let Some(staged) = stage.table_occurrences.values().find(|o| o.name.value == occurrence.value) else {
+        errors.push(CompileErr::UnknownTableOccurrence { token: occurrence.clone() });
+        return false;
+    };
+    if staged.data_source.is_some() {
+        return true;
+    }
+    if let Some(table) = find_table(stage, &staged.base_table.value) {
+        if !table.fields.values().any(|f| f.name.value == field.value) {
+            errors.push(CompileErr::UnknownField { token: field.clone() });
+        }
+    }
+    true
+}
+
+fn validate_relations(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    for relation in stage.relations.values() {
+        for criteria in &relation.criterias {
+            /* Every criteria in a compound relation joins the same pair. */
+            if criteria.occurrence1.value != relation.table1 {
+                errors.push(CompileErr::RelationCriteria { token: criteria.occurrence1.clone() });
+                continue;
+            }
+            if criteria.occurrence2.value != relation.table2 {
+                errors.push(CompileErr::RelationCriteria { token: criteria.occurrence2.clone() });
+                continue;
+            }
+            check_field_reference(stage, &criteria.occurrence1, &criteria.field1, errors);
+            check_field_reference(stage, &criteria.occurrence2, &criteria.field2, errors);
+        }
+
+        let sides = relation.allow_creation.iter()
+            .chain(&relation.delete_related)
+            .chain(relation.sort.iter().map(|sort| &sort.occurrence));
+        for side in sides {
+            if side.value != relation.table1 && side.value != relation.table2 {
+                errors.push(CompileErr::RelationOptionSide { token: side.clone() });
+            }
         }
+        for sort in &relation.sort {
+            if sort.occurrence.value == relation.table1 || sort.occurrence.value == relation.table2 {
+                check_field_reference(stage, &sort.occurrence, &sort.field, errors);
+            }
+        }
+    }
+
+    let graph = occurrence_graph(stage);
+    let name = |id: u32| stage.table_occurrences.values()
+        .find(|o| o.id as u32 == id)
+        .map(|o| o.name.value.clone())
+        .unwrap_or_default();
+    for (relation, cycle) in graph.cycles() {
+        let Some(criteria) = stage.relations.get(&(relation as u16)).and_then(|r| r.criterias.first()) else {
+            continue;
+        };
+        let mut path = cycle.iter().map(|id| name(*id)).collect::<Vec<_>>();
+        path.push(name(cycle[0]));
+        errors.push(CompileErr::RelationCycle { path, token: criteria.occurrence1.clone() });
+    }
+}
+
+/* Only what path finding needs: occurrence ids and which ones are joined. */
+fn occurrence_graph(stage: &Stage) -> RelationGraph {
+    let id = |name: &str| stage.table_occurrences.values()
+        .find(|o| o.name.value == name)
+        .map(|o| o.id as u32);
+    let mut graph = RelationGraph::new();
+    graph.nodes = stage.table_occurrences.values()
+        .map(|occurrence| TableOccurrence {
+            id: occurrence.id as u32,
+            name: occurrence.name.value.clone(),
+            base: TableReference { data_source: 0, table_id: 0 },
+            relations: stage.relations.values()
+                .filter_map(|r| match (id(&r.table1), id(&r.table2)) {
+                    (Some(a), Some(b)) if a == occurrence.id as u32 => Some((r.id, b)),
+                    (Some(a), Some(b)) if b == occurrence.id as u32 => Some((r.id, a)),
+                    _ => None,
+                })
+                .map(|(relation, other)| Relation { id: relation as u32, other_occurrence: other, criteria: vec![], options: RelationOptions::default() })
+                .collect(),
+        })
+        .collect();
+    graph
+}
+
+/* A lookup starts from an occurrence of the field's own table, and copies a
+ * field from an occurrence reachable from there. */
+fn validate_lookup(stage: &Stage, graph: &RelationGraph, table: &StagedTable, from: &Token, occurrence: &Token, field: &Token, errors: &mut Vec<CompileErr>) {
+    let Some(start) = stage.table_occurrences.values().find(|o| o.name.value == from.value) else {
+        errors.push(CompileErr::UnknownTableOccurrence { token: from.clone() });
+        return;
+    };
+    if start.data_source.is_some() || start.base_table.value != table.name.value {
+        errors.push(CompileErr::WrongBaseTable { table: table.name.value.clone(), token: from.clone() });
+        return;
+    }
+    if !check_field_reference(stage, occurrence, field, errors) {
+        return;
+    }
+    let end = stage.table_occurrences.values().find(|o| o.name.value == occurrence.value).unwrap();
+    if start.id == end.id || graph.get_path(start.id as u32, end.id as u32).is_none() {
+        errors.push(CompileErr::UnrelatedOccurrence { from: from.value.clone(), token: occurrence.clone() });
     }
-    Ok(())
 }
 
-pub fn validate_relation_references(schema: &mut Schema, bindings: &BindingList) -> Result<(), ValidationErr> {
-    for ref mut relation in &mut schema.relations {
-        let table_occ1 = match bindings.table_occurrences
-            .iter()
-            .find(|occ| occ.1 == relation.1.table1_name) {
-                Some(inner) => inner.0 as u16,
-                None => {
-                    return Err(ValidationErr::UnknownTableOccurrence(relation.1.table1_name.clone()))
+fn validate_fields(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    let graph = occurrence_graph(stage);
+    for table in stage.tables.values() {
+        for field in table.fields.values() {
+            if let StagedAutoEntryType::Lookup { from, occurrence, field: related } = &field.autoentry.definition {
+                validate_lookup(stage, &graph, table, from, occurrence, related, errors);
+            }
+            if let StagedFieldKind::Summary { field: summarised, .. } = &field.kind {
+                if !table.fields.values().any(|f| f.name.value == summarised.value) {
+                    errors.push(CompileErr::UnknownField { token: summarised.clone() });
                 }
-            };
-        let table_occ2 = match schema.table_occurrences
-            .iter()
-            .find(|occ| occ.1.name == relation.1.table2_name) {
-                Some(inner) => *inner.0 as u16,
-                None => {
-                    return Err(ValidationErr::UnknownTableOccurrence(relation.1.table2_name.clone()))
+            }
+        }
+    }
+    validate_calculation_cycles(stage, errors);
+    for field in stage.tables.values().flat_map(|t| t.fields.values()) {
+        for check in &field.validation.checks {
+            if let StagedValidationType::MemberOf(value_list) = check {
+                if !stage.value_lists.values().any(|v| v.name.value == value_list.value) {
+                    errors.push(CompileErr::UndefinedReference {
+                        construct: FMObjType::ValueList,
+                        token: value_list.clone(),
+                    });
                 }
+            }
+        }
+    }
+}
+
+/* A calculation field can't read itself, directly or through other
+ * calculation fields of this file. Each cycle is reported once, at the
+ * first field on it. */
+fn validate_calculation_cycles(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    let mut reads = HashMap::<(u16, u16), Vec<(u16, u16)>>::new();
+    for table in stage.tables.values() {
+        for field in table.fields.values() {
+            let StagedFieldKind::Calculation { code, .. } = &field.kind else {
+                continue;
             };
+            let fields = lex_text(code).into_iter()
+                .filter_map(|token| match token {
+                    CalcToken::FieldReference(occurrence, name) => Some((occurrence, name)),
+                    _ => None,
+                })
+                .filter_map(|(occurrence, name)| {
+                    let occurrence = stage.table_occurrences.values()
+                        .find(|o| o.name.value == occurrence && o.data_source.is_none())?;
+                    let table = find_table(stage, &occurrence.base_table.value)?;
+                    let field = table.fields.values().find(|f| f.name.value == name)?;
+                    Some((table.id, field.id))
+                })
+                .collect();
+            reads.insert((table.id, field.id), fields);
+        }
+    }
 
-        let table1 = schema.table_occurrences.get(&(table_occ1 as usize)).unwrap().base_table.top_id;
-        let table2 = schema.table_occurrences.get(&(table_occ2 as usize)).unwrap().base_table.top_id;
-        relation.1.table1 = table1;
-        relation.1.table2 = table2;
+    let name = |(table, field): (u16, u16)| format!("{}::{}",
+        stage.tables[&table].name.value, stage.tables[&table].fields[&field].name.value);
+    let mut starts = reads.keys().copied().collect::<Vec<_>>();
+    starts.sort();
+    for start in starts {
+        let mut seen = HashSet::new();
+        let mut stack = vec![vec![start]];
+        while let Some(path) = stack.pop() {
+            let last = *path.last().unwrap();
+            for next in reads.get(&last).into_iter().flatten() {
+                if *next == start {
+                    let mut path = path.iter().map(|id| name(*id)).collect::<Vec<_>>();
+                    path.push(name(start));
+                    let (table, field) = start;
+                    errors.push(CompileErr::CalculationCycle { path, token: stage.tables[&table].fields[&field].name.clone() });
+                    stack.clear();
+                    break;
+                }
+                /* A cycle through an earlier field was already reported. */
+                if *next > start && seen.insert(*next) {
+                    let mut longer = path.clone();
+                    longer.push(*next);
+                    stack.push(longer);
+                }
+            }
+        }
+    }
+}
 
+fn validate_value_lists(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    for value_list in stage.value_lists.values() {
+        if let StagedValueListDefinition::FromField { occurrence, field1, field2, from, .. } = &value_list.definition {
+            if check_field_reference(stage, occurrence, field1, errors) {
+                if let Some(field2) = field2 {
+                    check_field_reference(stage, occurrence, field2, errors);
+                }
+            }
+            if let Some(from) = from {
+                if !stage.table_occurrences.values().any(|o| o.name.value == from.value) {
+                    errors.push(CompileErr::UnknownTableOccurrence { token: from.clone() });
+                }
+            }
+        }
+    }
+}
 
-        for criteria in &mut relation.1.criterias {
-            let (field1_, field2_, comp) = match criteria {
-                RelationCriteria::ByName { field1, field2, comparison } => {
-                    (field1, field2, comparison)
-                },
-                RelationCriteria::ById { .. } => {
-                    continue
-                },
-            };
+/* Scripts in other files are only checked as far as their data source. */
+fn check_script_reference(stage: &Stage, data_source: Option<&Token>, script: &Token, errors: &mut Vec<CompileErr>) {
+    match data_source {
+        Some(data_source) => {
+            if !stage.data_sources.values().any(|ds| ds.name == data_source.value) {
+                errors.push(CompileErr::UndefinedReference {
+                    construct: FMObjType::DataSource,
+                    token: data_source.clone(),
+                });
+            }
+        }
+        None => {
+            if !stage.scripts.values().any(|s| s.name == script.value) {
+                errors.push(CompileErr::UndefinedReference {
+                    construct: FMObjType::Script,
+                    token: script.clone(),
+                });
+            }
+        }
+    }
+}
 
-            let field1_ = field1_.split("::").collect::<Vec<_>>()[1];
-            let field2_ = field2_.split("::").collect::<Vec<_>>()[1];
+fn validate_triggers(stage: &Stage, triggers: &[StagedTrigger], errors: &mut Vec<CompileErr>) {
+    for trigger in triggers {
+        check_script_reference(stage, trigger.data_source.as_ref(), &trigger.script, errors);
+    }
+}
 
-            let mut field1_idx = 0;
-            let mut field2_idx = 0;
-            for field in &schema.tables.get(&(table1 as usize)).unwrap().fields {
-                if field.1.name == *field1_ {
-                    field1_idx = *field.0;
+/* Checks the references made by layout objects, and that portals show an
+ * occurrence related to the one the layout is based on. */
+fn validate_layout_objects(stage: &Stage, base: &Token, objects: &[StagedLayoutObject], errors: &mut Vec<CompileErr>) {
+    for object in objects {
+        validate_triggers(stage, &object.triggers, errors);
+        match &object.kind {
+            StagedLayoutObjectKind::Field { occurrence, field } => {
+                check_field_reference(stage, occurrence, field, errors);
+            }
+            StagedLayoutObjectKind::Button { data_source, script, .. } => {
+                check_script_reference(stage, data_source.as_ref(), script, errors);
+            }
+            StagedLayoutObjectKind::Portal { occurrence, objects, .. } => {
+                if !stage.table_occurrences.values().any(|o| o.name.value == occurrence.value) {
+                    errors.push(CompileErr::UnknownTableOccurrence { token: occurrence.clone() });
+                } else if !stage.relations.values().any(|r|
+                    (r.table1 == base.value && r.table2 == occurrence.value)
+                    || (r.table2 == base.value && r.table1 == occurrence.value)) {
+                    errors.push(CompileErr::UnrelatedOccurrence { from: base.value.clone(), token: occurrence.clone() });
                 }
+                validate_layout_objects(stage, base, objects, errors);
             }
+            StagedLayoutObjectKind::TabControl { tabs } => {
+                for tab in tabs {
+                    validate_layout_objects(stage, base, &tab.objects, errors);
+                }
+            }
+        }
+    }
+}
 
-            for field in &schema.tables.get(&(table2 as usize)).unwrap().fields {
-                if field.1.name == *field2_ {
-                    field2_idx = *field.0;
+fn validate_layouts(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    validate_triggers(stage, &stage.triggers, errors);
+    for layout in stage.layouts.values() {
+        validate_triggers(stage, &layout.triggers, errors);
+        if !stage.table_occurrences.values().any(|o| o.name.value == layout.base_occurrence.value) {
+            errors.push(CompileErr::UnknownTableOccurrence { token: layout.base_occurrence.clone() });
+            continue;
+        }
+        for part in &layout.parts {
+            validate_layout_objects(stage, &layout.base_occurrence, &part.objects, errors);
+        }
+    }
+}
+
+fn validate_scripts(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    for script in stage.scripts.values() {
+        if script.instructions.iter().any(|i| matches!(i, ProtoInstruction::Assert { .. })) {
+            errors.push(CompileErr::InvalidAssert { token: script_name(&script.name, script.location) });
+        }
+    }
+    /* Step arguments carry no locations of their own, so unknown layouts are
+     * reported at the script that names them. */
+    for script in stage.scripts.values().chain(stage.tests.values()) {
+        for instruction in &script.instructions {
+            if let ProtoInstruction::GoToLayout { layout: ProtoLayoutSelection::UnresolvedName(name), .. } = instruction {
+                if !stage.layouts.values().any(|layout| layout.name.value == *name) {
+                    errors.push(CompileErr::UndefinedReference {
+                        construct: FMObjType::Layout,
+                        token: script_name(name, script.location),
+                    });
                 }
             }
-            *criteria = RelationCriteria::ById { field1: field1_idx as u16, field2: field2_idx as u16, comparison: *comp };
+            if let ProtoInstruction::PerformScript { script: ProtoScriptSelection::UnresolvedReference { data_source, script: name }, .. } = instruction {
+                if data_source.is_empty() && !stage.scripts.values().any(|script| script.name == *name) {
+                    errors.push(CompileErr::UndefinedReference {
+                        construct: FMObjType::Script,
+                        token: script_name(name, script.location),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/* Locals are checked in the order steps are written, so one set in an
+ * earlier branch or loop counts as assigned for everything after it.
+ * Parameters are assigned before the first step. */
+fn validate_variables(stage: &Stage, errors: &mut Vec<CompileErr>) {
+    for script in stage.scripts.values().chain(stage.tests.values()) {
+        let mut assigned = script.params.iter().map(|param| format!("${}", param)).collect::<HashSet<_>>();
+        for (instruction, location) in script.instructions.iter().zip(&script.step_locations) {
+            for calculation in step_calculations(instruction) {
+                for variable in local_variables(&calculation.0) {
+                    if assigned.insert(variable.clone()) {
+                        errors.push(CompileErr::UnassignedVariable { token: script_name(&variable, *location) });
+                    }
+                }
+            }
+            if let ProtoInstruction::SetVariable { name, .. } = instruction {
+                assigned.insert(name.clone());
+            }
+        }
+    }
+}
+
+fn step_calculations(instruction: &ProtoInstruction) -> Vec<&CalculationString> {
+    match instruction {
+        ProtoInstruction::PerformScript { script: ProtoScriptSelection::Calculation(script), args } => vec![script, args],
+        ProtoInstruction::PerformScript { args, .. } => vec![args],
+        ProtoInstruction::GoToLayout { layout: ProtoLayoutSelection::Calculation(layout), .. } => vec![layout],
+        ProtoInstruction::GoToRecordRequestPage { record: ProtoRecordSelection::ByCalc(record) } => vec![record],
+        ProtoInstruction::SetField { value, repetition, .. }
+            | ProtoInstruction::SetVariable { value, repetition, .. } => vec![value, repetition],
+        ProtoInstruction::If { condition }
+            | ProtoInstruction::ElseIf { condition }
+            | ProtoInstruction::ExitLoopIf { condition } => vec![condition],
+        ProtoInstruction::ExitScript { value } => vec![value],
+        ProtoInstruction::Assert { expr } => vec![expr],
+        _ => vec![],
+    }
+}
+
+/* `$locals` read by a calculation, skipping `$$globals` and text in quotes. */
+fn local_variables(code: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => { chars.next(); }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '$' => {
+                let global = chars.next_if_eq(&'$').is_some();
+                let mut name = String::from("$");
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if !global && name.len() > 1 {
+                    result.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cadlang::{backend::load_externs, error::CompileErr, lexer::lex, parser::{parse, parse_partial}};
+
+    use super::{validate, validate_externs};
+
+    fn errors(code: &str) -> Vec<String> {
+        let stage = parse(&lex(code).unwrap()).expect("Parsing failed.");
+        validate(&stage).iter().map(|e| e.to_string()).collect()
+    }
 
+    #[test]
+    fn valid_files() {
+        for path in ["test_data/cad_files/multi_file_solution/quotes.cad", "test_data/cad_files/multi_criteria_relation.cad"] {
+            let code = std::fs::read_to_string(path).unwrap();
+            assert_eq!(errors(&code), Vec::<String>::new(), "{}", path);
         }
-        
     }
 
-    Ok(())
+    #[test]
+    fn semantic_errors() {
+        let code = "
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+        member_of = Statuses,
+    },
+    field %2 id = {
+        datatype = Number,
+    },
 }
+table %2 Person = {
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Persn
+relation %1 = {
+    Person_occ::id == Person_occ2::id,
+    Person_occ::name == Person_occ2::id,
+    Person_occ2::id == Person_occ::id,
+}
+layout %1 People : People_occ = {
+}
+script %1 check = {
+    assert(|1 = 1|)
+}
+";
+        assert_eq!(errors(code), vec![
+            "Duplicate table name \"Person\" @ 11, 10",
+            "Duplicate field name \"id\" @ 7, 14",
+            "Invalid reference to table: Persn @ 14, 36",
+            "Invalid reference to field: name @ 17, 17",
+            "Found non-matching table \"Person_occ2\" reference in relation criteria. @ 18, 5",
+            "Undefined reference to valuelist \"Statuses\"",
+            "Invalid reference to table occurrence: People_occ @ 20, 20",
+            "Asserts can only be used in tests @ 22, 11",
+        ]);
+    }
 
+    #[test]
+    fn lookup_references() {
+        let code = "
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+    },
+    field %2 city = {
+        lookup = { start = Person_occ, related = Address_occ::city },
+    },
+    field %3 zip = {
+        lookup = { start = Address_occ, related = Address_occ::zip },
+    },
+    field %4 country = {
+        lookup = { start = Person_occ, related = Country_occ::name },
+    },
+    field %5 street = {
+        lookup = { start = Person_occ, related = Address_occ::stret },
+    },
+}
+table %2 Address = {
+    field %1 person_id = {
+        datatype = Number,
+    },
+    field %2 city = {
+        datatype = Text,
+    },
+}
+table %3 Country = {
+    field %1 name = {
+        datatype = Text,
+    },
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Address_occ : Address
+table_occurrence %3 Country_occ : Country
+relation %1 = Person_occ::id == Address_occ::person_id
+";
+        assert_eq!(errors(code), vec![
+            "Table occurrence Address_occ is not an occurrence of Person @ 10, 28",
+            "No relation between Person_occ and Country_occ @ 13, 50",
+            "Invalid reference to field: stret @ 16, 63",
+        ]);
+    }
+
+    #[test]
+    fn relation_graph_checks() {
+        let code = "
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+    },
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Person
+table_occurrence %3 Person_occ3 : Person
+relation %1 = {
+    Person_occ::id == Person_occ2::id,
+    allow_creation = Person_occ3,
+    sort = Person_occ2::nme,
+}
+relation %2 = Person_occ2::id == Person_occ3::id
+relation %3 = Person_occ3::id == Person_occ::id
+";
+        assert_eq!(errors(code), vec![
+            "Relation option names \"Person_occ3\", which is not joined by the relation. @ 12, 22",
+            "Invalid reference to field: nme @ 13, 25",
+            "Relation closes a cycle: Person_occ2 -> Person_occ -> Person_occ3 -> Person_occ2 @ 15, 15",
+        ]);
+    }
 
-pub fn validate(schema: &mut Schema, bindings: &BindingList) -> Result<(), ValidationErr> {
-    validate_table_occurrence_references(schema, bindings)?;
-    validate_layout_references(schema, bindings)?;
-    validate_relation_references(schema, bindings)?;
-    Ok(())
+    #[test]
+    fn summary_references() {
+        let code = "
+table %1 Invoice = {
+    field %1 amount = {
+        datatype = Number,
+    },
+    field %2 total = {
+        summary = total_of(amont),
+    },
+}
+";
+        assert_eq!(errors(code), vec![
+            "Invalid reference to field: amont @ 7, 28",
+        ]);
+    }
+
+    #[test]
+    fn calculation_cycles() {
+        let code = "
+table %1 Invoice = {
+    field %1 amount = {
+        calculation = |Invoice::amount + 1|,
+    },
+    field %2 doubled = {
+        calculation = |Invoice::tripled * 2|,
+    },
+    field %3 tripled = {
+        calculation = |Invoice::doubled * 3|,
+    },
+    field %4 total = {
+        calculation = |Invoice::doubled + Invoice::tripled|,
+    },
+}
+table_occurrence %1 Invoice : Invoice
+";
+        assert_eq!(errors(code), vec![
+            "Calculation refers back to itself: Invoice::amount -> Invoice::amount @ 3, 14",
+            "Calculation refers back to itself: Invoice::doubled -> Invoice::tripled -> Invoice::doubled @ 6, 14",
+        ]);
+    }
+
+    #[test]
+    fn layout_references() {
+        let code = "
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+    },
+}
+table_occurrence %1 Person_occ : Person
+table_occurrence %2 Person_occ2 : Person
+table_occurrence %3 Person_occ3 : Person
+relation %1 = Person_occ::id == Person_occ2::id
+layout %1 People : Person_occ = {
+    body = {
+        field Person_occ::nme = {},
+        button \"Go\" = { perform_script = go },
+        portal Person_occ2 = { on_object_enter = enter },
+        tab_control = {
+            tab \"Other\" = {
+                portal Person_occ3 = {},
+            },
+        },
+    },
+}
+";
+        assert_eq!(errors(code), vec![
+            "Invalid reference to field: nme @ 13, 27",
+            "Undefined reference to script \"go\"",
+            "Undefined reference to script \"enter\"",
+            "No relation between Person_occ and Person_occ3 @ 18, 24",
+        ]);
+    }
+
+    #[test]
+    fn extern_scripts() {
+        let code = "
+extern %1 Customers : \"customers.cad\"
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+    },
+}
+table_occurrence %1 Person_occ : Person
+layout %1 People : Person_occ = {
+    body = {
+        button \"Make\" = { perform_script = Customers::make_5_customers },
+        button \"Missing\" = { perform_script = Customers::make_6_customers },
+    },
+}
+";
+        let stage = parse(&lex(code).unwrap()).unwrap();
+        assert!(validate(&stage).is_empty());
+
+        let dir = std::path::Path::new("test_data/cad_files/multi_file_solution");
+        let externs = load_externs(&stage, dir);
+        assert_eq!(validate_externs(&stage, &externs).iter().map(|e| e.to_string()).collect::<Vec<_>>(), vec![
+            "Undefined reference to script \"make_6_customers\"",
+        ]);
+        assert_eq!(validate_externs(&stage, &HashMap::new()).len(), 2);
+    }
+
+    #[test]
+    fn script_variables() {
+        let code = "
+script %1 add(left, right) = {
+  set_variable($sum, |$left + $rigth|)
+  exit_script(|$sum & $$total & \"$literal\"|)
+}
+test %1 uses = {
+  if(|$count = 1|) {
+    set_variable($count, |$count + 1|)
+  }
+  perform_script(ad, |$count|)
+}
+";
+        assert_eq!(errors(code), vec![
+            "Undefined reference to script \"ad\"",
+            "Variable $rigth used before assignment @ 3, 3",
+            "Variable $count used before assignment @ 7, 3",
+        ]);
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let code = "
+table %1 Person = {
+    field %1 id = {
+        datatype = Number,
+    },
+    field %1 name = {
+        datatype = Text,
+    },
+}
+table %1 Other = {
+}
+";
+        let (_, errors) = parse_partial(&lex(code).unwrap());
+        assert!(matches!(&errors[..], [
+            CompileErr::DuplicateId { token: field, .. },
+            CompileErr::DuplicateId { token: table, .. },
+        ] if field.location.line == 6 && table.location.line == 10));
+        assert_eq!(errors[1].to_string(), "Duplicate table id %1 @ 10, 8");
+    }
 }
@@ -10,8 +10,11 @@ pub trait CalculationContext {
 
     fn get_var(&self, name: &str) -> Option<String>;
     fn get_global_var(&self, name: &str) -> Option<String>;
+    fn get_script_parameter(&self) -> String;
+    fn get_script_result(&self) -> String;
 
-    fn lookup_field(&self, reference: FieldReference) -> Result<Option<String>, String>;
+    /* Repetitions count from 1. */
+    fn lookup_field(&self, reference: FieldReference, repetition: u32) -> Result<Option<String>, String>;
 }
 
 pub struct DummyContext {
@@ -34,6 +37,8 @@ impl CalculationContext for DummyContext {
 
     fn get_var(&self, name: &str) -> Option<String> { todo!() }
     fn get_global_var(&self, name: &str) -> Option<String> { todo!() }
+    fn get_script_parameter(&self) -> String { todo!() }
+    fn get_script_result(&self) -> String { todo!() }
 
-    fn lookup_field(&self, reference: FieldReference) -> Result<Option<String>, String> { todo!() }
+    fn lookup_field(&self, reference: FieldReference, repetition: u32) -> Result<Option<String>, String> { todo!() }
 }
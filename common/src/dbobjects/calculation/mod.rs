@@ -4,6 +4,8 @@ mod parser;
 pub mod token;
 
 use super::calculation::token::{Token, Function, GetArgument}; use super::calculation::context::CalculationContext; use super::calculation::parser::*;
+use crate::cadlang::token::quote;
+use crate::dbobjects::reference::FieldReference;
 use crate::util::encoding_util::fm_string_decrypt;
 
 use serde::{Serialize, Deserialize};
@@ -38,11 +40,26 @@ pub struct CalculationString(pub String);
 
 impl Calculation {
 
+    /* A constant repetition of a field is stored in the field reference,
+     * anything else is written out as a subscript. */
     pub fn from_tokens(tokens: &Vec<Token>) -> Self {
-        Calculation(tokens.iter()
-            .map(|token| token.encode())
-            .flatten()
-            .collect())
+        let mut result = vec![];
+        let mut ptr = 0;
+        while ptr < tokens.len() {
+            match &tokens[ptr..] {
+                [Token::ResolvedFieldReference(reference), Token::OpenSquare, Token::Number(n), Token::CloseSquare, ..]
+                    if n.fract() == 0.0 && *n >= 1.0 && *n <= u16::MAX as f64 => {
+                    result.extend(token::encode_field_reference(reference, *n as u16));
+                    ptr += 4;
+                }
+                [token, ..] => {
+                    result.extend(token.encode());
+                    ptr += 1;
+                }
+                [] => unreachable!(),
+            }
+        }
+        Calculation(result)
     }
 
     pub fn from_text(code: &str) -> Self {
@@ -62,6 +79,10 @@ impl Calculation {
                     result.push(Token::CloseParen);
                     ptr += 1;
                 }
+                0x6 => {
+                    result.push(Token::SemiColon);
+                    ptr += 1;
+                }
                 12 => {
                     ptr += 1;
                     if self.0[ptr] != 19 {  } // error
@@ -73,12 +94,24 @@ impl Calculation {
                     if self.0[ptr] != 0 { } // error
                     ptr += 1;
                 }
+                0x7 => {
+                    result.push(Token::OpenSquare);
+                    ptr += 1;
+                }
+                0x8 => {
+                    result.push(Token::CloseSquare);
+                    ptr += 1;
+                }
                 0x16 => {
                     result.push(Token::ResolvedFieldReference(crate::dbobjects::reference::FieldReference {
                         data_source: 0,
-                        table_occurrence_id: 2,
-                        field_id: 2,
+                        table_occurrence_id: self.0[ptr + 6] as u32,
+                        field_id: self.0[ptr + 9] as u32,
                     }));
+                    let repetition = u16::from_be_bytes([self.0[ptr + 10], self.0[ptr + 11]]);
+                    if repetition > 1 {
+                        result.extend([Token::OpenSquare, Token::Number(repetition as f64), Token::CloseSquare]);
+                    }
                     ptr += 12;
                 }
                 0x2d => {
@@ -89,32 +122,25 @@ impl Calculation {
                     ptr += 1;
                     if self.0[ptr] == 0x9c {
                         ptr += 1;
-                        match self.0[ptr] {
-                            0x1d => {
-                                result.push(Token::Function(Function::Get(GetArgument::CurrentTime)))
-                            }
-                            0x20 => {
-                                result.push(Token::Function(Function::Get(GetArgument::AccountName)))
-                            }
-                            0x49 => {
-                                result.push(Token::Function(Function::Get(GetArgument::DocumentsPath)))
-                            }
-                            0x5d => {
-                                result.push(Token::Function(Function::Get(GetArgument::DocumentsPathListing)))
-                            }
-                            _ => {}
+                        match GetArgument::from_code(self.0[ptr]) {
+                            Some(arg) => result.push(Token::Function(Function::Get(arg))),
+                            None => eprintln!("unrecognized Get() argument."),
                         }
                     }
+                    ptr += 1;
                 }
                 0x9d => {
                     result.push(Token::Function(token::Function::Acos));
+                    ptr += 1;
                 }
                 0xfb => {
                     ptr += 1;
                     match self.0[ptr] {
                         0x3 => { result.push(Token::Function(Function::Char)) }
+                        0x4c => { result.push(Token::Function(Function::JSONGetElement)) }
                         _ => eprintln!("unrecognized intrinsic.")
                     }
+                    ptr += 1;
                 }
                 0x10 => {
                     /* decode number */
@@ -131,7 +157,9 @@ impl Calculation {
                     /* Processing String */
                     ptr += 1;
                     let len = self.0[ptr] as usize;
+                    ptr += 1;
                     result.push(Token::String(String::from(&fm_string_decrypt(&self.0[ptr..ptr+len]))));
+                    ptr += len;
                 }
                 0x1a => {
                     /* decode variable */
@@ -183,16 +211,84 @@ impl Calculation {
                 }
                 0x50 => {
                     result.push(Token::Concatenate);
+                    ptr += 1;
                 }
                 _ => {
+                    ptr += 1;
                 }
             }
         }
         result
     }
 
+    /* The fields the calculation reads, in order of appearance. */
+    pub fn field_references(&self) -> Vec<FieldReference> {
+        self.lex().into_iter()
+            .filter_map(|token| match token {
+                Token::ResolvedFieldReference(reference) => Some(reference),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /* Renders the calculation as it is written in .cad files. Spaces are not
+     * kept when decoding, so operators are spaced out uniformly. Field
+     * references are named by `field_name`. */
+    pub fn to_text(&self, field_name: impl Fn(&FieldReference) -> String) -> String {
+        let tokens = self.lex();
+        let operator = |token: &Token| match token {
+            Token::Equal => Some("=="),
+            Token::NotEqual => Some("!="),
+            Token::Less => Some("<"),
+            Token::LessEqual => Some("<="),
+            Token::Greater => Some(">"),
+            Token::GreaterEqual => Some(">="),
+            Token::Add => Some("+"),
+            Token::Subtract => Some("-"),
+            Token::Multiply => Some("*"),
+            Token::Divide => Some("/"),
+            Token::Concatenate => Some("&"),
+            _ => None,
+        };
+        let mut result = String::new();
+        for (i, token) in tokens.iter().enumerate() {
+            /* Negation shares its bytecode with subtraction, so a minus with
+             * no left operand is written as a prefix. */
+            let prefix = i == 0
+                || matches!(tokens[i - 1], Token::OpenParen | Token::SemiColon | Token::OpenSquare)
+                || operator(&tokens[i - 1]).is_some();
+            match (token, operator(token)) {
+                (Token::Subtract, _) if prefix => result.push('-'),
+                (_, Some(operator)) => {
+                    result.push(' ');
+                    result.push_str(operator);
+                    result.push(' ');
+                }
+                (Token::Negate, _) => result.push('!'),
+                (Token::SemiColon, _) => result.push_str("; "),
+                (Token::OpenParen, _) => result.push('('),
+                (Token::CloseParen, _) => result.push(')'),
+                (Token::OpenSquare, _) => result.push('['),
+                (Token::CloseSquare, _) => result.push(']'),
+                (Token::Number(n), _) => result.push_str(&n.to_string()),
+                (Token::String(text), _) => result.push_str(&quote(text)),
+                (Token::Variable(name) | Token::Global(name) | Token::Identifier(name), _) => result.push_str(name),
+                (Token::Function(Function::Get(arg)), _) => result.push_str(&format!("Get({:?})", arg)),
+                (Token::Function(function), _) => result.push_str(function.name()),
+                (Token::ResolvedFieldReference(reference), _) => result.push_str(&field_name(reference)),
+                (Token::FieldReference(occurrence, field), _) => result.push_str(&format!("{}::{}", occurrence, field)),
+                (Token::Space, _) => result.push(' '),
+                _ => {}
+            }
+        }
+        result
+    }
+
     pub fn eval<T: CalculationContext>(&self, ctx: &T) -> Result<String, String> {
         let tokens = self.lex();
+        if tokens.is_empty() {
+            return Ok(String::new());
+        }
         let ast = parser::Parser::new(&tokens).parse();
         match ast.eval(ctx) {
             Ok(inner) => Ok(inner.as_text()),
@@ -220,7 +316,7 @@ impl Expr {
 
             Expr::FieldReference(reference) => {
                 // Query database manager
-                let res = ctx.lookup_field(reference.clone());
+                let res = ctx.lookup_field(reference.clone(), 1);
                 let val = match res {
                     Ok(inner) => inner,
                     Err(e) => return Err(format!("Field not found: {:?}", reference))
@@ -250,6 +346,11 @@ impl Expr {
                     Token::Equal => {
                         Ok(Value::Text((left_val.as_number() == right_val.as_number()).to_string()))
                     },
+                    Token::NotEqual => Ok(Value::Text((left_val.as_number() != right_val.as_number()).to_string())),
+                    Token::Less => Ok(Value::Text((left_val.as_number() < right_val.as_number()).to_string())),
+                    Token::LessEqual => Ok(Value::Text((left_val.as_number() <= right_val.as_number()).to_string())),
+                    Token::Greater => Ok(Value::Text((left_val.as_number() > right_val.as_number()).to_string())),
+                    Token::GreaterEqual => Ok(Value::Text((left_val.as_number() >= right_val.as_number()).to_string())),
                     Token::Concatenate => Ok(Value::Text(left_val.as_text() + &right_val.as_text())), // Concatenation
                     _ => Err("Unsupported binary operator".to_string()),
                 }
@@ -269,10 +370,38 @@ impl Expr {
                 match name.as_str() {
                     "Abs" => arg_vals.get(0).map(|v| Ok(Value::Number(v.as_number().abs()))).unwrap_or(Err("Missing argument for Abs".to_string())),
                     "UpperCase" => arg_vals.get(0).map(|v| Ok(Value::Text(v.as_text().to_uppercase()))).unwrap_or(Err("Missing argument for UpperCase".to_string())),
+                    "JSONGetElement" => match &arg_vals[..] {
+                        [json, key] => Ok(Value::Text(json_get_element(&json.as_text(), &key.as_text()))),
+                        _ => Err("JSONGetElement takes a JSON value and a key".to_string()),
+                    },
                     _ => Err(format!("Unknown function: {}", name)),
                 }
             }
 
+            Expr::Get(arg) => match arg {
+                GetArgument::AccountName => Ok(Value::Text(ctx.get_account_name())),
+                GetArgument::ScriptParameter => Ok(Value::Text(ctx.get_script_parameter())),
+                GetArgument::ScriptResult => Ok(Value::Text(ctx.get_script_result())),
+                _ => Err(format!("Unsupported Get argument: {:?}", arg)),
+            },
+
+            /* Repetitions count from 1, and the first is the field or variable
+             * itself. */
+            Expr::Subscript { array, index } if matches!(**array, Expr::FieldReference(..) | Expr::Variable(..)) => {
+                let repetition = index.eval(ctx)?.as_number() as u32;
+                if repetition < 1 {
+                    return Ok(Value::Text(String::new()));
+                }
+                let value = match &**array {
+                    Expr::FieldReference(reference) => ctx.lookup_field(reference.clone(), repetition)
+                        .map_err(|_| format!("Field not found: {:?}", reference))?,
+                    Expr::Variable(name) if repetition == 1 => ctx.get_var(name),
+                    Expr::Variable(name) => ctx.get_var(&format!("{}[{}]", name, repetition)),
+                    _ => unreachable!(),
+                };
+                Ok(Value::Text(value.unwrap_or_default()))
+            }
+
             Expr::Subscript { array, index } => {
                 let array_val = array.eval(ctx)?.as_text(); // Assume arrays are stored as text
                 let index_val = index.eval(ctx)?.as_number() as usize;
@@ -284,6 +413,26 @@ impl Expr {
     }
 }
 
+/* Element of a JSON object or array at a dotted key path such as
+ * "address.lines[0]". Missing elements and invalid JSON give an empty string,
+ * and strings are returned without their quotes. */
+fn json_get_element(json: &str, key: &str) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(json) else {
+        return String::new();
+    };
+    for part in key.split(['.', '[']).filter(|part| !part.is_empty()) {
+        value = match part.strip_suffix(']').map(|index| index.parse::<usize>()) {
+            Some(Ok(index)) => value.get(index).cloned(),
+            _ => value.get(part).cloned(),
+        }.unwrap_or(serde_json::Value::Null);
+    }
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text,
+        other => other.to_string(),
+    }
+}
+
 pub fn lex_text(code: &str) -> Vec<Token> {
     let mut result = vec![];
     let mut iter = code.chars().into_iter().peekable();
@@ -314,6 +463,8 @@ pub fn lex_text(code: &str) -> Vec<Token> {
                 while let Some(c) = iter.next() {
                     if c == '"' {
                         break;
+                    } else if c == '\\' {
+                        buffer.extend(iter.next());
                     } else {
                         buffer.push(c)
                     }
@@ -328,6 +479,22 @@ pub fn lex_text(code: &str) -> Vec<Token> {
                 result.push(Token::Equal);
                 iter.next();
             }
+            '<' => {
+                if iter.peek() == Some(&'=') {
+                    result.push(Token::LessEqual);
+                    iter.next();
+                } else {
+                    result.push(Token::Less);
+                }
+            }
+            '>' => {
+                if iter.peek() == Some(&'=') {
+                    result.push(Token::GreaterEqual);
+                    iter.next();
+                } else {
+                    result.push(Token::Greater);
+                }
+            }
             '(' => {
                 result.push(Token::OpenParen);
             }
@@ -349,6 +516,12 @@ pub fn lex_text(code: &str) -> Vec<Token> {
             '&' => {
                 result.push(Token::Concatenate);
             }
+            '[' => {
+                result.push(Token::OpenSquare);
+            }
+            ']' => {
+                result.push(Token::CloseSquare);
+            }
             '!' => {
                 if *iter.peek().unwrap() == '=' {
                     result.push(Token::NotEqual);
@@ -395,6 +568,26 @@ pub fn lex_text(code: &str) -> Vec<Token> {
 
                 if !tmp.is_empty() {
                     result.push(Token::FieldReference(tmp, buffer.clone()));
+                } else if buffer == "Get" {
+                    /* Get() takes a bare name rather than an expression, so it
+                     * is folded into one token when the name is known. */
+                    let mut lookahead = iter.clone();
+                    let arg = match lookahead.next() {
+                        Some('(') => {
+                            let name = lookahead.by_ref().take_while(|c| *c != ')').collect::<String>();
+                            GetArgument::from_name(name.trim())
+                        }
+                        _ => None,
+                    };
+                    match arg {
+                        Some(arg) => {
+                            result.push(Token::Function(Function::Get(arg)));
+                            iter = lookahead;
+                        }
+                        None => result.push(Token::Identifier(buffer.clone())),
+                    }
+                } else if let Some(function) = Function::from_name(&buffer) {
+                    result.push(Token::Function(function));
                 } else {
                     result.push(Token::Identifier(buffer.clone()));
                 }
@@ -413,11 +606,12 @@ pub fn lex_text(code: &str) -> Vec<Token> {
 
 #[cfg(test)]
 mod tests {
-    use super::token::Token;
+    use super::token::{Function, GetArgument, Token};
     use super::Calculation;
     use super::lex_text;
     use super::parser::*;
     use super::context::DummyContext;
+    use crate::dbobjects::reference::FieldReference;
 
     use crate::dbobjects::schema::Schema;
     #[test]
@@ -490,12 +684,44 @@ mod tests {
         assert_eq!(lex_text(code), expected);
     }
 
+    #[test]
+    fn repetition_encoding() {
+        let reference = Token::ResolvedFieldReference(FieldReference { data_source: 0, table_occurrence_id: 1, field_id: 2 });
+        let constant = vec![reference.clone(), Token::OpenSquare, Token::Number(3.0), Token::CloseSquare];
+        let calc = Calculation::from_tokens(&constant);
+        assert_eq!(calc.0, vec![0x16, 0x4, 0x4, 0x3, 0xD0, 0x0, 1, 0x2, 0x1, 2, 0x0, 3]);
+        assert_eq!(calc.lex(), constant);
+
+        let calculated = vec![reference, Token::OpenSquare, Token::Variable(String::from("$i")), Token::CloseSquare];
+        assert_eq!(Calculation::from_tokens(&calculated).lex(), calculated);
+        assert_eq!(lex_text("$x[2]"), vec![Token::Variable(String::from("$x")), Token::OpenSquare, Token::Number(2.0), Token::CloseSquare]);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let reference = FieldReference { data_source: 0, table_occurrence_id: 1, field_id: 2 };
+        let tokens = vec![Token::ResolvedFieldReference(reference), Token::Space, Token::GreaterEqual, Token::Space,
+            Token::Number(3.0), Token::Space, Token::Concatenate, Token::Space, Token::String(String::from("a \"b\""))];
+        let text = Calculation::from_tokens(&tokens).to_text(|_| String::from("People::id"));
+        assert_eq!(text, "People::id >= 3 & \"a \\\"b\\\"\"");
+        assert_eq!(lex_text(&text)[2..], tokens[2..]);
+    }
+
     #[test]
     fn addition_eval() {
         let code = Calculation::from_text("3 + 12");
         println!("COde: {:?}", code);
         assert_eq!(code.eval(&DummyContext::new()).unwrap(), "15".to_string());
     }
+
+    #[test]
+    fn json_get_element_eval() {
+        assert_eq!(lex_text("Get(ScriptParameter)"), vec![Token::Function(Function::Get(GetArgument::ScriptParameter))]);
+        let code = Calculation::from_text("JSONGetElement(\"{\\\"a\\\": {\\\"b\\\": [\\\"x\\\", 7]}}\"; \"a.b[1]\")");
+        assert_eq!(code.eval(&DummyContext::new()).unwrap(), "7");
+        let code = Calculation::from_text("JSONGetElement(\"{\\\"a\\\": \\\"text\\\"}\"; \"a\")");
+        assert_eq!(code.eval(&DummyContext::new()).unwrap(), "text");
+    }
 }
 
 
@@ -27,6 +27,7 @@ pub enum Expr {
         name: String,
         args: Vec<Expr>,
     },
+    Get(GetArgument),
     Subscript {
         array: Box<Expr>,
         index: Box<Expr>,
@@ -140,37 +141,17 @@ impl<'a> Parser<'a> {
         match self.tokens.next() {
             Some(Token::Number(n)) => Expr::Number(*n),
             Some(Token::String(s)) => Expr::String(s.to_string()),
-            Some(Token::Variable(v)) => Expr::Variable(v.to_string()),
+            Some(Token::Variable(v)) => self.parse_repetition(Expr::Variable(v.to_string())),
             Some(Token::Global(g)) => Expr::Global(g.to_string()),
-            Some(Token::Identifier(name)) => {
-                if let Some(Token::OpenParen) = self.tokens.peek() {
-                    self.tokens.next(); // Consume '('
-                    let mut args = Vec::new();
-                    while let Some(token) = self.tokens.peek() {
-                        if matches!(token, Token::CloseParen) {
-                            break;
-                        }
-                        args.push(self.parse_expression());
-                        if matches!(self.tokens.peek(), Some(Token::SemiColon)) {
-                            self.tokens.next(); // Consume ';'
-                        }
-                    }
-                    self.tokens.next(); // Consume ')'
-                    Expr::FunctionCall {
-                        name: name.clone(),
-                        args,
-                    }
-                } else {
-                    // Error
-                    todo!()
-                }
-            }
+            Some(Token::Identifier(name)) => self.parse_call(name),
+            Some(Token::Function(Function::Get(arg))) => Expr::Get(arg.clone()),
+            Some(Token::Function(function)) => self.parse_call(function.name()),
             Some(Token::OpenParen) => {
                 let expr = self.parse_expression();
                 self.tokens.next(); // Consume ')'
                 expr
             }
-            Some(Token::ResolvedFieldReference(reference)) => Expr::FieldReference(reference.clone()),
+            Some(Token::ResolvedFieldReference(reference)) => self.parse_repetition(Expr::FieldReference(reference.clone())),
             Some(Token::OpenSquare) => {
                 let array = self.parse_expression();
                 self.tokens.next(); // Consume '['
@@ -184,6 +165,44 @@ impl<'a> Parser<'a> {
             Some(_) | None => panic!("Unexpected token or EOF"),
         }
     }
+
+    /* Fields and variables take a repetition as `[n]`. */
+    fn parse_repetition(&mut self, target: Expr) -> Expr {
+        if !matches!(self.tokens.peek(), Some(Token::OpenSquare)) {
+            return target;
+        }
+        self.tokens.next(); // Consume '['
+        let index = self.parse_expression();
+        self.tokens.next(); // Consume ']'
+        Expr::Subscript {
+            array: Box::new(target),
+            index: Box::new(index),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> Expr {
+        if let Some(Token::OpenParen) = self.tokens.peek() {
+            self.tokens.next(); // Consume '('
+            let mut args = Vec::new();
+            while let Some(token) = self.tokens.peek() {
+                if matches!(token, Token::CloseParen) {
+                    break;
+                }
+                args.push(self.parse_expression());
+                if matches!(self.tokens.peek(), Some(Token::SemiColon)) {
+                    self.tokens.next(); // Consume ';'
+                }
+            }
+            self.tokens.next(); // Consume ')'
+            Expr::FunctionCall {
+                name: name.to_string(),
+                args,
+            }
+        } else {
+            // Error
+            todo!()
+        }
+    }
 }
 
 #[cfg(test)]
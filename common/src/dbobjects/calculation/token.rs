@@ -8,6 +8,40 @@ pub enum GetArgument {
     AccountName,
     DocumentsPath,
     DocumentsPathListing,
+    ScriptParameter,
+    ScriptResult,
+}
+
+impl GetArgument {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "CurrentTime" => Some(Self::CurrentTime),
+            "AccountName" => Some(Self::AccountName),
+            "DocumentsPath" => Some(Self::DocumentsPath),
+            "DocumentsPathListing" => Some(Self::DocumentsPathListing),
+            "ScriptParameter" => Some(Self::ScriptParameter),
+            "ScriptResult" => Some(Self::ScriptResult),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::CurrentTime => 0x1d,
+            Self::AccountName => 0x20,
+            Self::DocumentsPath => 0x49,
+            Self::DocumentsPathListing => 0x5d,
+            Self::ScriptParameter => 0x16,
+            Self::ScriptResult => 0x52,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        [Self::CurrentTime, Self::AccountName, Self::DocumentsPath, Self::DocumentsPathListing,
+            Self::ScriptParameter, Self::ScriptResult]
+            .into_iter()
+            .find(|arg| arg.code() == code)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -20,9 +54,39 @@ pub enum Function {
     Acos,
     Asin,
     Atan,
+    JSONGetElement,
     Get(GetArgument),
 }
 
+impl Function {
+    /* Functions written as a name and argument list. Get() is lexed
+     * separately as its argument is part of the function. */
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Char" => Some(Self::Char),
+            "Abs" => Some(Self::Abs),
+            "Acos" => Some(Self::Acos),
+            "JSONGetElement" => Some(Self::JSONGetElement),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Char => "Char",
+            Self::Abs => "Abs",
+            Self::Cos => "Cos",
+            Self::Sin => "Sin",
+            Self::Tan => "Tan",
+            Self::Acos => "Acos",
+            Self::Asin => "Asin",
+            Self::Atan => "Atan",
+            Self::JSONGetElement => "JSONGetElement",
+            Self::Get(..) => "Get",
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     Variable,
@@ -136,6 +200,13 @@ pub enum Token {
     Space,
 }
 
+/* The last two bytes hold the repetition, counting from 1. */
+pub fn encode_field_reference(reference: &FieldReference, repetition: u16) -> Vec<u8> {
+    let (table, field) = (reference.table_occurrence_id, reference.field_id);
+    let [high, low] = repetition.to_be_bytes();
+    vec![0x16, 0x4, 0x4, 0x3, 0xD0, 0x0, table as u8, 0x2, 0x1, field as u8, high, low]
+}
+
 impl Token {
     pub fn encode(&self) -> Vec<u8> {
         match self {
@@ -151,19 +222,63 @@ impl Token {
             Self::Multiply => vec![0x27],
             Self::Divide => vec![0x28],
             Self::Concatenate => vec![0x50],
-            Self::ResolvedFieldReference(reference) => {
-                let (ds, table, field) = (reference.data_source, reference.table_occurrence_id, reference.field_id);
-                vec![0x16, 0x4, 0x4, 0x3, 0xD0, 0x0, table as u8, 0x2, 0x1, field as u8, 0x0, 0x1]
-            }
+            Self::ResolvedFieldReference(reference) => encode_field_reference(reference, 1),
             Self::Number(n) => {
                 vec![16, 2, 0, 1, 0, 16, 0, 0, 0, (*n) as u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 32]
             }
             Self::Equal => {
                 vec![0x44]
             }
+            Self::Less => vec![0x41],
+            Self::LessEqual => vec![0x43],
+            Self::NotEqual => vec![0x46],
+            Self::GreaterEqual => vec![0x47],
+            Self::Greater => vec![0x49],
             Self::Space => {
                 vec![12, 19, 1, 122, 0]
             }
+            Self::String(text) => {
+                /* The length is a single byte. Longer text is written as a
+                 * bracketed concatenation of pieces that each fit, split on
+                 * char boundaries so every piece still decodes on its own. */
+                let mut pieces = vec![];
+                let mut start = 0;
+                for (offset, c) in text.char_indices() {
+                    if offset + c.len_utf8() - start > u8::MAX as usize {
+                        pieces.push(&text[start..offset]);
+                        start = offset;
+                    }
+                }
+                pieces.push(&text[start..]);
+
+                let encode_piece = |piece: &str| {
+                    let mut result = vec![0x13, piece.len() as u8];
+                    result.extend(fm_string_encrypt(piece));
+                    result
+                };
+                if pieces.len() == 1 {
+                    return encode_piece(pieces[0]);
+                }
+                let mut result = vec![0x4];
+                for (i, piece) in pieces.iter().enumerate() {
+                    if i > 0 {
+                        result.push(0x50);
+                    }
+                    result.extend(encode_piece(piece));
+                }
+                result.push(0x5);
+                result
+            }
+            Self::OpenParen => vec![0x4],
+            Self::CloseParen => vec![0x5],
+            Self::OpenSquare => vec![0x7],
+            Self::CloseSquare => vec![0x8],
+            Self::SemiColon => vec![0x6],
+            Self::Function(Function::Abs) => vec![0x2d],
+            Self::Function(Function::Acos) => vec![0x9d],
+            Self::Function(Function::Char) => vec![0xfb, 0x3],
+            Self::Function(Function::JSONGetElement) => vec![0xfb, 0x4c],
+            Self::Function(Function::Get(arg)) => vec![0x9b, 0x9c, arg.code()],
             _ => todo!("{:?}", self)
         }
     }
@@ -1,14 +1,16 @@
+use serde::{Serialize, Deserialize};
 
 use std::collections::HashMap;
 use std::path::Path;
 use crate::{cadlang, hbam2};
 
 use super::schema::Schema;
-use super::scripting::script::Script;
+use super::scripting::{script::Script, trigger::ScriptTrigger};
 use super::layout::Layout;
 use super::data_source::*;
+use super::reference::FieldReference;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct File {
     pub name: String,
     pub schema: Schema,
@@ -16,10 +18,31 @@ pub struct File {
     pub data_sources: Vec<DataSource>,
     pub scripts: Vec<Script>,
     pub tests: Vec<Script>,
+    pub triggers: Vec<ScriptTrigger>,
+    #[serde(skip)]
     pub working_dir: String,
 }
 
 impl File {
+    /* Names a field reference as `Occurrence::field` for calculations. The
+     * data source is taken from the occurrence, as compiled references don't
+     * carry it. Unknown ids are written as placeholders. */
+    pub fn field_reference_name(&self, reference: &FieldReference, externs: &HashMap<usize, File>) -> String {
+        let node = self.schema.relation_graph.nodes.iter()
+            .find(|node| node.id == reference.table_occurrence_id);
+        let field = node
+            .and_then(|node| if node.base.data_source == 0 {
+                Some(self)
+            } else {
+                externs.get(&(node.base.data_source as usize))
+            }.map(|file| (node, file)))
+            .and_then(|(node, file)| file.schema.tables.iter().find(|table| table.id == node.base.table_id))
+            .and_then(|table| table.fields.get(&reference.field_id));
+        format!("{}::{}",
+            node.map(|node| node.name.clone()).unwrap_or_else(|| format!("occurrence_{}", reference.table_occurrence_id)),
+            field.map(|field| field.name.clone()).unwrap_or_else(|| format!("field_{}", reference.field_id)))
+    }
+
     pub fn to_cad(&self) -> String {
         let mut buffer = String::new();
 
@@ -45,6 +68,9 @@ impl File {
         buffer.push_str(&self.layouts.iter()
             .map(|layout| layout.to_cad(self))
             .collect::<Vec<String>>().join(&"\n"));
+        for trigger in &self.triggers {
+            buffer.push_str(&format!("\ntrigger {}", trigger.to_cad(self)));
+        }
         buffer
     }
 
@@ -73,6 +99,9 @@ impl File {
         buffer.push_str(&self.layouts.iter()
             .map(|layout| layout.to_cad(self))
             .collect::<Vec<String>>().join(&"\n"));
+        for trigger in &self.triggers {
+            buffer.push_str(&format!("\ntrigger {}", trigger.to_cad(self)));
+        }
         buffer.push_str("\n\n");
 
         buffer.push_str(&self.scripts.iter()
@@ -96,4 +125,23 @@ mod tests {
         let file = cadlang::compiler::compile_to_file(Path::new("test_data/cad_files/multi_file_solution/quotes.cad")).unwrap();
         println!("{}", file.to_cad());
     }
+
+    #[test]
+    fn json_roundtrip_test() {
+        let mut ctx = hbam2::Context::new();
+        let file = ctx.get_schema_contents("test_data/fmp_files/relation.fmp12");
+        let json = serde_json::to_value(&file).unwrap();
+
+        for key in ["name", "schema", "layouts", "data_sources", "scripts", "tests"] {
+            assert!(json.get(key).is_some(), "missing top level key: {}", key);
+        }
+        assert!(json.get("working_dir").is_none());
+        assert_eq!(json["schema"]["relation_graph"]["nodes"][0]["name"], "blank");
+        assert_eq!(json["schema"]["relation_graph"]["nodes"][0]["relations"][0]["criteria"][0]["comparison"], "Equal");
+        assert_eq!(json["data_sources"][0]["dstype"], "FileMaker");
+
+        let mut decoded: File = serde_json::from_value(json).unwrap();
+        decoded.working_dir = file.working_dir.clone();
+        assert_eq!(decoded, file);
+    }
 }
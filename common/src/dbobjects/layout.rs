@@ -1,25 +1,167 @@
+use serde::{Serialize, Deserialize};
 
-use super::{file::File, reference::TableOccurrenceReference};
+use super::{file::File, reference::{FieldReference, ScriptReference, TableOccurrenceReference}, scripting::trigger::ScriptTrigger};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Layout {
     pub id: u32,
     pub name: String,
     pub occurrence: TableOccurrenceReference,
+    pub parts: Vec<LayoutPart>,
+    pub triggers: Vec<ScriptTrigger>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct LayoutAttribute {
+/* Parts are ordered top to bottom. */
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum LayoutPartKind {
+    Header,
+    Body,
+    Footer,
+}
+
+/* Objects are kept in the order they are drawn, back to front. */
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LayoutPart {
+    pub kind: LayoutPartKind,
+    pub height: u32,
+    pub objects: Vec<LayoutObject>,
+}
+
+/* Positions are in points, relative to the top left of the part the object
+ * sits in. */
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Bounds {
+    pub top: u32,
+    pub left: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum LayoutObjectKind {
+    Field { field: FieldReference },
+    Button { label: String, script: ScriptReference },
+    /* Shows the records of `occurrence` related to the current one. */
+    Portal { occurrence: TableOccurrenceReference, rows: u32, objects: Vec<LayoutObject> },
+    TabControl { tabs: Vec<LayoutTab> },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LayoutObject {
+    pub bounds: Bounds,
+    pub kind: LayoutObjectKind,
+    pub triggers: Vec<ScriptTrigger>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LayoutTab {
+    pub name: String,
+    pub objects: Vec<LayoutObject>,
+}
+
+impl LayoutPartKind {
+    pub fn to_cad(&self) -> &'static str {
+        match self {
+            Self::Header => "header",
+            Self::Body => "body",
+            Self::Footer => "footer",
+        }
+    }
+}
+
+fn occurrence_name(file: &File, occurrence_id: u32) -> String {
+    file.schema.relation_graph.nodes.iter()
+        .find(|oc| oc.id == occurrence_id)
+        .map(|oc| oc.name.clone())
+        .unwrap_or_else(|| format!("occurrence_{}", occurrence_id))
+}
+
+/* Fields of tables in other files are written by id, as only this file is
+ * at hand. */
+fn field_name(file: &File, field: &FieldReference) -> String {
+    let occurrence = file.schema.relation_graph.nodes.iter()
+        .find(|oc| oc.id == field.table_occurrence_id);
+    let name = occurrence
+        .filter(|oc| oc.base.data_source == 0)
+        .and_then(|oc| file.schema.tables.iter().find(|t| t.id == oc.base.table_id))
+        .and_then(|table| table.fields.get(&field.field_id))
+        .map(|f| f.name.clone())
+        .unwrap_or_else(|| format!("field_{}", field.field_id));
+    format!("{}::{}", occurrence_name(file, field.table_occurrence_id), name)
+}
+
+pub(crate) fn script_name(file: &File, script: &ScriptReference) -> String {
+    match (script.data_source, file.scripts.iter().find(|s| s.id == script.script_id)) {
+        (0, Some(found)) => found.name.clone(),
+        _ => format!("script_{}", script.script_id),
+    }
+}
+
+impl Bounds {
+    fn to_cad(self, indent: &str) -> String {
+        format!("{indent}top = {},\n{indent}left = {},\n{indent}width = {},\n{indent}height = {},\n",
+            self.top, self.left, self.width, self.height)
+    }
+}
+
+impl LayoutObject {
+    pub fn to_cad(&self, file: &File, depth: usize) -> String {
+        let indent = "    ".repeat(depth);
+        let inner = "    ".repeat(depth + 1);
+        let mut buffer = match &self.kind {
+            LayoutObjectKind::Field { field } => format!("{}field {} = {{\n", indent, field_name(file, field)),
+            LayoutObjectKind::Button { label, .. } => format!("{}button \"{}\" = {{\n", indent, label),
+            LayoutObjectKind::Portal { occurrence, .. } => format!("{}portal {} = {{\n",
+                indent, occurrence_name(file, occurrence.table_occurrence_id)),
+            LayoutObjectKind::TabControl { .. } => format!("{}tab_control = {{\n", indent),
+        };
+        buffer.push_str(&self.bounds.to_cad(&inner));
+        for trigger in &self.triggers {
+            buffer.push_str(&format!("{}{},\n", inner, trigger.to_cad(file)));
+        }
+        match &self.kind {
+            LayoutObjectKind::Field { .. } => {}
+            LayoutObjectKind::Button { script, .. } => {
+                buffer.push_str(&format!("{}perform_script = {},\n", inner, script_name(file, script)));
+            }
+            LayoutObjectKind::Portal { rows, objects, .. } => {
+                buffer.push_str(&format!("{}rows = {},\n", inner, rows));
+                for object in objects {
+                    buffer.push_str(&object.to_cad(file, depth + 1));
+                }
+            }
+            LayoutObjectKind::TabControl { tabs } => {
+                for tab in tabs {
+                    buffer.push_str(&format!("{}tab \"{}\" = {{\n", inner, tab.name));
+                    for object in &tab.objects {
+                        buffer.push_str(&object.to_cad(file, depth + 2));
+                    }
+                    buffer.push_str(&format!("{}}},\n", inner));
+                }
+            }
+        }
+        buffer.push_str(&format!("{}}},\n", indent));
+        buffer
+    }
 }
 
 impl Layout {
     pub fn to_cad(&self, file: &File) -> String {
-        format!("layout %{} {} : {} = {{}}",
+        let mut buffer = format!("layout %{} {} : {} = {{\n",
             self.id,
             self.name,
-            file.schema.relation_graph.nodes.iter()
-            .inspect(|oc| println!("{}::{} == {}::{}?", self.occurrence.table_occurrence_id, self.name, oc.id, oc.name))
-            .find(|oc| oc.id == self.occurrence.table_occurrence_id)
-            .unwrap().name)
+            occurrence_name(file, self.occurrence.table_occurrence_id));
+        for part in &self.parts {
+            buffer.push_str(&format!("    {} = {{\n        height = {},\n", part.kind.to_cad(), part.height));
+            for object in &part.objects {
+                buffer.push_str(&object.to_cad(file, 2));
+            }
+            buffer.push_str("    },\n");
+        }
+        for trigger in &self.triggers {
+            buffer.push_str(&format!("    {},\n", trigger.to_cad(file)));
+        }
+        buffer.push('}');
+        buffer
     }
 }
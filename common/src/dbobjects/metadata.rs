@@ -1,8 +1,11 @@
 
 use serde::{Serialize, Deserialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/* Files only record when an object was last modified, in unix epoch
+ * seconds, not when it was created. */
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Metadata {
     pub created_by: String,
     pub modified_by: String,
+    pub modified_at: Option<i64>,
 }
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
-use crate::dbobjects::{calculation::Calculation, file::File, reference::*};
+use crate::{cadlang::token::quote, dbobjects::{calculation::Calculation, file::File, reference::*}};
+
+use super::table::Table;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum SerialTrigger {
@@ -55,7 +57,9 @@ pub enum AutoEntryDataPresets {
 pub enum AutoEntryType {
     NA,
     Serial { next: usize, increment: usize, trigger: SerialTrigger },
-    Lookup { from: TableReference, to: FieldReference },
+    /* `from` is an occurrence of the field's own table, and `to` must be
+     * reachable from it in the relation graph. */
+    Lookup { from: TableOccurrenceReference, to: FieldReference },
     Creation(AutoEntryDataPresets),
     Modification(AutoEntryDataPresets),
     LastVisited,
@@ -115,12 +119,17 @@ pub enum ValidationType {
 }
 
 impl ValidationType {
-    pub fn to_cad(&self) -> String {
+    pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
         match self {
             Self::NotEmpty => "        not_empty = true,\n".to_string(),
             Self::Unique => "        unique = true,\n".to_string(),
             Self::Required => "        required = true,\n".to_string(),
-            _ => todo!()
+            Self::MemberOf(value_list) => format!("        member_of = {},\n", value_list),
+            Self::Range { start, end } => format!(
+                "        range = {{\n            start = {},\n            end = {},\n        }},\n", start, end),
+            Self::MaxChars(max) => format!("        max_chars = {},\n", max),
+            Self::Calculation(calc) => format!("        validation_calc = |{}|,\n",
+                calc.to_text(|reference| file.field_reference_name(reference, externs))),
         }
     }
 }
@@ -134,23 +143,73 @@ pub struct Validation {
 }
 
 impl Validation {
-    pub fn to_cad(&self) -> String {
+    pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
         let mut buffer = String::new();
-        buffer.push_str(&format!("        trigger = {},\n", self.trigger));
-        buffer.push_str(&format!("        user_override = {},\n", self.user_override));
-        buffer.push_str(&format!("        message = \"{}\",\n", self.message));
+        let trigger = match self.trigger {
+            ValidationTrigger::OnCommit => "always",
+            ValidationTrigger::OnEntry => "entry",
+        };
+        buffer.push_str(&format!("        validate = {},\n", trigger));
+        buffer.push_str(&format!("        override = {},\n", self.user_override));
         for check in &self.checks {
-            buffer.push_str(&check.to_cad());
+            buffer.push_str(&check.to_cad(file, externs));
+        }
+        if !self.message.is_empty() {
+            buffer.push_str(&format!("        validation_message = {},\n", quote(&self.message)));
         }
 
         buffer
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum SummaryKind {
+    ListOf,
+    Total,
+    Count,
+    Average,
+    Minimum,
+    Maximum,
+    StdDev,
+    Fraction,
+}
+
+impl SummaryKind {
+    pub const ALL: [Self; 8] = [Self::ListOf, Self::Total, Self::Count, Self::Average,
+        Self::Minimum, Self::Maximum, Self::StdDev, Self::Fraction];
+
+    pub fn to_cad(&self) -> &'static str {
+        match self {
+            Self::ListOf => "list_of",
+            Self::Total => "total_of",
+            Self::Count => "count_of",
+            Self::Average => "average_of",
+            Self::Minimum => "minimum_of",
+            Self::Maximum => "maximum_of",
+            Self::StdDev => "std_dev_of",
+            Self::Fraction => "fraction_of",
+        }
+    }
+
+    pub fn from_cad(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.to_cad() == name)
+    }
+}
+
+/* Calculation and summary fields hold no entered data. A summary is taken
+ * over `field` of the same table, across the found set. */
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum FieldKind {
+    Simple,
+    Calculation { code: Calculation, stored: bool },
+    Summary { kind: SummaryKind, field: u32 },
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Field {
     pub id: u32,
     pub name: String,
+    pub kind: FieldKind,
     pub dtype: DataType,
     pub validation: Validation,
     pub autoentry: AutoEntry,
@@ -158,6 +217,7 @@ pub struct Field {
     pub repetitions: u8,
     pub created_by: String,
     pub modified_by: String,
+    pub comment: String,
 }
 
 impl Field {
@@ -165,8 +225,10 @@ impl Field {
         Self {
             id: id_,
             name: name_,
+            kind: FieldKind::Simple,
             created_by: String::from("admin"),
             modified_by: String::from("admin"),
+            comment: String::new(),
             dtype: DataType::Text,
             global: false,
             repetitions: 1,
@@ -183,18 +245,48 @@ impl Field {
         }
     }
 
-    pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
+    pub fn to_cad(&self, table: &Table, file: &File, externs: &HashMap<usize, File>) -> String {
         let mut buffer = String::new();
         buffer.push_str(&format!("field %{} {} = {{\n", self.id, self.name));
 
         buffer.push_str(&format!("        datatype = {},\n", self.dtype));
+        match &self.kind {
+            FieldKind::Simple => {}
+            FieldKind::Calculation { code, stored } => {
+                buffer.push_str(&format!("        calculation = |{}|,\n",
+                    code.to_text(|reference| file.field_reference_name(reference, externs))));
+                if !stored {
+                    buffer.push_str("        stored = false,\n");
+                }
+            }
+            FieldKind::Summary { kind, field } => {
+                let name = table.fields.get(field)
+                    .map(|f| f.name.clone())
+                    .unwrap_or_else(|| format!("field_{}", field));
+                buffer.push_str(&format!("        summary = {}({}),\n", kind.to_cad(), name));
+            }
+        }
+        if self.global {
+            buffer.push_str("        global = true,\n");
+        }
+        if self.repetitions > 1 {
+            buffer.push_str(&format!("        repetitions = {},\n", self.repetitions));
+        }
         buffer.push_str(&self.autoentry.to_cad());
-        buffer.push_str(&self.validation.to_cad());
+        buffer.push_str(&self.validation.to_cad(file, externs));
+        if !self.comment.is_empty() {
+            buffer.push_str(&format!("        comment = {},\n", quote(&self.comment)));
+        }
 
         buffer.push_str("    }");
         buffer
     }
 
+    pub fn kind(mut self, kind_: FieldKind) -> Self {
+        self.kind = kind_;
+        self
+    }
+
     pub fn datatype(mut self, dtype_: DataType) -> Self {
         self.dtype = dtype_;
         self
@@ -205,6 +297,11 @@ impl Field {
         self
     }
 
+    pub fn global(mut self, global_: bool) -> Self {
+        self.global = global_;
+        self
+    }
+
     pub fn created_by(mut self, account: String) -> Self {
         self.created_by = account;
         self
@@ -215,6 +312,11 @@ impl Field {
         self
     }
 
+    pub fn comment(mut self, comment_: String) -> Self {
+        self.comment = comment_;
+        self
+    }
+
     pub fn autoentry(mut self, definition_: AutoEntryType, nomodify_: bool) -> Self {
         self.autoentry = AutoEntry {
             definition: definition_,
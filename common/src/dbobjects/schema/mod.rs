@@ -1,3 +1,4 @@
+use serde::{Serialize, Deserialize};
 
 pub mod field;
 pub mod table;
@@ -6,10 +7,11 @@ pub mod relationgraph;
 use std::collections::HashMap;
 
 use super::file::File;
-use super::schema::relationgraph::graph::RelationGraph;
+use super::schema::field::{AutoEntryType, ValidationType};
+use super::schema::relationgraph::{graph::RelationGraph, relation::{Cardinality, Relation, RelationComparison}};
 use super::schema::table::Table;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub tables: Vec<Table>,
     pub relation_graph: RelationGraph,
@@ -23,6 +25,25 @@ impl Schema {
         }
     }
 
+    /* A relation matches at most one record when one of its criteria is an
+     * equality against a field that is unique or serial on the other side.
+     * Tables in other files can't be checked, so are taken to be many. */
+    pub fn cardinality(&self, relation: &Relation) -> Cardinality {
+        let Some(table) = self.relation_graph.nodes.iter()
+            .find(|node| node.id == relation.other_occurrence)
+            .filter(|node| node.base.data_source == 0)
+            .and_then(|node| self.tables.iter().find(|table| table.id == node.base.table_id)) else {
+            return Cardinality::Many;
+        };
+        let one = relation.criteria.iter().any(|criteria| {
+            criteria.comparison == RelationComparison::Equal
+                && table.fields.get(&criteria.field_other).is_some_and(|field|
+                    field.validation.checks.contains(&ValidationType::Unique)
+                    || matches!(field.autoentry.definition, AutoEntryType::Serial { .. }))
+        });
+        if one { Cardinality::One } else { Cardinality::Many }
+    }
+
     pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
         let mut buffer = String::new();
         buffer.push_str(&self.tables.iter().map(|table| table.to_cad(file, externs)).collect::<Vec<String>>().join(&"\n"));
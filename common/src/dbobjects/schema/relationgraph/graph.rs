@@ -1,11 +1,11 @@
+use serde::{Serialize, Deserialize};
 
-use std::collections::{HashMap, HashSet};
-use crate::dbobjects::file::File;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::dbobjects::{file::File, reference::TableReference};
 
 use super::{table_occurrence::TableOccurrence, relation::Relation};
-use std::collections::VecDeque;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct RelationGraph {
     pub nodes: Vec<TableOccurrence>
 }
@@ -58,6 +58,132 @@ impl RelationGraph {
         None
     }
 
+    /* Every occurrence of `table`, in id order. */
+    pub fn occurrences_of(&self, table: &TableReference) -> Vec<&TableOccurrence> {
+        let mut result = self.nodes.iter()
+            .filter(|node| node.base == *table)
+            .collect::<Vec<_>>();
+        result.sort_by_key(|node| node.id);
+        result
+    }
+
+    /* The relations crossed on the shortest path from `node1` to `node2`, in
+     * order, each seen from the occurrence it leaves. */
+    pub fn get_join_path(&self, node1: u32, node2: u32) -> Option<Vec<(u32, &Relation)>> {
+        let path = self.get_path(node1, node2)?;
+        Some(path.windows(2)
+            .map(|pair| {
+                let relation = self.nodes.iter()
+                    .find(|node| node.id == pair[0]).unwrap()
+                    .relations.iter()
+                    .find(|relation| relation.other_occurrence == pair[1]).unwrap();
+                (pair[0], relation)
+            })
+            .collect())
+    }
+
+    /* Groups of occurrences joined to each other, but to nothing outside the
+     * group. Each group is in id order, and groups are ordered by their
+     * first id. */
+    pub fn islands(&self) -> Vec<Vec<u32>> {
+        let mut seen = HashSet::new();
+        let mut result = vec![];
+        let mut ids = self.nodes.iter().map(|node| node.id).collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            if seen.contains(&id) {
+                continue;
+            }
+            let mut island = vec![];
+            let mut stack = vec![id];
+            seen.insert(id);
+            while let Some(current) = stack.pop() {
+                island.push(current);
+                for relation in self.relations_of(current) {
+                    if seen.insert(relation.other_occurrence) {
+                        stack.push(relation.other_occurrence);
+                    }
+                }
+            }
+            island.sort();
+            result.push(island);
+        }
+        result
+    }
+
+    /* FileMaker keeps the graph free of cycles, as there would be more than
+     * one path between two occurrences. Each cycle is returned as the id of
+     * the relation closing it and the occurrences around it, starting from
+     * the one that relation leaves. */
+    pub fn cycles(&self) -> Vec<(u32, Vec<u32>)> {
+        let mut parents: HashMap<u32, (u32, u32)> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut tree_relations = HashSet::new();
+        let mut result = vec![];
+        /* Relations to occurrences missing from the graph can't close a
+         * cycle, so they are left out of the tree and skipped below. */
+        let known = self.nodes.iter().map(|node| node.id).collect::<HashSet<_>>();
+        for island in self.islands() {
+            let root = island[0];
+            seen.insert(root);
+            let mut queue = VecDeque::from([root]);
+            while let Some(current) = queue.pop_front() {
+                for relation in self.relations_of(current) {
+                    if !known.contains(&relation.other_occurrence) {
+                        continue;
+                    }
+                    if seen.insert(relation.other_occurrence) {
+                        parents.insert(relation.other_occurrence, (current, relation.id));
+                        tree_relations.insert(relation.id);
+                        queue.push_back(relation.other_occurrence);
+                    }
+                }
+            }
+        }
+
+        let ancestors = |mut node: u32| {
+            let mut path = vec![node];
+            while let Some((parent, _)) = parents.get(&node) {
+                path.push(*parent);
+                node = *parent;
+            }
+            path
+        };
+        let mut closing = HashSet::new();
+        let mut nodes = self.nodes.iter().collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.id);
+        for node in nodes {
+            for relation in &node.relations {
+                if !known.contains(&relation.other_occurrence)
+                    || tree_relations.contains(&relation.id)
+                    || !closing.insert(relation.id) {
+                    continue;
+                }
+                /* The cycle runs up the tree from one end to where the two
+                 * ends meet, then back down to the other. */
+                let up = ancestors(node.id);
+                let down = ancestors(relation.other_occurrence);
+                let Some(meet) = up.iter().position(|id| down.contains(id)) else {
+                    continue;
+                };
+                let mut cycle = up[..=meet].to_vec();
+                let Some(back) = down.iter().position(|id| *id == up[meet]) else {
+                    continue;
+                };
+                cycle.extend(down[..back].iter().rev());
+                result.push((relation.id, cycle));
+            }
+        }
+        result
+    }
+
+    fn relations_of(&self, occurrence: u32) -> &[Relation] {
+        self.nodes.iter()
+            .find(|node| node.id == occurrence)
+            .map(|node| node.relations.as_slice())
+            .unwrap_or_default()
+    }
+
     pub fn to_cad(&self, file: &File, externs: &HashMap<usize, File>) -> String {
         let mut buffer = String::new();
         let mut relation_buffer = String::new();
@@ -132,7 +258,7 @@ impl RelationGraph {
 
 #[cfg(test)]
 mod tests {
-    use crate::dbobjects::{reference::TableReference, schema::relationgraph::{self, graph::RelationGraph, table_occurrence::TableOccurrence}};
+    use crate::dbobjects::{reference::TableReference, schema::relationgraph::{self, graph::RelationGraph, relation::*, table_occurrence::TableOccurrence}};
     use crate::cadlang::compiler::compile_to_file;
     use std::path::Path;
 
@@ -159,6 +285,53 @@ mod tests {
         let path = graph.get_path(node1.id, node2.id);
         assert_eq!(path, None);
     }
+
+    #[test]
+    fn graph_queries() {
+        let db = compile_to_file(Path::new("test_data/cad_files/multi_file_solution/quotes.cad")).unwrap();
+        let graph = &db.schema.relation_graph;
+        let quotes = graph.occurrences_of(&TableReference { data_source: 0, table_id: 1 });
+        assert_eq!(quotes.iter().map(|node| node.id).collect::<Vec<_>>(), vec![1]);
+
+        let join = graph.get_join_path(1, 4).unwrap();
+        assert_eq!(join.iter().map(|(from, relation)| (*from, relation.id)).collect::<Vec<_>>(), vec![(1, 2), (5, 3)]);
+        assert_eq!(join[0].1.criteria[0].field_self, 1);
+        assert_eq!(join[0].1.criteria[0].field_other, 1);
+        assert_eq!(db.schema.cardinality(join[0].1), Cardinality::Many);
+        let back = graph.get_join_path(5, 1).unwrap();
+        assert_eq!(db.schema.cardinality(back[0].1), Cardinality::One);
+
+        assert_eq!(graph.islands(), vec![vec![1, 2, 4, 5], vec![3]]);
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn graph_cycles() {
+        let occurrence = |id: u32, relations: Vec<(u32, u32)>| TableOccurrence {
+            id,
+            name: format!("occ{}", id),
+            base: TableReference { data_source: 0, table_id: 1 },
+            relations: relations.into_iter()
+                .map(|(relation, other)| Relation { id: relation, other_occurrence: other, criteria: vec![], options: RelationOptions::default() })
+                .collect(),
+        };
+        let mut graph = RelationGraph::new();
+        graph.nodes = vec![
+            occurrence(1, vec![(1, 2), (3, 3)]),
+            occurrence(2, vec![(1, 1), (2, 3)]),
+            occurrence(3, vec![(2, 2), (3, 1)]),
+            occurrence(4, vec![]),
+        ];
+        assert_eq!(graph.cycles(), vec![(2, vec![2, 1, 3])]);
+        assert_eq!(graph.islands(), vec![vec![1, 2, 3], vec![4]]);
+
+        /* Relations to an occurrence that isn't in the graph are skipped. */
+        graph.nodes = vec![
+            occurrence(1, vec![(1, 9)]),
+            occurrence(2, vec![(2, 9)]),
+        ];
+        assert!(graph.cycles().is_empty());
+    }
 }
 
 
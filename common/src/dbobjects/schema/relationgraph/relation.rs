@@ -49,9 +49,33 @@ pub struct RelationCriteria {
     pub comparison: RelationComparison,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct RelationSort {
+    pub field: u32,
+    pub descending: bool,
+}
+
+/* How records of the other occurrence are treated when reached through the
+ * relation. Each side of a relation has its own. */
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct RelationOptions {
+    pub allow_creation: bool,
+    pub delete_related: bool,
+    pub sort: Vec<RelationSort>,
+}
+
+/* Whether a record matches one or many records through a relation. */
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum Cardinality {
+    One,
+    Many,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Relation {
     pub id: u32,
     pub other_occurrence: u32,
     pub criteria: Vec<RelationCriteria>,
+    #[serde(default)]
+    pub options: RelationOptions,
 }
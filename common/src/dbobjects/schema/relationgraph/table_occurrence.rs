@@ -1,8 +1,9 @@
+use serde::{Serialize, Deserialize};
 use crate::dbobjects::reference::TableReference;
 
 use super::relation::Relation;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct TableOccurrence {
     pub id: u32,
     pub name: String,
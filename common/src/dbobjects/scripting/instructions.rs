@@ -11,13 +11,14 @@ use super::arguments::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[repr(u16)] pub enum Instruction {
+	// Generated by scripts/script_instruction_gen.py, do not edit by hand.
 	PerformScript { script: ScriptSelection, args: Calculation },
 	SaveACopyAsXml,
 	GoToNextField,
 	GoToPreviousField,
 	GoToLayout { layout: LayoutSelection, animation: LayoutAnimation },
 	NewRecordRequest,
-        DuplicateRecordRequest,
+	DuplicateRecordRequest,
 	DeleteRecordRequest,
 	DeleteAllRecords,
 	InsertFromIndex,
@@ -51,7 +52,7 @@ use super::arguments::*;
 	RelookupFieldContents,
 	EnterPreviewMode,
 	PrintSetup,
-	Print,
+	Print { expr: Calculation },
 	ExitApplication,
 	UndoRedo,
 	Cut,
@@ -75,7 +76,7 @@ use super::arguments::*;
 	Else,
 	EndIf,
 	Loop,
-	ExitLoopIf { condition: Calculation } ,
+	ExitLoopIf { condition: Calculation },
 	EndLoop,
 	GoToRelatedRecord,
 	CommitRecordsRequests,
@@ -170,7 +171,7 @@ use super::arguments::*;
 	OpenUploadToHost,
 	EnableTouchKeyboard,
 	PerformJavascriptInWebViewer,
-    CommentedOut,
+	CommentedOut,
 	AvplayerPlay,
 	AvplayerSetPlaybackState,
 	AvplayerSetOptions,
@@ -202,7 +203,7 @@ use super::arguments::*;
 	SetDictionary,
 	PerformScriptOnServerWithCallback,
 	TriggerClarisConnectFlow,
-        Assert { expr: Calculation },
+	Assert { expr: Calculation },
 }
 
 impl Instruction {
@@ -211,299 +212,206 @@ impl Instruction {
     }
 }
 
-//impl FromStr for Instruction {
-//    type Err = ();
-//    fn from_str(input: &str) -> Result<Instruction, Self::Err> {
-//        match input {
-//            "perform_script" => Ok(Instruction::PerformScript),
-//            "go_to_layout" => Ok(Instruction::GoToLayout),
-//            "go_to_record" | "go_to_request" => Ok(Instruction::GoToRecordRequestPage),
-//            "enter_find_mode" => Ok(Instruction::EnterFindMode),
-//            "perform_find" => Ok(Instruction::PerformFind),
-//            "show_all_records" => Ok(Instruction::ShowAllRecords),
-//            "set_field" => Ok(Instruction::SetField),
-//            "set_variable" => Ok(Instruction::SetVariable),
-//            "loop" => Ok(Instruction::Loop),
-//            "if" => Ok(Instruction::If),
-//            "elif" => Ok(Instruction::ElseIf),
-//            "else" => Ok(Instruction::Else),
-//            "exit_loop_if" => Ok(Instruction::ExitLoopIf),
-//            "new_record_request" => Ok(Instruction::NewRecordRequest),
-//            "exit_script" => Ok(Instruction::ExitScript),
-//            "show_custom_dialog" => Ok(Instruction::ShowCustomDialog),
-//            "assert" => Ok(Instruction::Assert),
-//            _ => Err(()),
-//        }
-//    }
-//}
-
-//pub static INSTRUCTIONMAP : [Option<Instruction>; 255] = [
-//    None,
-//	  Some(Instruction::PerformScript),
-//    None,
-//	  Some(Instruction::SaveACopyAsXml),
-//	  Some(Instruction::GoToNextField),
-//	  Some(Instruction::GoToPreviousField),
-//	  Some(Instruction::GoToLayout),
-//	  Some(Instruction::NewRecordRequest),
-//    Some(Instruction::DuplicateRecordRequest),
-//	  Some(Instruction::DeleteRecordRequest),
-//	  Some(Instruction::DeleteAllRecords),
-//	  Some(Instruction::InsertFromIndex),
-//	  Some(Instruction::InsertFromLastVisited),
-//	  Some(Instruction::InsertCurrentDate),
-//	  Some(Instruction::InsertCurrentTime),
-//    None,
-//	  Some(Instruction::GoToRecordRequestPage),
-//	  Some(Instruction::GoToField),
-//	  Some(Instruction::CheckSelection),
-//	  Some(Instruction::CheckRecord),
-//	  Some(Instruction::CheckFoundSet),
-//	  Some(Instruction::UnsortRecords),
-//	  Some(Instruction::EnterFindMode),
-//	  Some(Instruction::ShowAllRecords),
-//	  Some(Instruction::ModifyLastFind),
-//	  Some(Instruction::OmitRecord),
-//	  Some(Instruction::OmitMultipleRecords),
-//	  Some(Instruction::ShowOmmitedOnly),
-//	  Some(Instruction::PerformFind),
-//	  Some(Instruction::ShowHideToolbars),
-//	  Some(Instruction::ViewAs),
-//	  Some(Instruction::AdjustWindow),
-//	  Some(Instruction::OpenHelp),
-//	  Some(Instruction::OpenFile),
-//	  Some(Instruction::CloseFile),
-//	  Some(Instruction::ImportRecords),
-//	  Some(Instruction::ExportRecords),
-//	  Some(Instruction::SaveACopyAs),
-//	  Some(Instruction::OpenManageDatabase),
-//	  Some(Instruction::SortRecords),
-//	  Some(Instruction::RelookupFieldContents),
-//	  Some(Instruction::EnterPreviewMode),
-//	  Some(Instruction::PrintSetup),
-//	  Some(Instruction::Print),
-//	  Some(Instruction::ExitApplication),
-//	  Some(Instruction::UndoRedo),
-//	  Some(Instruction::Cut),
-//	  Some(Instruction::Copy),
-//	  Some(Instruction::Paste),
-//	  Some(Instruction::Clear),
-//	  Some(Instruction::SelectAll),
-//	  Some(Instruction::RevertRecordRequest),
-//    None,
-//    None,
-//    None,
-//	  Some(Instruction::EnterBrowserMode),
-//	  Some(Instruction::InsertPicture),
-//	  Some(Instruction::SendEvent),
-//    None,
-//    None,
-//	  Some(Instruction::InsertCurrentUserName),
-//	  Some(Instruction::InsertText),
-//	  Some(Instruction::PauseResumeScript),
-//	  Some(Instruction::SendMail),
-//	  Some(Instruction::SendDdeExecute),
-//	  Some(Instruction::DialPhone),
-//	  Some(Instruction::Speak),
-//	  Some(Instruction::PerformApplescript),
-//	  Some(Instruction::If),
-//	  Some(Instruction::Else),
-//	  Some(Instruction::EndIf),
-//	  Some(Instruction::Loop),
-//	  Some(Instruction::ExitLoopIf),
-//	  Some(Instruction::EndLoop),
-//	  Some(Instruction::GoToRelatedRecord),
-//	  Some(Instruction::CommitRecordsRequests),
-//	  Some(Instruction::SetField),
-//	  Some(Instruction::InsertCalculatedResult),
-//    None,
-//	  Some(Instruction::FreezeWindow),
-//	  Some(Instruction::RefreshWindow),
-//	  Some(Instruction::ScrollWindow),
-//	  Some(Instruction::NewFile),
-//	  Some(Instruction::ChangePassword),
-//	  Some(Instruction::SetMultiUser),
-//	  Some(Instruction::AllowUserAbort),
-//	  Some(Instruction::SetErrorCapture),
-//	  Some(Instruction::ShowCustomDialog),
-//	  Some(Instruction::OpenScriptWorkspace),
-//	  Some(Instruction::BlankLineComment),
-//	  Some(Instruction::HaltScript),
-//	  Some(Instruction::ReplaceFieldContents),
-//	  Some(Instruction::ShowHideTextRuler),
-//	  Some(Instruction::Beep),
-//	  Some(Instruction::SetUseSystemFormats),
-//	  Some(Instruction::RecoverFile),
-//	  Some(Instruction::SaveACopyAsAddOnPackage),
-//	  Some(Instruction::SetZoomLevel),
-//	  Some(Instruction::CopyAllRecordsRequests),
-//	  Some(Instruction::GoToPortalRow),
-//    None,
-//	  Some(Instruction::CopyRecordRequest),
-//	  Some(Instruction::FluchCacheToDisk),
-//	  Some(Instruction::ExitScript),
-//	  Some(Instruction::DeletePortalRow),
-//	  Some(Instruction::OpenPreferences),
-//	  Some(Instruction::CorrectWord),
-//	  Some(Instruction::SpellingOptions),
-//	  Some(Instruction::SelectDictionaries),
-//	  Some(Instruction::EditUserDictionary),
-//    None,
-//	  Some(Instruction::OpenUrl),
-//	  Some(Instruction::OpenManageValueLists),
-//	  Some(Instruction::OpenSharing),
-//	  Some(Instruction::OpenFileOptions),
-//	  Some(Instruction::AllowFormattingBar),
-//	  Some(Instruction::SetNextSerialValue),
-//	  Some(Instruction::ExecuteSql),
-//	  Some(Instruction::OpenHosts),
-//	  Some(Instruction::MoveResizeWindow),
-//	  Some(Instruction::ArrangeAllWindows),
-//	  Some(Instruction::CloseWindow),
-//	  Some(Instruction::NewWindow),
-//	  Some(Instruction::SelectWindow),
-//	  Some(Instruction::SetWindowTitle),
-//	  Some(Instruction::ElseIf),
-//	  Some(Instruction::ConstrainFoundSet),
-//	  Some(Instruction::ExtendFoundSet),
-//	  Some(Instruction::PerformFindReplace),
-//	  Some(Instruction::OpenFindReplace),
-//	  Some(Instruction::SetSelection),
-//	  Some(Instruction::InsertFile),
-//	  Some(Instruction::ExportFieldContents),
-//	  Some(Instruction::OpenRecordRequest),
-//	  Some(Instruction::AddAccount),
-//	  Some(Instruction::DeleteAccount),
-//	  Some(Instruction::ResetAccountPassword),
-//	  Some(Instruction::EnableAccount),
-//	  Some(Instruction::Relogin),
-//	  Some(Instruction::ConvertFile),
-//	  Some(Instruction::OpenManageDataSources),
-//	  Some(Instruction::SetVariable),
-//	  Some(Instruction::InstallMenuSet),
-//	  Some(Instruction::SaveRecordsAsExcel),
-//	  Some(Instruction::SaveRecordsAsPdf),
-//	  Some(Instruction::GoToObject),
-//	  Some(Instruction::SetWebViewer),
-//	  Some(Instruction::SetFieldByName),
-//	  Some(Instruction::InstallOntimerScript),
-//	  Some(Instruction::OpenEditSavedFinds),
-//	  Some(Instruction::PerformQuickFind),
-//	  Some(Instruction::OpenManageLayouts),
-//	  Some(Instruction::SaveRecordsAsSnapshotLink),
-//    None,
-//	  Some(Instruction::SortRecordsByField),
-//	  Some(Instruction::FindMatchingRecords),
-//	  Some(Instruction::ManageContainers),
-//	  Some(Instruction::InstallPluginFile),
-//	  Some(Instruction::InsertPdf),
-//	  Some(Instruction::InsertAudioVideo),
-//	  Some(Instruction::InsertFromUrl),
-//	  Some(Instruction::InsertFromDevice),
-//    None,
-//    None,
-//	  Some(Instruction::PerformScriptOnServer),
-//	  Some(Instruction::OpenManageThemes),
-//	  Some(Instruction::ShowHideMenubar),
-//	  Some(Instruction::RefreshObject),
-//	  Some(Instruction::SetLayoutObjectAnimation),
-//	  Some(Instruction::ClosePopover),
-//    None,
-//    None,
-//	  Some(Instruction::OpenUploadToHost),
-//    None,
-//	  Some(Instruction::EnableTouchKeyboard),
-//	  Some(Instruction::PerformJavascriptInWebViewer),
-//      Some(Instruction::CommentedOut),
-//	  Some(Instruction::AvplayerPlay),
-//	  Some(Instruction::AvplayerSetPlaybackState),
-//	  Some(Instruction::AvplayerSetOptions),
-//	  Some(Instruction::RefreshPortal),
-//	  Some(Instruction::GetFolderPath),
-//	  Some(Instruction::TruncateTable),
-//	  Some(Instruction::OpenFavorites),
-//    None,
-//	  Some(Instruction::ConfigureRegionMonitorScript),
-//    None,
-//	  Some(Instruction::ConfigureLocalNotification),
-//	  Some(Instruction::GetFileExists),
-//	  Some(Instruction::GetFileSize),
-//	  Some(Instruction::CreateDataFile),
-//	  Some(Instruction::OpenDataFile),
-//	  Some(Instruction::WriteToDataFile),
-//	  Some(Instruction::ReadFromDataFile),
-//	  Some(Instruction::GetDataFilePosition),
-//	  Some(Instruction::SetDataFilePosition),
-//	  Some(Instruction::CloseDataFile),
-//	  Some(Instruction::DeleteFile),
-//    None,
-//	  Some(Instruction::RenameFile),
-//	  Some(Instruction::SetErrorLogging),
-//	  Some(Instruction::ConfigureNfcReading),
-//	  Some(Instruction::ConfigureMachineLearningModel),
-//	  Some(Instruction::ExecuteFilemakerDataApi),
-//    None,
-//	  Some(Instruction::OpenTransaction),
-//	  Some(Instruction::CommitTransaction),
-//	  Some(Instruction::RevertTransaction),
-//	  Some(Instruction::SetSessionIdentifier),
-//	  Some(Instruction::SetDictionary),
-//	  Some(Instruction::PerformScriptOnServerWithCallback),
-//	  Some(Instruction::TriggerClarisConnectFlow),
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    None,
-//    Some(Instruction::Assert),
-//];
-
 impl Instruction {
     pub fn new(name: &str, args: &Vec<(Option<String>, String)>) -> Self {
         todo!()
     }
 
     pub fn get_opcode(&self) -> u32 {
+        // Generated by scripts/script_instruction_gen.py, do not edit by hand.
         match self {
             Instruction::PerformScript { .. } => 1,
-            _ => 0,
+            Instruction::SaveACopyAsXml => 3,
+            Instruction::GoToNextField => 4,
+            Instruction::GoToPreviousField => 5,
+            Instruction::GoToLayout { .. } => 6,
+            Instruction::NewRecordRequest => 7,
+            Instruction::DuplicateRecordRequest => 8,
+            Instruction::DeleteRecordRequest => 9,
+            Instruction::DeleteAllRecords => 10,
+            Instruction::InsertFromIndex => 11,
+            Instruction::InsertFromLastVisited => 12,
+            Instruction::InsertCurrentDate => 13,
+            Instruction::InsertCurrentTime => 14,
+            Instruction::GoToRecordRequestPage { .. } => 16,
+            Instruction::GoToField => 17,
+            Instruction::CheckSelection => 18,
+            Instruction::CheckRecord => 19,
+            Instruction::CheckFoundSet => 20,
+            Instruction::UnsortRecords => 21,
+            Instruction::EnterFindMode => 22,
+            Instruction::ShowAllRecords => 23,
+            Instruction::ModifyLastFind => 24,
+            Instruction::OmitRecord => 25,
+            Instruction::OmitMultipleRecords => 26,
+            Instruction::ShowOmmitedOnly => 27,
+            Instruction::PerformFind => 28,
+            Instruction::ShowHideToolbars => 29,
+            Instruction::ViewAs => 30,
+            Instruction::AdjustWindow => 31,
+            Instruction::OpenHelp => 32,
+            Instruction::OpenFile => 33,
+            Instruction::CloseFile => 34,
+            Instruction::ImportRecords => 35,
+            Instruction::ExportRecords => 36,
+            Instruction::SaveACopyAs => 37,
+            Instruction::OpenManageDatabase => 38,
+            Instruction::SortRecords => 39,
+            Instruction::RelookupFieldContents => 40,
+            Instruction::EnterPreviewMode => 41,
+            Instruction::PrintSetup => 42,
+            Instruction::Print { .. } => 43,
+            Instruction::ExitApplication => 44,
+            Instruction::UndoRedo => 45,
+            Instruction::Cut => 46,
+            Instruction::Copy => 47,
+            Instruction::Paste => 48,
+            Instruction::Clear => 49,
+            Instruction::SelectAll => 50,
+            Instruction::RevertRecordRequest => 51,
+            Instruction::EnterBrowserMode => 55,
+            Instruction::InsertPicture => 56,
+            Instruction::SendEvent => 57,
+            Instruction::InsertCurrentUserName => 60,
+            Instruction::InsertText => 61,
+            Instruction::PauseResumeScript => 62,
+            Instruction::SendMail => 63,
+            Instruction::SendDdeExecute => 64,
+            Instruction::DialPhone => 65,
+            Instruction::Speak => 66,
+            Instruction::PerformApplescript => 67,
+            Instruction::If { .. } => 68,
+            Instruction::Else => 69,
+            Instruction::EndIf => 70,
+            Instruction::Loop => 71,
+            Instruction::ExitLoopIf { .. } => 72,
+            Instruction::EndLoop => 73,
+            Instruction::GoToRelatedRecord => 74,
+            Instruction::CommitRecordsRequests => 75,
+            Instruction::SetField { .. } => 76,
+            Instruction::InsertCalculatedResult => 77,
+            Instruction::FreezeWindow => 79,
+            Instruction::RefreshWindow => 80,
+            Instruction::ScrollWindow => 81,
+            Instruction::NewFile => 82,
+            Instruction::ChangePassword => 83,
+            Instruction::SetMultiUser => 84,
+            Instruction::AllowUserAbort => 85,
+            Instruction::SetErrorCapture => 86,
+            Instruction::ShowCustomDialog => 87,
+            Instruction::OpenScriptWorkspace => 88,
+            Instruction::BlankLineComment => 89,
+            Instruction::HaltScript => 90,
+            Instruction::ReplaceFieldContents => 91,
+            Instruction::ShowHideTextRuler => 92,
+            Instruction::Beep => 93,
+            Instruction::SetUseSystemFormats => 94,
+            Instruction::RecoverFile => 95,
+            Instruction::SaveACopyAsAddOnPackage => 96,
+            Instruction::SetZoomLevel => 97,
+            Instruction::CopyAllRecordsRequests => 98,
+            Instruction::GoToPortalRow => 99,
+            Instruction::CopyRecordRequest => 101,
+            Instruction::FluchCacheToDisk => 102,
+            Instruction::ExitScript { .. } => 103,
+            Instruction::DeletePortalRow => 104,
+            Instruction::OpenPreferences => 105,
+            Instruction::CorrectWord => 106,
+            Instruction::SpellingOptions => 107,
+            Instruction::SelectDictionaries => 108,
+            Instruction::EditUserDictionary => 109,
+            Instruction::OpenUrl => 111,
+            Instruction::OpenManageValueLists => 112,
+            Instruction::OpenSharing => 113,
+            Instruction::OpenFileOptions => 114,
+            Instruction::AllowFormattingBar => 115,
+            Instruction::SetNextSerialValue => 116,
+            Instruction::ExecuteSql => 117,
+            Instruction::OpenHosts => 118,
+            Instruction::MoveResizeWindow => 119,
+            Instruction::ArrangeAllWindows => 120,
+            Instruction::CloseWindow => 121,
+            Instruction::NewWindow => 122,
+            Instruction::SelectWindow => 123,
+            Instruction::SetWindowTitle => 124,
+            Instruction::ElseIf { .. } => 125,
+            Instruction::ConstrainFoundSet => 126,
+            Instruction::ExtendFoundSet => 127,
+            Instruction::PerformFindReplace => 128,
+            Instruction::OpenFindReplace => 129,
+            Instruction::SetSelection => 130,
+            Instruction::InsertFile => 131,
+            Instruction::ExportFieldContents => 132,
+            Instruction::OpenRecordRequest => 133,
+            Instruction::AddAccount => 134,
+            Instruction::DeleteAccount => 135,
+            Instruction::ResetAccountPassword => 136,
+            Instruction::EnableAccount => 137,
+            Instruction::Relogin => 138,
+            Instruction::ConvertFile => 139,
+            Instruction::OpenManageDataSources => 140,
+            Instruction::SetVariable { .. } => 141,
+            Instruction::InstallMenuSet => 142,
+            Instruction::SaveRecordsAsExcel => 143,
+            Instruction::SaveRecordsAsPdf => 144,
+            Instruction::GoToObject => 145,
+            Instruction::SetWebViewer => 146,
+            Instruction::SetFieldByName => 147,
+            Instruction::InstallOntimerScript => 148,
+            Instruction::OpenEditSavedFinds => 149,
+            Instruction::PerformQuickFind => 150,
+            Instruction::OpenManageLayouts => 151,
+            Instruction::SaveRecordsAsSnapshotLink => 152,
+            Instruction::SortRecordsByField => 154,
+            Instruction::FindMatchingRecords => 155,
+            Instruction::ManageContainers => 156,
+            Instruction::InstallPluginFile => 157,
+            Instruction::InsertPdf => 158,
+            Instruction::InsertAudioVideo => 159,
+            Instruction::InsertFromUrl => 160,
+            Instruction::InsertFromDevice => 161,
+            Instruction::PerformScriptOnServer => 164,
+            Instruction::OpenManageThemes => 165,
+            Instruction::ShowHideMenubar => 166,
+            Instruction::RefreshObject => 167,
+            Instruction::SetLayoutObjectAnimation => 168,
+            Instruction::ClosePopover => 169,
+            Instruction::OpenUploadToHost => 172,
+            Instruction::EnableTouchKeyboard => 174,
+            Instruction::PerformJavascriptInWebViewer => 175,
+            Instruction::CommentedOut => 176,
+            Instruction::AvplayerPlay => 177,
+            Instruction::AvplayerSetPlaybackState => 178,
+            Instruction::AvplayerSetOptions => 179,
+            Instruction::RefreshPortal => 180,
+            Instruction::GetFolderPath => 181,
+            Instruction::TruncateTable => 182,
+            Instruction::OpenFavorites => 183,
+            Instruction::ConfigureRegionMonitorScript => 185,
+            Instruction::ConfigureLocalNotification => 187,
+            Instruction::GetFileExists => 188,
+            Instruction::GetFileSize => 189,
+            Instruction::CreateDataFile => 190,
+            Instruction::OpenDataFile => 191,
+            Instruction::WriteToDataFile => 192,
+            Instruction::ReadFromDataFile => 193,
+            Instruction::GetDataFilePosition => 194,
+            Instruction::SetDataFilePosition => 195,
+            Instruction::CloseDataFile => 196,
+            Instruction::DeleteFile => 197,
+            Instruction::RenameFile => 199,
+            Instruction::SetErrorLogging => 200,
+            Instruction::ConfigureNfcReading => 201,
+            Instruction::ConfigureMachineLearningModel => 202,
+            Instruction::ExecuteFilemakerDataApi => 203,
+            Instruction::OpenTransaction => 205,
+            Instruction::CommitTransaction => 206,
+            Instruction::RevertTransaction => 207,
+            Instruction::SetSessionIdentifier => 208,
+            Instruction::SetDictionary => 209,
+            Instruction::PerformScriptOnServerWithCallback => 210,
+            Instruction::TriggerClarisConnectFlow => 211,
+            Instruction::Assert { .. } => 254,
         }
     }
 }
@@ -24,18 +24,27 @@ pub struct Script {
 
 impl Script {
     pub fn to_cad(&self, file: &File, externs: HashMap<usize, File>) -> String {
-        let mut buffer = format!("script %{} {} = {{\n", self.id, self.name);
-
-        let mut indent = 4;
-        for instruction in &self.instructions {
+        let mut buffer = if self.args.is_empty() {
+            format!("script %{} {} = {{\n", self.id, self.name)
+        } else {
+            format!("script %{} {}({}) = {{\n", self.id, self.name, self.args.join(", "))
+        };
+
+        let mut indent: usize = 4;
+        /* The first steps read the parameters, which the header stands for. */
+        for instruction in self.instructions.iter().skip(self.args.len()) {
+            /* Branches line up with the `If` they continue. */
+            if matches!(instruction.instruction, Instruction::ElseIf { .. } | Instruction::Else
+                | Instruction::EndIf | Instruction::EndLoop) {
+                indent = indent.saturating_sub(4);
+            }
 
             buffer.push_str(&" ".repeat(indent));
 
             buffer.push_str(&format!("{:?}", instruction.instruction));
 
             match instruction.instruction {
-                Instruction::Loop | Instruction::If { .. } | Instruction::ElseIf { .. } => indent += 4,
-                Instruction::EndIf | Instruction::EndLoop => indent -= 4,
+                Instruction::Loop | Instruction::If { .. } | Instruction::ElseIf { .. } | Instruction::Else => indent += 4,
                 _ => {}
             }
 
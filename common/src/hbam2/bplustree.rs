@@ -237,7 +237,6 @@ pub fn get_view_from_key<'a, 'b>(key: &HBAMPath, cache: &mut PageStore, file: &s
                 }
                 Err(e) => return Err(BPlusTreeErr::InvalidChunkComposition(e)),
             };
-            println!("skipping: {}", chunk);
             match chunk.contents {
                 ChunkContents::Push { key } => {
                     current_path.components.push(key.to_vec());
@@ -1,9 +1,9 @@
 
 use std::{collections::BTreeMap, iter::zip};
 
-use crate::{dbobjects::file::File, hbam2::chunk::{Chunk, LocalChunk, LocalChunkContents}, util::encoding_util::put_path_int};
+use crate::{dbobjects::{file::File, schema::field::{DataType, Field, FieldKind, SummaryKind, ValidationTrigger, ValidationType}}, hbam2::chunk::{Chunk, LocalChunk, LocalChunkContents}, util::{dbcharconv::encode_name_index, encoding_util::put_path_int}};
 
-use super::{super::HBAMPath, error::Result};
+use super::{super::HBAMPath, error::{Error, Result}};
 
 pub trait DataTree {
     fn insert(&mut self, path: HBAMPath, value: LocalChunkContents) -> Result<()>;
@@ -52,6 +52,9 @@ impl DataTree for DataBTree {
                     LocalChunkContents::SimpleData { data } => {
                         println!("data := {:?}", data);
                     }
+                    LocalChunkContents::LongRef { key, data } => {
+                        println!("kv {:?} := {:?}", key, data);
+                    }
                     _ => {},
                 }
             }
@@ -62,8 +65,68 @@ impl DataTree for DataBTree {
     }
 }
 
-impl From<File> for DataBTree {
-    fn from(value: File) -> Self {
+/* Builds the field type options tuple stored under key 2. See "Field type
+ * options" in docs/fmp_format.md for the meaning of each byte. */
+fn field_options(field: &Field) -> Vec<u8> {
+    let mut options = vec![0; 26];
+    options[1] = match field.dtype {
+        DataType::Text => 1,
+        DataType::Number => 2,
+        DataType::Date => 3,
+        DataType::Time => 4,
+        DataType::Timestamp => 5,
+        DataType::Container => 6,
+    };
+    options[9] = field.global as u8;
+    match &field.kind {
+        FieldKind::Simple => {}
+        FieldKind::Calculation { stored, .. } => {
+            options[0] = 2;
+            options[9] = if *stored { 8 } else { 10 };
+        }
+        /* Summaries store their subtype where the data type would be. */
+        FieldKind::Summary { kind, .. } => {
+            options[0] = 3;
+            options[1] = match kind {
+                SummaryKind::ListOf => 1,
+                SummaryKind::Total | SummaryKind::Count | SummaryKind::StdDev | SummaryKind::Fraction => 2,
+                SummaryKind::Average | SummaryKind::Minimum | SummaryKind::Maximum => 5,
+            };
+        }
+    }
+
+    if field.validation.trigger == ValidationTrigger::OnCommit {
+        options[14] |= 4;
+    }
+    if !field.validation.user_override {
+        options[15] |= 4;
+    }
+    if !field.validation.message.is_empty() {
+        options[15] |= 128;
+    }
+    for check in &field.validation.checks {
+        match check {
+            ValidationType::MemberOf(_) => options[14] |= 1,
+            ValidationType::MaxChars(_) => options[14] |= 2,
+            ValidationType::Calculation(_) => options[15] |= 1,
+            ValidationType::NotEmpty => options[15] |= 8,
+            ValidationType::Unique => options[15] |= 16,
+            ValidationType::Required => options[15] |= 32,
+            ValidationType::Range { .. } => options[15] |= 64,
+        }
+    }
+
+    options[25] = field.repetitions;
+    options
+}
+
+impl TryFrom<File> for DataBTree {
+    type Error = Error;
+
+    fn try_from(value: File) -> Result<Self> {
+        if let Some(table) = value.schema.tables.iter().find(|table| table.id > 127) {
+            return Err(Error::TableIdOutOfRange(table.id));
+        }
         let mut result = DataBTree::default();
 
         result.insert(
@@ -77,16 +140,41 @@ impl From<File> for DataBTree {
             data: put_path_int(value.schema.tables.len() as u32)
         }).unwrap();
 
+        let names = value.schema.tables.iter()
+            .map(|table| (table.name.as_str(), table.id as u16 + 128))
+            .collect::<Vec<_>>();
+        let index = encode_name_index(&names)
+            .ok_or_else(|| Error::TableIdOutOfRange(value.schema.tables.iter().map(|table| table.id).max().unwrap_or_default()))?;
+        for (key, data) in index {
+            result.insert(HBAMPath::new(vec![&[3], &[16], &[1], &[1]]),
+                LocalChunkContents::LongRef { key, data }).unwrap();
+        }
+
         for table in value.schema.tables {
             result.insert(HBAMPath::new(vec![&[3], &[16], &[5], &[table.id as u8]]), 
                 LocalChunkContents::SimpleRef {
                     key: 16, data: table.name.into()
                 }).unwrap();
+            if !table.comment.is_empty() {
+                result.insert(HBAMPath::new(vec![&[3], &[16], &[5], &[table.id as u8]]),
+                    LocalChunkContents::SimpleRef { key: 3, data: table.comment.into() }).unwrap();
+            }
 
             for field in &table.fields {
                 result.insert(
                     HBAMPath::new(vec![&[table.id as u8 + 128], &[3], &[5], &[field.1.id as u8]]),
                     LocalChunkContents::SimpleRef { key: 16, data: field.1.name.clone().into() }).unwrap();
+                result.insert(
+                    HBAMPath::new(vec![&[table.id as u8 + 128], &[3], &[5], &[field.1.id as u8]]),
+                    LocalChunkContents::SimpleRef { key: 2, data: field_options(field.1) }).unwrap();
+                /* Where FileMaker keeps calculation code and the exact kind
+                 * and field of a summary is unknown, so only the options are
+                 * written for them. */
+                if !field.1.comment.is_empty() {
+                    result.insert(
+                        HBAMPath::new(vec![&[table.id as u8 + 128], &[3], &[5], &[field.1.id as u8]]),
+                        LocalChunkContents::SimpleRef { key: 3, data: field.1.comment.clone().into() }).unwrap();
+                }
             }
         }
 
@@ -120,7 +208,7 @@ impl From<File> for DataBTree {
                 ).unwrap();
         }
 
-        result
+        Ok(result)
     }
 }
 
@@ -128,17 +216,91 @@ impl From<File> for DataBTree {
 mod tests {
     use std::{collections::BTreeMap, path::Path};
 
-    use crate::cadlang;
+    use crate::{cadlang, dbobjects::calculation::Calculation};
 
     use super::*;
 
     #[test]
     fn data_tree_test() {
         let file = cadlang::compiler::compile_to_file(Path::new("./test_data/cad_files/multi_file_solution/quotes.cad")).unwrap();
-        let result = DataBTree::from(file);
+        let result = DataBTree::try_from(file).unwrap();
 
         for chunk in result.flatten().unwrap() {
             println!("{:?}", chunk);
         }
     }
+
+    #[test]
+    fn field_validation_options() {
+        let code = "
+            table %1 Person = {
+                field %1 id = {
+                    datatype = Number,
+                    validate = always,
+                    override = false,
+                    not_empty = true,
+                    unique = true,
+                    max_chars = 8,
+                    validation_message = \"Invalid ID chosen.\",
+                },
+            }
+            ";
+        let mut stage = cadlang::parser::parse(&cadlang::lexer::lex(code).unwrap()).unwrap();
+        let file = cadlang::backend::build_file(&mut stage, Path::new("."));
+        let result = DataBTree::try_from(file).unwrap();
+
+        let options = result.map.get(&HBAMPath::new(vec![&[129], &[3], &[5], &[1]])).unwrap()
+            .iter()
+            .find_map(|chunk| match chunk {
+                LocalChunkContents::SimpleRef { key: 2, data } => Some(data.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(options[1], 2);
+        assert_eq!(options[14], 4 | 2);
+        assert_eq!(options[15], 4 | 8 | 16 | 128);
+        assert_eq!(options[25], 1);
+    }
+
+    #[test]
+    fn table_id_out_of_range() {
+        let code = "
+            table %300 Person = {
+                field %1 id = {
+                    datatype = Number,
+                },
+            }
+            ";
+        let mut stage = cadlang::parser::parse(&cadlang::lexer::lex(code).unwrap()).unwrap();
+        let file = cadlang::backend::build_file(&mut stage, Path::new("."));
+        assert!(matches!(DataBTree::try_from(file), Err(Error::TableIdOutOfRange(300))));
+    }
+
+    #[test]
+    fn field_kind_options() {
+        let mut field = Field::new(1, String::from("total"))
+            .kind(FieldKind::Summary { kind: SummaryKind::Average, field: 2 });
+        assert_eq!(field_options(&field)[..2], [3, 5]);
+        field.kind = FieldKind::Calculation { code: Calculation(vec![]), stored: false };
+        let options = field_options(&field);
+        assert_eq!((options[0], options[9]), (2, 10));
+    }
+
+    #[test]
+    fn field_kind_round_trip() {
+        /* Only the group of a summary's kind and whether a calculation is
+         * stored are kept in the options. */
+        let cases = [
+            (FieldKind::Summary { kind: SummaryKind::ListOf, field: 300 }, FieldKind::Summary { kind: SummaryKind::ListOf, field: 0 }),
+            (FieldKind::Summary { kind: SummaryKind::Count, field: 300 }, FieldKind::Summary { kind: SummaryKind::Total, field: 0 }),
+            (FieldKind::Summary { kind: SummaryKind::Maximum, field: 300 }, FieldKind::Summary { kind: SummaryKind::Average, field: 0 }),
+            (FieldKind::Calculation { code: Calculation::from_text("$x + 10"), stored: true }, FieldKind::Calculation { code: Calculation(vec![]), stored: true }),
+            (FieldKind::Calculation { code: Calculation::from_text("$x + 10"), stored: false }, FieldKind::Calculation { code: Calculation(vec![]), stored: false }),
+        ];
+        for (kind, expected) in cases {
+            let field = Field::new(1, String::from("field")).datatype(DataType::Number).kind(kind);
+            let decoded = crate::hbam2::decode_field_options(Field::new(1, String::from("field")), &field_options(&field));
+            assert_eq!(decoded.kind, expected);
+        }
+    }
 }
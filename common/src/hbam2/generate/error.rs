@@ -3,7 +3,7 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
-
+    TableIdOutOfRange(u32), // tables are written under the single byte 128 + id.
 }
 
 impl core::fmt::Display for Error {
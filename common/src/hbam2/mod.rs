@@ -16,15 +16,19 @@ use crate::{dbobjects::{
     hbam2::bplustree::get_view_from_key,
     util::encoding_util::{
         fm_string_decrypt,
-        get_path_int
+        get_path_int,
+        get_timestamp,
     }
 };
 
+use chunk::LocalChunkContents;
+
 use bplustree::{search_key, BPlusTreeErr};
 use page_store::PageStore;
 use path::HBAMPath;
+use view::SubView;
 
-use crate::dbobjects::{file::File, schema::{relationgraph::{graph::RelationGraph, table_occurrence::TableOccurrence, relation::*}, table::Table, field::Field}};
+use crate::dbobjects::{file::File, schema::{relationgraph::{graph::RelationGraph, table_occurrence::TableOccurrence, relation::*}, table::Table, field::{DataType, Field, FieldKind, SummaryKind}}};
 
 use std::collections::HashMap;
 use std::collections::BTreeMap;
@@ -61,42 +65,77 @@ impl Context {
     }
 
     pub fn get_schema_contents(&mut self, file: &str) -> File {
-
-        File {
-            name: file.to_string(),
-            data_sources: get_datasource_catalog(&mut self.cache, file).into_iter().map(|(_, datasource)| datasource).collect(),
-            layouts: get_layout_catalog(&mut self.cache, file).into_iter().map(|(_, layout)| layout).collect(),
-            schema: Schema {
-                tables: get_table_catalog(&mut self.cache, file).into_iter().map(|(_, table)| table).collect(),
-                relation_graph: RelationGraph {
-                    nodes: get_occurrence_catalog(&mut self.cache, file).into_iter().map(|(_, occurrence)| occurrence).collect(),
-                },
-            },
-            scripts: get_script_catalog(&mut self.cache, file).into_iter().map(|(_, script)| script).collect(),
-            tests: vec![],
-            working_dir: Path::new(file).parent().unwrap().to_str().unwrap().to_string(),
-        }
+        get_schema_contents(&mut self.cache, file)
     }
 }
 
+/* Catalogs are keyed by id, objects are emitted in id order so that
+ * output such as the JSON export is stable between runs. */
+fn sorted_by_id<T>(catalog: HashMap<usize, T>) -> Vec<T> {
+    catalog.into_iter()
+        .collect::<BTreeMap<usize, T>>()
+        .into_values()
+        .collect()
+}
+
 pub fn get_schema_contents(cache: &mut PageStore, file: &str) -> File {
 
     File {
         name: file.to_string(),
-        data_sources: get_datasource_catalog(cache, file).into_iter().map(|(_, datasource)| datasource).collect(),
-        layouts: get_layout_catalog(cache, file).into_iter().map(|(_, layout)| layout).collect(),
+        data_sources: sorted_by_id(get_datasource_catalog(cache, file)),
+        layouts: sorted_by_id(get_layout_catalog(cache, file)),
         schema: Schema {
-            tables: get_table_catalog(cache, file).into_iter().map(|(_, table)| table).collect(),
+            tables: sorted_by_id(get_table_catalog(cache, file)),
             relation_graph: RelationGraph {
-                nodes: get_occurrence_catalog(cache, file).into_iter().map(|(_, occurrence)| occurrence).collect(),
+                nodes: sorted_by_id(get_occurrence_catalog(cache, file)),
             },
         },
-        scripts: get_script_catalog(cache, file).into_iter().map(|(_, script)| script).collect(),
+        scripts: sorted_by_id(get_script_catalog(cache, file)),
         tests: vec![],
         working_dir: Path::new(file).parent().unwrap().to_str().unwrap().to_string(),
+        /* Where FileMaker keeps script triggers is not known yet. `[2].[24]`
+         * holds the same 34 bytes in every test file, none of which declare
+         * triggers, so it isn't a trigger list. See docs/fmp_format.md. */
+        triggers: vec![],
     }
 }
 
+/* The options only give the group of a summary's kind. Where FileMaker keeps
+ * the exact kind, the summarised field and calculation code is unknown, so
+ * calculations are read without code and summaries as the first kind in
+ * their group, over no field. */
+fn decode_field_options(mut field: Field, options: &[u8]) -> Field {
+    if options.len() < 26 {
+        return field;
+    }
+    field.dtype = match options[1] {
+        2 => DataType::Number,
+        3 => DataType::Date,
+        4 => DataType::Time,
+        5 => DataType::Timestamp,
+        6 => DataType::Container,
+        _ => DataType::Text,
+    };
+    field.repetitions = options[25];
+    match options[0] {
+        2 => field.kind = FieldKind::Calculation {
+            code: Calculation(vec![]),
+            stored: options[9] != 10,
+        },
+        3 => {
+            field.dtype = DataType::Number;
+            let kind = match options[1] {
+                1 => SummaryKind::ListOf,
+                5 => SummaryKind::Average,
+                _ => SummaryKind::Total,
+            };
+            field.kind = FieldKind::Summary { kind, field: 0 };
+        }
+        _ => field.global = options[9] == 1,
+    }
+    field
+}
+
 pub fn get_table_catalog(cache: &mut PageStore, file: &str) -> HashMap<usize, Table> {
     let view_option = match get_view_from_key(&HBAMPath::new(vec![&[3], &[16], &[5]]), cache, file)
         .expect("Unable to get table info from file.") {
@@ -120,17 +159,21 @@ pub fn get_table_catalog(cache: &mut PageStore, file: &str) -> HashMap<usize, Ta
                 }
         };
         let mut fields_ = BTreeMap::new();
-        for chunk in &field_view.chunks {
-            println!("{}", chunk);
-        }
 
         for field in field_view.get_dirs().unwrap() {
             let path_id = field.path.components.last().unwrap();
             let id_ = get_path_int(path_id);
 
+            let options = field.get_value(2).map(|options| options.to_vec());
             let field = Field::new(id_ as u32, fm_string_decrypt(field.get_value(16).expect("Unable to get field name.")))
                 .created_by(fm_string_decrypt(field.get_value(64513).expect("Unable to get created by for field.")))
-                .modified_by(fm_string_decrypt(field.get_value(64514).expect("Unable to get modified by for field.")));
+                .modified_by(fm_string_decrypt(field.get_value(64514).expect("Unable to get modified by for field.")))
+                .comment(field.get_value(3).map(fm_string_decrypt).unwrap_or_default());
+
+            let field = match options {
+                Some(options) => decode_field_options(field, &options),
+                None => field,
+            };
 
             fields_.insert(id_ as u32, field);
         }
@@ -138,9 +181,12 @@ pub fn get_table_catalog(cache: &mut PageStore, file: &str) -> HashMap<usize, Ta
         result.insert(id_, Table {
             id: id_ as u32,
             name: fm_string_decrypt(dir.get_value(16).unwrap()),
-            created_by: fm_string_decrypt(dir.get_value(64513).unwrap()),
-            modified_by: fm_string_decrypt(dir.get_value(64513).unwrap()),
-            comment: String::new(),
+            metadata: Metadata {
+                created_by: fm_string_decrypt(dir.get_value(64513).unwrap()),
+                modified_by: fm_string_decrypt(dir.get_value(64514).unwrap()),
+                modified_at: dir.get_value(64515).and_then(get_timestamp),
+            },
+            comment: dir.get_value(3).map(fm_string_decrypt).unwrap_or_default(),
             fields: fields_,
         });
     }
@@ -191,8 +237,6 @@ pub fn get_occurrence_catalog(cache: &mut PageStore, file: &str) -> HashMap<usiz
         let from = top_definition[4];
         let to = top_definition[9];
 
-
-        println!("Definition of relation: {:?}", top_definition);
         let criteria_dir = relation_dir.get_dir_relative(&mut HBAMPath::new(vec![&[3]])).unwrap();
         let criterias = match criteria_dir.get_all_simple_keyvalues() {
             Some(inner) => inner,
@@ -201,16 +245,26 @@ pub fn get_occurrence_catalog(cache: &mut PageStore, file: &str) -> HashMap<usiz
 
         let id_ = relation_dir.path.components.last().unwrap();
 
+        /* Where relation options are stored is unknown, as none of the
+         * sample files set any, so both sides keep the defaults. A relation
+         * holding anything the samples don't may have options that are
+         * lost here. */
+        if has_unknown_relation_data(&relation_dir) {
+            eprintln!("warning: relation {} may have options that aren't read yet, so they are reset to the defaults.",
+                get_path_int(relation_dir.path.components.last().unwrap()));
+        }
         let mut from_relation = Relation {
             id: get_path_int(id_) as u32,
             other_occurrence: to as u32,
             criteria: vec![],
+            options: RelationOptions::default(),
         };
 
         let mut to_relation = Relation {
             id: get_path_int(id_) as u32,
             other_occurrence: from as u32,
             criteria: vec![],
+            options: RelationOptions::default(),
         };
 
         for criteria in criterias {
@@ -259,6 +313,20 @@ pub fn get_occurrence_catalog(cache: &mut PageStore, file: &str) -> HashMap<usiz
     occurrences
 }
 
+/* Every relation in the sample files has the definition, criteria and
+ * metadata keys below, and zeros after the two occurrence ids in its
+ * definition. */
+fn has_unknown_relation_data(relation_dir: &SubView) -> bool {
+    let definition = relation_dir.get_value(2).unwrap_or_default();
+    let unknown_key = relation_dir.get_all_simple_keyvalues().unwrap_or_default()
+        .iter()
+        .any(|(key, _)| ![2, 216, 64513, 64514, 64515].contains(key));
+    let unknown_dir = relation_dir.get_dirs().unwrap_or_default()
+        .iter()
+        .any(|dir| dir.path.components.last().unwrap() != &[3]);
+    unknown_key || unknown_dir || definition.iter().skip(10).any(|byte| *byte != 0)
+}
+
 pub fn get_datasource_catalog(cache: &mut PageStore, file: &str) -> HashMap::<usize, DataSource> {
     let mut result = HashMap::new();
     let datasource_view = match get_view_from_key(&HBAMPath::new(vec![&[32], &[5]]), cache, file).expect("Unable to read data source view.") {
@@ -310,16 +378,11 @@ pub fn get_script_catalog(cache: &mut PageStore, file: &str) -> HashMap::<usize,
         None => { return result }
     };
 
-    for chunk in &script_catalog_view.chunks {
-        println!("{:?}", chunk);
-    }
 
     for script_view in script_views {
         let id_ = get_path_int(script_view.path.components.last().unwrap());
         let name_ = fm_string_decrypt(script_view.get_value(16).unwrap());
-        println!("{}", name_);
         let code = script_view.get_value(4).unwrap();
-        println!("===============================");
         //for chunk in &script_view.chunks {
         //    println!("{}::{:?}", script_view.path, chunk);
         //}
@@ -362,6 +425,7 @@ pub fn get_script_catalog(cache: &mut PageStore, file: &str) -> HashMap::<usize,
 
         let created_by_ = script_view.get_value(64513).unwrap();
         let modified_by_ = script_view.get_value(64514).unwrap();
+        let modified_at_ = script_view.get_value(64515).and_then(get_timestamp);
 
         let script = Script {
             name: name_,
@@ -370,6 +434,7 @@ pub fn get_script_catalog(cache: &mut PageStore, file: &str) -> HashMap::<usize,
             metadata: Metadata {
                 created_by: fm_string_decrypt(created_by_), 
                 modified_by: fm_string_decrypt(modified_by_),
+                modified_at: modified_at_,
             },
             args: vec![],
         };
@@ -398,6 +463,9 @@ pub fn get_layout_catalog(cache: &mut PageStore, file: &str) -> HashMap::<usize,
                 data_source: 0,
                 table_occurrence_id: meta_definition[1] as u32 - 128,
             },
+            parts: vec![],
+            /* Not decoded yet, as for the file's triggers. */
+            triggers: vec![],
         });
     }
     result
@@ -428,10 +496,13 @@ pub fn set_keyvalue<'a>(key: Key, val: Value) -> Result<(), BPlusTreeErr> {
 #[cfg(test)]
 mod tests {
 
-    use super::{get_keyvalue, get_table_catalog, get_script_catalog, get_datasource_catalog, KeyValue, PageStore};
-    use crate::hbam2::{get_occurrence_catalog, path::HBAMPath};
+    use super::{decode_field_options, get_keyvalue, get_table_catalog, get_script_catalog, get_datasource_catalog, sorted_by_id, KeyValue, PageStore};
+    use crate::hbam2::{bplustree::get_view_from_key, chunk::LocalChunkContents, get_occurrence_catalog, path::HBAMPath};
+    use crate::util::dbcharconv::{decode_name_index_key, decode_name_index_value};
+    use std::collections::HashMap;
     use crate::dbobjects::schema::relationgraph::{table_occurrence::TableOccurrence, relation::*};
     use crate::dbobjects::reference::TableReference;
+    use crate::dbobjects::{calculation::Calculation, metadata::Metadata, schema::field::{DataType, Field, FieldKind, SummaryKind}};
     #[test]
     fn get_keyval_test() {
 
@@ -450,6 +521,37 @@ mod tests {
         assert_eq!(result, Some(expected));
     }
 
+    /* Table ids by lowercased name, from the name index FileMaker looks
+     * tables up by. Entries hold the table's directory, 128 + id. */
+    fn table_name_index(cache: &mut PageStore, file: &str) -> HashMap<String, usize> {
+        let view = get_view_from_key(&HBAMPath::new(vec![&[3], &[16], &[1], &[1]]), cache, file)
+            .unwrap()
+            .unwrap();
+        view.chunks.iter()
+            .filter_map(|chunk| match &chunk.contents {
+                LocalChunkContents::LongRef { key, data } => Some((
+                    decode_name_index_key(key).unwrap(),
+                    decode_name_index_value(data).unwrap() as usize - 128,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_table_name_index_test() {
+        for file in ["blank", "mixed", "relation", "relation_sec_diff"] {
+            let file = format!("test_data/fmp_files/{}.fmp12", file);
+            let mut cache = PageStore::new();
+            let tables = get_table_catalog(&mut cache, &file);
+            let index = table_name_index(&mut cache, &file);
+            assert_eq!(tables.len(), index.len());
+            for (id, table) in tables {
+                assert_eq!(index.get(&table.name.to_lowercase()), Some(&id));
+            }
+        }
+    }
+
     #[test]
     fn get_table_catalog_test() {
         let mut cache = PageStore::new();
@@ -462,6 +564,30 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn table_metadata_test() {
+        let mut cache = PageStore::new();
+        let result = get_table_catalog(&mut cache, "test_data/fmp_files/mixed.fmp12");
+        /* Times from [3].[16].[5].[128, 1] and [128, 2], see timestamp_testing. */
+        assert_eq!(result[&1].metadata, Metadata {
+            created_by: String::from("admin"),
+            modified_by: String::from("Admin"),
+            modified_at: Some(1697238717),
+        });
+        assert!(result[&2].metadata.modified_at.is_some());
+    }
+
+    #[test]
+    fn field_comment_test() {
+        let mut cache = PageStore::new();
+        let result = get_table_catalog(&mut cache, "test_data/fmp_files/mixed.fmp12");
+        let fields = &result[&1].fields;
+        assert_eq!(fields[&1].comment, "Unique identifier of each record in this table");
+        assert_eq!(fields[&5].comment, "Account name of the user who last modified each record");
+        assert_eq!(fields[&6].comment, "");
+        assert_eq!(result[&1].comment, "");
+    }
+
     #[test]
     fn get_table_catalog_split_page_test() {
         let mut cache = PageStore::new();
@@ -477,6 +603,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_field_options_test() {
+        let mut options = vec![0; 26];
+        options[0] = 3;
+        options[1] = 5;
+        options[25] = 1;
+        let field = decode_field_options(Field::new(1, String::from("average")), &options);
+        assert_eq!(field.kind, FieldKind::Summary { kind: SummaryKind::Average, field: 0 });
+
+        options[0] = 2;
+        options[1] = 2;
+        options[9] = 10;
+        let field = decode_field_options(Field::new(2, String::from("doubled")), &options);
+        assert_eq!(field.kind, FieldKind::Calculation { code: Calculation(vec![]), stored: false });
+        assert_eq!(field.dtype, DataType::Number);
+    }
+
+    #[test]
+    fn no_invented_triggers() {
+        for file in ["blank", "mixed", "relation", "relation_sec_diff"] {
+            let mut cache = PageStore::new();
+            let contents = super::get_schema_contents(&mut cache, &format!("test_data/fmp_files/{}.fmp12", file));
+            assert!(contents.triggers.is_empty());
+            assert!(contents.layouts.iter().all(|layout| layout.triggers.is_empty()));
+        }
+    }
+
+    #[test]
+    fn sample_relations_hold_no_unknown_data() {
+        for file in ["relation", "relation_sec_diff"] {
+            let mut cache = PageStore::new();
+            let path = format!("test_data/fmp_files/{}.fmp12", file);
+            let relations = get_view_from_key(&HBAMPath::new(vec![&[3], &[251], &[5]]), &mut cache, &path).unwrap().unwrap();
+            assert!(relations.get_dirs().unwrap().iter().all(|dir| !super::has_unknown_relation_data(dir)));
+        }
+    }
+
     #[test]
     fn get_data_source_catalog_test() {
         let mut cache = PageStore::new();
@@ -496,6 +659,11 @@ mod tests {
         let result = get_script_catalog(&mut cache, "test_data/fmp_files/mixed.fmp12");
 
         assert_eq!(3, result.len());
+        for script in result.values() {
+            /* Sample files were all last edited on 2023-10-13/14. */
+            let modified = script.metadata.modified_at.expect("Script has no modification time.");
+            assert!((1697155200..1697328000).contains(&modified));
+        }
     }
 
     #[test]
@@ -521,6 +689,7 @@ mod tests {
                            comparison: RelationComparison::Equal,
                        }
                    ],
+                   options: RelationOptions::default(),
                },
                Relation {
                    id: 3,
@@ -532,6 +701,7 @@ mod tests {
                            comparison: RelationComparison::Equal,
                        }
                    ],
+                   options: RelationOptions::default(),
 
                },
 
@@ -555,6 +725,7 @@ mod tests {
                            comparison: RelationComparison::Equal,
                        }
                    ],
+                   options: RelationOptions::default(),
                },
            ],
        });
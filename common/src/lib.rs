@@ -2,3 +2,4 @@ pub mod dbobjects;
 pub mod cadlang;
 pub mod hbam2;
 pub mod util;
+pub mod shell;
@@ -21,11 +21,14 @@ fn main() -> Result<(), std::io::Error>{
         cli::Command::Test { file, tests } => {
         },
         cli::Command::Sync { cadmus_file, fmp_file } => todo!(),
-        cli::Command::Hbam { fmp_file, print_dir, print_all_blocks, .. } => {
+        cli::Command::Hbam { fmp_file, print_dir, print_all_blocks, json_out, .. } => {
             let fmp_file_uw = fmp_file.unwrap();
             let mut ctx = Context::new();
 
-            if print_all_blocks {
+            if json_out {
+                let file = ctx.get_schema_contents(&fmp_file_uw);
+                println!("{}", serde_json::to_string_pretty(&file)?);
+            } else if print_all_blocks {
                 ctx.print_full(&fmp_file_uw)
             } else if print_dir.is_some() {
                 //println!("Printing key: {}", print_dir.as_ref().unwrap());
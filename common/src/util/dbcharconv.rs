@@ -41,11 +41,78 @@ pub fn encode_text(text: &str) -> Vec<u8> {
         .collect()
 }
 
+/* Name indexes, e.g [3].[16].[1].[1] for tables, are long key-value pairs
+ * used by FileMaker to look objects up by name.
+ * key:   double-byte encoded name, followed by NAME_INDEX_KEY_SUFFIX.
+ * value: [len, id_hi, id_lo, flag]. The id is always 2 bytes wide, with ids
+ *        >= 128 stored as 0x80 | (id - 128) in the high byte, e.g 129 -> [0x80, 0x1].
+ *        This leaves 15 bits for id - 128, so ids from 0x8080 can't be stored.
+ *        The flag is NAME_INDEX_LAST for the entry that sorts last in the
+ *        directory, and NAME_INDEX_ENTRY for every other entry. */
+
+pub const NAME_INDEX_KEY_SUFFIX: [u8; 4] = [0x0, 0x0, 0x0, 0x3];
+pub const NAME_INDEX_ENTRY: u8 = 0x1e;
+pub const NAME_INDEX_LAST: u8 = 0x40;
+
+pub fn encode_name_index_key(name: &str) -> Vec<u8> {
+    let mut result = encode_text(name);
+    result.extend(NAME_INDEX_KEY_SUFFIX);
+    result
+}
+
+pub fn decode_name_index_key(bytes: &[u8]) -> Option<String> {
+    let name = bytes.strip_suffix(&NAME_INDEX_KEY_SUFFIX)?;
+    Some(name.chunks_exact(2)
+        .map(|b| decode_char(b[0], b[1]))
+        .collect::<String>())
+}
+
+pub const NAME_INDEX_MAX_ID: u16 = 0x807f;
+
+/* Returns None for ids past NAME_INDEX_MAX_ID. */
+pub fn encode_name_index_value(id: u16, last: bool) -> Option<Vec<u8>> {
+    let (high, low) = if id > NAME_INDEX_MAX_ID {
+        return None
+    } else if id >= 0x80 {
+        (0x80 | ((id - 0x80) >> 8) as u8, ((id - 0x80) & 0xff) as u8)
+    } else {
+        (0x0, id as u8)
+    };
+    let flag = if last { NAME_INDEX_LAST } else { NAME_INDEX_ENTRY };
+    Some(vec![2, high, low, flag])
+}
+
+pub fn decode_name_index_value(bytes: &[u8]) -> Option<u16> {
+    match bytes {
+        [2, high, low, _] if high & 0x80 != 0 => {
+            Some(0x80 + (((*high & 0x7f) as u16) << 8) + *low as u16)
+        }
+        [2, high, low, _] => Some(((*high as u16) << 8) + *low as u16),
+        _ => None,
+    }
+}
+
+/* Builds a full name index directory from (name, id) pairs. Entries are
+ * returned in key order, which is the order they must be written in. Returns
+ * None if an id can't be stored. */
+pub fn encode_name_index(entries: &[(&str, u16)]) -> Option<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut keys = entries.iter()
+        .map(|(name, id)| (encode_name_index_key(name), *id))
+        .collect::<Vec<_>>();
+    keys.sort_by(|a, b| a.0.cmp(&b.0));
+    let n = keys.len();
+    keys.into_iter()
+        .enumerate()
+        .map(|(i, (key, id))| Some((key, encode_name_index_value(id, i + 1 == n)?)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::util::dbcharconv::{decode_char, encode_text};
 
-    use super::encode_char;
+    use super::{encode_char, decode_name_index_key, decode_name_index_value, encode_name_index,
+        encode_name_index_key, encode_name_index_value, NAME_INDEX_MAX_ID};
 
     #[test]
     fn encode_test() {
@@ -93,7 +160,38 @@ mod tests {
             .collect();
         println!("{}", out);
     }
-}
-
 
+    #[test]
+    fn name_index_test() {
+        /* Taken from [3].[16].[1].[1] in test_data/fmp_files/mixed.fmp12 */
+        let blank_key = [18, 37, 19, 48, 18, 15, 19, 109, 19, 30, 0, 0, 0, 3];
+        let second_key = [20, 16, 18, 107, 18, 61, 19, 142, 19, 109, 18, 80, 2, 29, 20, 51,
+            18, 15, 18, 37, 19, 48, 18, 107, 0, 0, 0, 3];
+
+        assert_eq!(encode_name_index_key("Blank"), blank_key);
+        assert_eq!(decode_name_index_key(&second_key), Some(String::from("second_table")));
+        assert_eq!(decode_name_index_key(&[18, 37]), None);
+
+        assert_eq!(encode_name_index_value(129, false), Some(vec![2, 128, 1, 30]));
+        assert_eq!(encode_name_index_value(130, true), Some(vec![2, 128, 2, 64]));
+        assert_eq!(encode_name_index_value(6, true), Some(vec![2, 0, 6, 64]));
+        assert_eq!(decode_name_index_value(&[2, 128, 2, 64]), Some(130));
+        assert_eq!(decode_name_index_value(&[2, 0, 3, 30]), Some(3));
+        assert_eq!(decode_name_index_value(&[128, 2]), None);
+        for id in [1, 127, 128, 129, 400, 1000, 0x807f] {
+            assert_eq!(decode_name_index_value(&encode_name_index_value(id, false).unwrap()), Some(id));
+        }
+        /* The high byte has no room left past the last id. */
+        assert_eq!(encode_name_index_value(NAME_INDEX_MAX_ID, false), Some(vec![2, 0xff, 0xff, 30]));
+        assert_eq!(encode_name_index_value(NAME_INDEX_MAX_ID + 1, false), None);
+        assert_eq!(encode_name_index(&[("Blank", 129), ("second_table", 0x8080)]), None);
 
+        assert_eq!(
+            encode_name_index(&[("second_table", 130), ("Blank", 129)]),
+            Some(vec![
+                (blank_key.to_vec(), vec![2, 128, 1, 30]),
+                (second_key.to_vec(), vec![2, 128, 2, 64]),
+            ])
+        );
+    }
+}
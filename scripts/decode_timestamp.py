@@ -2,12 +2,18 @@
 
 import sys
 import datetime
-import struct
+
+# Timestamps are variable length ints: [len, big-endian bytes...],
+# counting 1/16ths of a nanosecond since 2001-01-01 00:00:00 UTC.
+# See cadmus_util::encoding_util::get_timestamp.
+FM_EPOCH = datetime.datetime(2001, 1, 1, tzinfo=datetime.timezone.utc)
 
 
 def time_decode(byte_list: [int]):
-    unpacked, = struct.unpack('<q', bytes(byte_list))
-    print(datetime.timedelta(microseconds=unpacked/10.))
+    if byte_list[0] == len(byte_list) - 1:
+        byte_list = byte_list[1:]
+    ticks = int.from_bytes(bytes(byte_list), 'big')
+    return FM_EPOCH + datetime.timedelta(microseconds=ticks / 16000.)
 
 
 if __name__ == "__main__":
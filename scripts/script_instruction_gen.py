@@ -1,27 +1,373 @@
 #!/usr/bin/python3
 
-# Used to generate Enum for scripting engine's instructions
-# Uses "fmp_format.md" to construct enum
-
-lookup = {}
-
-with open(r"fmp_format.md", 'r') as fp:
-    ins = False
-    for l_no, line in enumerate(fp):
-        if ins is True and not line.startswith("#### "):
-            continue
-        if ins is True and line.startswith("### "):
-            break
-        if ins is True:
-            print(line)
-            num = int(line.split(" ")[1][:-1])
-            name = line.split(".")[1][1:].strip().title().replace(" ", "").replace("-", "").replace("/", "")
-            lookup[num] = name
-        if line.startswith("### List of Instructions"):
-            ins = True
-
-with open("src/decompile/script_engine_instructions.rs", 'w') as fp:
-    fp.write("pub enum Instructions {\n")
-    for ins in sorted(lookup.keys()):
-        fp.write(f"\t{lookup[ins]} = {ins},\n")
-    fp.write("}")
+# Generates the script step lists from the table below, so that the object
+# model, the cadscript frontend and its argument names stay in sync.
+# Run from the repository root after editing the table:
+#   python3 scripts/script_instruction_gen.py
+#
+# Each step has its FileMaker opcode and variant name. Steps with fields hold
+# the (Instruction, ProtoInstruction) type of each, and are parsed and lowered
+# by hand. Steps without fields are parsed and lowered by generated code.
+# `names` are the cadscript spellings, the first being canonical, and default
+# to the variant name in snake case. `args` are the argument labels, in
+# positional order, and a label ending in `?` may be left out. Steps without
+# fields have nowhere to keep their FileMaker options, so the parser reports
+# any argument given to them as unsupported. Block steps are spelled by the
+# parser's block syntax.
+
+import re
+
+CALC = ("Calculation", "CalculationString")
+NAME = ("String", "String")
+SCRIPT = ("ScriptSelection", "ProtoScriptSelection")
+LAYOUT = ("LayoutSelection", "ProtoLayoutSelection")
+ANIMATION = ("LayoutAnimation", "LayoutAnimation")
+RECORD = ("RecordSelection", "ProtoRecordSelection")
+FIELD = ("FieldReference", "ProtoFieldSelection")
+
+
+def snake_case(name):
+    return re.sub(r"(?<!^)(?=[A-Z])", "_", name).lower()
+
+
+def step(opcode, variant, fields=(), args=(), names=None, block=False):
+    return {
+        "opcode": opcode,
+        "variant": variant,
+        "fields": list(fields),
+        "args": list(args),
+        "names": [snake_case(variant)] if names is None else names,
+        "block": block,
+    }
+
+
+STEPS = [
+    step(1, "PerformScript", fields=[("script", SCRIPT), ("args", CALC)], args=["script", "args?"]),
+    step(3, "SaveACopyAsXml", args=["window_name?", "destination?"]),
+    step(4, "GoToNextField"),
+    step(5, "GoToPreviousField"),
+    step(6, "GoToLayout", fields=[("layout", LAYOUT), ("animation", ANIMATION)], args=["layout", "animation?"]),
+    step(7, "NewRecordRequest", names=["new_record_request", "new_record", "new_request"]),
+    step(8, "DuplicateRecordRequest"),
+    step(9, "DeleteRecordRequest", args=["with_dialog?"]),
+    step(10, "DeleteAllRecords", args=["with_dialog?"]),
+    step(11, "InsertFromIndex", args=["select?", "target?"]),
+    step(12, "InsertFromLastVisited", args=["select?", "target?"]),
+    step(13, "InsertCurrentDate", args=["select?", "target?"]),
+    step(14, "InsertCurrentTime", args=["select?", "target?"]),
+    step(16, "GoToRecordRequestPage", fields=[("record", RECORD)], args=["record"], names=["go_to_record", "go_to_request", "go_to_record_request_page"]),
+    step(17, "GoToField", args=["select?", "field?"]),
+    step(18, "CheckSelection", args=["select?", "field?"]),
+    step(19, "CheckRecord"),
+    step(20, "CheckFoundSet"),
+    step(21, "UnsortRecords"),
+    step(22, "EnterFindMode", args=["pause?", "requests?"]),
+    step(23, "ShowAllRecords"),
+    step(24, "ModifyLastFind"),
+    step(25, "OmitRecord"),
+    step(26, "OmitMultipleRecords", args=["with_dialog?", "records?"]),
+    step(27, "ShowOmmitedOnly", names=["show_omitted_only"]),
+    step(28, "PerformFind", args=["requests?"]),
+    step(29, "ShowHideToolbars", args=["action?", "lock?"]),
+    step(30, "ViewAs", args=["view?"]),
+    step(31, "AdjustWindow", args=["action?"]),
+    step(32, "OpenHelp"),
+    step(33, "OpenFile", args=["file?", "open_hidden?"]),
+    step(34, "CloseFile", args=["file?"]),
+    step(35, "ImportRecords", args=["source?", "with_dialog?", "target?"]),
+    step(36, "ExportRecords", args=["output_file?", "with_dialog?"]),
+    step(37, "SaveACopyAs", args=["output_file?", "with_dialog?", "kind?"]),
+    step(38, "OpenManageDatabase"),
+    step(39, "SortRecords", args=["with_dialog?", "order?"]),
+    step(40, "RelookupFieldContents", args=["with_dialog?", "field?"]),
+    step(41, "EnterPreviewMode", args=["pause?"]),
+    step(42, "PrintSetup", args=["with_dialog?"]),
+    step(43, "Print", fields=[("expr", CALC)], args=["expr?"]),
+    step(44, "ExitApplication"),
+    step(45, "UndoRedo", args=["action?"]),
+    step(46, "Cut", args=["select?", "field?"]),
+    step(47, "Copy", args=["select?", "field?"]),
+    step(48, "Paste", args=["select?", "field?"]),
+    step(49, "Clear", args=["select?", "field?"]),
+    step(50, "SelectAll"),
+    step(51, "RevertRecordRequest", args=["with_dialog?"]),
+    step(55, "EnterBrowserMode", args=["pause?"]),
+    step(56, "InsertPicture", args=["file?"]),
+    step(57, "SendEvent", args=["target?", "event?"]),
+    step(60, "InsertCurrentUserName", args=["select?", "target?"]),
+    step(61, "InsertText", args=["select?", "target?", "text?"]),
+    step(62, "PauseResumeScript", args=["duration?"]),
+    step(63, "SendMail", args=["to?", "cc?", "bcc?", "subject?", "message?", "attachment?", "with_dialog?"]),
+    step(64, "SendDdeExecute", args=["service?", "topic?", "command?"]),
+    step(65, "DialPhone", args=["number?", "with_dialog?"]),
+    step(66, "Speak", args=["text?", "voice?", "wait?"]),
+    step(67, "PerformApplescript", args=["script?"]),
+    step(68, "If", fields=[("condition", CALC)], args=["expr"], names=["if"], block=True),
+    step(69, "Else", names=["else"], block=True),
+    step(70, "EndIf", names=[], block=True),
+    step(71, "Loop", names=["loop"], block=True),
+    step(72, "ExitLoopIf", fields=[("condition", CALC)], args=["expr"]),
+    step(73, "EndLoop", names=[], block=True),
+    step(74, "GoToRelatedRecord", args=["occurrence?", "layout?", "match_found_set?"]),
+    step(75, "CommitRecordsRequests", args=["with_dialog?", "skip_validation?"], names=["commit_records_requests", "commit_records", "commit_requests"]),
+    step(76, "SetField", fields=[("field", FIELD), ("value", CALC), ("repetition", CALC)], args=["field", "expr", "repetition?"]),
+    step(77, "InsertCalculatedResult", args=["select?", "target?", "expr?"]),
+    step(79, "FreezeWindow"),
+    step(80, "RefreshWindow", args=["flush_join_results?", "flush_sql_data?"]),
+    step(81, "ScrollWindow", args=["direction?"]),
+    step(82, "NewFile"),
+    step(83, "ChangePassword", args=["old_password?", "new_password?", "with_dialog?"]),
+    step(84, "SetMultiUser", args=["state?"]),
+    step(85, "AllowUserAbort", args=["state?"]),
+    step(86, "SetErrorCapture", args=["state?"]),
+    step(87, "ShowCustomDialog", args=["title?", "message?", "buttons?"]),
+    step(88, "OpenScriptWorkspace"),
+    step(89, "BlankLineComment", args=["text?"]),
+    step(90, "HaltScript"),
+    step(91, "ReplaceFieldContents", args=["with_dialog?", "field?", "expr?"]),
+    step(92, "ShowHideTextRuler", args=["action?"]),
+    step(93, "Beep"),
+    step(94, "SetUseSystemFormats", args=["state?"]),
+    step(95, "RecoverFile", args=["file?", "with_dialog?"]),
+    step(96, "SaveACopyAsAddOnPackage", args=["window_name?", "replace_uuids?"]),
+    step(97, "SetZoomLevel", args=["level?", "lock?"]),
+    step(98, "CopyAllRecordsRequests"),
+    step(99, "GoToPortalRow", args=["row?", "select?", "with_dialog?"]),
+    step(101, "CopyRecordRequest"),
+    step(102, "FluchCacheToDisk", names=["flush_cache_to_disk"]),
+    step(103, "ExitScript", fields=[("value", CALC)], args=["expr?"]),
+    step(104, "DeletePortalRow", args=["with_dialog?"]),
+    step(105, "OpenPreferences"),
+    step(106, "CorrectWord"),
+    step(107, "SpellingOptions"),
+    step(108, "SelectDictionaries"),
+    step(109, "EditUserDictionary"),
+    step(111, "OpenUrl", args=["url?", "with_dialog?", "in_external_browser?"]),
+    step(112, "OpenManageValueLists"),
+    step(113, "OpenSharing"),
+    step(114, "OpenFileOptions"),
+    step(115, "AllowFormattingBar", args=["state?"]),
+    step(116, "SetNextSerialValue", args=["field?", "expr?"]),
+    step(117, "ExecuteSql", args=["query?", "with_dialog?"]),
+    step(118, "OpenHosts"),
+    step(119, "MoveResizeWindow", args=["window?", "height?", "width?", "top?", "left?"]),
+    step(120, "ArrangeAllWindows", args=["action?"]),
+    step(121, "CloseWindow", args=["window?"]),
+    step(122, "NewWindow", args=["name?", "height?", "width?", "top?", "left?", "style?"]),
+    step(123, "SelectWindow", args=["window?"]),
+    step(124, "SetWindowTitle", args=["window?", "title?"]),
+    step(125, "ElseIf", fields=[("condition", CALC)], args=["expr"], names=["elif"], block=True),
+    step(126, "ConstrainFoundSet", args=["requests?"]),
+    step(127, "ExtendFoundSet", args=["requests?"]),
+    step(128, "PerformFindReplace", args=["with_dialog?", "find?", "replace?"]),
+    step(129, "OpenFindReplace"),
+    step(130, "SetSelection", args=["field?", "start?", "end?"]),
+    step(131, "InsertFile", args=["target?", "file?"]),
+    step(132, "ExportFieldContents", args=["field?", "output_file?", "create_folders?"]),
+    step(133, "OpenRecordRequest"),
+    step(134, "AddAccount", args=["name?", "password?", "privilege_set?", "expire_password?"]),
+    step(135, "DeleteAccount", args=["name?"]),
+    step(136, "ResetAccountPassword", args=["name?", "password?", "expire_password?"]),
+    step(137, "EnableAccount", args=["name?", "state?"]),
+    step(138, "Relogin", args=["name?", "password?", "with_dialog?"]),
+    step(139, "ConvertFile", args=["source?", "with_dialog?"]),
+    step(140, "OpenManageDataSources"),
+    step(141, "SetVariable", fields=[("name", NAME), ("value", CALC), ("repetition", CALC)], args=["var", "expr", "repetition?"]),
+    step(142, "InstallMenuSet", args=["menu_set?", "use_as_file_default?"]),
+    step(143, "SaveRecordsAsExcel", args=["output_file?", "records?", "with_dialog?"]),
+    step(144, "SaveRecordsAsPdf", args=["output_file?", "records?", "with_dialog?", "append?"]),
+    step(145, "GoToObject", args=["object?", "repetition?"]),
+    step(146, "SetWebViewer", args=["object?", "action?", "url?"]),
+    step(147, "SetFieldByName", args=["field?", "expr?"]),
+    step(148, "InstallOntimerScript", args=["script?", "args?", "interval?"]),
+    step(149, "OpenEditSavedFinds"),
+    step(150, "PerformQuickFind", args=["expr?"]),
+    step(151, "OpenManageLayouts"),
+    step(152, "SaveRecordsAsSnapshotLink", args=["output_file?", "records?"]),
+    step(154, "SortRecordsByField", args=["field?", "order?"]),
+    step(155, "FindMatchingRecords", args=["action?", "field?"]),
+    step(156, "ManageContainers"),
+    step(157, "InstallPluginFile", args=["field?"]),
+    step(158, "InsertPdf", args=["target?", "file?"]),
+    step(159, "InsertAudioVideo", args=["target?", "file?"]),
+    step(160, "InsertFromUrl", args=["target?", "url?", "with_dialog?", "curl_options?"]),
+    step(161, "InsertFromDevice", args=["target?", "device?"]),
+    step(164, "PerformScriptOnServer", args=["script?", "args?", "wait?"]),
+    step(165, "OpenManageThemes"),
+    step(166, "ShowHideMenubar", args=["action?", "lock?"]),
+    step(167, "RefreshObject", args=["object?", "repetition?"]),
+    step(168, "SetLayoutObjectAnimation", args=["state?"]),
+    step(169, "ClosePopover"),
+    step(172, "OpenUploadToHost"),
+    step(174, "EnableTouchKeyboard", args=["state?"]),
+    step(175, "PerformJavascriptInWebViewer", args=["object?", "function?", "args?"]),
+    step(176, "CommentedOut", args=["text?"]),
+    step(177, "AvplayerPlay", args=["source?", "presentation?", "start?", "end?"]),
+    step(178, "AvplayerSetPlaybackState", args=["state?"]),
+    step(179, "AvplayerSetOptions", args=["presentation?", "position?", "volume?"]),
+    step(180, "RefreshPortal", args=["object?"]),
+    step(181, "GetFolderPath", args=["target?", "dialog_title?", "default_location?"]),
+    step(182, "TruncateTable", args=["table?", "with_dialog?"]),
+    step(183, "OpenFavorites"),
+    step(185, "ConfigureRegionMonitorScript", args=["monitor?", "name?", "script?"]),
+    step(187, "ConfigureLocalNotification", args=["action?", "name?", "script?", "text?"]),
+    step(188, "GetFileExists", args=["file?", "target?"]),
+    step(189, "GetFileSize", args=["file?", "target?"]),
+    step(190, "CreateDataFile", args=["file?", "create_folders?"]),
+    step(191, "OpenDataFile", args=["file?", "target?"]),
+    step(192, "WriteToDataFile", args=["file_id?", "data?", "encoding?"]),
+    step(193, "ReadFromDataFile", args=["file_id?", "amount?", "target?", "encoding?"]),
+    step(194, "GetDataFilePosition", args=["file_id?", "target?"]),
+    step(195, "SetDataFilePosition", args=["file_id?", "position?"]),
+    step(196, "CloseDataFile", args=["file_id?"]),
+    step(197, "DeleteFile", args=["file?"]),
+    step(199, "RenameFile", args=["file?", "name?"]),
+    step(200, "SetErrorLogging", args=["state?", "expr?"]),
+    step(201, "ConfigureNfcReading", args=["action?", "script?"]),
+    step(202, "ConfigureMachineLearningModel", args=["operation?", "name?", "from?"]),
+    step(203, "ExecuteFilemakerDataApi", args=["target?", "expr?"]),
+    step(205, "OpenTransaction"),
+    step(206, "CommitTransaction"),
+    step(207, "RevertTransaction", args=["condition?", "error_code?", "error_message?"]),
+    step(208, "SetSessionIdentifier", args=["expr?"]),
+    step(209, "SetDictionary", args=["language?"]),
+    step(210, "PerformScriptOnServerWithCallback", args=["script?", "args?", "callback_script?", "callback_args?"]),
+    step(211, "TriggerClarisConnectFlow", args=["flow?", "json?", "target?"]),
+    step(254, "Assert", fields=[("expr", CALC)], args=["expr"]),
+]
+
+GENERATED = "Generated by scripts/script_instruction_gen.py, do not edit by hand."
+
+
+def is_unit(s):
+    return not s["fields"] and not s["block"]
+
+
+def variant(s, proto):
+    if not s["fields"]:
+        return f"\t{s['variant']},"
+    fields = ", ".join(f"{name}: {types[1 if proto else 0]}" for name, types in s["fields"])
+    return f"\t{s['variant']} {{ {fields} }},"
+
+
+def replace_enum(path, enum, proto):
+    with open(path) as fp:
+        code = fp.read()
+    start = code.index(f"pub enum {enum} {{")
+    end = code.index("\n}", start)
+    body = [f"\t// {GENERATED}"] + [variant(s, proto) for s in STEPS]
+    code = code[:code.index("\n", start) + 1] + "\n".join(body) + code[end:]
+    return code
+
+
+def write_instructions(path):
+    code = replace_enum(path, "Instruction", False)
+    start = code.index("    pub fn get_opcode(&self) -> u32 {")
+    end = code.index("\n    }\n", start) + len("\n    }\n")
+    arms = "\n".join(
+        f"            Instruction::{s['variant']}{' { .. }' if s['fields'] else ''} => {s['opcode']},"
+        for s in STEPS)
+    opcode = ("    pub fn get_opcode(&self) -> u32 {\n"
+              f"        // {GENERATED}\n"
+              "        match self {\n"
+              f"{arms}\n"
+              "        }\n"
+              "    }\n")
+    with open(path, "w") as fp:
+        fp.write(code[:start] + opcode + code[end:])
+
+
+def write_proto_instructions(path):
+    code = replace_enum(path, "ProtoInstruction", True)
+    with open(path, "w") as fp:
+        fp.write(code)
+
+
+def write_arg_lookups(path):
+    entries = [(name, i, arg.rstrip("?"), not arg.endswith("?"))
+               for s in STEPS for name in s["names"] for i, arg in enumerate(s["args"])]
+    rows = "\n".join(f'            ("{name}", {i}, "{arg}", {str(required).lower()}),'
+                     for name, i, arg, required in entries)
+    with open(path, "w") as fp:
+        fp.write(f"""// {GENERATED}
+
+// No indirection, we store duplicate instruction names.
+
+/* (step, position, label, required) */
+pub struct ArgLookupTable([(&'static str, u8, &'static str, bool); {len(entries)}]);
+
+impl ArgLookupTable {{
+    pub const fn new() -> Self {{
+        Self([
+{rows}
+        ])
+    }}
+
+    pub fn get_argname(&self, instruction: &str, position: u8) -> Option<&'static str> {{
+        let entry = self.0.iter()
+            .find(|entry| entry.0 == instruction && entry.1 == position);
+
+        match entry {{
+            Some(inner) => Some(inner.2),
+            None => None,
+        }}
+    }}
+
+    pub fn has_arg(&self, instruction: &str, label: &str) -> bool {{
+        self.0.iter().any(|entry| entry.0 == instruction && entry.2 == label)
+    }}
+
+    pub fn required_args<'a>(&'a self, instruction: &'a str) -> impl Iterator<Item = &'static str> + 'a {{
+        self.0.iter()
+            .filter(move |entry| entry.0 == instruction && entry.3)
+            .map(|entry| entry.2)
+    }}
+}}
+
+pub(crate) const ARG_LOOKUP: ArgLookupTable = ArgLookupTable::new();
+""")
+
+
+def write_steps(path):
+    units = [s for s in STEPS if is_unit(s)]
+    parse_arms = "\n".join(
+        f'        {" | ".join(chr(34) + n + chr(34) for n in s["names"])} => Some(ProtoInstruction::{s["variant"]}),'
+        for s in units)
+    lower_arms = "\n".join(
+        f"        ProtoInstruction::{s['variant']} => Some(Instruction::{s['variant']}),"
+        for s in units)
+    names = "\n".join(f'    "{s["names"][0]}",' for s in units)
+    with open(path, "w") as fp:
+        fp.write(f"""// {GENERATED}
+
+use crate::dbobjects::scripting::instructions::Instruction;
+use super::proto_instruction::ProtoInstruction;
+
+/* Canonical spellings of the steps which take no arguments. */
+#[allow(dead_code)]
+pub(crate) const UNIT_STEPS: [&str; {len(units)}] = [
+{names}
+];
+
+/* Steps which take no arguments, by any of their spellings. */
+pub(crate) fn unit_step(name: &str) -> Option<ProtoInstruction> {{
+    match name {{
+{parse_arms}
+        _ => None,
+    }}
+}}
+
+pub(crate) fn lower_unit_step(step: &ProtoInstruction) -> Option<Instruction> {{
+    match step {{
+{lower_arms}
+        _ => None,
+    }}
+}}
+""")
+
+
+if __name__ == "__main__":
+    write_instructions("common/src/dbobjects/scripting/instructions.rs")
+    write_proto_instructions("common/src/cadlang/cadscript/proto_instruction.rs")
+    write_arg_lookups("common/src/cadlang/cadscript/arg_lookups.rs")
+    write_steps("common/src/cadlang/cadscript/steps.rs")